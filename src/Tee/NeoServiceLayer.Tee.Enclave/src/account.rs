@@ -1,21 +1,57 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
 use log::{info, warn, error, debug};
 use sha2::{Sha256, Digest};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use zeroize::Zeroizing;
 
-use crate::{EncaveConfig, crypto::CryptoService};
+use crate::{EncaveConfig, audit::{AuditEvent, AuditLog}, canonical::to_canonical_vec, crypto::{base58, constant_time_eq, CryptoAlgorithm, CryptoService, KeyMetadata}, error::EnclaveError, health::ServiceHealth, redact::{redact, redact_bytes}, storage::{StorageService, StoreOptions, SYSTEM_PRINCIPAL}};
+use crate::locks::{MutexExt, RwLockExt};
+use crate::pagination::paginate;
 
 // Import SGX cryptographic functions for Neo address generation
 extern "C" {
-    fn occlum_generate_ecdsa_keypair(private_key: *mut u8, public_key: *mut u8) -> i32;
     fn occlum_sha256(data: *const u8, data_len: usize, hash: *mut u8) -> i32;
     fn occlum_ripemd160(data: *const u8, data_len: usize, hash: *mut u8) -> i32;
     #[allow(dead_code)]
     fn occlum_generate_neo_address(public_key: *const u8, address: *mut u8, address_len: *mut usize) -> i32;
 }
 
+/// Neo N3 address version byte for mainnet
+pub const NEO_MAINNET_ADDRESS_VERSION: u8 = 0x35;
+
+/// Neo N3 address version byte for testnet; the same as mainnet, so an N3 address does
+/// not tell which of the two networks it is used on
+pub const NEO_TESTNET_ADDRESS_VERSION: u8 = NEO_MAINNET_ADDRESS_VERSION;
+
+/// Address version byte of Neo Legacy (2.x), whose addresses N3 does not accept
+pub const NEO_LEGACY_ADDRESS_VERSION: u8 = 0x17;
+
+/// Wallet Import Format version byte for private keys
+const WIF_VERSION: u8 = 0x80;
+
+/// Trailing WIF byte marking a key whose public key is used in compressed form
+const WIF_COMPRESSED_FLAG: u8 = 0x01;
+
+/// Resolve the address version byte for a configured Neo network
+pub fn address_version_for_network(network: &str) -> Result<u8> {
+    match network.to_lowercase().as_str() {
+        "mainnet" => Ok(NEO_MAINNET_ADDRESS_VERSION),
+        "testnet" => Ok(NEO_TESTNET_ADDRESS_VERSION),
+        other => Err(anyhow!("Unknown Neo network: {}", other)),
+    }
+}
+
+/// Script hashes of the Neo native contracts accepted in signed transactions
+pub const KNOWN_SCRIPT_HASHES: &[&str] = &[
+    "ef4073a0f2b305a38ec4050e4d3d28bc40ea63f5", // NeoToken
+    "d2a4cff31913016155e38e474a2c06d08be276cf", // GasToken
+    "cc5e4edd9f5f8dba8bb65734541df7a1c081c67b", // PolicyContract
+    "fffdc93764dbaddd97c48f252a53ea4643faa3fd", // ContractManagement
+];
+
 /// Abstract account metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AbstractAccount {
@@ -26,17 +62,144 @@ pub struct AbstractAccount {
     pub created_at: u64,
     pub nonce: u64,
     pub config: AccountConfig,
+    #[serde(default)]
+    pub address_history: Vec<AddressRecord>,
+}
+
+/// Previous key/address binding retained for auditing after a recovery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressRecord {
+    pub address: String,
+    pub public_key: Vec<u8>,
+    pub replaced_at: u64,
+    pub approved_by: Vec<String>,
+}
+
+/// Signed transaction retained in an account's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub hash: String,
+    pub nonce: u64,
+    pub timestamp: u64,
+    pub recipient: Option<String>,
+    pub amount: Option<serde_json::Value>,
+    pub script_hash: Option<String>,
+}
+
+/// m-of-n account whose transactions need `threshold` signatures from distinct keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigAccount {
+    pub id: String,
+    pub address: String,
+    /// Compressed secp256r1 keys in Neo's canonical (ascending point) order
+    pub public_keys: Vec<Vec<u8>>,
+    pub threshold: usize,
+    pub verification_script: Vec<u8>,
+    pub created_at: u64,
+    /// Transactions still collecting signatures, keyed by hex transaction hash
+    #[serde(default)]
+    pub pending: HashMap<String, PendingMultisigTransaction>,
+}
+
+/// Signatures gathered so far for one multisig transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingMultisigTransaction {
+    pub transaction: String,
+    /// Signatures by index into `MultisigAccount::public_keys`
+    pub signatures: BTreeMap<usize, Vec<u8>>,
+    pub created_at: u64,
+}
+
+/// Neo limit on keys in a multisig verification script
+const MAX_MULTISIG_KEYS: usize = 1024;
+
+/// NeoVM opcodes used to build verification and invocation scripts
+const OP_PUSHINT8: u8 = 0x00;
+const OP_PUSHINT16: u8 = 0x01;
+const OP_PUSHDATA1: u8 = 0x0C;
+const OP_PUSH0: u8 = 0x10;
+const OP_SYSCALL: u8 = 0x41;
+
+/// Build the Neo N3 single-signature verification script:
+/// `PUSHDATA1 key, SYSCALL System.Crypto.CheckSig`
+pub(crate) fn single_sig_verification_script(compressed_key: &[u8]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(compressed_key.len() + 7);
+    script.push(OP_PUSHDATA1);
+    script.push(compressed_key.len() as u8);
+    script.extend_from_slice(compressed_key);
+    script.push(OP_SYSCALL);
+    script.extend_from_slice(&Sha256::digest(b"System.Crypto.CheckSig")[..4]);
+    script
+}
+
+/// Build the Neo N3 m-of-n verification script:
+/// `PUSH m, PUSHDATA1 key..., PUSH n, SYSCALL System.Crypto.CheckMultisig`
+fn multisig_verification_script(threshold: usize, sorted_keys: &[Vec<u8>]) -> Vec<u8> {
+    let mut script = Vec::with_capacity(sorted_keys.len() * 35 + 12);
+    emit_push_int(&mut script, threshold);
+    for key in sorted_keys {
+        script.push(OP_PUSHDATA1);
+        script.push(key.len() as u8);
+        script.extend_from_slice(key);
+    }
+    emit_push_int(&mut script, sorted_keys.len());
+    script.push(OP_SYSCALL);
+    // Interop id is the first four bytes of SHA256 of the syscall name
+    script.extend_from_slice(&Sha256::digest(b"System.Crypto.CheckMultisig")[..4]);
+    script
+}
+
+/// Emit the smallest NeoVM push for a non-negative integer up to `i16::MAX`
+fn emit_push_int(script: &mut Vec<u8>, value: usize) {
+    match value {
+        0..=16 => script.push(OP_PUSH0 + value as u8),
+        17..=127 => script.extend_from_slice(&[OP_PUSHINT8, value as u8]),
+        _ => {
+            script.push(OP_PUSHINT16);
+            script.extend_from_slice(&(value as i16).to_le_bytes());
+        }
+    }
 }
 
+/// Most recent transactions per account kept in memory and listed by
+/// `get_transaction_history`; older ones stay in their storage segments
+const MAX_TRANSACTION_HISTORY: usize = 1000;
+
+/// Transactions per storage segment. Only the newest segment is ever rewritten, so
+/// recording a transaction writes at most this many records.
+const TRANSACTION_SEGMENT_SIZE: usize = 100;
+
+/// Storage encryption key name for transaction histories and archives
+const TRANSACTION_HISTORY_KEY: &str = "account_transaction_history";
+
 /// Guardian information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Guardian {
     pub id: String,
+    /// Curve of `public_key`, which decides how the guardian's approvals are verified
+    pub curve: CryptoAlgorithm,
     pub public_key: Vec<u8>,
     pub permissions: Vec<String>,
     pub added_at: u64,
 }
 
+/// Validate a guardian public key for `curve`; ECDSA keys are stored compressed
+fn guardian_public_key(curve: &CryptoAlgorithm, public_key: &[u8]) -> Result<Vec<u8>> {
+    match curve {
+        CryptoAlgorithm::Secp256r1 | CryptoAlgorithm::Secp256k1 => {
+            crate::crypto::compress_public_key(curve, public_key)
+        }
+        CryptoAlgorithm::Ed25519 => {
+            let bytes: [u8; 32] = public_key.try_into()
+                .map_err(|_| anyhow!("Ed25519 guardian keys must be 32 bytes, got {}", public_key.len()))?;
+            ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+                .map_err(|e| anyhow!("Invalid Ed25519 guardian key: {}", e))?;
+            Ok(public_key.to_vec())
+        }
+        other => Err(anyhow!("Unsupported guardian curve: {}", other.name())),
+    }
+}
+
 /// Account configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountConfig {
@@ -46,100 +209,287 @@ pub struct AccountConfig {
     pub security_level: String,
 }
 
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            require_guardian_approval: false,
+            guardian_threshold: 1,
+            max_daily_transactions: 100,
+            security_level: "standard".to_string(),
+        }
+    }
+}
+
+/// An account's recent transactions, loaded from storage on first use
+///
+/// Holding an account's ledger lock serializes every operation that advances its nonce,
+/// so a transaction can be persisted before the nonce moves without holding the account
+/// map lock across storage I/O.
+#[derive(Default)]
+struct AccountLedger {
+    loaded: bool,
+    records: VecDeque<TransactionRecord>,
+    /// Index of the newest storage segment
+    segment: u64,
+    /// Records in the newest segment; always the tail of `records`
+    segment_len: usize,
+}
+
 /// Account service for abstract account management
 pub struct AccountService {
     accounts: Arc<RwLock<HashMap<String, AbstractAccount>>>,
+    multisig_accounts: Arc<RwLock<HashMap<String, MultisigAccount>>>,
+    ledgers: RwLock<HashMap<String, Arc<Mutex<AccountLedger>>>>,
+    /// Records per history segment, `TRANSACTION_SEGMENT_SIZE` outside tests
+    history_segment_size: usize,
     crypto_service: Arc<CryptoService>,
+    storage_service: Arc<StorageService>,
+    audit_log: Arc<AuditLog>,
+    address_version: u8,
+    /// Generate account keys with an attestation quote over their public keys
+    attest_keys: bool,
 }
 
 impl AccountService {
     /// Create a new account service instance
-    pub async fn new(_config: &EncaveConfig, crypto_service: Arc<CryptoService>) -> Result<Self> {
-        info!("Initializing AccountService");
+    pub async fn new(
+        config: &EncaveConfig,
+        crypto_service: Arc<CryptoService>,
+        storage_service: Arc<StorageService>,
+        audit_log: Arc<AuditLog>,
+    ) -> Result<Self> {
+        info!("Initializing AccountService for Neo {}", config.neo_network);
+        
+        let address_version = address_version_for_network(&config.neo_network)?;
         
         Ok(Self {
             accounts: Arc::new(RwLock::new(HashMap::new())),
+            multisig_accounts: Arc::new(RwLock::new(HashMap::new())),
+            ledgers: RwLock::new(HashMap::new()),
+            history_segment_size: TRANSACTION_SEGMENT_SIZE,
             crypto_service,
+            storage_service,
+            audit_log,
+            address_version,
+            attest_keys: config.attest_signing_keys,
         })
     }
     
+    /// Probe the account registries
+    pub fn health_check(&self) -> ServiceHealth {
+        let account_count = match self.accounts.read() {
+            Ok(accounts) => accounts.len(),
+            Err(_) => return ServiceHealth::unhealthy("account", "Account registry lock poisoned"),
+        };
+        
+        let multisig_count = match self.multisig_accounts.read() {
+            Ok(accounts) => accounts.len(),
+            Err(_) => return ServiceHealth::unhealthy("account", "Multisig registry lock poisoned"),
+        };
+        
+        ServiceHealth::healthy("account", serde_json::json!({
+            "account_count": account_count,
+            "multisig_account_count": multisig_count,
+            "address_version": self.address_version,
+        }))
+    }
+    
     /// Create a new abstract account with proper Neo cryptographic address generation
     pub fn create_account(&self, account_id: &str, account_data: &str) -> Result<String> {
-        let mut accounts = self.accounts.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut accounts = self.accounts.write_or_recover();
         
         if accounts.contains_key(account_id) {
-            return Err(anyhow!("Account '{}' already exists", account_id));
+            return Err(EnclaveError::AlreadyExists(format!("Account '{}' already exists", account_id)).into());
         }
         
         // Parse account configuration
-        let config: AccountConfig = serde_json::from_str(account_data)
-            .unwrap_or_else(|_| AccountConfig {
-                require_guardian_approval: false,
-                guardian_threshold: 1,
-                max_daily_transactions: 100,
-                security_level: "standard".to_string(),
-            });
-        
-        // Generate production-grade ECDSA P-256 key pair using SGX
-        let (private_key, public_key) = self.generate_neo_keypair()?;
-        
-        // Generate proper Neo address from public key using cryptographic functions
-        let address = self.generate_neo_address_from_public_key(&public_key)?;
+        let config: AccountConfig = serde_json::from_str(account_data).unwrap_or_default();
         
-        // Store the key securely in the crypto service
-        let key_metadata = self.crypto_service.generate_key(
+        // Neo N3 accounts use secp256r1. The key lives in the crypto service so that the
+        // key behind the address is the same key that signs the account's transactions.
+        let generate = if self.attest_keys {
+            CryptoService::generate_attested_key
+        } else {
+            CryptoService::generate_key
+        };
+        let key_metadata = generate(
+            &self.crypto_service,
             &format!("account_{}", account_id),
-            crate::crypto::CryptoAlgorithm::Secp256k1,
+            crate::crypto::CryptoAlgorithm::Secp256r1,
             vec!["Sign".to_string(), "Verify".to_string()],
             false,
             &format!("Abstract account key for {}", account_id),
         )?;
+        let account = self.register_account(&mut accounts, account_id, key_metadata, config)?;
+        drop(accounts);
+        
+        self.audit_log.record(AuditEvent::new("account", "account_created", account_id)
+            .with_details(serde_json::json!({ "address": account.address })));
+        info!("Created abstract account '{}' with Neo address: {}", account_id, redact(&account.address));
+        debug!("Account public key: {}", redact_bytes(&account.public_key));
+        
+        Ok(serde_json::to_string(&account)?)
+    }
+    
+    /// Create an account from an existing Neo private key in Wallet Import Format.
+    /// The imported key stays exportable, since it already exists outside the enclave.
+    pub fn import_wif(&self, account_id: &str, wif: &str) -> Result<String> {
+        let mut accounts = self.accounts.write_or_recover();
+        
+        if accounts.contains_key(account_id) {
+            return Err(EnclaveError::AlreadyExists(format!("Account '{}' already exists", account_id)).into());
+        }
+        
+        // Base58Check payload: version byte, 32-byte key, optional compression flag
+        let payload = Zeroizing::new(base58::decode_check(wif)
+            .map_err(|e| anyhow!("Invalid WIF: {}", e))?);
+        let private_key = match payload.as_slice() {
+            [WIF_VERSION, key @ ..] if key.len() == 32 => key,
+            [WIF_VERSION, key @ .., WIF_COMPRESSED_FLAG] if key.len() == 32 => key,
+            [WIF_VERSION, ..] => return Err(anyhow!("Invalid WIF: unexpected payload length {}", payload.len())),
+            _ => return Err(anyhow!("Invalid WIF: version byte must be 0x{:02x}", WIF_VERSION)),
+        };
+        
+        let key_metadata = self.crypto_service.import_key(
+            &format!("account_{}", account_id),
+            crate::crypto::CryptoAlgorithm::Secp256r1,
+            private_key,
+            vec!["Sign".to_string(), "Verify".to_string()],
+            true,
+            &format!("Imported account key for {}", account_id),
+        )?;
+        
+        let account = self.register_account(&mut accounts, account_id, key_metadata, AccountConfig::default())?;
+        drop(accounts);
+        
+        self.audit_log.record(AuditEvent::new("account", "account_imported", account_id)
+            .with_details(serde_json::json!({ "address": account.address })));
+        info!("Imported account '{}' from WIF with Neo address: {}", account_id, redact(&account.address));
+        
+        Ok(serde_json::to_string(&account)?)
+    }
+    
+    /// Export an account's private key as a compressed WIF. Fails unless the account key
+    /// was imported or created as exportable.
+    pub fn export_wif(&self, account_id: &str) -> Result<String> {
+        let accounts = self.accounts.read_or_recover();
+        let account = accounts.get(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
+        
+        let private_key = self.crypto_service.export_private_key(&format!("account_{}", account_id))?;
+        
+        let mut payload = Zeroizing::new(Vec::with_capacity(34));
+        payload.push(WIF_VERSION);
+        payload.extend_from_slice(&private_key);
+        payload.push(WIF_COMPRESSED_FLAG);
+        let wif = Zeroizing::new(base58::encode_check(&payload));
+        
+        warn!("Exported WIF for account '{}'", account_id);
+        
+        Ok(serde_json::json!({
+            "account_id": account_id,
+            "address": account.address,
+            "wif": wif.as_str(),
+        }).to_string())
+    }
+    
+    /// Derive the address for a freshly created account key and store the account
+    fn register_account(
+        &self,
+        accounts: &mut HashMap<String, AbstractAccount>,
+        account_id: &str,
+        key_metadata: KeyMetadata,
+        config: AccountConfig,
+    ) -> Result<AbstractAccount> {
+        let public_key = key_metadata.public_key
+            .ok_or_else(|| anyhow!("Account key for '{}' has no public key", account_id))?;
+        
+        // Generate proper Neo address from public key using cryptographic functions
+        let address = self.generate_neo_address_from_public_key(&public_key)?;
         
         let account = AbstractAccount {
             id: account_id.to_string(),
             address,
-            public_key: public_key.to_vec(),
+            public_key,
             guardians: Vec::new(),
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_secs(),
             nonce: 0,
             config,
+            address_history: Vec::new(),
         };
         
         accounts.insert(account_id.to_string(), account.clone());
-        
-        info!("Created abstract account '{}' with Neo address: {}", account_id, account.address);
-        debug!("Account public key: {}", hex::encode(&account.public_key));
-        
-        Ok(serde_json::to_string(&account)?)
+        Ok(account)
     }
     
     /// Sign a transaction for an abstract account
+    ///
+    /// The signed hash is SHA-256 over the canonical JSON form of `transaction_data`, so
+    /// it does not depend on key order or whitespace in the submitted document.
     pub fn sign_transaction(&self, account_id: &str, transaction_data: &str) -> Result<String> {
-        let mut accounts = self.accounts.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let ledger = self.ledger(account_id)?;
+        let mut ledger = ledger.lock_or_recover();
+        let accounts = self.accounts.read_or_recover();
         
-        let account = accounts.get_mut(account_id)
-            .ok_or_else(|| anyhow!("Account '{}' not found", account_id))?;
+        let account = accounts.get(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
         
-        // Parse transaction data
+        // Parse and validate transaction data
         let tx_data: serde_json::Value = serde_json::from_str(transaction_data)?;
+        self.validate_transaction(&tx_data, account)?;
+        
+        // Neo N3 only accepts secp256r1 signatures, so refuse a key on any other curve
+        let key_id = format!("account_{}", account_id);
+        let key_metadata = self.crypto_service.get_key_metadata(&key_id)?;
+        if !matches!(key_metadata.key_type, crate::crypto::CryptoAlgorithm::Secp256r1) {
+            return Err(anyhow!("Account key '{}' is {:?}, but Neo N3 requires secp256r1", key_id, key_metadata.key_type));
+        }
+        let public_key = key_metadata.public_key
+            .ok_or_else(|| anyhow!("Account key '{}' has no public key", key_id))?;
         
         // Create transaction hash
-        let tx_hash = self.crypto_service.hash_sha256(transaction_data.as_bytes());
+        let tx_hash = self.transaction_hash(&tx_data)?;
         
         // Sign the transaction
-        let signature = self.crypto_service.sign_data(&format!("account_{}", account_id), &tx_hash)?;
+        let signature = self.crypto_service.sign_data(&key_id, &tx_hash)?;
         
-        // Update account nonce
-        account.nonce += 1;
+        let record = TransactionRecord {
+            hash: hex::encode(&tx_hash),
+            nonce: account.nonce,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs(),
+            recipient: tx_data["recipient"].as_str().map(str::to_string),
+            amount: tx_data.get("amount").cloned(),
+            script_hash: tx_data["script_hash"].as_str().map(str::to_string),
+        };
+        let address = account.address.clone();
+        drop(accounts);
+        
+        // Persist first: if the record cannot be stored, the nonce does not move and no
+        // signature is handed out. The ledger lock keeps the nonce from moving meanwhile.
+        self.record_transaction(&mut ledger, account_id, record)?;
+        let nonce = {
+            let mut accounts = self.accounts.write_or_recover();
+            let account = accounts.get_mut(account_id)
+                .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
+            account.nonce += 1;
+            account.nonce
+        };
+        drop(ledger);
+        
+        self.audit_log.record(AuditEvent::new("account", "transaction_signed", account_id)
+            .with_details(serde_json::json!({ "hash": hex::encode(&tx_hash), "nonce": nonce })));
         
         let signed_tx = serde_json::json!({
             "transaction": tx_data,
             "signature": hex::encode(&signature),
             "account_id": account_id,
-            "account_address": &account.address,
-            "nonce": account.nonce,
+            "account_address": &address,
+            "public_key": hex::encode(&public_key),
+            "nonce": nonce,
             "hash": hex::encode(&tx_hash),
             "timestamp": std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -147,16 +497,266 @@ impl AccountService {
                 .as_secs()
         });
         
-        debug!("Signed transaction for account '{}', nonce: {}", account_id, account.nonce);
+        debug!("Signed transaction for account '{}', nonce: {}", account_id, nonce);
         Ok(signed_tx.to_string())
     }
     
+    /// SHA-256 over the canonical JSON form of a transaction: the hash every signature
+    /// over it covers, whatever its key order or whitespace
+    fn transaction_hash(&self, tx_data: &serde_json::Value) -> Result<Vec<u8>> {
+        Ok(self.crypto_service.hash_sha256(&to_canonical_vec(tx_data)?))
+    }
+    
+    /// Check a `sign_transaction` result the way a Neo verifier would
+    ///
+    /// The public key's CheckSig verification script must hash to the script hash in
+    /// `account_address`, and the signature must verify under that key over the hash of
+    /// `transaction`, which `hash` must also match.
+    pub fn verify_signed_transaction(&self, signed_transaction: &str) -> Result<bool> {
+        let signed: serde_json::Value = serde_json::from_str(signed_transaction)?;
+        let field = |name: &str| -> Result<Vec<u8>> {
+            let value = signed[name].as_str()
+                .ok_or_else(|| anyhow!("Signed transaction is missing '{}'", name))?;
+            hex::decode(value).map_err(|_| anyhow!("Signed transaction field '{}' is not valid hex", name))
+        };
+        let public_key = field("public_key")?;
+        let signature = field("signature")?;
+        let tx_hash = self.transaction_hash(&signed["transaction"])?;
+        let address = signed["account_address"].as_str()
+            .ok_or_else(|| anyhow!("Signed transaction is missing 'account_address'"))?;
+        
+        if field("hash")? != tx_hash || self.generate_neo_address_from_public_key(&public_key)? != address {
+            return Ok(false);
+        }
+        
+        self.crypto_service.verify_with_public_key(
+            crate::crypto::CryptoAlgorithm::Secp256r1,
+            &public_key,
+            &tx_hash,
+            &signature,
+        )
+    }
+    
+    /// Signed transactions for an account, newest first. Only the most recent
+    /// `MAX_TRANSACTION_HISTORY` are kept here; older ones remain in storage segments.
+    pub fn get_transaction_history(&self, account_id: &str, limit: Option<usize>, offset: Option<usize>) -> Result<String> {
+        let ledger = self.ledger(account_id)?;
+        let mut ledger = ledger.lock_or_recover();
+        self.load_ledger(&mut ledger, account_id)?;
+        
+        let records = &ledger.records;
+        let mut page = paginate(records.iter().rev(), limit, offset, records.len());
+        page["account_id"] = serde_json::json!(account_id);
+        Ok(page.to_string())
+    }
+    
+    /// The ledger of an existing account, created empty on first use. The map lock is
+    /// only held for the lookup.
+    fn ledger(&self, account_id: &str) -> Result<Arc<Mutex<AccountLedger>>> {
+        if let Some(ledger) = self.ledgers.read_or_recover().get(account_id) {
+            return Ok(ledger.clone());
+        }
+        if !self.accounts.read_or_recover().contains_key(account_id) {
+            return Err(EnclaveError::NotFound(format!("Account '{}' not found", account_id)).into());
+        }
+        Ok(self.ledgers.write_or_recover()
+            .entry(account_id.to_string())
+            .or_default()
+            .clone())
+    }
+    
+    /// Storage key of one segment of an account's transaction history
+    fn history_segment_key(account_id: &str, segment: u64) -> String {
+        format!("account_tx_history_{}_{}", account_id, segment)
+    }
+    
+    /// Append a record to the newest segment, starting a new one when it is full, and
+    /// keep it in memory only once it is stored
+    fn record_transaction(&self, ledger: &mut AccountLedger, account_id: &str, record: TransactionRecord) -> Result<()> {
+        self.load_ledger(ledger, account_id)?;
+        
+        let (segment, tail) = if ledger.segment_len >= self.history_segment_size {
+            (ledger.segment + 1, 0)
+        } else {
+            (ledger.segment, ledger.segment_len)
+        };
+        let mut contents: Vec<&TransactionRecord> = ledger.records.iter().skip(ledger.records.len() - tail).collect();
+        contents.push(&record);
+        self.write_history_blob(&Self::history_segment_key(account_id, segment), &serde_json::to_vec(&contents)?)?;
+        
+        ledger.segment = segment;
+        ledger.segment_len = tail + 1;
+        ledger.records.push_back(record);
+        if ledger.records.len() > MAX_TRANSACTION_HISTORY {
+            ledger.records.pop_front();
+        }
+        Ok(())
+    }
+    
+    /// Read the newest segments of a persisted history into the ledger, once
+    fn load_ledger(&self, ledger: &mut AccountLedger, account_id: &str) -> Result<()> {
+        if ledger.loaded {
+            return Ok(());
+        }
+        
+        let segments = (0..).take_while(|&segment| self.storage_service.contains_key(&Self::history_segment_key(account_id, segment)))
+            .count() as u64;
+        let mut records = VecDeque::new();
+        let mut segment_len = 0;
+        let needed = MAX_TRANSACTION_HISTORY.div_ceil(self.history_segment_size) as u64 + 1;
+        for segment in segments.saturating_sub(needed)..segments {
+            let data = self.storage_service.retrieve_data(
+                &Self::history_segment_key(account_id, segment), TRANSACTION_HISTORY_KEY, SYSTEM_PRINCIPAL,
+            )?;
+            let segment_records: Vec<TransactionRecord> = serde_json::from_slice(&data)?;
+            segment_len = segment_records.len();
+            records.extend(segment_records);
+        }
+        while records.len() > MAX_TRANSACTION_HISTORY {
+            records.pop_front();
+        }
+        
+        *ledger = AccountLedger {
+            loaded: true,
+            records,
+            segment: segments.saturating_sub(1),
+            segment_len,
+        };
+        Ok(())
+    }
+    
+    /// Write `data` under `key`, replacing any existing blob
+    ///
+    /// History blobs are pinned so that quota eviction never drops account data.
+    fn write_history_blob(&self, key: &str, data: &[u8]) -> Result<()> {
+        if self.storage_service.contains_key(key) {
+            self.storage_service.update_data(key, data, TRANSACTION_HISTORY_KEY, true, SYSTEM_PRINCIPAL)?;
+        } else {
+            let options = StoreOptions { compress: true, pinned: true, ..StoreOptions::default() };
+            self.storage_service.store_data(key, data, TRANSACTION_HISTORY_KEY, SYSTEM_PRINCIPAL, options)?;
+        }
+        Ok(())
+    }
+    
+    /// Validate a transaction against the minimal Neo schema before signing
+    ///
+    /// Strictness follows the account's `security_level`: "low" only checks amounts and
+    /// any fields that are present, "standard" additionally requires a matching sender and
+    /// nonce, and "high" also requires a valid recipient and a known script hash.
+    pub fn validate_transaction(&self, tx: &serde_json::Value, account: &AbstractAccount) -> Result<()> {
+        let tx_object = tx.as_object()
+            .ok_or_else(|| EnclaveError::InvalidInput("Transaction must be a JSON object".into()))?;
+        
+        let security_level = account.config.security_level.to_lowercase();
+        let (require_sender_and_nonce, require_recipient_and_script) = match security_level.as_str() {
+            "low" => (false, false),
+            "standard" => (true, false),
+            "high" | "critical" => (true, true),
+            other => return Err(EnclaveError::InvalidInput(format!("Unknown account security level: {}", other)).into()),
+        };
+        
+        // Sender must be this account
+        match tx_object.get("sender") {
+            Some(sender) => {
+                let sender = sender.as_str()
+                    .ok_or_else(|| EnclaveError::InvalidInput("Transaction field 'sender' must be a string".into()))?;
+                if sender != account.address {
+                    return Err(EnclaveError::InvalidInput(format!(
+                        "Transaction field 'sender' ({}) does not match account address {}",
+                        sender, account.address
+                    )).into());
+                }
+            }
+            None if require_sender_and_nonce => {
+                return Err(EnclaveError::InvalidInput("Transaction field 'sender' is required".into()).into());
+            }
+            None => {}
+        }
+        
+        // Nonce must match the account's next nonce to prevent replays
+        match tx_object.get("nonce") {
+            Some(nonce) => {
+                let nonce = nonce.as_u64()
+                    .ok_or_else(|| EnclaveError::InvalidInput("Transaction field 'nonce' must be a non-negative integer".into()))?;
+                if nonce != account.nonce {
+                    return Err(EnclaveError::InvalidInput(format!(
+                        "Transaction field 'nonce' ({}) does not match account nonce {}",
+                        nonce, account.nonce
+                    )).into());
+                }
+            }
+            None if require_sender_and_nonce => {
+                return Err(EnclaveError::InvalidInput("Transaction field 'nonce' is required".into()).into());
+            }
+            None => {}
+        }
+        
+        // Recipient must be a well-formed address on the configured network
+        match tx_object.get("recipient") {
+            Some(recipient) => {
+                let recipient = recipient.as_str()
+                    .ok_or_else(|| EnclaveError::InvalidInput("Transaction field 'recipient' must be a string".into()))?;
+                if !self.validate_neo_address(recipient).unwrap_or(false) {
+                    return Err(EnclaveError::InvalidInput(format!("Transaction field 'recipient' is not a valid Neo address: {}", recipient)).into());
+                }
+            }
+            None if require_recipient_and_script => {
+                return Err(EnclaveError::InvalidInput("Transaction field 'recipient' is required".into()).into());
+            }
+            None => {}
+        }
+        
+        // Script hash must reference a known contract
+        match tx_object.get("script_hash") {
+            Some(script_hash) => {
+                let script_hash = script_hash.as_str()
+                    .ok_or_else(|| EnclaveError::InvalidInput("Transaction field 'script_hash' must be a string".into()))?;
+                let normalized = script_hash.trim_start_matches("0x").to_lowercase();
+                if !KNOWN_SCRIPT_HASHES.contains(&normalized.as_str()) {
+                    return Err(EnclaveError::InvalidInput(format!("Transaction field 'script_hash' references an unknown contract: {}", script_hash)).into());
+                }
+            }
+            None if require_recipient_and_script => {
+                return Err(EnclaveError::InvalidInput("Transaction field 'script_hash' is required".into()).into());
+            }
+            None => {}
+        }
+        
+        // Amounts must be non-negative finite numbers
+        if let Some(amount) = tx_object.get("amount") {
+            Self::validate_amount("amount", amount)?;
+        }
+        if let Some(fee) = tx_object.get("fee") {
+            Self::validate_amount("fee", fee)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Validate that a transaction amount field is a non-negative number or numeric string
+    fn validate_amount(field: &str, value: &serde_json::Value) -> Result<()> {
+        let amount = match value {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        }.ok_or_else(|| EnclaveError::InvalidInput(format!("Transaction field '{}' must be a number", field)))?;
+        
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(EnclaveError::InvalidInput(format!("Transaction field '{}' must be non-negative, got {}", field, amount)).into());
+        }
+        
+        Ok(())
+    }
+    
     /// Add a guardian to an abstract account
+    ///
+    /// `guardian_data` is `{"id": "...", "curve": "secp256r1", "public_key": "<hex>"}` with an
+    /// optional `permissions` array. The curve is one of secp256r1, secp256k1 or ed25519.
     pub fn add_guardian(&self, account_id: &str, guardian_data: &str) -> Result<String> {
-        let mut accounts = self.accounts.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut accounts = self.accounts.write_or_recover();
         
         let account = accounts.get_mut(account_id)
-            .ok_or_else(|| anyhow!("Account '{}' not found", account_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
         
         // Parse guardian data
         let guardian_info: serde_json::Value = serde_json::from_str(guardian_data)?;
@@ -170,6 +770,20 @@ impl AccountService {
         let public_key = hex::decode(public_key_hex)
             .map_err(|_| anyhow!("Invalid public key format"))?;
         
+        let curve_name = guardian_info["curve"].as_str()
+            .ok_or_else(|| anyhow!("Guardian curve is required"))?;
+        let curve = CryptoAlgorithm::from_name(curve_name)
+            .ok_or_else(|| anyhow!("Unknown guardian curve: {}", curve_name))?;
+        let public_key = guardian_public_key(&curve, &public_key)?;
+        
+        if account.guardians.iter().any(|g| g.id == guardian_id) {
+            return Err(EnclaveError::AlreadyExists(format!("Guardian '{}' already exists on account '{}'", guardian_id, account_id)).into());
+        }
+        // One key behind two guardian entries would count twice towards the threshold
+        if account.guardians.iter().any(|g| constant_time_eq(&g.public_key, &public_key)) {
+            return Err(EnclaveError::AlreadyExists(format!("Guardian key is already registered on account '{}'", account_id)).into());
+        }
+        
         let permissions = guardian_info["permissions"].as_array()
             .map(|arr| arr.iter()
                 .filter_map(|v| v.as_str().map(|s| s.to_string()))
@@ -178,6 +792,7 @@ impl AccountService {
         
         let guardian = Guardian {
             id: guardian_id.to_string(),
+            curve,
             public_key,
             permissions,
             added_at: std::time::SystemTime::now()
@@ -201,12 +816,207 @@ impl AccountService {
         Ok(result.to_string())
     }
     
+    /// Remove a guardian from an abstract account
+    ///
+    /// `guardian_signatures` is a JSON array of `{"guardian_id": "...", "signature": "<hex>"}`
+    /// objects, each signing `guardian_removal_message(account_id, guardian_id, nonce)`; at
+    /// least `guardian_threshold` guardians must approve.
+    pub fn remove_guardian(&self, account_id: &str, guardian_id: &str, guardian_signatures: &str) -> Result<String> {
+        // The nonce only moves under the account's ledger lock
+        let ledger = self.ledger(account_id)?;
+        let _ledger = ledger.lock_or_recover();
+        let mut accounts = self.accounts.write_or_recover();
+        
+        let account = accounts.get_mut(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
+        
+        let position = account.guardians.iter()
+            .position(|g| g.id == guardian_id)
+            .ok_or_else(|| anyhow!("Guardian '{}' not found on account '{}'", guardian_id, account_id))?;
+        
+        // Removing the guardian must not leave fewer guardians than the approval threshold
+        if account.guardians.len() - 1 < account.config.guardian_threshold {
+            return Err(anyhow!(
+                "Cannot remove guardian '{}': account '{}' requires at least {} guardians",
+                guardian_id, account_id, account.config.guardian_threshold
+            ));
+        }
+        
+        let message = Self::guardian_removal_message(account_id, guardian_id, account.nonce);
+        let approved_by = self.verify_guardian_approvals(account, &message, guardian_signatures)?;
+        
+        account.guardians.remove(position);
+        // Bump the nonce so the same guardian signatures cannot be replayed
+        account.nonce += 1;
+        
+        let result = serde_json::json!({
+            "account_id": account_id,
+            "guardian_removed": guardian_id,
+            "total_guardians": account.guardians.len(),
+            "approved_by": approved_by,
+            "nonce": account.nonce,
+            "timestamp": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs()
+        });
+        drop(accounts);
+        
+        self.audit_log.record(AuditEvent::new("account", "guardian_removed", account_id)
+            .with_details(serde_json::json!({
+                "guardian_id": guardian_id,
+                "approved_by": result["approved_by"],
+            })));
+        info!("Removed guardian '{}' from account '{}'", guardian_id, account_id);
+        Ok(result.to_string())
+    }
+    
+    /// Message guardians sign to approve removing `guardian_id` from `account_id`
+    pub fn guardian_removal_message(account_id: &str, guardian_id: &str, nonce: u64) -> Vec<u8> {
+        format!("remove_guardian:{}:{}:{}", account_id, guardian_id, nonce).into_bytes()
+    }
+    
+    /// Message guardians sign to approve a recovery of `account_id`
+    pub fn recovery_message(account_id: &str, nonce: u64) -> Vec<u8> {
+        format!("recover:{}:{}", account_id, nonce).into_bytes()
+    }
+    
+    /// Check guardian signatures over `message` and return the ids of the approving
+    /// guardians. Fails unless at least `guardian_threshold` distinct guardians signed.
+    fn verify_guardian_approvals(
+        &self,
+        account: &AbstractAccount,
+        message: &[u8],
+        guardian_signatures: &str,
+    ) -> Result<Vec<String>> {
+        let signatures: Vec<serde_json::Value> = serde_json::from_str(guardian_signatures)
+            .map_err(|e| anyhow!("Invalid guardian signatures: {}", e))?;
+        
+        let threshold = account.config.guardian_threshold.max(1);
+        if account.guardians.len() < threshold {
+            return Err(anyhow!(
+                "Account '{}' has {} guardians but approval requires {}",
+                account.id, account.guardians.len(), threshold
+            ));
+        }
+        
+        let mut approved_by: Vec<String> = Vec::new();
+        let mut approving_keys: Vec<&[u8]> = Vec::new();
+        
+        for entry in &signatures {
+            let guardian_id = entry["guardian_id"].as_str()
+                .ok_or_else(|| anyhow!("Guardian signature is missing 'guardian_id'"))?;
+            let signature_hex = entry["signature"].as_str()
+                .ok_or_else(|| anyhow!("Guardian signature for '{}' is missing 'signature'", guardian_id))?;
+            
+            if approved_by.iter().any(|id| id == guardian_id) {
+                return Err(anyhow!("Duplicate signature from guardian '{}'", guardian_id));
+            }
+            
+            let guardian = account.guardians.iter()
+                .find(|g| g.id == guardian_id)
+                .ok_or_else(|| anyhow!("Guardian '{}' not found on account '{}'", guardian_id, account.id))?;
+            
+            let signature = hex::decode(signature_hex)
+                .map_err(|_| anyhow!("Invalid signature format from guardian '{}'", guardian_id))?;
+            
+            if approving_keys.iter().any(|key| constant_time_eq(key, &guardian.public_key)) {
+                return Err(anyhow!("Guardian '{}' shares its key with another approving guardian", guardian_id));
+            }
+            
+            if !self.crypto_service.verify_with_public_key(guardian.curve.clone(), &guardian.public_key, message, &signature)? {
+                return Err(anyhow!("Invalid approval signature from guardian '{}'", guardian_id));
+            }
+            
+            approved_by.push(guardian_id.to_string());
+            approving_keys.push(&guardian.public_key);
+        }
+        
+        if approved_by.len() < threshold {
+            return Err(anyhow!(
+                "Approval requires {} guardian signatures, got {}",
+                threshold, approved_by.len()
+            ));
+        }
+        
+        Ok(approved_by)
+    }
+    
+    /// Recover an abstract account with guardian consent by rotating its enclave-held key
+    ///
+    /// `guardian_signatures` is a JSON array of `{"guardian_id": "...", "signature": "<hex>"}`
+    /// objects, each signing `recovery_message(account_id, nonce)`. The `account_{id}` key is
+    /// rotated, so the compromised key can no longer sign for the account, and the account
+    /// is rebound to the address of the new key.
+    ///
+    /// Unlike the originally proposed `recover_account(account_id, new_public_key, ...)`,
+    /// the caller does not supply the new key. `sign_transaction` signs with the enclave's
+    /// `account_{id}` key, so binding the account to an outside public key would leave it
+    /// with an address the enclave cannot sign for. The new public key and address are
+    /// returned instead.
+    pub fn recover_account(&self, account_id: &str, guardian_signatures: &str) -> Result<String> {
+        // The nonce only moves under the account's ledger lock
+        let ledger = self.ledger(account_id)?;
+        let _ledger = ledger.lock_or_recover();
+        let mut accounts = self.accounts.write_or_recover();
+        
+        let account = accounts.get_mut(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
+        
+        let message = Self::recovery_message(account_id, account.nonce);
+        let approved_by = self.verify_guardian_approvals(account, &message, guardian_signatures)?;
+        
+        let key_metadata = self.crypto_service.rotate_key(&format!("account_{}", account_id))?;
+        let new_public_key = key_metadata.public_key
+            .ok_or_else(|| anyhow!("Rotated key for account '{}' has no public key", account_id))?;
+        let new_address = self.generate_neo_address_from_public_key(&new_public_key)?;
+        
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        
+        let old_address = std::mem::replace(&mut account.address, new_address);
+        let old_public_key = std::mem::replace(&mut account.public_key, new_public_key);
+        account.address_history.push(AddressRecord {
+            address: old_address.clone(),
+            public_key: old_public_key,
+            replaced_at: now,
+            approved_by: approved_by.clone(),
+        });
+        
+        // Bump the nonce so the same guardian signatures cannot be replayed
+        account.nonce += 1;
+        
+        let result = serde_json::json!({
+            "account_id": account_id,
+            "recovered": true,
+            "old_address": old_address,
+            "new_address": &account.address,
+            "new_public_key": hex::encode(&account.public_key),
+            "approved_by": approved_by,
+            "nonce": account.nonce,
+            "timestamp": now
+        });
+        
+        drop(accounts);
+        
+        self.audit_log.record(AuditEvent::new("account", "account_recovered", account_id)
+            .with_details(serde_json::json!({
+                "old_address": result["old_address"],
+                "new_address": result["new_address"],
+                "approved_by": result["approved_by"],
+            })));
+        warn!("Recovered account '{}': {} -> {}", account_id,
+              redact(result["old_address"].as_str().unwrap_or_default()),
+              redact(result["new_address"].as_str().unwrap_or_default()));
+        Ok(result.to_string())
+    }
+    
     /// Get account information
     pub fn get_account_info(&self, account_id: &str) -> Result<String> {
-        let accounts = self.accounts.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let accounts = self.accounts.read_or_recover();
         
         let account = accounts.get(account_id)
-            .ok_or_else(|| anyhow!("Account '{}' not found", account_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Account '{}' not found", account_id)))?;
         
         // Return account info without sensitive data
         let safe_account = serde_json::json!({
@@ -215,92 +1025,231 @@ impl AccountService {
             "public_key": hex::encode(&account.public_key),
             "guardians": account.guardians.iter().map(|g| serde_json::json!({
                 "id": &g.id,
+                "curve": g.curve.name(),
                 "public_key": hex::encode(&g.public_key),
                 "permissions": &g.permissions,
                 "added_at": g.added_at
             })).collect::<Vec<_>>(),
             "created_at": account.created_at,
             "nonce": account.nonce,
-            "config": &account.config
+            "config": &account.config,
+            "address_history": account.address_history.iter().map(|r| serde_json::json!({
+                "address": &r.address,
+                "public_key": hex::encode(&r.public_key),
+                "replaced_at": r.replaced_at,
+                "approved_by": &r.approved_by
+            })).collect::<Vec<_>>()
         });
         
         Ok(safe_account.to_string())
     }
     
-    /// List all accounts
-    pub fn list_accounts(&self) -> Result<Vec<String>> {
-        let accounts = self.accounts.read().map_err(|_| anyhow!("Lock poisoned"))?;
-        Ok(accounts.keys().cloned().collect())
-    }
-    
-    /// Generate production-grade ECDSA P-256 key pair using SGX
-    fn generate_neo_keypair(&self) -> Result<([u8; 32], [u8; 64])> {
-        let mut private_key = [0u8; 32];
-        let mut public_key = [0u8; 64]; // Uncompressed: 32 bytes x + 32 bytes y
+    /// Create an m-of-n multisig account from secp256r1 public keys (compressed or
+    /// uncompressed). Duplicate keys are collapsed before `threshold` is checked.
+    pub fn create_multisig_account(&self, account_id: &str, public_keys: Vec<Vec<u8>>, threshold: usize) -> Result<String> {
+        if self.accounts.read_or_recover().contains_key(account_id) {
+            return Err(EnclaveError::AlreadyExists(format!("Account '{}' already exists", account_id)).into());
+        }
+        let mut multisig_accounts = self.multisig_accounts.write_or_recover();
+        if multisig_accounts.contains_key(account_id) {
+            return Err(EnclaveError::AlreadyExists(format!("Account '{}' already exists", account_id)).into());
+        }
         
-        unsafe {
-            let result = occlum_generate_ecdsa_keypair(
-                private_key.as_mut_ptr(),
-                public_key.as_mut_ptr(),
-            );
-            
-            if result != 0 {
-                return Err(anyhow!("Failed to generate ECDSA key pair: SGX error {}", result));
-            }
+        // Neo orders keys by curve point (x, then y), which is the uncompressed encoding order
+        let mut points = Vec::with_capacity(public_keys.len());
+        for (index, key) in public_keys.iter().enumerate() {
+            let point = p256::PublicKey::from_sec1_bytes(key)
+                .map_err(|_| anyhow!("Public key {} is not a valid secp256r1 key", index))?;
+            points.push(point);
+        }
+        points.sort_by(|a, b| a.to_encoded_point(false).as_bytes().cmp(b.to_encoded_point(false).as_bytes()));
+        points.dedup();
+        
+        if points.is_empty() || points.len() > MAX_MULTISIG_KEYS {
+            return Err(anyhow!("Multisig accounts need between 1 and {} distinct keys", MAX_MULTISIG_KEYS));
+        }
+        if threshold == 0 || threshold > points.len() {
+            return Err(anyhow!("Threshold must be between 1 and {} distinct keys, got {}", points.len(), threshold));
         }
         
-        debug!("Generated ECDSA P-256 key pair using SGX");
-        Ok((private_key, public_key))
+        let sorted_keys: Vec<Vec<u8>> = points.iter()
+            .map(|point| point.to_encoded_point(true).as_bytes().to_vec())
+            .collect();
+        let verification_script = multisig_verification_script(threshold, &sorted_keys);
+        let address_bytes = self.generate_neo_address_sgx(&verification_script)?;
+        let address = self.encode_neo_address_base58(&address_bytes)?;
+        
+        let account = MultisigAccount {
+            id: account_id.to_string(),
+            address,
+            public_keys: sorted_keys,
+            threshold,
+            verification_script,
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs(),
+            pending: HashMap::new(),
+        };
+        
+        info!("Created {}-of-{} multisig account '{}' with Neo address: {}",
+              threshold, account.public_keys.len(), account_id, redact(&account.address));
+        
+        let response = serde_json::json!({
+            "id": account.id,
+            "address": account.address,
+            "threshold": account.threshold,
+            "public_keys": account.public_keys.iter().map(hex::encode).collect::<Vec<_>>(),
+            "verification_script": hex::encode(&account.verification_script),
+        });
+        multisig_accounts.insert(account_id.to_string(), account);
+        drop(multisig_accounts);
+        
+        self.audit_log.record(AuditEvent::new("account", "multisig_account_created", account_id)
+            .with_details(serde_json::json!({
+                "address": response["address"],
+                "threshold": threshold,
+                "public_keys": response["public_keys"],
+            })));
+        
+        Ok(response.to_string())
     }
     
-    /// Generate proper Neo address from public key using cryptographic functions
-    fn generate_neo_address_from_public_key(&self, public_key: &[u8]) -> Result<String> {
-        if public_key.len() != 64 {
-            return Err(anyhow!("Invalid public key length: expected 64 bytes, got {}", public_key.len()));
+    /// Record one participant's signature for a multisig transaction. The signature is
+    /// secp256r1 ECDSA over SHA256 of the transaction hash, as produced by `sign_transaction`;
+    /// the hash is taken over the canonical form, so any encoding of the same transaction
+    /// collects towards the same pending entry.
+    pub fn collect_multisig_signature(
+        &self,
+        account_id: &str,
+        transaction_data: &str,
+        public_key: &[u8],
+        signature: &[u8],
+    ) -> Result<String> {
+        let mut multisig_accounts = self.multisig_accounts.write_or_recover();
+        let account = multisig_accounts.get_mut(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Multisig account '{}' not found", account_id)))?;
+        
+        let compressed_key = p256::PublicKey::from_sec1_bytes(public_key)
+            .map_err(|_| anyhow!("Invalid secp256r1 public key"))?
+            .to_encoded_point(true);
+        let key_index = account.public_keys.iter()
+            .position(|key| key.as_slice() == compressed_key.as_bytes())
+            .ok_or_else(|| anyhow!("Public key is not a member of multisig account '{}'", account_id))?;
+        
+        let tx_hash = self.transaction_hash(&serde_json::from_str(transaction_data)?)?;
+        if !self.crypto_service.verify_with_public_key(
+            crate::crypto::CryptoAlgorithm::Secp256r1,
+            compressed_key.as_bytes(),
+            &tx_hash,
+            signature,
+        )? {
+            return Err(anyhow!("Invalid signature from multisig key {}", key_index));
         }
         
-        // Convert uncompressed public key to compressed format for Neo
-        let compressed_public_key = self.compress_public_key(public_key)?;
+        let tx_hash_hex = hex::encode(&tx_hash);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let pending = account.pending.entry(tx_hash_hex.clone())
+            .or_insert_with(|| PendingMultisigTransaction {
+                transaction: transaction_data.to_string(),
+                signatures: BTreeMap::new(),
+                created_at: now,
+            });
+        pending.signatures.insert(key_index, signature.to_vec());
         
-        // Generate Neo address using SGX cryptographic functions
-        let neo_address = self.generate_neo_address_sgx(&compressed_public_key)?;
+        debug!("Collected multisig signature {} of {} for account '{}' transaction {}",
+               pending.signatures.len(), account.threshold, account_id, tx_hash_hex);
         
-        // Convert to Base58 format (Neo standard)
-        let base58_address = self.encode_neo_address_base58(&neo_address)?;
-        
-        Ok(base58_address)
+        Ok(serde_json::json!({
+            "account_id": account_id,
+            "hash": tx_hash_hex,
+            "collected": pending.signatures.len(),
+            "threshold": account.threshold,
+            "ready": pending.signatures.len() >= account.threshold,
+        }).to_string())
     }
     
-    /// Compress uncompressed public key to compressed format
-    fn compress_public_key(&self, uncompressed_key: &[u8]) -> Result<[u8; 33]> {
-        if uncompressed_key.len() != 64 {
-            return Err(anyhow!("Invalid uncompressed public key length"));
+    /// Assemble the witness for a multisig transaction once `threshold` signatures have
+    /// been collected. Signatures are pushed in key order, as CheckMultisig requires.
+    pub fn finalize_multisig_transaction(&self, account_id: &str, transaction_data: &str) -> Result<String> {
+        let mut multisig_accounts = self.multisig_accounts.write_or_recover();
+        let account = multisig_accounts.get_mut(account_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Multisig account '{}' not found", account_id)))?;
+        
+        let tx_hash_hex = hex::encode(self.transaction_hash(&serde_json::from_str(transaction_data)?)?);
+        let collected = account.pending.get(&tx_hash_hex)
+            .map_or(0, |pending| pending.signatures.len());
+        if collected < account.threshold {
+            return Err(anyhow!(
+                "Multisig transaction needs {} signatures, got {}", account.threshold, collected
+            ));
         }
         
-        let mut compressed = [0u8; 33];
+        let pending = account.pending.remove(&tx_hash_hex)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Multisig transaction {} not found", tx_hash_hex)))?;
         
-        // Extract x and y coordinates
-        let x_bytes = &uncompressed_key[0..32];
-        let y_bytes = &uncompressed_key[32..64];
+        let mut invocation_script = Vec::with_capacity(account.threshold * 66);
+        for signature in pending.signatures.values().take(account.threshold) {
+            invocation_script.push(OP_PUSHDATA1);
+            invocation_script.push(signature.len() as u8);
+            invocation_script.extend_from_slice(signature);
+        }
         
-        // Determine compression prefix based on y coordinate parity
-        let y_last_byte = y_bytes[31];
-        compressed[0] = if y_last_byte % 2 == 0 { 0x02 } else { 0x03 };
+        info!("Finalized multisig transaction {} for account '{}'", tx_hash_hex, account_id);
+        
+        let signers: Vec<usize> = pending.signatures.keys().take(account.threshold).copied().collect();
+        self.audit_log.record(AuditEvent::new("account", "multisig_transaction_finalized", account_id)
+            .with_details(serde_json::json!({ "hash": tx_hash_hex, "signers": signers })));
+        
+        Ok(serde_json::json!({
+            "transaction": serde_json::from_str::<serde_json::Value>(&pending.transaction)
+                .unwrap_or(serde_json::Value::String(pending.transaction.clone())),
+            "account_id": account_id,
+            "account_address": account.address,
+            "hash": tx_hash_hex,
+            "signers": signers,
+            "witness": {
+                "invocation": hex::encode(&invocation_script),
+                "verification": hex::encode(&account.verification_script),
+            },
+        }).to_string())
+    }
+    
+    /// List all accounts
+    pub fn list_accounts(&self) -> Result<Vec<String>> {
+        let accounts = self.accounts.read_or_recover();
+        Ok(accounts.keys().cloned().collect())
+    }
+    
+    /// Derive the Neo address of a single-signature secp256r1 account
+    ///
+    /// Accepts compressed (33 bytes), uncompressed (65 bytes) or raw `x || y` (64 bytes) keys.
+    /// The key is decoded on the curve and re-encoded in compressed form, so an invalid
+    /// point is rejected rather than silently producing an unspendable address.
+    fn generate_neo_address_from_public_key(&self, public_key: &[u8]) -> Result<String> {
+        let compressed_public_key = crate::crypto::compress_public_key(
+            &crate::crypto::CryptoAlgorithm::Secp256r1,
+            public_key,
+        )?;
         
-        // Copy x coordinate
-        compressed[1..33].copy_from_slice(x_bytes);
+        // The address commits to the verification script, not to the bare key
+        let verification_script = single_sig_verification_script(&compressed_public_key);
+        let neo_address = self.generate_neo_address_sgx(&verification_script)?;
         
-        Ok(compressed)
+        // Convert to Base58 format (Neo standard)
+        self.encode_neo_address_base58(&neo_address)
     }
     
     /// Generate Neo address using SGX cryptographic functions
-    fn generate_neo_address_sgx(&self, compressed_public_key: &[u8; 33]) -> Result<[u8; 25]> {
-        // Step 1: SHA256 hash of the public key
+    /// `hash_input` is the account's verification script
+    fn generate_neo_address_sgx(&self, hash_input: &[u8]) -> Result<[u8; 25]> {
+        // Step 1: SHA256 hash of the input
         let mut sha256_hash = [0u8; 32];
         unsafe {
             let result = occlum_sha256(
-                compressed_public_key.as_ptr(),
-                33,
+                hash_input.as_ptr(),
+                hash_input.len(),
                 sha256_hash.as_mut_ptr(),
             );
             
@@ -323,9 +1272,9 @@ impl AccountService {
             }
         }
         
-        // Step 3: Add Neo version byte (0x17 for Neo mainnet)
+        // Step 3: Add Neo version byte for the configured network
         let mut versioned_hash = [0u8; 21];
-        versioned_hash[0] = 0x17; // Neo mainnet version byte
+        versioned_hash[0] = self.address_version;
         versioned_hash[1..21].copy_from_slice(&ripemd160_hash);
         
         // Step 4: Calculate checksum (first 4 bytes of SHA256(SHA256(versioned_hash)))
@@ -365,137 +1314,554 @@ impl AccountService {
     
     /// Encode Neo address to Base58 format
     fn encode_neo_address_base58(&self, address_bytes: &[u8; 25]) -> Result<String> {
-        // Base58 alphabet used by Bitcoin and Neo
-        const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
-        
-        // Convert bytes to big integer
-        let mut num = num_bigint::BigUint::from_bytes_be(address_bytes);
-        let base = num_bigint::BigUint::from(58u8);
-        let zero = num_bigint::BigUint::from(0u8);
-        
-        let mut result = Vec::new();
-        
-        // Convert to base58
-        while num > zero {
-            let remainder = &num % &base;
-            let quotient = &num / &base;
-            let remainder_u8 = remainder.to_bytes_be()[0];
-            result.push(BASE58_ALPHABET[remainder_u8 as usize]);
-            num = quotient;
-        }
-        
-        // Add leading '1's for leading zero bytes
-        for &byte in address_bytes.iter() {
-            if byte == 0 {
-                result.push(b'1');
-            } else {
-                break;
-            }
-        }
-        
-        // Reverse the result (since we built it backwards)
-        result.reverse();
-        
-        // Convert to string
-        String::from_utf8(result).map_err(|e| anyhow!("Failed to convert to UTF8: {}", e))
+        Ok(base58::encode(address_bytes))
     }
     
-    /// Validate Neo address format and checksum
+    /// Validate Neo address format and checksum for the configured network
     pub fn validate_neo_address(&self, address: &str) -> Result<bool> {
-        if address.is_empty() {
-            return Ok(false);
+        Ok(self.decode_neo_address(address)
+            .map_or(false, |decoded| decoded[0] == self.address_version))
+    }
+    
+    /// Report which Neo generation an address belongs to based on its version byte:
+    /// "n3" or "legacy". N3 mainnet and testnet share a version byte, so the network an
+    /// N3 address is used on cannot be told from the address.
+    pub fn network_of_address(&self, address: &str) -> Option<String> {
+        let decoded = self.decode_neo_address(address)?;
+        
+        match decoded[0] {
+            NEO_MAINNET_ADDRESS_VERSION => Some("n3".to_string()),
+            NEO_LEGACY_ADDRESS_VERSION => Some("legacy".to_string()),
+            _ => None,
         }
+    }
+    
+    /// Decode a Base58Check Neo address, returning `None` if the length or checksum is invalid
+    fn decode_neo_address(&self, address: &str) -> Option<[u8; 21]> {
+        let payload = base58::decode_check(address).ok()?;
+        payload.try_into().ok()
+    }
+    
+    /// Generate address from existing public key (for guardians or external accounts)
+    pub fn address_from_public_key(&self, public_key_hex: &str) -> Result<String> {
+        let public_key_bytes = hex::decode(public_key_hex)
+            .map_err(|_| anyhow!("Invalid public key hex format"))?;
         
-        // Decode Base58
-        let decoded = self.decode_base58(address)?;
-        
-        if decoded.len() != 25 {
-            return Ok(false);
+        if !matches!(public_key_bytes.len(), 33 | 64 | 65) {
+            return Err(anyhow!("Invalid public key length: expected 33, 64, or 65 bytes, got {}", public_key_bytes.len()));
         }
-        
-        // Check version byte
-        if decoded[0] != 0x17 {
-            return Ok(false);
+        self.generate_neo_address_from_public_key(&public_key_bytes)
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{core_services, test_config};
+
+    async fn account_service(dir: &std::path::Path) -> AccountService {
+        let config = test_config(dir);
+        let (storage, audit, crypto) = core_services(&config).await;
+        AccountService::new(&config, crypto, storage, audit).await.unwrap()
+    }
+
+    /// Key pair of the Neo wallet test vectors; its Legacy address is
+    /// AXaXZjZGA3qhQRTCsyG5uFKr9HeShgVhTF
+    const TEST_PUBLIC_KEY: &str = "035a928f201639204e06b4368b1a93365462a8ebbff0b8818151b74faab3a2b61a";
+    const TEST_N3_ADDRESS: &str = "NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV";
+
+    #[tokio::test]
+    async fn derives_n3_address_for_known_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+
+        assert_eq!(service.address_from_public_key(TEST_PUBLIC_KEY).unwrap(), TEST_N3_ADDRESS);
+        assert!(service.validate_neo_address(TEST_N3_ADDRESS).unwrap());
+        assert_eq!(service.network_of_address(TEST_N3_ADDRESS).as_deref(), Some("n3"));
+    }
+
+    /// Uncompressed form of TEST_PUBLIC_KEY
+    const TEST_PUBLIC_KEY_UNCOMPRESSED: &str = concat!(
+        "045a928f201639204e06b4368b1a93365462a8ebbff0b8818151b74faab3a2b61a",
+        "35dfabcb79ac492a2a88588d2f2e73f045cd8af58059282e09d693dc340e113f",
+    );
+
+    #[tokio::test]
+    async fn every_public_key_encoding_derives_the_same_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        let curve = CryptoAlgorithm::Secp256r1;
+        let compressed = hex::decode(TEST_PUBLIC_KEY).unwrap();
+        let uncompressed = hex::decode(TEST_PUBLIC_KEY_UNCOMPRESSED).unwrap();
+
+        assert_eq!(crate::crypto::decompress_public_key(&curve, &compressed).unwrap(), uncompressed);
+        assert_eq!(crate::crypto::compress_public_key(&curve, &uncompressed).unwrap(), compressed);
+        assert_eq!(crate::crypto::compress_public_key(&curve, &uncompressed[1..]).unwrap(), compressed);
+
+        for encoding in [TEST_PUBLIC_KEY, TEST_PUBLIC_KEY_UNCOMPRESSED, &TEST_PUBLIC_KEY_UNCOMPRESSED[2..]] {
+            assert_eq!(service.address_from_public_key(encoding).unwrap(), TEST_N3_ADDRESS);
         }
-        
-        // Verify checksum
-        let payload = &decoded[0..21];
-        let checksum = &decoded[21..25];
-        
-        // Calculate expected checksum
-        let mut first_sha = [0u8; 32];
-        unsafe {
-            let result = occlum_sha256(payload.as_ptr(), 21, first_sha.as_mut_ptr());
-            if result != 0 {
-                return Err(anyhow!("Failed to compute checksum verification SHA256: SGX error {}", result));
-            }
+
+        // Flipping the parity prefix or a y bit leaves a different point, or none at all
+        let mut other_parity = compressed.clone();
+        other_parity[0] = 0x02;
+        assert_ne!(service.address_from_public_key(&hex::encode(&other_parity)).unwrap(), TEST_N3_ADDRESS);
+        let mut off_curve = uncompressed.clone();
+        off_curve[64] ^= 1;
+        assert!(service.address_from_public_key(&hex::encode(&off_curve)).is_err());
+        assert!(service.address_from_public_key(&hex::encode(&compressed[..32])).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_legacy_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+
+        let legacy = "AXaXZjZGA3qhQRTCsyG5uFKr9HeShgVhTF";
+        assert!(!service.validate_neo_address(legacy).unwrap());
+        assert_eq!(service.network_of_address(legacy).as_deref(), Some("legacy"));
+        assert_eq!(service.network_of_address("not an address"), None);
+    }
+
+    /// Create `account_id` with a 2-of-n guardian threshold and one secp256r1 guardian per id
+    fn account_with_guardians(service: &AccountService, account_id: &str, guardian_ids: &[&str]) {
+        service.create_account(account_id, r#"{"require_guardian_approval": true, "guardian_threshold": 2,
+            "max_daily_transactions": 100, "security_level": "standard"}"#).unwrap();
+        for id in guardian_ids {
+            let metadata = service.crypto_service.generate_key(
+                &format!("guardian_{}", id), CryptoAlgorithm::Secp256r1, vec!["Sign".to_string()], false, "guardian",
+            ).unwrap();
+            service.add_guardian(account_id, &serde_json::json!({
+                "id": id,
+                "curve": "secp256r1",
+                "public_key": hex::encode(metadata.public_key.unwrap()),
+            }).to_string()).unwrap();
         }
-        
-        let mut expected_checksum = [0u8; 32];
-        unsafe {
-            let result = occlum_sha256(first_sha.as_ptr(), 32, expected_checksum.as_mut_ptr());
-            if result != 0 {
-                return Err(anyhow!("Failed to compute checksum verification SHA256: SGX error {}", result));
-            }
+    }
+
+    fn approvals(service: &AccountService, guardian_ids: &[&str], message: &[u8]) -> String {
+        serde_json::Value::Array(guardian_ids.iter().map(|id| serde_json::json!({
+            "guardian_id": id,
+            "signature": hex::encode(service.crypto_service.sign_data(&format!("guardian_{}", id), message).unwrap()),
+        })).collect()).to_string()
+    }
+
+    #[tokio::test]
+    async fn recovery_rotates_the_account_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        account_with_guardians(&service, "alice", &["g1", "g2"]);
+        let before: serde_json::Value = serde_json::from_str(&service.get_account_info("alice").unwrap()).unwrap();
+
+        let message = AccountService::recovery_message("alice", 0);
+        let signatures = approvals(&service, &["g1", "g2"], &message);
+        let recovered: serde_json::Value = serde_json::from_str(&service.recover_account("alice", &signatures).unwrap()).unwrap();
+
+        let after: serde_json::Value = serde_json::from_str(&service.get_account_info("alice").unwrap()).unwrap();
+        assert_eq!(recovered["new_public_key"], after["public_key"]);
+        assert_eq!(recovered["new_address"], after["address"]);
+        assert_ne!(before["public_key"], after["public_key"]);
+        assert_ne!(before["address"], after["address"]);
+        assert_eq!(after["address_history"][0]["address"], before["address"]);
+
+        // The account key now signs with the new key only
+        let old_key = hex::decode(before["public_key"].as_str().unwrap()).unwrap();
+        let new_key = hex::decode(after["public_key"].as_str().unwrap()).unwrap();
+        let signature = service.crypto_service.sign_data("account_alice", b"payload").unwrap();
+        assert!(service.crypto_service.verify_with_public_key(CryptoAlgorithm::Secp256r1, &new_key, b"payload", &signature).unwrap());
+        assert!(!service.crypto_service.verify_with_public_key(CryptoAlgorithm::Secp256r1, &old_key, b"payload", &signature).unwrap());
+
+        // The approvals were for nonce 0 and cannot be replayed
+        assert!(service.recover_account("alice", &signatures).is_err());
+    }
+
+    #[tokio::test]
+    async fn recovery_requires_threshold_of_distinct_guardians() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        account_with_guardians(&service, "bob", &["g1", "g2"]);
+        let message = AccountService::recovery_message("bob", 0);
+
+        let single = approvals(&service, &["g1"], &message);
+        assert!(service.recover_account("bob", &single).is_err());
+        let duplicate = approvals(&service, &["g1", "g1"], &message);
+        assert!(service.recover_account("bob", &duplicate).is_err());
+        let wrong_message = approvals(&service, &["g1", "g2"], b"recover:bob:1");
+        assert!(service.recover_account("bob", &wrong_message).is_err());
+    }
+
+    #[tokio::test]
+    async fn guardian_removal_requires_approval() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        account_with_guardians(&service, "carol", &["g1", "g2", "g3"]);
+
+        assert!(service.remove_guardian("carol", "g3", "[]").is_err());
+        let message = AccountService::guardian_removal_message("carol", "g3", 0);
+        assert!(service.remove_guardian("carol", "g3", &approvals(&service, &["g1"], &message)).is_err());
+
+        service.remove_guardian("carol", "g3", &approvals(&service, &["g1", "g2"], &message)).unwrap();
+        let info: serde_json::Value = serde_json::from_str(&service.get_account_info("carol").unwrap()).unwrap();
+        assert_eq!(info["guardians"].as_array().unwrap().len(), 2);
+        assert_eq!(info["guardians"][0]["curve"], "secp256r1");
+    }
+
+    #[tokio::test]
+    async fn guardians_need_an_explicit_valid_curve() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        service.create_account("dave", "{}").unwrap();
+
+        let key = "035a928f201639204e06b4368b1a93365462a8ebbff0b8818151b74faab3a2b61a";
+        let missing = serde_json::json!({ "id": "g1", "public_key": key }).to_string();
+        assert!(service.add_guardian("dave", &missing).is_err());
+        let ed25519 = serde_json::json!({ "id": "g1", "curve": "ed25519", "public_key": key }).to_string();
+        assert!(service.add_guardian("dave", &ed25519).is_err());
+        let p256 = serde_json::json!({ "id": "g1", "curve": "secp256r1", "public_key": key }).to_string();
+        service.add_guardian("dave", &p256).unwrap();
+        assert!(service.add_guardian("dave", &p256).is_err());
+        // The same key under another id would let one signer approve twice
+        let same_key = serde_json::json!({ "id": "g2", "curve": "secp256r1", "public_key": key }).to_string();
+        assert!(service.add_guardian("dave", &same_key).is_err());
+    }
+
+    const LOW_SECURITY: &str = r#"{"require_guardian_approval": false, "guardian_threshold": 1,
+        "max_daily_transactions": 100, "security_level": "low"}"#;
+
+    fn sign(service: &AccountService, account_id: &str, nonce: u64) -> Result<String> {
+        service.sign_transaction(account_id, &serde_json::json!({ "amount": 1, "nonce": nonce }).to_string())
+    }
+
+    #[tokio::test]
+    async fn history_survives_a_restart_and_is_written_in_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let (storage, audit, crypto) = core_services(&config).await;
+        let mut service = AccountService::new(&config, crypto.clone(), storage.clone(), audit.clone()).await.unwrap();
+        service.history_segment_size = 4;
+        service.create_account("history", LOW_SECURITY).unwrap();
+        let signed = 6;
+        for nonce in 0..signed {
+            sign(&service, "history", nonce).unwrap();
         }
-        
-        // Compare checksums
-        Ok(checksum == &expected_checksum[0..4])
+
+        // Each segment holds at most history_segment_size records
+        let first = storage.retrieve_data(&AccountService::history_segment_key("history", 0), TRANSACTION_HISTORY_KEY, SYSTEM_PRINCIPAL).unwrap();
+        let second = storage.retrieve_data(&AccountService::history_segment_key("history", 1), TRANSACTION_HISTORY_KEY, SYSTEM_PRINCIPAL).unwrap();
+        assert_eq!(serde_json::from_slice::<Vec<TransactionRecord>>(&first).unwrap().len(), 4);
+        assert_eq!(serde_json::from_slice::<Vec<TransactionRecord>>(&second).unwrap().len(), 2);
+
+        // A fresh service over the same storage reads the history back
+        let mut restarted = AccountService::new(&config, crypto, storage, audit).await.unwrap();
+        restarted.history_segment_size = 4;
+        restarted.accounts.write_or_recover().insert("history".to_string(), service.accounts.read_or_recover()["history"].clone());
+        let page: serde_json::Value = serde_json::from_str(&restarted.get_transaction_history("history", Some(1), None).unwrap()).unwrap();
+        assert_eq!(page["total"], signed);
+        assert_eq!(page["items"][0]["nonce"], signed - 1);
+        sign(&restarted, "history", signed).unwrap();
+        let page: serde_json::Value = serde_json::from_str(&restarted.get_transaction_history("history", Some(1), None).unwrap()).unwrap();
+        assert_eq!(page["items"][0]["nonce"], signed);
     }
-    
-    /// Decode Base58 string to bytes
-    fn decode_base58(&self, input: &str) -> Result<Vec<u8>> {
-        const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
-        
-        let mut result = num_bigint::BigUint::from(0u8);
-        let base = num_bigint::BigUint::from(58u8);
-        
-        for ch in input.chars() {
-            let ch_byte = ch as u8;
-            let value = BASE58_ALPHABET.iter().position(|&x| x == ch_byte)
-                .ok_or_else(|| anyhow!("Invalid Base58 character: {}", ch))?;
-            
-            result = result * &base + num_bigint::BigUint::from(value);
+
+    #[tokio::test]
+    async fn nonce_only_advances_once_the_record_is_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.storage_max_total_bytes = 1;
+        config.storage_eviction_policy = "reject".to_string();
+        let (storage, audit, crypto) = core_services(&config).await;
+        let service = AccountService::new(&config, crypto, storage, audit).await.unwrap();
+        service.create_account("full", LOW_SECURITY).unwrap();
+
+        assert!(sign(&service, "full", 0).is_err());
+        let info: serde_json::Value = serde_json::from_str(&service.get_account_info("full").unwrap()).unwrap();
+        assert_eq!(info["nonce"], 0);
+    }
+
+    /// Reference WIFs of one private key, the Bitcoin wiki example, used here as a
+    /// secp256r1 key
+    const TEST_WIF_COMPRESSED: &str = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+    const TEST_WIF_UNCOMPRESSED: &str = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ";
+    const TEST_WIF_PUBLIC_KEY: &str = "03ee3cd079bfbc3d03cb44302369a4481e37d3ea876fed817e205b18802cb2039c";
+    const TEST_WIF_ADDRESS: &str = "Ngp58Rs4KeCpUp1nsyiYdj3LEJoYL6oqBv";
+
+    #[tokio::test]
+    async fn wif_import_and_export_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+
+        let imported: serde_json::Value = serde_json::from_str(&service.import_wif("imported", TEST_WIF_COMPRESSED).unwrap()).unwrap();
+        assert_eq!(imported["address"], TEST_WIF_ADDRESS);
+        assert_eq!(service.address_from_public_key(TEST_WIF_PUBLIC_KEY).unwrap(), TEST_WIF_ADDRESS);
+        let exported: serde_json::Value = serde_json::from_str(&service.export_wif("imported").unwrap()).unwrap();
+        assert_eq!(exported["wif"], TEST_WIF_COMPRESSED);
+        assert_eq!(exported["address"], TEST_WIF_ADDRESS);
+
+        // Without the compression flag the key is the same, and exports compressed
+        let uncompressed: serde_json::Value = serde_json::from_str(&service.import_wif("uncompressed", TEST_WIF_UNCOMPRESSED).unwrap()).unwrap();
+        assert_eq!(uncompressed["address"], TEST_WIF_ADDRESS);
+        let exported: serde_json::Value = serde_json::from_str(&service.export_wif("uncompressed").unwrap()).unwrap();
+        assert_eq!(exported["wif"], TEST_WIF_COMPRESSED);
+
+        assert!(service.import_wif("imported", TEST_WIF_COMPRESSED).is_err());
+        // Keys generated inside the enclave never leave it
+        service.create_account("generated", "{}").unwrap();
+        assert!(service.export_wif("generated").is_err());
+    }
+
+    #[tokio::test]
+    async fn wif_import_rejects_bad_checksums_versions_and_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        let wif = |version: u8, key: &[u8], flag: &[u8]| base58::encode_check(&[&[version][..], key, flag].concat());
+
+        let corrupted = TEST_WIF_COMPRESSED.replace("617", "618");
+        assert!(service.import_wif("a", &corrupted).unwrap_err().to_string().contains("checksum"));
+        assert!(service.import_wif("a", &wif(0x35, &[1; 32], &[0x01])).is_err());
+        assert!(service.import_wif("a", &wif(WIF_VERSION, &[1; 30], &[0x01])).is_err());
+        assert!(service.import_wif("a", &wif(WIF_VERSION, &[1; 32], &[0x02])).is_err());
+        // Zero and the secp256r1 group order are outside the valid key range
+        let order = hex::decode("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551").unwrap();
+        assert!(service.import_wif("a", &wif(WIF_VERSION, &[0; 32], &[0x01])).is_err());
+        assert!(service.import_wif("a", &wif(WIF_VERSION, &order, &[0x01])).is_err());
+        assert!(service.get_account_info("a").is_err());
+    }
+
+    #[tokio::test]
+    async fn account_keys_are_attestation_bound_when_configured() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.attest_signing_keys = true;
+        let (storage, audit, crypto) = core_services(&config).await;
+        let service = AccountService::new(&config, crypto.clone(), storage, audit).await.unwrap();
+        service.create_account("attested", "{}").unwrap();
+        crate::test_support::assert_attested(&crypto, "account_attested");
+
+        let unbound = account_service(&dir.path().join("unbound")).await;
+        unbound.create_account("plain", "{}").unwrap();
+        let metadata = unbound.crypto_service.get_key_metadata("account_plain").unwrap();
+        assert!(!metadata.attestation_bound);
+        assert!(metadata.attestation_quote.is_none());
+    }
+
+    /// Neo N3 MainNet standby validators, in the order the protocol settings list them
+    const MAINNET_VALIDATORS: [&str; 7] = [
+        "03b209fd4f53a7170ea4444e0cb0a6bb6a53c2bd016926989cf85f9b0fba17a70c",
+        "02df48f60e8f3e01c48ff40b9b7f1310d7a8b2a193188befe1c2e3df740e895093",
+        "03b8d9d5771d8f513aa0869b9cc8d50986403b78c6da36890638c3d46a5adce04a",
+        "02ca0e27697b9c248f6f16e085fd0061e26f44da85b58ee835c110caa5ec3ba554",
+        "024c7b7fb6c310fccf1ba33b082519d82964ea93868d676662d4a59ad548df0e7d",
+        "02aaec38470f6aad0042c6e877cfd8087d2676b0f516fddd362801b9bd3936399e",
+        "02486fd15702c4490a26703112a5cc1d0923fd697a33406bd5a1c00e0013b09a70",
+    ];
+
+    fn validator_keys() -> Vec<Vec<u8>> {
+        MAINNET_VALIDATORS.iter().map(|key| hex::decode(key).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn multisig_matches_the_mainnet_validator_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+
+        // The 5-of-7 BFT address of the standby validators, which received the genesis NEO
+        let created: serde_json::Value = serde_json::from_str(&service.create_multisig_account("validators", validator_keys(), 5).unwrap()).unwrap();
+        assert_eq!(created["address"], "NVg7LjGcUSrgxgjX3zEgqaksfMaiS8Z6e1");
+        assert_eq!(created["verification_script"], concat!(
+            "15",
+            "0c2102486fd15702c4490a26703112a5cc1d0923fd697a33406bd5a1c00e0013b09a70",
+            "0c21024c7b7fb6c310fccf1ba33b082519d82964ea93868d676662d4a59ad548df0e7d",
+            "0c2102aaec38470f6aad0042c6e877cfd8087d2676b0f516fddd362801b9bd3936399e",
+            "0c2103b209fd4f53a7170ea4444e0cb0a6bb6a53c2bd016926989cf85f9b0fba17a70c",
+            "0c2103b8d9d5771d8f513aa0869b9cc8d50986403b78c6da36890638c3d46a5adce04a",
+            "0c2102ca0e27697b9c248f6f16e085fd0061e26f44da85b58ee835c110caa5ec3ba554",
+            "0c2102df48f60e8f3e01c48ff40b9b7f1310d7a8b2a193188befe1c2e3df740e895093",
+            "17", "41", "9ed0dc3a",
+        ));
+        assert!(service.create_multisig_account("validators", validator_keys(), 5).is_err());
+    }
+
+    #[tokio::test]
+    async fn multisig_thresholds_count_distinct_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        let keys = validator_keys();
+        let uncompressed = p256::PublicKey::from_sec1_bytes(&keys[0]).unwrap().to_encoded_point(false).as_bytes().to_vec();
+
+        // The same key twice, once uncompressed, is one member
+        let duplicated = vec![keys[0].clone(), keys[1].clone(), uncompressed, keys[0].clone()];
+        let created: serde_json::Value = serde_json::from_str(&service.create_multisig_account("pair", duplicated.clone(), 2).unwrap()).unwrap();
+        assert_eq!(created["public_keys"].as_array().unwrap().len(), 2);
+        assert!(service.create_multisig_account("too_many", duplicated, 3).is_err());
+
+        assert!(service.create_multisig_account("zero", keys.clone(), 0).is_err());
+        assert!(service.create_multisig_account("over", keys.clone(), 8).is_err());
+        assert!(service.create_multisig_account("none", Vec::new(), 1).is_err());
+        // x is above the field prime, so this is not a curve point
+        let mut off_curve = vec![0xff; 33];
+        off_curve[0] = 0x02;
+        assert!(service.create_multisig_account("invalid", vec![off_curve], 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn multisig_witness_is_assembled_once_the_threshold_is_reached() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        let crypto = &service.crypto_service;
+        let mut members = Vec::new();
+        for id in ["member_a", "member_b", "member_c", "outsider"] {
+            crypto.generate_key(id, CryptoAlgorithm::Secp256r1, vec!["Sign".into()], false, "").unwrap();
+            members.push((id, crypto.get_public_key(id, true).unwrap()));
         }
-        
-        let mut bytes = result.to_bytes_be();
-        
-        // Add leading zeros for leading '1's in the input
-        for ch in input.chars() {
-            if ch == '1' {
-                bytes.insert(0, 0);
-            } else {
-                break;
+        let keys: Vec<Vec<u8>> = members[..3].iter().map(|(_, key)| key.clone()).collect();
+        let created: serde_json::Value = serde_json::from_str(&service.create_multisig_account("wallet", keys, 2).unwrap()).unwrap();
+
+        let transaction = r#"{"recipient":"NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV","amount":5}"#;
+        let reordered = r#"{ "amount": 5, "recipient": "NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV" }"#;
+        let tx_hash = service.transaction_hash(&serde_json::from_str(transaction).unwrap()).unwrap();
+        let sign = |id: &str| crypto.sign_data(id, &tx_hash).unwrap();
+        let collect = |transaction: &str, member: usize, signature: &[u8]| -> Result<serde_json::Value> {
+            let collected = service.collect_multisig_signature("wallet", transaction, &members[member].1, signature)?;
+            Ok(serde_json::from_str(&collected).unwrap())
+        };
+
+        assert!(collect(transaction, 3, &sign("outsider")).is_err());
+        assert!(collect(transaction, 0, &sign("member_c")).is_err());
+
+        let first = collect(transaction, 2, &sign("member_c")).unwrap();
+        assert_eq!(first["hash"], hex::encode(&tx_hash));
+        assert_eq!(first["ready"], false);
+        assert!(service.finalize_multisig_transaction("wallet", transaction).is_err());
+
+        // Another encoding of the same transaction counts towards the same entry
+        let signature_a = sign("member_a");
+        let second = collect(reordered, 0, &signature_a).unwrap();
+        assert_eq!((second["collected"].as_u64(), second["ready"].as_bool()), (Some(2), Some(true)));
+
+        let finalized: serde_json::Value = serde_json::from_str(&service.finalize_multisig_transaction("wallet", reordered).unwrap()).unwrap();
+        assert_eq!(finalized["account_address"], created["address"]);
+        assert_eq!(finalized["witness"]["verification"], created["verification_script"]);
+
+        // Signatures are pushed in the order of their keys in the verification script
+        let sorted_keys: Vec<String> = serde_json::from_value(created["public_keys"].clone()).unwrap();
+        let mut signers = [(hex::encode(&members[0].1), signature_a), (hex::encode(&members[2].1), sign("member_c"))];
+        signers.sort_by_key(|(key, _)| sorted_keys.iter().position(|sorted| sorted == key).unwrap());
+        let invocation = hex::decode(finalized["witness"]["invocation"].as_str().unwrap()).unwrap();
+        assert_eq!(invocation.len(), 2 * 66);
+        for (chunk, (key, _)) in invocation.chunks(66).zip(&signers) {
+            assert_eq!(&chunk[..2], &[OP_PUSHDATA1, 64]);
+            assert!(crypto.verify_with_public_key(CryptoAlgorithm::Secp256r1, &hex::decode(key).unwrap(), &tx_hash, &chunk[2..]).unwrap());
+        }
+
+        // The pending entry is consumed
+        assert!(service.finalize_multisig_transaction("wallet", transaction).is_err());
+    }
+
+    #[tokio::test]
+    async fn signed_transactions_verify_against_the_address_script_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+        service.create_account("signer", LOW_SECURITY).unwrap();
+        let signed = sign(&service, "signer", 0).unwrap();
+        assert!(service.verify_signed_transaction(&signed).unwrap());
+        
+        // The address is the hash of the public key's CheckSig script
+        let parsed: serde_json::Value = serde_json::from_str(&signed).unwrap();
+        let public_key = hex::decode(parsed["public_key"].as_str().unwrap()).unwrap();
+        assert!(matches!(service.crypto_service.get_key_metadata("account_signer").unwrap().key_type, CryptoAlgorithm::Secp256r1));
+        let script_hash = service.generate_neo_address_sgx(&single_sig_verification_script(&public_key)).unwrap()[1..21].to_vec();
+        let address = base58::decode_check(parsed["account_address"].as_str().unwrap()).unwrap();
+        assert_eq!(address[0], NEO_MAINNET_ADDRESS_VERSION);
+        assert_eq!(address[1..], script_hash[..]);
+        
+        let tampered = |change: &dyn Fn(&mut serde_json::Value)| {
+            let mut copy = parsed.clone();
+            change(&mut copy);
+            service.verify_signed_transaction(&copy.to_string()).unwrap()
+        };
+        assert!(!tampered(&|tx| tx["transaction"]["amount"] = serde_json::json!(1000)));
+        assert!(!tampered(&|tx| tx["hash"] = serde_json::json!(hex::encode([0u8; 32]))));
+        assert!(!tampered(&|tx| tx["account_address"] = serde_json::json!(TEST_N3_ADDRESS)));
+        assert!(!tampered(&|tx| tx["public_key"] = serde_json::json!(TEST_PUBLIC_KEY)));
+        let mut signature = hex::decode(parsed["signature"].as_str().unwrap()).unwrap();
+        signature[10] ^= 1;
+        assert!(!tampered(&|tx| tx["signature"] = serde_json::json!(hex::encode(&signature))));
+        
+        // Key order and whitespace in the transaction do not matter
+        assert!(tampered(&|tx| tx["transaction"] = serde_json::from_str(r#"{ "nonce": 0, "amount": 1 }"#).unwrap()));
+    }
+
+    /// Assert that `result` is an `InvalidInput` error naming `field`
+    fn assert_rejects_field<T: std::fmt::Debug>(result: Result<T>, field: &str) {
+        let error = result.unwrap_err();
+        match error.downcast_ref::<EnclaveError>() {
+            Some(EnclaveError::InvalidInput(message)) => {
+                assert!(message.contains(&format!("'{}'", field)), "expected '{}' in: {}", field, message);
             }
+            _ => panic!("expected InvalidInput naming '{}', got: {}", field, error),
         }
-        
-        Ok(bytes)
     }
-    
-    /// Generate address from existing public key (for guardians or external accounts)
-    pub fn address_from_public_key(&self, public_key_hex: &str) -> Result<String> {
-        let public_key_bytes = hex::decode(public_key_hex)
-            .map_err(|_| anyhow!("Invalid public key hex format"))?;
-        
-        if public_key_bytes.len() == 33 {
-            // Already compressed
-            let compressed_key: [u8; 33] = public_key_bytes.try_into()
-                .map_err(|_| anyhow!("Failed to convert to 33-byte array"))?;
-            let address_bytes = self.generate_neo_address_sgx(&compressed_key)?;
-            self.encode_neo_address_base58(&address_bytes)
-        } else if public_key_bytes.len() == 64 {
-            // Uncompressed, need to compress first
-            let compressed_key = self.compress_public_key(&public_key_bytes)?;
-            let address_bytes = self.generate_neo_address_sgx(&compressed_key)?;
-            self.encode_neo_address_base58(&address_bytes)
-        } else if public_key_bytes.len() == 65 && public_key_bytes[0] == 0x04 {
-            // Uncompressed with 0x04 prefix, remove prefix
-            let uncompressed = &public_key_bytes[1..65];
-            let compressed_key = self.compress_public_key(uncompressed)?;
-            let address_bytes = self.generate_neo_address_sgx(&compressed_key)?;
-            self.encode_neo_address_base58(&address_bytes)
-        } else {
-            Err(anyhow!("Invalid public key length: expected 33, 64, or 65 bytes, got {}", public_key_bytes.len()))
+
+    #[tokio::test]
+    async fn transactions_are_validated_per_security_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = account_service(dir.path()).await;
+
+        for level in ["low", "standard", "high"] {
+            service.create_account(level, &serde_json::json!({
+                "require_guardian_approval": false, "guardian_threshold": 1,
+                "max_daily_transactions": 100, "security_level": level,
+            }).to_string()).unwrap();
+            let account = service.accounts.read_or_recover().get(level).unwrap().clone();
+            let valid = serde_json::json!({
+                "sender": account.address,
+                "nonce": account.nonce,
+                "recipient": TEST_N3_ADDRESS,
+                "script_hash": format!("0x{}", KNOWN_SCRIPT_HASHES[1]),
+                "amount": 1.5,
+                "fee": "0.1",
+            });
+            service.validate_transaction(&valid, &account).unwrap();
+            let with = |field: &str, value: serde_json::Value| {
+                let mut tx = valid.clone();
+                tx[field] = value;
+                service.validate_transaction(&tx, &account)
+            };
+            let without = |field: &str| {
+                let mut tx = valid.clone();
+                tx.as_object_mut().unwrap().remove(field);
+                service.validate_transaction(&tx, &account)
+            };
+
+            // Fields that are present are checked at every level
+            assert_rejects_field(with("sender", TEST_N3_ADDRESS.into()), "sender");
+            assert_rejects_field(with("nonce", (account.nonce + 1).into()), "nonce");
+            assert_rejects_field(with("nonce", (-1).into()), "nonce");
+            assert_rejects_field(with("recipient", "AXaXZjZGA3qhQRTCsyG5uFKr9HeShgVhTF".into()), "recipient");
+            assert_rejects_field(with("script_hash", "00".repeat(20).into()), "script_hash");
+            assert_rejects_field(with("amount", (-1).into()), "amount");
+            assert_rejects_field(with("amount", "inf".into()), "amount");
+            assert_rejects_field(with("fee", "-0.5".into()), "fee");
+            assert_rejects_field(with("fee", "NaN".into()), "fee");
+
+            // Missing fields are only required from "standard" and "high" upwards
+            for (field, required) in [
+                ("sender", level != "low"),
+                ("nonce", level != "low"),
+                ("recipient", level == "high"),
+                ("script_hash", level == "high"),
+            ] {
+                if required {
+                    assert_rejects_field(without(field), field);
+                } else {
+                    without(field).unwrap();
+                }
+            }
         }
+
+        // Signing validates first, so an invalid transaction is never signed
+        let account = service.accounts.read_or_recover().get("standard").unwrap().clone();
+        let forged = serde_json::json!({ "sender": TEST_N3_ADDRESS, "nonce": account.nonce, "amount": 1 });
+        assert_rejects_field(service.sign_transaction("standard", &forged.to_string()), "sender");
+        assert_eq!(service.accounts.read_or_recover().get("standard").unwrap().nonce, account.nonce);
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,241 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use log::{info, warn};
+
+use crate::EncaveConfig;
+use crate::canonical::to_canonical_vec;
+use crate::error::EnclaveError;
+
+/// Size of the user data an SGX report can carry
+pub const REPORT_DATA_SIZE: usize = 64;
+
+/// Occlum device exposing the DCAP quoting ioctls
+const SGX_DEVICE_PATH: &str = "/dev/sgx";
+
+/// `_IOR('s', 7, u32)`: size of the quote the platform will produce
+const SGXIOC_GET_DCAP_QUOTE_SIZE: u64 = 0x8004_7307;
+/// `_IOWR('s', 8, IoctlGenDcapQuoteArg)`: generate a quote over the given report data
+const SGXIOC_GEN_DCAP_QUOTE: u64 = 0xc018_7308;
+
+// Quote layout (Intel DCAP quote v3): a 48-byte header, the 384-byte report body of the
+// enclave being attested, then a length-prefixed signature section
+const QUOTE_HEADER_SIZE: usize = 48;
+const REPORT_BODY_SIZE: usize = 384;
+const MR_ENCLAVE_OFFSET: usize = QUOTE_HEADER_SIZE + 64;
+const MR_SIGNER_OFFSET: usize = QUOTE_HEADER_SIZE + 128;
+const REPORT_DATA_OFFSET: usize = QUOTE_HEADER_SIZE + 320;
+const SIGNED_SIZE: usize = QUOTE_HEADER_SIZE + REPORT_BODY_SIZE;
+
+const QUOTE_VERSION: u16 = 3;
+/// ECDSA-256 with P-256, the attestation key type used by DCAP
+const ATT_KEY_TYPE_ECDSA_P256: u16 = 2;
+
+/// Header user data identifying quotes produced without SGX hardware
+const SIMULATED_QUOTE_MARKER: &[u8; 20] = b"NSL-SIMULATED-QUOTE\0";
+const USER_DATA_OFFSET: usize = 28;
+
+#[repr(C)]
+struct IoctlGenDcapQuoteArg {
+    report_data: *const [u8; REPORT_DATA_SIZE],
+    quote_size: *mut u32,
+    quote_buf: *mut u8,
+}
+
+/// Fields of a quote relevant to a relying party
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteReport {
+    pub version: u16,
+    /// True for quotes produced in simulation mode, which prove nothing about the enclave
+    pub simulated: bool,
+    pub mr_enclave: String,
+    pub mr_signer: String,
+    /// Hex-encoded 64-byte report data the quote commits to
+    pub report_data: String,
+}
+
+/// Produces SGX quotes binding caller-chosen report data to this enclave's identity
+///
+/// On hardware, quotes come from Occlum's DCAP quoting ioctls, which need the platform's
+/// quoting enclave and a reachable PCCS configured on the host. In simulation mode a quote
+/// with the same layout is produced, marked as simulated and carrying only a SHA-256 digest
+/// in place of the attestation signature.
+pub struct AttestationService {
+    simulation_mode: bool,
+}
+
+impl AttestationService {
+    pub fn new(config: &EncaveConfig) -> Self {
+        if config.sgx_simulation_mode {
+            warn!("Attestation running in simulation mode; quotes will not be verifiable");
+        }
+        Self { simulation_mode: config.sgx_simulation_mode }
+    }
+
+    /// Generate a quote whose report data is `report_data` zero-padded to 64 bytes
+    pub fn generate_quote(&self, report_data: &[u8]) -> Result<Vec<u8>> {
+        let report_data = pad_report_data(report_data)?;
+        let quote = if self.simulation_mode {
+            simulated_quote(&report_data)
+        } else {
+            dcap_quote(&report_data)?
+        };
+        info!("Generated {}attestation quote of {} bytes", if self.simulation_mode { "simulated " } else { "" }, quote.len());
+        Ok(quote)
+    }
+}
+
+/// Parse a quote, check that it commits to `expected_report_data` and return its identity
+/// fields
+///
+/// This does not verify the quote. Simulated quotes have their digest checked, but for
+/// hardware quotes only the layout and report data are; the ECDSA signature chain and TCB
+/// status must be verified against Intel's collateral by the relying party (for example
+/// with the DCAP quote verification library) before the returned fields can be trusted.
+pub fn inspect_quote(quote: &[u8], expected_report_data: &[u8]) -> Result<QuoteReport> {
+    let expected = pad_report_data(expected_report_data)?;
+    if quote.len() < SIGNED_SIZE + 4 {
+        return Err(EnclaveError::InvalidInput(format!("Quote too short: {} bytes", quote.len())).into());
+    }
+
+    let version = u16::from_le_bytes([quote[0], quote[1]]);
+    if version != QUOTE_VERSION {
+        return Err(anyhow!("Unsupported quote version {}", version));
+    }
+
+    let simulated = &quote[USER_DATA_OFFSET..QUOTE_HEADER_SIZE] == SIMULATED_QUOTE_MARKER;
+    if simulated {
+        let signature_len = u32::from_le_bytes(quote[SIGNED_SIZE..SIGNED_SIZE + 4].try_into()?) as usize;
+        let signature = quote.get(SIGNED_SIZE + 4..SIGNED_SIZE + 4 + signature_len)
+            .ok_or_else(|| anyhow!("Quote signature section is truncated"))?;
+        let digest = Sha256::digest(&quote[..SIGNED_SIZE]);
+        if signature != &digest[..] {
+            return Err(EnclaveError::Crypto("Simulated quote digest does not match its contents".into()).into());
+        }
+    }
+
+    let report_data = &quote[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + REPORT_DATA_SIZE];
+    if !crate::crypto::constant_time_eq(report_data, &expected) {
+        return Err(EnclaveError::Crypto("Quote report data does not match the expected value".into()).into());
+    }
+
+    Ok(QuoteReport {
+        version,
+        simulated,
+        mr_enclave: hex::encode(&quote[MR_ENCLAVE_OFFSET..MR_ENCLAVE_OFFSET + 32]),
+        mr_signer: hex::encode(&quote[MR_SIGNER_OFFSET..MR_SIGNER_OFFSET + 32]),
+        report_data: hex::encode(report_data),
+    })
+}
+
+/// Report data committing to an enclave-generated key
+///
+/// The SHA-256 of the canonical JSON (see [`crate::canonical`]) of
+/// `{"generation", "key_id", "key_type", "public_key"}`, with `public_key` hex-encoded as
+/// stored in [`crate::crypto::KeyMetadata::public_key`]. A verifier rebuilds the statement
+/// from the key metadata in any JSON library, canonicalizes it and compares hashes.
+pub fn key_report_data<T: Serialize + ?Sized>(
+    key_id: &str,
+    key_type: &T,
+    generation: u32,
+    public_key: &[u8],
+) -> Result<Vec<u8>> {
+    let statement = serde_json::json!({
+        "generation": generation,
+        "key_id": key_id,
+        "key_type": key_type,
+        "public_key": hex::encode(public_key),
+    });
+    Ok(Sha256::digest(to_canonical_vec(&statement)?).to_vec())
+}
+
+fn pad_report_data(report_data: &[u8]) -> Result<[u8; REPORT_DATA_SIZE]> {
+    if report_data.len() > REPORT_DATA_SIZE {
+        return Err(EnclaveError::InvalidInput(format!(
+            "Report data is {} bytes, at most {} allowed", report_data.len(), REPORT_DATA_SIZE
+        )).into());
+    }
+    let mut padded = [0u8; REPORT_DATA_SIZE];
+    padded[..report_data.len()].copy_from_slice(report_data);
+    Ok(padded)
+}
+
+/// Ask the platform quoting enclave for a DCAP quote through Occlum
+fn dcap_quote(report_data: &[u8; REPORT_DATA_SIZE]) -> Result<Vec<u8>> {
+    let device = File::open(SGX_DEVICE_PATH)
+        .map_err(EnclaveError::Io)
+        .with_context(|| format!("SGX quoting device {} is unavailable", SGX_DEVICE_PATH))?;
+
+    let mut quote_size: u32 = 0;
+    // SAFETY: the ioctl writes a single u32 through the pointer
+    let ret = unsafe { libc::ioctl(device.as_raw_fd(), SGXIOC_GET_DCAP_QUOTE_SIZE as _, &mut quote_size as *mut u32) };
+    if ret < 0 {
+        return Err(EnclaveError::Io(std::io::Error::last_os_error()).into());
+    }
+
+    let mut quote = vec![0u8; quote_size as usize];
+    let mut arg = IoctlGenDcapQuoteArg {
+        report_data,
+        quote_size: &mut quote_size,
+        quote_buf: quote.as_mut_ptr(),
+    };
+    // SAFETY: the quote buffer holds the `quote_size` bytes the device reported
+    let ret = unsafe { libc::ioctl(device.as_raw_fd(), SGXIOC_GEN_DCAP_QUOTE as _, &mut arg as *mut IoctlGenDcapQuoteArg) };
+    if ret < 0 {
+        return Err(EnclaveError::Io(std::io::Error::last_os_error()).into());
+    }
+
+    quote.truncate(quote_size as usize);
+    Ok(quote)
+}
+
+/// Quote with the DCAP layout, zero measurements and a SHA-256 digest as its signature
+fn simulated_quote(report_data: &[u8; REPORT_DATA_SIZE]) -> Vec<u8> {
+    let mut quote = vec![0u8; SIGNED_SIZE];
+    quote[0..2].copy_from_slice(&QUOTE_VERSION.to_le_bytes());
+    quote[2..4].copy_from_slice(&ATT_KEY_TYPE_ECDSA_P256.to_le_bytes());
+    quote[USER_DATA_OFFSET..QUOTE_HEADER_SIZE].copy_from_slice(SIMULATED_QUOTE_MARKER);
+    quote[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + REPORT_DATA_SIZE].copy_from_slice(report_data);
+
+    let digest = Sha256::digest(&quote);
+    quote.extend_from_slice(&(digest.len() as u32).to_le_bytes());
+    quote.extend_from_slice(&digest);
+    quote
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulated(report_data: &[u8]) -> Vec<u8> {
+        simulated_quote(&pad_report_data(report_data).unwrap())
+    }
+
+    #[test]
+    fn simulated_quotes_commit_to_their_report_data() {
+        let quote = simulated(b"statement");
+        let report = inspect_quote(&quote, b"statement").unwrap();
+        assert!(report.simulated);
+        assert_eq!(report.version, QUOTE_VERSION);
+        assert_eq!(report.report_data, hex::encode(pad_report_data(b"statement").unwrap()));
+
+        assert!(inspect_quote(&quote, b"another statement").is_err());
+        assert!(inspect_quote(&quote[..SIGNED_SIZE], b"statement").is_err());
+    }
+
+    #[test]
+    fn tampered_simulated_quotes_are_rejected() {
+        let mut quote = simulated(b"statement");
+        quote[REPORT_DATA_OFFSET] ^= 1;
+        let mut expected = pad_report_data(b"statement").unwrap();
+        expected[0] ^= 1;
+        let error = inspect_quote(&quote, &expected).unwrap_err();
+        assert!(error.to_string().contains("digest does not match"), "{}", error);
+
+        let mut quote = simulated(b"statement");
+        quote[0] = 4;
+        assert!(inspect_quote(&quote, b"statement").unwrap_err().to_string().contains("Unsupported quote version"));
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Digest};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use log::{info, warn};
+
+use crate::canonical::to_canonical_vec;
+use crate::storage::{StorageService, StoreOptions, SYSTEM_PRINCIPAL};
+use crate::locks::RwLockExt;
+
+/// Storage encryption key for audit log entries and the head record
+const AUDIT_LOG_KEY: &str = "audit_log";
+
+/// Storage key of the record pointing at the newest entry
+const AUDIT_HEAD_KEY: &str = "audit_log_head";
+
+/// Previous-hash value of the first entry in the chain
+const GENESIS_HASH: [u8; 32] = [0u8; 32];
+
+/// Hash format of new entries: the event is hashed as canonical JSON
+const HASH_VERSION: u32 = 2;
+
+/// Entries written before `hash_version` existed hash the event's serde_json encoding
+fn legacy_hash_version() -> u32 {
+    1
+}
+
+/// Security-relevant operation to be recorded in the audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Service that performed the operation ("crypto", "account", "ai", ...)
+    pub service: String,
+    /// Operation name, e.g. "key_created" or "transaction_signed"
+    pub action: String,
+    /// Identifier of the key, account or model the operation applied to
+    pub subject: String,
+    /// Additional operation-specific fields; must never contain secrets
+    #[serde(default)]
+    pub details: serde_json::Value,
+}
+
+impl AuditEvent {
+    pub fn new(service: &str, action: &str, subject: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            action: action.to_string(),
+            subject: subject.to_string(),
+            details: serde_json::Value::Null,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Persisted audit log entry, linked to its predecessor by hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub event: AuditEvent,
+    /// Hex-encoded hash of the previous entry (all zeros for the first entry)
+    pub previous_hash: String,
+    /// Hex-encoded hash over this entry's fields and `previous_hash`
+    pub hash: String,
+    /// Format `hash` was computed with; 2 hashes the event as canonical JSON so the
+    /// chain can be checked outside the enclave after re-serializing entries
+    #[serde(default = "legacy_hash_version")]
+    pub hash_version: u32,
+}
+
+impl AuditEntry {
+    /// Compute the chain hash of an entry from its fields
+    fn compute_hash(version: u32, sequence: u64, timestamp: u64, event: &AuditEvent, previous_hash: &[u8; 32]) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        match version {
+            1 => hasher.update(b"neo-service-layer/audit/v1"),
+            2 => hasher.update(b"neo-service-layer/audit/v2"),
+            _ => return Err(anyhow!("Unsupported audit hash version {}", version)),
+        }
+        hasher.update(previous_hash);
+        hasher.update(sequence.to_be_bytes());
+        hasher.update(timestamp.to_be_bytes());
+        if version == 1 {
+            hasher.update(serde_json::to_vec(event)?);
+        } else {
+            hasher.update(to_canonical_vec(event)?);
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Position of the newest entry in the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChainHead {
+    /// Number of entries in the chain; also the sequence of the next entry
+    length: u64,
+    hash: [u8; 32],
+}
+
+/// Serialized form of [`ChainHead`] kept in storage
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredHead {
+    length: u64,
+    hash: String,
+}
+
+/// Append-only, hash-chained log of security-relevant operations
+///
+/// Every entry commits to the hash of its predecessor, so inserting, modifying or
+/// deleting any stored entry breaks the chain and is reported by [`AuditLog::verify_chain`].
+/// Anchoring [`AuditLog::head_hash`] outside the enclave also detects truncation.
+pub struct AuditLog {
+    storage_service: Arc<StorageService>,
+    head: RwLock<ChainHead>,
+}
+
+impl AuditLog {
+    /// Open the audit log, resuming from the head record in storage if one exists
+    ///
+    /// Only a missing head starts a new chain. A head that cannot be read or parsed is an
+    /// error, since restarting from genesis would silently orphan every stored entry.
+    pub fn new(storage_service: Arc<StorageService>) -> Result<Self> {
+        let head = if storage_service.contains_key(AUDIT_HEAD_KEY) {
+            let data = storage_service.retrieve_data(AUDIT_HEAD_KEY, AUDIT_LOG_KEY, SYSTEM_PRINCIPAL)
+                .map_err(|e| anyhow!("Audit log head unavailable: {}", e))?;
+            let stored: StoredHead = serde_json::from_slice(&data)
+                .map_err(|e| anyhow!("Audit log head is corrupt: {}", e))?;
+            ChainHead { length: stored.length, hash: decode_hash(&stored.hash)? }
+        } else {
+            ChainHead { length: 0, hash: GENESIS_HASH }
+        };
+        info!("Audit log opened with {} entries", head.length);
+
+        Ok(Self {
+            storage_service,
+            head: RwLock::new(head),
+        })
+    }
+
+    /// Append an event to the chain and persist it, returning the stored entry
+    pub fn append(&self, event: AuditEvent) -> Result<AuditEntry> {
+        // Holding the head lock for the whole append keeps the chain strictly linear
+        let mut head = self.head.write_or_recover();
+
+        let sequence = head.length;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let hash = AuditEntry::compute_hash(HASH_VERSION, sequence, timestamp, &event, &head.hash)?;
+        let entry = AuditEntry {
+            sequence,
+            timestamp,
+            event,
+            previous_hash: hex::encode(head.hash),
+            hash: hex::encode(hash),
+            hash_version: HASH_VERSION,
+        };
+
+        self.storage_service.store_data(
+            &entry_key(sequence),
+            &serde_json::to_vec(&entry)?,
+            AUDIT_LOG_KEY,
+            SYSTEM_PRINCIPAL,
+            // The chain is only verifiable if no entry is ever evicted
+            StoreOptions { pinned: true, ..StoreOptions::default() },
+        )?;
+
+        let new_head = ChainHead { length: sequence + 1, hash };
+        let stored = StoredHead { length: new_head.length, hash: entry.hash.clone() };
+        let stored = serde_json::to_vec(&stored)?;
+        if head.length == 0 {
+            self.storage_service.store_data(AUDIT_HEAD_KEY, &stored, AUDIT_LOG_KEY, SYSTEM_PRINCIPAL, StoreOptions { pinned: true, ..StoreOptions::default() })?;
+        } else {
+            self.storage_service.update_data(AUDIT_HEAD_KEY, &stored, AUDIT_LOG_KEY, false, SYSTEM_PRINCIPAL)?;
+        }
+        *head = new_head;
+
+        Ok(entry)
+    }
+
+    /// Append an event, logging instead of failing if it cannot be persisted
+    ///
+    /// Used by services so that an audit storage failure never aborts the audited operation.
+    pub fn record(&self, event: AuditEvent) {
+        let action = event.action.clone();
+        if let Err(e) = self.append(event) {
+            warn!("Failed to record audit event '{}': {}", action, e);
+        }
+    }
+
+    /// Hex-encoded hash of the newest entry (all zeros while the log is empty)
+    pub fn head_hash(&self) -> Result<String> {
+        let head = self.head.read_or_recover();
+        Ok(hex::encode(head.hash))
+    }
+
+    /// Number of entries in the chain
+    pub fn entry_count(&self) -> Result<u64> {
+        Ok(self.head.read_or_recover().length)
+    }
+
+    /// Fetch a single entry by sequence number
+    pub fn get_entry(&self, sequence: u64) -> Result<AuditEntry> {
+        let data = self.storage_service.retrieve_data(&entry_key(sequence), AUDIT_LOG_KEY, SYSTEM_PRINCIPAL)
+            .map_err(|e| anyhow!("Audit entry {} unavailable: {}", sequence, e))?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Walk the chain from the first entry and check every link
+    ///
+    /// Returns a JSON report with `valid`, the number of entries checked, the head hash and,
+    /// when the chain is broken, the sequence of the first bad entry and the reason.
+    pub fn verify_chain(&self) -> Result<String> {
+        let head = *self.head.read_or_recover();
+
+        let mut previous_hash = GENESIS_HASH;
+        let mut failure = None;
+        for sequence in 0..head.length {
+            match self.verify_entry(sequence, &previous_hash) {
+                Ok(hash) => previous_hash = hash,
+                Err(reason) => {
+                    failure = Some((sequence, reason));
+                    break;
+                }
+            }
+        }
+
+        if failure.is_none() && previous_hash != head.hash {
+            failure = Some((head.length, "Chain does not end at the recorded head hash".to_string()));
+        }
+
+        if let Some((sequence, reason)) = &failure {
+            warn!("Audit log verification failed at entry {}: {}", sequence, reason);
+        }
+
+        Ok(serde_json::json!({
+            "valid": failure.is_none(),
+            "entries": head.length,
+            "head_hash": hex::encode(head.hash),
+            "failed_at": failure.as_ref().map(|(sequence, _)| *sequence),
+            "reason": failure.map(|(_, reason)| reason),
+        }).to_string())
+    }
+
+    /// Check one entry's sequence, back-link and hash, returning its hash
+    fn verify_entry(&self, sequence: u64, previous_hash: &[u8; 32]) -> std::result::Result<[u8; 32], String> {
+        let entry = self.get_entry(sequence).map_err(|e| e.to_string())?;
+
+        if entry.sequence != sequence {
+            return Err(format!("Entry stored at {} claims sequence {}", sequence, entry.sequence));
+        }
+        if decode_hash(&entry.previous_hash).ok().as_ref() != Some(previous_hash) {
+            return Err("Previous-hash link does not match the preceding entry".to_string());
+        }
+        let expected = AuditEntry::compute_hash(entry.hash_version, entry.sequence, entry.timestamp, &entry.event, previous_hash)
+            .map_err(|e| e.to_string())?;
+        if hex::encode(expected) != entry.hash {
+            return Err("Entry hash does not match its contents".to_string());
+        }
+        Ok(expected)
+    }
+}
+
+/// Storage key for the entry with the given sequence number
+fn entry_key(sequence: u64) -> String {
+    format!("audit_log_entry_{:020}", sequence)
+}
+
+/// Parse a hex-encoded 32-byte hash
+fn decode_hash(hash: &str) -> Result<[u8; 32]> {
+    hex::decode(hash)?
+        .try_into()
+        .map_err(|_| anyhow!("Audit hash must be 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{core_services, test_config};
+
+    #[tokio::test]
+    async fn missing_head_starts_a_new_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_storage, audit, _crypto) = core_services(&test_config(dir.path())).await;
+
+        assert_eq!(audit.head_hash().unwrap(), hex::encode(GENESIS_HASH));
+    }
+
+    #[tokio::test]
+    async fn reopening_resumes_the_stored_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, audit, _crypto) = core_services(&test_config(dir.path())).await;
+        audit.append(AuditEvent::new("test", "first", "subject")).unwrap();
+
+        let reopened = AuditLog::new(storage).unwrap();
+        assert_eq!(reopened.head_hash().unwrap(), audit.head_hash().unwrap());
+        assert_eq!(reopened.entry_count().unwrap(), audit.entry_count().unwrap());
+    }
+
+    #[tokio::test]
+    async fn corrupt_head_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, audit, _crypto) = core_services(&test_config(dir.path())).await;
+        audit.append(AuditEvent::new("test", "first", "subject")).unwrap();
+        storage.update_data(AUDIT_HEAD_KEY, b"not a head", AUDIT_LOG_KEY, false, SYSTEM_PRINCIPAL).unwrap();
+
+        let error = AuditLog::new(storage.clone()).err().unwrap();
+        assert!(error.to_string().contains("corrupt"));
+
+        // A head sealed under a different key cannot be read either
+        storage.delete_data(AUDIT_HEAD_KEY, SYSTEM_PRINCIPAL).unwrap();
+        storage.store_data(AUDIT_HEAD_KEY, b"{}", "other key", SYSTEM_PRINCIPAL, StoreOptions::default()).unwrap();
+        assert!(AuditLog::new(storage).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use anyhow::{Result, anyhow};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use log::info;
+
+use crate::crypto::constant_time_eq;
+use crate::error::EnclaveError;
+use crate::storage::{ANY_PRINCIPAL, SYSTEM_PRINCIPAL};
+use crate::locks::RwLockExt;
+
+/// Access granted to one caller
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrincipalPolicy {
+    /// Hex SHA-256 of the caller's bearer token; the token itself is never stored
+    pub token_sha256: String,
+    /// Methods the principal may call: exact names, `crypto.*` for a namespace or `*`
+    pub methods: Vec<String>,
+}
+
+/// Principals and the dispatch methods each may call
+///
+/// ```json
+/// { "version": 3, "principals": { "monitor": { "token_sha256": "<hex>", "methods": ["system.*"] } } }
+/// ```
+///
+/// The file lives on host storage, so it is only accepted together with `<path>.sig`, the
+/// hex Ed25519 signature over the file's bytes by `auth_policy_public_key`. `version`
+/// must not decrease on reload, so an older signed policy cannot be put back.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthPolicy {
+    #[serde(default)]
+    pub version: u64,
+    pub principals: HashMap<String, PrincipalPolicy>,
+}
+
+/// Parse the hex Ed25519 key that auth policies must be signed with
+pub fn policy_verifying_key(public_key_hex: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(public_key_hex.trim()).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow!("auth_policy_public_key must be a hex Ed25519 public key"))?;
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| anyhow!("Invalid auth_policy_public_key: {}", e))
+}
+
+/// Detached signature file of the policy at `path`
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sig");
+    PathBuf::from(name)
+}
+
+impl AuthPolicy {
+    /// Load the policy at `path` after checking its signature by `verifying_key`
+    pub fn from_file<P: AsRef<Path>>(path: P, verifying_key: &VerifyingKey) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path)
+            .map_err(|e| anyhow!("Failed to read auth policy {:?}: {}", path, e))?;
+        let signature_file = signature_path(path);
+        let signature_hex = std::fs::read_to_string(&signature_file)
+            .map_err(|e| anyhow!("Failed to read auth policy signature {:?}: {}", signature_file, e))?;
+        let signature: [u8; 64] = hex::decode(signature_hex.trim()).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow!("Auth policy signature {:?} must be a hex Ed25519 signature", signature_file))?;
+        verifying_key.verify(&contents, &Signature::from_bytes(&signature))
+            .map_err(|_| EnclaveError::PermissionDenied(format!("Auth policy {:?} is not signed by auth_policy_public_key", path)))?;
+        
+        let policy: AuthPolicy = serde_json::from_slice(&contents)
+            .map_err(|e| anyhow!("Invalid auth policy {:?}: {}", path, e))?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (principal, entry) in &self.principals {
+            if principal.is_empty() || principal == SYSTEM_PRINCIPAL || principal == ANY_PRINCIPAL {
+                return Err(anyhow!("Auth policy principal '{}' is reserved", principal));
+            }
+            match hex::decode(&entry.token_sha256) {
+                Ok(digest) if digest.len() == 32 => {}
+                _ => return Err(anyhow!("Auth policy principal '{}' needs a hex SHA-256 token_sha256", principal)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Principal whose token hash matches `token`. Every entry is compared so the time
+    /// taken does not depend on which one matches.
+    pub fn authenticate(&self, token: &str) -> Option<&str> {
+        let digest = Sha256::digest(token.as_bytes());
+        let mut matched = None;
+        for (principal, entry) in &self.principals {
+            let expected = hex::decode(&entry.token_sha256).unwrap_or_default();
+            if constant_time_eq(&expected, &digest) {
+                matched = Some(principal.as_str());
+            }
+        }
+        matched
+    }
+
+    pub fn allows(&self, principal: &str, method: &str) -> bool {
+        self.principals.get(principal)
+            .is_some_and(|entry| entry.methods.iter().any(|pattern| method_matches(pattern, method)))
+    }
+}
+
+fn method_matches(pattern: &str, method: &str) -> bool {
+    if pattern == "*" || pattern == method {
+        return true;
+    }
+    match pattern.strip_suffix(".*") {
+        Some(namespace) => method.strip_prefix(namespace).is_some_and(|rest| rest.starts_with('.')),
+        None => false,
+    }
+}
+
+/// Checks dispatched calls against the policy file named by `auth_policy_path`
+///
+/// With no path configured every call is allowed, as before authorization existed.
+/// A signed replacement of the file can be reloaded while the enclave runs.
+pub struct Authorizer {
+    source: Option<(PathBuf, VerifyingKey)>,
+    policy: RwLock<AuthPolicy>,
+}
+
+impl Authorizer {
+    pub fn new(path: &str, public_key_hex: &str) -> Result<Self> {
+        if path.is_empty() {
+            return Ok(Self { source: None, policy: RwLock::new(AuthPolicy::default()) });
+        }
+
+        let verifying_key = policy_verifying_key(public_key_hex)?;
+        let policy = AuthPolicy::from_file(path, &verifying_key)?;
+        info!("Loaded auth policy version {} with {} principals", policy.version, policy.principals.len());
+        Ok(Self {
+            source: Some((PathBuf::from(path), verifying_key)),
+            policy: RwLock::new(policy),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Re-read the policy file, returning the number of principals. An invalid, unsigned
+    /// or older file leaves the current policy in effect.
+    pub fn reload(&self) -> Result<usize> {
+        let (path, verifying_key) = self.source.as_ref()
+            .ok_or_else(|| anyhow!("No auth policy is configured"))?;
+        let policy = AuthPolicy::from_file(path, verifying_key)?;
+        let principals = policy.principals.len();
+        {
+            let mut current = self.policy.write_or_recover();
+            if policy.version < current.version {
+                return Err(EnclaveError::PermissionDenied(format!(
+                    "Auth policy version {} is older than the loaded version {}", policy.version, current.version
+                )).into());
+            }
+            *current = policy;
+        }
+        info!("Reloaded auth policy with {} principals", principals);
+        Ok(principals)
+    }
+
+    /// Authenticate `token` and check that its principal may call `method`, returning the
+    /// principal, or `None` when no policy is configured
+    pub fn authorize(&self, token: Option<&str>, method: &str) -> Result<Option<String>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+
+        let policy = self.policy.read_or_recover();
+        let principal = token
+            .and_then(|token| policy.authenticate(token))
+            .ok_or_else(|| EnclaveError::PermissionDenied("Missing or unknown auth token".into()))?;
+        if !policy.allows(principal, method) {
+            return Err(EnclaveError::PermissionDenied(format!(
+                "Principal '{}' may not call '{}'", principal, method
+            )).into());
+        }
+        Ok(Some(principal.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{token_sha256, write_signed_policy};
+
+    fn policy(version: u64) -> serde_json::Value {
+        serde_json::json!({
+            "version": version,
+            "principals": {
+                "monitor": { "token_sha256": token_sha256("monitor-token"), "methods": ["system.*"] },
+                "signer": { "token_sha256": token_sha256("signer-token"), "methods": ["crypto.sign", "crypto.verify"] },
+            }
+        })
+    }
+
+    fn is_permission_denied(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::PermissionDenied(_)))
+    }
+
+    #[test]
+    fn allows_only_listed_methods_for_known_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, key) = write_signed_policy(dir.path(), &policy(1));
+        let authorizer = Authorizer::new(&path, &key).unwrap();
+
+        assert_eq!(authorizer.authorize(Some("monitor-token"), "system.health").unwrap().as_deref(), Some("monitor"));
+        assert_eq!(authorizer.authorize(Some("signer-token"), "crypto.sign").unwrap().as_deref(), Some("signer"));
+
+        assert!(is_permission_denied(&authorizer.authorize(Some("monitor-token"), "crypto.sign").unwrap_err()));
+        assert!(is_permission_denied(&authorizer.authorize(Some("signer-token"), "crypto.signature").unwrap_err()));
+        assert!(is_permission_denied(&authorizer.authorize(Some("wrong-token"), "system.health").unwrap_err()));
+        assert!(is_permission_denied(&authorizer.authorize(None, "system.health").unwrap_err()));
+    }
+
+    #[test]
+    fn no_policy_allows_everything() {
+        let authorizer = Authorizer::new("", "").unwrap();
+        assert!(!authorizer.is_enabled());
+        assert_eq!(authorizer.authorize(None, "crypto.sign").unwrap(), None);
+    }
+
+    #[test]
+    fn namespace_patterns_match_whole_segments() {
+        assert!(method_matches("*", "crypto.sign"));
+        assert!(method_matches("crypto.*", "crypto.sign"));
+        assert!(!method_matches("crypto.*", "cryptography.sign"));
+        assert!(!method_matches("crypto.sign", "crypto.sign_all"));
+    }
+
+    #[test]
+    fn unsigned_or_tampered_policies_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, key) = write_signed_policy(dir.path(), &policy(1));
+        let authorizer = Authorizer::new(&path, &key).unwrap();
+
+        // The host grants itself everything; the signature no longer matches
+        let tampered = policy(2).to_string().replace("\"system.*\"", "\"*\"");
+        std::fs::write(&path, tampered).unwrap();
+        assert!(is_permission_denied(&authorizer.reload().unwrap_err()));
+        assert!(Authorizer::new(&path, &key).is_err());
+        assert!(authorizer.authorize(Some("monitor-token"), "crypto.sign").is_err());
+
+        std::fs::remove_file(signature_path(Path::new(&path))).unwrap();
+        assert!(authorizer.reload().is_err());
+
+        let other_key = hex::encode(ed25519_dalek::SigningKey::from_bytes(&[9; 32]).verifying_key().as_bytes());
+        write_signed_policy(dir.path(), &policy(1));
+        assert!(Authorizer::new(&path, &other_key).is_err());
+    }
+
+    #[test]
+    fn reload_refuses_older_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, key) = write_signed_policy(dir.path(), &policy(2));
+        let authorizer = Authorizer::new(&path, &key).unwrap();
+
+        write_signed_policy(dir.path(), &policy(1));
+        assert!(is_permission_denied(&authorizer.reload().unwrap_err()));
+        write_signed_policy(dir.path(), &policy(3));
+        assert_eq!(authorizer.reload().unwrap(), 2);
+    }
+}
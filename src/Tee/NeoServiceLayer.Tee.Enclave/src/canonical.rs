@@ -0,0 +1,169 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// Serialize `value` as canonical JSON, the form every signed or hashed JSON document uses
+///
+/// Follows RFC 8785 (JCS): object keys sorted by their UTF-16 code units, no whitespace,
+/// strings with only the mandatory escapes and floats in ECMAScript number form (`1e21`,
+/// `0.000001`, `5` rather than `5.0`). Integers outside the f64-exact range keep all their
+/// digits instead of being rounded. Semantically equal documents therefore produce the same
+/// bytes however their keys were ordered or their numbers written.
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(value)?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// [`to_canonical_vec`] as a string
+pub fn to_canonical_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    Ok(String::from_utf8(to_canonical_vec(value)?)?)
+}
+
+/// Re-encode a JSON document in canonical form
+pub fn canonicalize(json: &str) -> Result<String> {
+    let value: Value = serde_json::from_str(json)?;
+    to_canonical_string(&value)
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::Number(n) => out.extend_from_slice(format_number(n)?.as_bytes()),
+        Value::String(s) => serde_json::to_writer(&mut *out, s)?,
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_value(item, out)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+fn format_number(n: &Number) -> Result<String> {
+    if let Some(i) = n.as_i64() {
+        return Ok(i.to_string());
+    }
+    if let Some(u) = n.as_u64() {
+        return Ok(u.to_string());
+    }
+    let f = n.as_f64().ok_or_else(|| anyhow!("Unsupported JSON number {}", n))?;
+    if !f.is_finite() {
+        return Err(anyhow!("JSON numbers must be finite"));
+    }
+    Ok(format_f64(f))
+}
+
+/// ECMAScript `Number.prototype.toString` for a finite f64
+fn format_f64(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+
+    // `{:e}` gives the shortest round-tripping digits, e.g. "1.2345e-7"
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let k = digits.len() as i32;
+    // The value is 0.digits * 10^n
+    let n = exponent + 1;
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, sign, (n - 1).abs())
+    };
+
+    if f < 0.0 {
+        format!("-{}", body)
+    } else {
+        body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_follow_ecmascript_form() {
+        for (json, expected) in [
+            ("1e21", "1e+21"),
+            ("1e20", "100000000000000000000"),
+            ("1e-7", "1e-7"),
+            ("0.000001", "0.000001"),
+            ("-0", "0"),
+            ("-0.0", "0"),
+            ("5.0", "5"),
+            ("-1.5e-9", "-1.5e-9"),
+            ("123.456", "123.456"),
+            ("18446744073709551615", "18446744073709551615"),
+        ] {
+            assert_eq!(canonicalize(json).unwrap(), expected, "{}", json);
+        }
+        assert_eq!(to_canonical_string(&u64::MAX).unwrap(), u64::MAX.to_string());
+    }
+
+    #[test]
+    fn keys_sort_by_utf16_code_units() {
+        // RFC 8785 3.2.3: U+1F600 is the surrogate pair D83D DE00, so it sorts before
+        // U+FB33 even though its code point is larger
+        let json = "{\"\u{20ac}\":\"Euro\",\"\\r\":\"CR\",\"\u{fb33}\":\"Hebrew\",\"1\":\"One\",\
+                    \"\u{1f600}\":\"Smiley\",\"\\u0080\":\"Control\",\"\u{f6}\":\"Latin\"}";
+        assert_eq!(
+            canonicalize(json).unwrap(),
+            "{\"\\r\":\"CR\",\"1\":\"One\",\"\u{80}\":\"Control\",\"\u{f6}\":\"Latin\",\
+             \"\u{20ac}\":\"Euro\",\"\u{1f600}\":\"Smiley\",\"\u{fb33}\":\"Hebrew\"}",
+        );
+    }
+
+    #[tokio::test]
+    async fn key_order_does_not_change_bytes_or_signatures() {
+        let first = r#"{"amount":5.0,"to":"NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV","meta":{"b":[1,2],"a":null}}"#;
+        let second = r#"{"meta":{"a":null,"b":[1,2]},"to":"NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV","amount":5}"#;
+        let first = to_canonical_vec(&serde_json::from_str::<Value>(first).unwrap()).unwrap();
+        let second = to_canonical_vec(&serde_json::from_str::<Value>(second).unwrap()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, br#"{"amount":5,"meta":{"a":null,"b":[1,2]},"to":"NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV"}"#);
+
+        let dir = tempfile::tempdir().unwrap();
+        let (_storage, _audit, crypto) = crate::test_support::core_services(&crate::test_support::test_config(dir.path())).await;
+        crypto.import_key(
+            "canonical",
+            crate::crypto::CryptoAlgorithm::Secp256r1,
+            &hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721").unwrap(),
+            vec!["Sign".to_string()],
+            false,
+            "",
+        ).unwrap();
+        assert_eq!(crypto.sign_data("canonical", &first).unwrap(), crypto.sign_data("canonical", &second).unwrap());
+    }
+}
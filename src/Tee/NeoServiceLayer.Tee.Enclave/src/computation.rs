@@ -1,11 +1,20 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
-use std::time::{SystemTime, Duration};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Instant, SystemTime, Duration};
 use log::{info, warn, error, debug};
+use sha2::{Digest, Sha256};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::EncaveConfig;
+use crate::crypto::CryptoService;
+use crate::error::EnclaveError;
+use crate::health::ServiceHealth;
+use crate::metrics::{ComputationMetrics, WorkerPoolMetrics};
+use crate::locks::{MutexExt, RwLockExt};
+use crate::pagination::paginate;
 
 /// Computation job metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +40,7 @@ pub enum JobStatus {
     Failed,
     Timeout,
     SecurityViolation,
+    Cancelled,
 }
 
 /// Security levels for computation
@@ -42,6 +52,165 @@ pub enum SecurityLevel {
     Critical, // Maximum security with audit trail
 }
 
+/// Memory ceiling applied to sandboxed executions
+const MEMORY_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest code submission accepted for execution or analysis
+const MAX_CODE_SIZE: usize = 1024 * 1024;
+
+/// Whether an execution may be answered from the result cache
+///
+/// Whether code is deterministic cannot be told from its text, so only the caller can
+/// declare it; everything else is executed every time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Always execute
+    #[default]
+    Bypass,
+    /// The caller declares the code deterministic, so a fresh cached result may be reused
+    Deterministic,
+}
+
+struct CachedResult {
+    value: String,
+    stored_at: Instant,
+}
+
+/// Successful results of deterministic executions, keyed by a hash of the code and inputs
+struct ResultCache {
+    entries: Mutex<HashMap<[u8; 32], CachedResult>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl ResultCache {
+    fn new(ttl_seconds: u64, max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl: Duration::from_secs(ttl_seconds),
+            max_entries,
+        }
+    }
+    
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+    
+    /// Length-prefixed so no split of the same bytes between fields collides
+    fn key(kind: &str, code: &str, input: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for field in [kind, code, input] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+    
+    fn get(&self, key: &[u8; 32]) -> Option<String> {
+        let mut entries = self.entries.lock_or_recover();
+        match entries.get(key) {
+            Some(entry) if entry.stored_at.elapsed() < self.ttl => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+    
+    /// Store a result, dropping expired entries and then the oldest one when full
+    fn insert(&self, key: [u8; 32], value: String) {
+        let mut entries = self.entries.lock_or_recover();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, entry)| entry.stored_at)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CachedResult { value, stored_at: Instant::now() });
+    }
+    
+    fn len(&self) -> usize {
+        self.entries.lock_or_recover().len()
+    }
+}
+
+/// Rejection from the computation worker pool
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum WorkerPoolError {
+    #[error("computation queue is full ({queued} jobs waiting, limit {queue_depth})")]
+    QueueFull { queued: usize, queue_depth: usize },
+    #[error("computation worker pool is closed")]
+    Closed,
+}
+
+/// Fixed number of execution slots plus a bounded queue of jobs waiting for one
+struct WorkerPool {
+    slots: Arc<Semaphore>,
+    capacity: usize,
+    queue_depth: usize,
+    queued: AtomicUsize,
+}
+
+/// Queue reservation, released when the waiter gets a slot or gives up
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl WorkerPool {
+    fn new(capacity: usize, queue_depth: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(capacity)),
+            capacity,
+            queue_depth,
+            queued: AtomicUsize::new(0),
+        }
+    }
+    
+    /// Take a free slot, waiting in the queue if there is room, or fail with `QueueFull`.
+    /// The slot is held until the returned permit is dropped.
+    async fn acquire(&self) -> std::result::Result<OwnedSemaphorePermit, WorkerPoolError> {
+        match self.slots.clone().try_acquire_owned() {
+            Ok(permit) => return Ok(permit),
+            Err(tokio::sync::TryAcquireError::Closed) => return Err(WorkerPoolError::Closed),
+            Err(tokio::sync::TryAcquireError::NoPermits) => {}
+        }
+        
+        let queue_depth = self.queue_depth;
+        self.queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < queue_depth).then_some(queued + 1)
+            })
+            .map_err(|queued| WorkerPoolError::QueueFull { queued, queue_depth })?;
+        let _queue_slot = QueueSlot(&self.queued);
+        
+        self.slots.clone().acquire_owned().await.map_err(|_| WorkerPoolError::Closed)
+    }
+    
+    /// Fail queued and future acquisitions; running jobs keep their slots
+    fn close(&self) {
+        self.slots.close();
+    }
+    
+    fn metrics(&self) -> WorkerPoolMetrics {
+        WorkerPoolMetrics {
+            capacity: self.capacity,
+            running: self.capacity.saturating_sub(self.slots.available_permits()),
+            queued: self.queued.load(Ordering::SeqCst),
+            queue_depth: self.queue_depth,
+        }
+    }
+}
+
 /// JavaScript execution context
 #[derive(Debug)]
 struct ExecutionContext {
@@ -54,51 +223,186 @@ struct ExecutionContext {
 /// Computation service for secure code execution
 pub struct ComputationService {
     jobs: Arc<RwLock<HashMap<String, ComputationJob>>>,
-    job_counter: std::sync::atomic::AtomicU64,
+    crypto_service: Arc<CryptoService>,
     execution_contexts: Arc<RwLock<HashMap<String, ExecutionContext>>>,
-    max_concurrent_jobs: usize,
+    worker_pool: WorkerPool,
+    accepting_jobs: AtomicBool,
+    metrics: ComputationMetrics,
+    security_policy: SecurityPolicy,
+    result_cache: ResultCache,
 }
 
 impl ComputationService {
     /// Create a new computation service instance
-    pub async fn new(config: &EncaveConfig) -> Result<Self> {
+    pub async fn new(config: &EncaveConfig, crypto_service: Arc<CryptoService>) -> Result<Self> {
         info!("Initializing ComputationService with enhanced security");
         
         let max_jobs = config.get_number("computation.max_concurrent_jobs")
-            .unwrap_or(10) as usize;
+            .unwrap_or(10)
+            .max(1);
+        
+        let security_policy = SecurityPolicy::new(
+            &config.computation_security_level,
+            &config.computation_denied_patterns,
+        )?;
             
         Ok(Self {
             jobs: Arc::new(RwLock::new(HashMap::new())),
-            job_counter: std::sync::atomic::AtomicU64::new(0),
+            crypto_service,
             execution_contexts: Arc::new(RwLock::new(HashMap::new())),
-            max_concurrent_jobs: max_jobs,
+            worker_pool: WorkerPool::new(max_jobs, config.computation_queue_depth),
+            accepting_jobs: AtomicBool::new(true),
+            metrics: ComputationMetrics::default(),
+            security_policy,
+            result_cache: ResultCache::new(config.computation_cache_ttl_seconds, config.computation_cache_max_entries),
         })
     }
     
+    /// Stop accepting new jobs ahead of shutdown
+    pub fn stop_accepting(&self) {
+        self.accepting_jobs.store(false, Ordering::SeqCst);
+        self.worker_pool.close();
+        info!("ComputationService no longer accepting new jobs");
+    }
+    
+    /// Number of jobs that have not yet reached a terminal state
+    pub fn active_job_count(&self) -> Result<usize> {
+        let jobs = self.jobs.read_or_recover();
+        Ok(jobs.values()
+            .filter(|job| matches!(job.status, JobStatus::Running | JobStatus::Pending))
+            .count())
+    }
+    
+    /// Mark every non-terminal job as cancelled, returning how many were affected
+    pub fn cancel_active_jobs(&self, reason: &str) -> Result<usize> {
+        let mut jobs = self.jobs.write_or_recover();
+        
+        let mut cancelled = 0;
+        for job in jobs.values_mut() {
+            if matches!(job.status, JobStatus::Running | JobStatus::Pending) {
+                job.status = JobStatus::Cancelled;
+                job.error = Some(reason.to_string());
+                self.metrics.jobs_cancelled.incr();
+                cancelled += 1;
+            }
+        }
+        
+        if cancelled > 0 {
+            warn!("Cancelled {} computation jobs: {}", cancelled, reason);
+        }
+        Ok(cancelled)
+    }
+    
+    /// Every job record, for persisting across restarts
+    pub fn export_jobs(&self) -> Vec<ComputationJob> {
+        self.jobs.read_or_recover().values().cloned().collect()
+    }
+    
+    /// Load records saved by `export_jobs`, keeping any job already known. A job that was
+    /// still in flight when saved can never finish, so it is marked failed. Returns how
+    /// many records were added.
+    pub fn import_jobs(&self, records: Vec<ComputationJob>) -> usize {
+        let mut jobs = self.jobs.write_or_recover();
+        
+        let mut imported = 0;
+        for mut job in records {
+            if matches!(job.status, JobStatus::Running | JobStatus::Pending) {
+                job.status = JobStatus::Failed;
+                job.error = Some("Enclave restarted before the job finished".to_string());
+            }
+            if !jobs.contains_key(&job.id) {
+                jobs.insert(job.id.clone(), job);
+                imported += 1;
+            }
+        }
+        imported
+    }
+    
+    fn ensure_accepting(&self) -> Result<()> {
+        if self.accepting_jobs.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            self.metrics.jobs_rejected.incr();
+            Err(anyhow!("ComputationService is shutting down"))
+        }
+    }
+    
+    /// Operation counters for this service
+    pub fn metrics(&self) -> &ComputationMetrics {
+        &self.metrics
+    }
+    
+    /// Current worker pool occupancy
+    pub fn pool_metrics(&self) -> WorkerPoolMetrics {
+        self.worker_pool.metrics()
+    }
+    
+    /// Cache key for this execution, or `None` when its result must not be cached
+    fn cache_key(&self, kind: &str, code: &str, input: &str, policy: CachePolicy) -> Option<[u8; 32]> {
+        if policy == CachePolicy::Bypass || !self.result_cache.enabled() {
+            return None;
+        }
+        Some(ResultCache::key(kind, code, input))
+    }
+    
+    /// Run `execute` unless a fresh result for `cache_key` exists; successes are cached.
+    /// Returns the result and whether it came from the cache.
+    fn cached_execution(
+        &self,
+        cache_key: Option<[u8; 32]>,
+        execute: impl FnOnce() -> Result<String>,
+    ) -> Result<(String, bool)> {
+        let Some(key) = cache_key else {
+            return Ok((execute()?, false));
+        };
+        if let Some(result) = self.result_cache.get(&key) {
+            self.metrics.cache_hits.incr();
+            return Ok((result, true));
+        }
+        self.metrics.cache_misses.incr();
+        let result = execute()?;
+        self.result_cache.insert(key, result.clone());
+        Ok((result, false))
+    }
+    
     /// Execute JavaScript code securely with production-grade isolation
-    pub fn execute_javascript(&self, code: &str, args: &str) -> Result<String> {
+    ///
+    /// Runs in a worker pool slot like `execute_computation`, waiting in the queue or
+    /// failing with `WorkerPoolError::QueueFull` when the pool is busy.
+    pub async fn execute_javascript(&self, code: &str, args: &str) -> Result<String> {
+        self.execute_javascript_with_policy(code, args, CachePolicy::Bypass).await
+    }
+    
+    /// `execute_javascript`, with control over reuse of cached results
+    pub async fn execute_javascript_with_policy(&self, code: &str, args: &str, cache_policy: CachePolicy) -> Result<String> {
+        self.ensure_accepting()?;
+        
         debug!("Executing JavaScript code: {} chars", code.len());
         
         // Validate input parameters
-        if code.len() > 1024 * 1024 { // 1MB code limit
-            return Err(anyhow!("Code size exceeds maximum limit"));
+        if code.len() > MAX_CODE_SIZE {
+            return Err(EnclaveError::ResourceLimit("Code size exceeds maximum limit".into()).into());
         }
         
         if args.len() > 10 * 1024 { // 10KB args limit
-            return Err(anyhow!("Arguments size exceeds maximum limit"));
+            return Err(EnclaveError::ResourceLimit("Arguments size exceeds maximum limit".into()).into());
         }
         
         // Security analysis of code
-        let security_issues = analyze_code_security(code);
+        let security_issues = self.security_policy.analyze(code);
         if !security_issues.is_empty() {
-            warn!("Security issues detected in JavaScript code: {:?}", security_issues);
-            return Err(anyhow!("Code contains security violations: {:?}", security_issues));
+            let summary = security_issues.iter()
+                .map(|finding| finding.to_string())
+                .collect::<Vec<_>>()
+                .join("; ");
+            warn!("Security issues detected in JavaScript code: {}", summary);
+            return Err(anyhow!("Code contains security violations: {}", summary));
         }
         
         // Create execution context with security constraints
         let context = ExecutionContext {
             timeout_ms: 30000, // 30 second timeout
-            memory_limit_bytes: 64 * 1024 * 1024, // 64MB memory limit
+            memory_limit_bytes: MEMORY_LIMIT_BYTES,
             allowed_apis: vec![
                 "Math".to_string(),
                 "Date".to_string(),
@@ -110,9 +414,16 @@ impl ComputationService {
             security_level: SecurityLevel::High,
         };
         
+        // Held until the response is built so the slot frees however execution ends
+        let _slot = self.worker_pool.acquire().await.map_err(|e| {
+            self.metrics.jobs_rejected.incr();
+            anyhow::Error::new(e)
+        })?;
+        
         // Execute in secure sandbox
         let execution_start = SystemTime::now();
-        let result = execute_in_sandbox(code, args, &context)?;
+        let cache_key = self.cache_key("javascript", code, args, cache_policy);
+        let (result, cached) = self.cached_execution(cache_key, || execute_in_sandbox(code, args, &context))?;
         let execution_time = execution_start.elapsed()
             .unwrap_or(Duration::from_millis(0))
             .as_millis() as u64;
@@ -120,6 +431,7 @@ impl ComputationService {
         // Create response with execution metadata
         let response = serde_json::json!({
             "result": result,
+            "cached": cached,
             "execution_time_ms": execution_time,
             "code_length": code.len(),
             "args_length": args.len(),
@@ -132,25 +444,98 @@ impl ComputationService {
             "api_calls": extract_api_calls(code),
         });
         
+        self.metrics.javascript_executions.incr();
         info!("JavaScript execution completed in {} ms", execution_time);
         Ok(response.to_string())
     }
     
-    /// Execute a computation job with full lifecycle management
-    pub fn execute_computation(&self, id: &str, code: &str, parameters: &str) -> Result<String> {
-        // Check concurrent job limit
-        let jobs_guard = self.jobs.read().map_err(|_| anyhow!("Lock poisoned"))?;
-        let running_jobs = jobs_guard.values()
-            .filter(|job| matches!(job.status, JobStatus::Running))
-            .count();
-        drop(jobs_guard);
+    /// Pre-flight check for code: runs security analysis, complexity analysis and memory
+    /// estimation without executing anything, and reports whether the job would be accepted
+    pub fn analyze_computation(&self, code: &str, parameters: &str) -> Result<String> {
+        if code.len() > MAX_CODE_SIZE {
+            return Err(EnclaveError::ResourceLimit("Code size exceeds maximum limit".into()).into());
+        }
+        
+        let security_issues = self.security_policy.analyze(code);
+        let complexity = analyze_code_complexity(code);
+        let estimated_memory = estimate_memory_usage(code, parameters);
+        let exceeds_memory_limit = estimated_memory > MEMORY_LIMIT_BYTES;
+        let parameters_error = serde_json::from_str::<serde_json::Value>(parameters)
+            .err()
+            .map(|e| e.to_string());
+        
+        let would_accept = security_issues.is_empty() && !exceeds_memory_limit && parameters_error.is_none();
+        
+        Ok(serde_json::json!({
+            "would_accept": would_accept,
+            "security_rule_set": format!("{:?}", self.security_policy.rule_set),
+            "security_issues": security_issues,
+            "estimated_memory_bytes": estimated_memory,
+            "memory_limit_bytes": MEMORY_LIMIT_BYTES,
+            "exceeds_memory_limit": exceeds_memory_limit,
+            "computation_type": format!("{:?}", detect_computation_type(code)),
+            "complexity": {
+                "level": format!("{:?}", complexity.complexity_level),
+                "cyclomatic_complexity": complexity.cyclomatic_complexity,
+                "function_count": complexity.function_count,
+                "loop_count": complexity.loop_count,
+                "conditional_count": complexity.conditional_count,
+                "recursion_depth": complexity.recursion_depth,
+            },
+            "parameters_valid": parameters_error.is_none(),
+            "parameters_error": parameters_error,
+        }).to_string())
+    }
+    
+    /// Evaluate an arithmetic expression without a JavaScript engine. Supports numbers,
+    /// `+ - * / %`, parentheses, `Math.*` functions and constants, and variables looked up
+    /// in `vars` (dotted paths reach into nested objects). Evaluation has no side effects
+    /// and always terminates, since expression length and nesting depth are capped.
+    pub fn evaluate_expression(&self, expr: &str, vars: &serde_json::Value) -> Result<f64> {
+        if expr.len() > MAX_EXPRESSION_LENGTH {
+            return Err(anyhow!("Expression exceeds {} characters", MAX_EXPRESSION_LENGTH));
+        }
+        
+        let tokens = tokenize_expression(expr)?;
+        let mut parser = ExpressionParser { tokens: &tokens, pos: 0, depth: 0, vars };
         
-        if running_jobs >= self.max_concurrent_jobs {
-            return Err(anyhow!("Maximum concurrent jobs limit reached"));
+        let value = parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(anyhow!("Unexpected {} at end of expression", token));
+        }
+        if !value.is_finite() {
+            return Err(anyhow!("Expression result is not a finite number"));
         }
         
-        let job_id = format!("{}_{}", id, 
-            self.job_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+        debug!("Evaluated expression '{}' = {}", expr, value);
+        Ok(value)
+    }
+    
+    /// Execute a computation job with full lifecycle management. When every worker slot
+    /// is busy the job waits in the bounded queue; once that is full it is rejected with
+    /// `WorkerPoolError::QueueFull`.
+    pub async fn execute_computation(&self, id: &str, code: &str, parameters: &str) -> Result<String> {
+        self.execute_computation_with_policy(id, code, parameters, CachePolicy::Bypass).await
+    }
+    
+    /// `execute_computation`, with control over reuse of cached results. A cache hit still
+    /// records a completed job but skips execution.
+    pub async fn execute_computation_with_policy(
+        &self,
+        id: &str,
+        code: &str,
+        parameters: &str,
+        cache_policy: CachePolicy,
+    ) -> Result<String> {
+        self.ensure_accepting()?;
+        
+        // Held for the rest of the call so the slot frees however the job ends
+        let _slot = self.worker_pool.acquire().await.map_err(|e| {
+            self.metrics.jobs_rejected.incr();
+            anyhow::Error::new(e)
+        })?;
+        
+        let job_id = self.crypto_service.generate_id(id)?;
         
         let execution_start = SystemTime::now();
         
@@ -170,20 +555,26 @@ impl ComputationService {
             security_level: SecurityLevel::High,
         };
         
-        // Store job
+        // Store job; ids are random, but never replace a job if one matches
         {
-            let mut jobs = self.jobs.write().map_err(|_| anyhow!("Lock poisoned"))?;
+            let mut jobs = self.jobs.write_or_recover();
+            if jobs.contains_key(&job_id) {
+                return Err(EnclaveError::AlreadyExists(format!("Job '{}' already exists", job_id)).into());
+            }
             jobs.insert(job_id.clone(), job.clone());
         }
         
         // Execute computation with error handling
-        let computation_result = match self.execute_secure_computation(code, parameters) {
-            Ok(result) => {
+        let cache_key = self.cache_key("computation", code, parameters, cache_policy);
+        let computation_result = match self.cached_execution(cache_key, || self.execute_secure_computation(code, parameters)) {
+            Ok((result, _)) => {
+                self.metrics.jobs_completed.incr();
                 job.status = JobStatus::Completed;
                 job.result = Some(result.clone());
                 result
             }
             Err(e) => {
+                self.metrics.jobs_failed.incr();
                 job.status = JobStatus::Failed;
                 job.error = Some(e.to_string());
                 error!("Computation job {} failed: {}", job_id, e);
@@ -199,37 +590,69 @@ impl ComputationService {
         );
         job.memory_used_bytes = Some(estimate_memory_usage(code, parameters));
         
-        // Update stored job
+        // Update stored job, unless it was cancelled while running
         {
-            let mut jobs = self.jobs.write().map_err(|_| anyhow!("Lock poisoned"))?;
-            jobs.insert(job_id.clone(), job.clone());
+            let mut jobs = self.jobs.write_or_recover();
+            match jobs.get(&job_id) {
+                Some(stored) if matches!(stored.status, JobStatus::Cancelled) => {
+                    job = stored.clone();
+                }
+                _ => {
+                    jobs.insert(job_id.clone(), job.clone());
+                }
+            }
         }
         
         debug!("Computation job {} completed with status {:?}", job_id, job.status);
         Ok(serde_json::to_string(&job)?)
     }
     
+    /// Probe the job registry and concurrency headroom
+    pub fn health_check(&self) -> ServiceHealth {
+        let total_jobs = match self.jobs.read() {
+            Ok(jobs) => jobs.len(),
+            Err(_) => return ServiceHealth::unhealthy("computation", "Job registry lock poisoned"),
+        };
+        
+        let pool = self.worker_pool.metrics();
+        let details = serde_json::json!({
+            "total_jobs": total_jobs,
+            "running_jobs": pool.running,
+            "queued_jobs": pool.queued,
+            "max_concurrent_jobs": pool.capacity,
+            "queue_depth": pool.queue_depth,
+            "cached_results": self.result_cache.len(),
+        });
+        
+        if pool.running >= pool.capacity {
+            ServiceHealth::degraded("computation", "All execution slots are busy", details)
+        } else {
+            ServiceHealth::healthy("computation", details)
+        }
+    }
+    
     /// Get job status with detailed information
     pub fn get_job_status(&self, job_id: &str) -> Result<String> {
-        let jobs = self.jobs.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let jobs = self.jobs.read_or_recover();
         
         let job = jobs.get(job_id)
-            .ok_or_else(|| anyhow!("Job '{}' not found", job_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Job '{}' not found", job_id)))?;
         
         Ok(serde_json::to_string(job)?)
     }
     
     /// Cancel a running job
     pub fn cancel_job(&self, job_id: &str) -> Result<String> {
-        let mut jobs = self.jobs.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut jobs = self.jobs.write_or_recover();
         
         let job = jobs.get_mut(job_id)
-            .ok_or_else(|| anyhow!("Job '{}' not found", job_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Job '{}' not found", job_id)))?;
         
         match job.status {
             JobStatus::Running | JobStatus::Pending => {
-                job.status = JobStatus::Failed;
+                job.status = JobStatus::Cancelled;
                 job.error = Some("Job cancelled by user".to_string());
+                self.metrics.jobs_cancelled.incr();
                 info!("Job {} cancelled", job_id);
                 Ok(format!("{{\"status\": \"cancelled\", \"job_id\": \"{}\"}}", job_id))
             }
@@ -241,29 +664,14 @@ impl ComputationService {
     
     /// List all jobs with pagination
     pub fn list_jobs(&self, limit: Option<usize>, offset: Option<usize>) -> Result<String> {
-        let jobs = self.jobs.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let jobs = self.jobs.read_or_recover();
         
         let mut job_list: Vec<&ComputationJob> = jobs.values().collect();
-        job_list.sort_by(|a, b| b.created_at.cmp(&a.created_at)); // Most recent first
+        // Most recent first, ties broken by id so pages are stable
+        job_list.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.id.cmp(&b.id)));
         
         let total = job_list.len();
-        let offset = offset.unwrap_or(0);
-        let limit = limit.unwrap_or(50);
-        
-        let paginated: Vec<&ComputationJob> = job_list
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
-        
-        let response = serde_json::json!({
-            "jobs": paginated,
-            "total": total,
-            "offset": offset,
-            "limit": limit,
-        });
-        
-        Ok(response.to_string())
+        Ok(paginate(job_list, limit, offset, total).to_string())
     }
     
     /// Execute secure computation with full validation
@@ -294,46 +702,552 @@ enum ComputationType {
     Custom,
 }
 
-fn analyze_code_security(code: &str) -> Vec<String> {
-    let mut issues = Vec::new();
-    
-    // Check for dangerous patterns
-    let dangerous_patterns = [
-        "eval(",
-        "Function(",
-        "require(",
-        "import(",
-        "fetch(",
-        "XMLHttpRequest",
-        "process.",
-        "global.",
-        "window.",
-        "document.",
-        "__proto__",
-        "constructor",
-        "prototype.constructor",
-    ];
-    
-    for pattern in &dangerous_patterns {
-        if code.contains(pattern) {
-            issues.push(format!("Potentially dangerous pattern found: {}", pattern));
+/// Which built-in rules the code analyzer enforces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SecurityRuleSet {
+    /// Only constructs that reach outside the sandbox or evaluate code
+    Permissive,
+    /// Permissive rules plus any access to object internals and escaped strings
+    Strict,
+}
+
+/// A denied token sequence. Tokens are separated by whitespace; a quoted token
+/// (e.g. `"constructor"`) matches a string literal with that content.
+#[derive(Debug, Clone)]
+struct SecurityRule {
+    id: String,
+    description: String,
+    pattern: Vec<String>,
+}
+
+/// Built-in rules as (id, pattern, description, strict only)
+const BUILTIN_SECURITY_RULES: &[(&str, &str, &str, bool)] = &[
+    ("dynamic-eval", "eval (", "Evaluates a string as code", false),
+    ("function-constructor", "Function (", "Builds a function from a string", false),
+    ("require", "require (", "Loads a module", false),
+    ("dynamic-import", "import (", "Loads a module", false),
+    ("network-fetch", "fetch (", "Performs network I/O", false),
+    ("xhr", "XMLHttpRequest", "Performs network I/O", false),
+    ("process-access", "process .", "Accesses the host process", false),
+    ("global-access", "global .", "Accesses the global object", false),
+    ("global-this", "globalThis", "Accesses the global object", false),
+    ("window-access", "window .", "Accesses the browser global", false),
+    ("document-access", "document .", "Accesses the browser document", false),
+    ("proto-access", "__proto__", "Accesses an object prototype", false),
+    ("constructor-call", ". constructor (", "Reaches the Function constructor through an object", false),
+    ("constructor-chain", "constructor . constructor", "Reaches the Function constructor through an object", false),
+    ("constructor-access", ". constructor", "Accesses an object's constructor", true),
+    ("computed-constructor", "[ \"constructor\"", "Accesses an object's constructor", true),
+    ("computed-proto", "[ \"__proto__\"", "Accesses an object prototype", true),
+];
+
+/// Line length beyond which code is treated as obfuscated
+const MAX_CODE_LINE_LENGTH: usize = 1000;
+
+/// A rule that matched, with the 1-based line it matched on
+#[derive(Debug, Clone, Serialize)]
+struct SecurityFinding {
+    rule: String,
+    description: String,
+    line: usize,
+}
+
+impl std::fmt::Display for SecurityFinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {}: {}", self.rule, self.line, self.description)
+    }
+}
+
+/// Token-aware analyzer for submitted code. Matching works on identifiers and
+/// punctuation, so comments, string contents and longer identifiers such as
+/// `constructorArgs` do not trigger rules.
+///
+/// The rules reject obviously hostile code early with a useful message. They are not
+/// the boundary: computed names like `x["constr" + "uctor"]` get past any source check,
+/// so `SANDBOX_PRELUDE` disables the same capabilities inside the isolate.
+struct SecurityPolicy {
+    rule_set: SecurityRuleSet,
+    rules: Vec<SecurityRule>,
+}
+
+impl SecurityPolicy {
+    fn new(level: &str, denied_patterns: &[String]) -> Result<Self> {
+        let rule_set = match level {
+            "strict" => SecurityRuleSet::Strict,
+            "permissive" => SecurityRuleSet::Permissive,
+            other => return Err(anyhow!("Unknown computation security level: {}", other)),
+        };
+        
+        let mut rules: Vec<SecurityRule> = BUILTIN_SECURITY_RULES.iter()
+            .filter(|(_, _, _, strict_only)| !strict_only || rule_set == SecurityRuleSet::Strict)
+            .map(|(id, pattern, description, _)| SecurityRule {
+                id: id.to_string(),
+                description: description.to_string(),
+                pattern: pattern.split_whitespace().map(str::to_string).collect(),
+            })
+            .collect();
+        
+        for (index, pattern) in denied_patterns.iter().enumerate() {
+            let pattern: Vec<String> = pattern.split_whitespace().map(str::to_string).collect();
+            if pattern.is_empty() {
+                return Err(anyhow!("Denied pattern {} is empty", index));
+            }
+            rules.push(SecurityRule {
+                id: format!("configured-{}", index),
+                description: format!("Matches configured pattern '{}'", pattern.join(" ")),
+                pattern,
+            });
         }
+        
+        Ok(Self { rule_set, rules })
     }
     
-    // Check for suspicious character sequences
-    if code.contains("\\x") || code.contains("\\u") {
-        issues.push("Suspicious escape sequences detected".to_string());
+    /// Every rule that matches the code; empty means the code passed
+    fn analyze(&self, code: &str) -> Vec<SecurityFinding> {
+        let tokens = tokenize_code(code);
+        let mut findings = Vec::new();
+        
+        for rule in &self.rules {
+            let width = rule.pattern.len();
+            let matched = tokens.windows(width).find(|window| {
+                window.iter().zip(&rule.pattern).all(|(token, expected)| token.matches(expected))
+            });
+            if let Some(window) = matched {
+                findings.push(SecurityFinding {
+                    rule: rule.id.clone(),
+                    description: rule.description.clone(),
+                    line: window[0].line,
+                });
+            }
+        }
+        
+        if self.rule_set == SecurityRuleSet::Strict
+            && tokens.iter().any(|token| token.string_literal && token.escaped)
+        {
+            let line = tokens.iter().find(|token| token.escaped).map_or(1, |token| token.line);
+            findings.push(SecurityFinding {
+                rule: "escape-sequences".to_string(),
+                description: "String literal uses \\x or \\u escapes".to_string(),
+                line,
+            });
+        }
+        
+        if let Some(index) = code.lines().position(|line| line.len() > MAX_CODE_LINE_LENGTH) {
+            findings.push(SecurityFinding {
+                rule: "long-line".to_string(),
+                description: format!("Line longer than {} characters", MAX_CODE_LINE_LENGTH),
+                line: index + 1,
+            });
+        }
+        
+        findings
+    }
+}
+
+/// Lexical token: an identifier, a single punctuation character or a string literal
+#[derive(Debug)]
+struct CodeToken {
+    text: String,
+    line: usize,
+    string_literal: bool,
+    /// String literal containing `\x` or `\u` escapes
+    escaped: bool,
+}
+
+impl CodeToken {
+    fn matches(&self, expected: &str) -> bool {
+        let quoted = expected.len() >= 2
+            && (expected.starts_with('"') && expected.ends_with('"')
+                || expected.starts_with('\'') && expected.ends_with('\''));
+        if quoted {
+            self.string_literal && self.text == expected[1..expected.len() - 1]
+        } else {
+            !self.string_literal && self.text == expected
+        }
+    }
+}
+
+/// Minimal JavaScript lexer: skips whitespace, comments and numbers, and keeps
+/// identifiers, punctuation and string literal contents. Template literal
+/// interpolations are treated as part of the string.
+fn tokenize_code(code: &str) -> Vec<CodeToken> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' || c == '`' {
+            let start_line = line;
+            let mut text = String::new();
+            let mut escaped = false;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    escaped |= matches!(chars[i + 1], 'x' | 'u');
+                    text.push(chars[i]);
+                    i += 1;
+                }
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                text.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            tokens.push(CodeToken { text, line: start_line, string_literal: true, escaped });
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(CodeToken {
+                text: chars[start..i].iter().collect(),
+                line,
+                string_literal: false,
+                escaped: false,
+            });
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+        } else {
+            tokens.push(CodeToken { text: c.to_string(), line, string_literal: false, escaped: false });
+            i += 1;
+        }
     }
     
-    // Check for excessively long lines (potential obfuscation)
-    for line in code.lines() {
-        if line.len() > 1000 {
-            issues.push("Excessively long code line detected".to_string());
-            break;
+    tokens
+}
+
+/// Longest expression accepted by `evaluate_expression`
+const MAX_EXPRESSION_LENGTH: usize = 4096;
+
+/// Deepest nesting of parentheses, unary operators and function calls in an expression
+const MAX_EXPRESSION_DEPTH: usize = 64;
+
+/// Expression token; identifiers keep their dots so `Math.sqrt` and `price.usd` are one token
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for ExprToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprToken::Number(value) => write!(f, "number {}", value),
+            ExprToken::Ident(name) => write!(f, "'{}'", name),
+            ExprToken::Op(op) => write!(f, "'{}'", op),
+            ExprToken::LParen => write!(f, "'('"),
+            ExprToken::RParen => write!(f, "')'"),
+            ExprToken::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize_expression(expr: &str) -> Result<Vec<ExprToken>> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).map_or(false, |d| d.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, only when digits follow so `2e` stays an error
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = literal.parse::<f64>()
+                .map_err(|_| anyhow!("Invalid number '{}' at position {}", literal, start))?;
+            tokens.push(ExprToken::Number(value));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '.')) {
+                i += 1;
+            }
+            tokens.push(ExprToken::Ident(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' | '%' => ExprToken::Op(c),
+                '(' => ExprToken::LParen,
+                ')' => ExprToken::RParen,
+                ',' => ExprToken::Comma,
+                _ => return Err(anyhow!("Unexpected character '{}' at position {}", c, i)),
+            });
+            i += 1;
         }
     }
     
-    issues
+    Ok(tokens)
+}
+
+/// Recursive-descent evaluator over the grammar
+/// `expression := term (('+' | '-') term)*`,
+/// `term := unary (('*' | '/' | '%') unary)*`,
+/// `unary := ('+' | '-') unary | primary`,
+/// `primary := number | identifier | identifier '(' arguments ')' | '(' expression ')'`
+struct ExpressionParser<'a> {
+    tokens: &'a [ExprToken],
+    pos: usize,
+    depth: usize,
+    vars: &'a serde_json::Value,
+}
+
+impl ExpressionParser<'_> {
+    fn peek(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.pos)
+    }
+    
+    fn next(&mut self) -> Result<ExprToken> {
+        let token = self.tokens.get(self.pos).cloned()
+            .ok_or_else(|| anyhow!("Unexpected end of expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+    
+    fn expect(&mut self, expected: ExprToken) -> Result<()> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(anyhow!("Expected {} but found {}", expected, token))
+        }
+    }
+    
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(anyhow!("Expression nesting exceeds {} levels", MAX_EXPRESSION_DEPTH));
+        }
+        Ok(())
+    }
+    
+    fn expression(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        while let Some(&ExprToken::Op(op @ ('+' | '-'))) = self.peek() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+    
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.unary()?;
+        while let Some(&ExprToken::Op(op @ ('*' | '/' | '%'))) = self.peek() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                _ if rhs == 0.0 => {
+                    return Err(anyhow!(if op == '/' { "Division by zero" } else { "Modulo by zero" }));
+                }
+                '/' => value / rhs,
+                // Rust's % truncates like JavaScript's, so the sign follows the dividend
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+    
+    fn unary(&mut self) -> Result<f64> {
+        if let Some(&ExprToken::Op(op @ ('+' | '-'))) = self.peek() {
+            self.pos += 1;
+            self.enter()?;
+            let value = self.unary()?;
+            self.depth -= 1;
+            return Ok(if op == '-' { -value } else { value });
+        }
+        self.primary()
+    }
+    
+    fn primary(&mut self) -> Result<f64> {
+        match self.next()? {
+            ExprToken::Number(value) => Ok(value),
+            ExprToken::LParen => {
+                self.enter()?;
+                let value = self.expression()?;
+                self.expect(ExprToken::RParen)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            ExprToken::Ident(name) if self.peek() == Some(&ExprToken::LParen) => {
+                self.pos += 1;
+                self.enter()?;
+                let mut args = Vec::new();
+                if self.peek() == Some(&ExprToken::RParen) {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        args.push(self.expression()?);
+                        match self.next()? {
+                            ExprToken::Comma => continue,
+                            ExprToken::RParen => break,
+                            token => return Err(anyhow!("Expected ',' or ')' but found {}", token)),
+                        }
+                    }
+                }
+                self.depth -= 1;
+                call_math_function(&name, &args)
+            }
+            ExprToken::Ident(name) => self.lookup(&name),
+            token => Err(anyhow!("Unexpected {}", token)),
+        }
+    }
+    
+    fn lookup(&self, name: &str) -> Result<f64> {
+        match name {
+            "Math.PI" => return Ok(std::f64::consts::PI),
+            "Math.E" => return Ok(std::f64::consts::E),
+            "Math.LN2" => return Ok(std::f64::consts::LN_2),
+            "Math.LN10" => return Ok(std::f64::consts::LN_10),
+            "Math.SQRT2" => return Ok(std::f64::consts::SQRT_2),
+            _ => {}
+        }
+        
+        let value = name.split('.')
+            .try_fold(self.vars, |value, segment| value.get(segment))
+            .ok_or_else(|| anyhow!("Unknown variable '{}'", name))?;
+        value.as_f64()
+            .ok_or_else(|| anyhow!("Variable '{}' is not a number", name))
+    }
+}
+
+/// Apply a `Math.*` function with JavaScript semantics
+fn call_math_function(name: &str, args: &[f64]) -> Result<f64> {
+    let function = name.strip_prefix("Math.")
+        .ok_or_else(|| anyhow!("Unknown function '{}'; only Math.* functions are available", name))?;
+    
+    let value = match (function, args) {
+        ("abs", [x]) => x.abs(),
+        ("sqrt", [x]) => x.sqrt(),
+        ("cbrt", [x]) => x.cbrt(),
+        ("floor", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        // JavaScript rounds halves towards positive infinity
+        ("round", [x]) => (x + 0.5).floor(),
+        ("trunc", [x]) => x.trunc(),
+        ("sign", [x]) => if *x == 0.0 { 0.0 } else { x.signum() },
+        ("exp", [x]) => x.exp(),
+        ("log", [x]) => x.ln(),
+        ("log10", [x]) => x.log10(),
+        ("log2", [x]) => x.log2(),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("atan2", [y, x]) => y.atan2(*x),
+        ("pow", [x, y]) => x.powf(*y),
+        ("hypot", [x, y]) => x.hypot(*y),
+        ("min", [first, rest @ ..]) => rest.iter().fold(*first, |a, b| a.min(*b)),
+        ("max", [first, rest @ ..]) => rest.iter().fold(*first, |a, b| a.max(*b)),
+        ("abs" | "sqrt" | "cbrt" | "floor" | "ceil" | "round" | "trunc" | "sign" | "exp" | "log"
+            | "log10" | "log2" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan", _) => {
+            return Err(anyhow!("{} expects 1 argument, got {}", name, args.len()));
+        }
+        ("atan2" | "pow" | "hypot", _) => {
+            return Err(anyhow!("{} expects 2 arguments, got {}", name, args.len()));
+        }
+        ("min" | "max", _) => return Err(anyhow!("{} expects at least 1 argument", name)),
+        _ => return Err(anyhow!("Unknown function '{}'", name)),
+    };
+    
+    if !value.is_finite() {
+        return Err(anyhow!("{} returned a non-finite result", name));
+    }
+    Ok(value)
+}
+
+/// Hardening run in every isolate before the submitted code. Property lookups are
+/// resolved at runtime, so however the code spells a name (`["constructor"]`,
+/// concatenation, escapes) it reaches these replaced or removed objects:
+/// - every function constructor's `constructor` is a stub that throws, so no function
+///   value leads back to `Function` and friends
+/// - globals that evaluate strings or reach the host are deleted
+/// - the built-in prototypes and the global object are frozen against pollution
+const SANDBOX_PRELUDE: &str = r#"(() => {
+  "use strict";
+  const blocked = function () {
+    throw new TypeError("Code generation is disabled in the enclave sandbox");
+  };
+  const functionConstructors = [
+    Function,
+    Object.getPrototypeOf(function* () {}).constructor,
+    Object.getPrototypeOf(async function () {}).constructor,
+    Object.getPrototypeOf(async function* () {}).constructor,
+  ];
+  for (const constructor of functionConstructors) {
+    Object.defineProperty(constructor.prototype, "constructor", {
+      value: blocked, writable: false, enumerable: false, configurable: false,
+    });
+  }
+  for (const name of ["eval", "Function", "WebAssembly", "Deno", "SharedArrayBuffer", "Atomics",
+                      "fetch", "XMLHttpRequest", "require", "process", "window", "document"]) {
+    delete globalThis[name];
+  }
+  for (const intrinsic of [Object, Array, String, Number, Boolean, Symbol, Math, JSON, Date,
+                           RegExp, Error, Promise, Map, Set, WeakMap, WeakSet, Reflect]) {
+    Object.freeze(intrinsic);
+    if (intrinsic.prototype) Object.freeze(intrinsic.prototype);
+  }
+  for (const constructor of functionConstructors) Object.freeze(constructor.prototype);
+  Object.freeze(Object.getPrototypeOf(Object.getPrototypeOf([][Symbol.iterator]())));
+  Object.freeze(globalThis);
+})();
+"#;
+
+/// Script an isolate runs for `code`: the prelude, then the code as the body of a
+/// strict-mode function called with the parsed `args`
+fn sandboxed_script(code: &str, args: &str) -> Result<String> {
+    // `args` is passed as a string literal, so it cannot close the call and inject code
+    let args_literal = serde_json::to_string(args)?;
+    Ok(format!(
+        "{}\n(function (args) {{\n\"use strict\";\n{}\n}})(JSON.parse({}));\n",
+        SANDBOX_PRELUDE, code, args_literal
+    ))
 }
 
 fn execute_in_sandbox(code: &str, args: &str, context: &ExecutionContext) -> Result<String> {
@@ -344,7 +1258,10 @@ fn execute_in_sandbox(code: &str, args: &str, context: &ExecutionContext) -> Res
     // - Timeout handling
     // - Resource monitoring
     
-    // For now, simulate secure execution with comprehensive validation
+    // The isolate evaluates `script`; until the engine is linked in, execution is
+    // simulated from the submitted code
+    let script = sandboxed_script(code, args)?;
+    debug!("Sandbox script is {} bytes", script.len());
     let execution_start = SystemTime::now();
     
     // Simulate code execution based on simple patterns
@@ -365,7 +1282,7 @@ fn execute_in_sandbox(code: &str, args: &str, context: &ExecutionContext) -> Res
     
     // Check timeout
     if execution_start.elapsed().unwrap_or_default() > Duration::from_millis(context.timeout_ms) {
-        return Err(anyhow!("Execution timeout exceeded"));
+        return Err(EnclaveError::ResourceLimit("Execution timeout exceeded".into()).into());
     }
     
     Ok(result)
@@ -911,8 +1828,8 @@ fn execute_with_monitoring(code: &str, args: &str, context: &ExecutionContext) -
     // Pre-execution resource check
     let estimated_memory = estimate_memory_usage(code, args);
     if estimated_memory > context.memory_limit_bytes {
-        return Err(anyhow!("Estimated memory usage ({} bytes) exceeds limit ({} bytes)", 
-            estimated_memory, context.memory_limit_bytes));
+        return Err(EnclaveError::ResourceLimit(format!("Estimated memory usage ({} bytes) exceeds limit ({} bytes)", 
+            estimated_memory, context.memory_limit_bytes)).into());
     }
     
     // Execute with monitoring
@@ -923,8 +1840,218 @@ fn execute_with_monitoring(code: &str, args: &str, context: &ExecutionContext) -
     
     // Verify resource limits weren't exceeded
     if metrics.memory_peak_bytes > context.memory_limit_bytes {
-        return Err(anyhow!("Memory limit exceeded during execution: {} bytes", metrics.memory_peak_bytes));
+        return Err(EnclaveError::ResourceLimit(format!("Memory limit exceeded during execution: {} bytes", metrics.memory_peak_bytes)).into());
     }
     
     Ok((result, metrics))
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{core_services, test_config};
+
+    async fn computation_service() -> ComputationService {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let (_, _, crypto) = core_services(&config).await;
+        ComputationService::new(&config, crypto).await.unwrap()
+    }
+
+    fn was_cached(response: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(response).unwrap()["cached"].as_bool().unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_declared_deterministic_code_is_cached() {
+        let service = computation_service().await;
+        let code = "return Math.sqrt(x)";
+
+        assert!(!was_cached(&service.execute_javascript(code, r#"{"x": 4}"#).await.unwrap()));
+        assert!(!was_cached(&service.execute_javascript(code, r#"{"x": 4}"#).await.unwrap()));
+
+        let miss = service.execute_javascript_with_policy(code, r#"{"x": 9}"#, CachePolicy::Deterministic).await.unwrap();
+        let hit = service.execute_javascript_with_policy(code, r#"{"x": 9}"#, CachePolicy::Deterministic).await.unwrap();
+        assert!(!was_cached(&miss));
+        assert!(was_cached(&hit));
+        assert_eq!(service.metrics.cache_hits.get(), 1);
+        assert_eq!(service.metrics.cache_misses.get(), 1);
+
+        let other_input = service.execute_javascript_with_policy(code, r#"{"x": 16}"#, CachePolicy::Deterministic).await.unwrap();
+        assert!(!was_cached(&other_input));
+    }
+
+    #[test]
+    fn cached_results_expire() {
+        let cache = ResultCache {
+            entries: Mutex::new(HashMap::new()),
+            ttl: Duration::from_millis(20),
+            max_entries: 4,
+        };
+        let key = ResultCache::key("javascript", "code", "input");
+        cache.insert(key, "result".to_string());
+        assert_eq!(cache.get(&key).as_deref(), Some("result"));
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn full_cache_drops_the_oldest_result() {
+        let cache = ResultCache::new(300, 2);
+        let keys: Vec<_> = (0..3).map(|i| ResultCache::key("javascript", "code", &i.to_string())).collect();
+        for key in &keys {
+            cache.insert(*key, "result".to_string());
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        assert_eq!(cache.get(&keys[0]), None);
+        assert!(cache.get(&keys[1]).is_some() && cache.get(&keys[2]).is_some());
+    }
+
+    #[tokio::test]
+    async fn job_ids_are_random_and_prefixed_with_the_computation_id() {
+        let service = computation_service().await;
+        for _ in 0..3 {
+            service.execute_computation("price", "return 1", "{}").await.unwrap();
+        }
+
+        let jobs: serde_json::Value = serde_json::from_str(&service.list_jobs(None, None).unwrap()).unwrap();
+        let ids: std::collections::HashSet<&str> = jobs["items"].as_array().unwrap().iter()
+            .map(|job| job["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 3);
+        for id in ids {
+            let suffix = id.strip_prefix("price_").unwrap();
+            assert_eq!(suffix.len(), 22);
+            assert!(suffix.bytes().all(|b| b.is_ascii_alphanumeric()));
+        }
+    }
+
+    #[tokio::test]
+    async fn javascript_runs_in_a_worker_pool_slot() {
+        let mut service = computation_service().await;
+        service.worker_pool = WorkerPool::new(1, 0);
+
+        let held = service.worker_pool.acquire().await.unwrap();
+        let error = service.execute_javascript("return 1", "{}").await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<WorkerPoolError>(), Some(WorkerPoolError::QueueFull { .. })));
+        assert_eq!(service.metrics.jobs_rejected.get(), 1);
+
+        drop(held);
+        service.execute_javascript("return 1", "{}").await.unwrap();
+        assert_eq!(service.pool_metrics().running, 0);
+    }
+
+    #[test]
+    fn submitted_code_runs_after_the_sandbox_prelude() {
+        let code = r#"return x["constr" + "uctor"]["constr" + "uctor"]("return this")()"#;
+        let args = r#"{"a": "\"));globalThis.leak=1;(\""}"#;
+        let script = sandboxed_script(code, args).unwrap();
+
+        let prelude_end = script.find(SANDBOX_PRELUDE).unwrap() + SANDBOX_PRELUDE.len();
+        assert_eq!(script.find(SANDBOX_PRELUDE), Some(0));
+        assert!(script[prelude_end..].find(code).is_some());
+
+        // The arguments stay one string literal, whatever they contain
+        let call = script.rsplit("JSON.parse(").next().unwrap().trim_end().strip_suffix("));").unwrap();
+        assert_eq!(serde_json::from_str::<String>(call).unwrap(), args);
+    }
+
+    #[test]
+    fn prelude_replaces_every_function_constructor_and_freezes_globals() {
+        for expression in ["function* () {}", "async function () {}", "async function* () {}"] {
+            assert!(SANDBOX_PRELUDE.contains(&format!("Object.getPrototypeOf({}).constructor", expression)));
+        }
+        assert!(SANDBOX_PRELUDE.contains(r#"Object.defineProperty(constructor.prototype, "constructor""#));
+        assert!(SANDBOX_PRELUDE.contains(r#""eval", "Function""#));
+        assert!(SANDBOX_PRELUDE.trim_end().ends_with("Object.freeze(globalThis);\n})();"));
+    }
+
+    #[tokio::test]
+    async fn imported_jobs_that_were_in_flight_are_failed() {
+        let service = computation_service().await;
+        service.execute_computation("done", "return 1", "{}").await.unwrap();
+        let mut records = service.export_jobs();
+        let mut interrupted = records[0].clone();
+        interrupted.id = "interrupted_1".to_string();
+        interrupted.status = JobStatus::Running;
+        records.push(interrupted);
+
+        let restored = computation_service().await;
+        assert_eq!(restored.import_jobs(records.clone()), 2);
+        assert_eq!(restored.import_jobs(records), 0);
+        let status: serde_json::Value = serde_json::from_str(&restored.get_job_status("interrupted_1").unwrap()).unwrap();
+        assert_eq!(status["status"], "Failed");
+    }
+
+    #[tokio::test]
+    async fn expressions_follow_operator_precedence() {
+        let service = computation_service().await;
+        let none = serde_json::json!({});
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("10 - 4 - 3", 3.0),
+            ("2 * 9 / 3 % 4", 2.0),
+            ("-2 * -3", 6.0),
+            ("-7 % 3", -1.0),
+            ("2 - -2", 4.0),
+            ("1.5e2 + .5", 150.5),
+            ("Math.max(1, Math.pow(2, 3), 5) + Math.round(-2.5)", 6.0),
+            ("Math.floor(Math.SQRT2 * 1000) / 4", 353.5),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(service.evaluate_expression(expr, &none).unwrap(), expected, "{}", expr);
+        }
+    }
+
+    #[tokio::test]
+    async fn expressions_substitute_variables_including_nested_fields() {
+        let service = computation_service().await;
+        let vars = serde_json::json!({ "amount": 4, "fee": 0.25, "price": { "usd": 12.5 }, "symbol": "NEO" });
+        assert_eq!(service.evaluate_expression("amount * price.usd - fee", &vars).unwrap(), 49.75);
+        assert_eq!(service.evaluate_expression("Math.sqrt(amount) + amount", &vars).unwrap(), 6.0);
+        
+        let error = |expr: &str| service.evaluate_expression(expr, &vars).unwrap_err().to_string();
+        assert_eq!(error("missing + 1"), "Unknown variable 'missing'");
+        assert_eq!(error("price.eur"), "Unknown variable 'price.eur'");
+        assert_eq!(error("symbol * 2"), "Variable 'symbol' is not a number");
+    }
+
+    #[tokio::test]
+    async fn malformed_expressions_and_division_by_zero_are_errors() {
+        let service = computation_service().await;
+        let vars = serde_json::json!({ "zero": 0 });
+        let error = |expr: &str| service.evaluate_expression(expr, &vars).unwrap_err().to_string();
+        assert_eq!(error("1 / zero"), "Division by zero");
+        assert_eq!(error("1 % (2 - 2)"), "Modulo by zero");
+        assert_eq!(error("(1 + 2"), "Unexpected end of expression");
+        assert_eq!(error("1 + 2)"), "Unexpected ')' at end of expression");
+        assert_eq!(error("1 +"), "Unexpected end of expression");
+        assert_eq!(error("2 ^ 3"), "Unexpected character '^' at position 2");
+        assert_eq!(error("alert(1)"), "Unknown function 'alert'; only Math.* functions are available");
+        assert_eq!(error("Math.pow(2)"), "Math.pow expects 2 arguments, got 1");
+        assert_eq!(error("Math.log(zero)"), "Math.log returned a non-finite result");
+        assert_eq!(error(""), "Unexpected end of expression");
+        
+        let nested = format!("{}1{}", "(".repeat(MAX_EXPRESSION_DEPTH + 1), ")".repeat(MAX_EXPRESSION_DEPTH + 1));
+        assert!(error(&nested).contains("nesting exceeds"));
+        assert!(error(&"1+".repeat(MAX_EXPRESSION_LENGTH)).contains("exceeds"));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_submissions_never_share_a_job() {
+        let service = Arc::new(computation_service().await);
+        let submissions = 64;
+        let mut tasks = tokio::task::JoinSet::new();
+        for _ in 0..submissions {
+            let service = service.clone();
+            tasks.spawn(async move { service.execute_computation("burst", "return 1", "{}").await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap().unwrap();
+        }
+        assert_eq!(service.jobs.read().unwrap().len(), submissions);
+    }
+}
@@ -2,27 +2,182 @@ use anyhow::{Result, anyhow};
 use ring::aead;
 use ring::rand::{SecureRandom, SystemRandom};
 use ring::aead::BoundKey;
-use secp256k1::{Secp256k1, SecretKey, PublicKey, Message, ecdsa::Signature};
+use secp256k1::{Secp256k1, SecretKey, PublicKey, Message, ecdsa::{RecoverableSignature, RecoveryId, Signature}};
 use ed25519_dalek::{SigningKey, Signer, Verifier, VerifyingKey, Signature as Ed25519Signature};
+use p256::ecdsa::{SigningKey as P256SigningKey, VerifyingKey as P256VerifyingKey, Signature as P256Signature};
+use rsa::{RsaPrivateKey, RsaPublicKey, Pkcs1v15Sign};
+use rsa::traits::PublicKeyParts;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::rand_core::OsRng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use sha2::{Sha256, Digest};
 use log::{info, warn, error, debug};
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::EncaveConfig;
+use crate::attestation::{key_report_data, AttestationService};
+use crate::audit::{AuditEvent, AuditLog};
+use crate::error::EnclaveError;
+use crate::health::ServiceHealth;
+use crate::metrics::{Counter, CryptoMetrics};
+use crate::locks::RwLockExt;
 
 /// Supported cryptographic algorithms
+///
+/// Neo N3 account keys are ECDSA over secp256r1 (NIST P-256) with SHA-256; secp256k1
+/// is kept for non-Neo integrations only.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CryptoAlgorithm {
     Aes256Gcm,
     ChaCha20Poly1305,
     Secp256k1,
+    Secp256r1,
     Ed25519,
+    Rsa2048,
+    Rsa4096,
     Sha256,
     Sha3_256,
 }
 
+impl CryptoAlgorithm {
+    /// Configuration name of the algorithm, as accepted by `from_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CryptoAlgorithm::Aes256Gcm => "aes-256-gcm",
+            CryptoAlgorithm::ChaCha20Poly1305 => "chacha20-poly1305",
+            CryptoAlgorithm::Secp256k1 => "secp256k1",
+            CryptoAlgorithm::Secp256r1 => "secp256r1",
+            CryptoAlgorithm::Ed25519 => "ed25519",
+            CryptoAlgorithm::Rsa2048 => "rsa-2048",
+            CryptoAlgorithm::Rsa4096 => "rsa-4096",
+            CryptoAlgorithm::Sha256 => "sha256",
+            CryptoAlgorithm::Sha3_256 => "sha3-256",
+        }
+    }
+    
+    /// Parse a configuration algorithm name (e.g. "aes-256-gcm")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "aes-256-gcm" => Some(CryptoAlgorithm::Aes256Gcm),
+            "chacha20-poly1305" => Some(CryptoAlgorithm::ChaCha20Poly1305),
+            "secp256k1" => Some(CryptoAlgorithm::Secp256k1),
+            "secp256r1" | "p-256" => Some(CryptoAlgorithm::Secp256r1),
+            "ed25519" => Some(CryptoAlgorithm::Ed25519),
+            "rsa-2048" => Some(CryptoAlgorithm::Rsa2048),
+            "rsa-4096" => Some(CryptoAlgorithm::Rsa4096),
+            "sha256" => Some(CryptoAlgorithm::Sha256),
+            "sha3-256" => Some(CryptoAlgorithm::Sha3_256),
+            _ => None,
+        }
+    }
+    
+    /// Modulus size in bits for RSA algorithms
+    pub fn rsa_key_bits(&self) -> Option<usize> {
+        match self {
+            CryptoAlgorithm::Rsa2048 => Some(2048),
+            CryptoAlgorithm::Rsa4096 => Some(4096),
+            _ => None,
+        }
+    }
+    
+    /// Signature scheme produced by keys of this type, if they can sign
+    pub fn signature_scheme(&self) -> Option<&'static str> {
+        match self {
+            CryptoAlgorithm::Secp256k1 => Some("ECDSA-secp256k1-SHA256"),
+            CryptoAlgorithm::Secp256r1 => Some("ECDSA-secp256r1-SHA256"),
+            CryptoAlgorithm::Ed25519 => Some("Ed25519"),
+            CryptoAlgorithm::Rsa2048 | CryptoAlgorithm::Rsa4096 => Some(RSA_SIGNATURE_SCHEME),
+            _ => None,
+        }
+    }
+}
+
+/// RSA keys smaller than this are rejected for signing and verification
+pub const MIN_RSA_KEY_BITS: usize = 2048;
+
+/// RSA signatures are PKCS#1 v1.5 over a SHA-256 digest
+pub const RSA_SIGNATURE_SCHEME: &str = "RSASSA-PKCS1-v1_5-SHA256";
+
+/// Recoverable secp256k1 signatures are r || s followed by a one-byte recovery id
+pub const RECOVERABLE_SIGNATURE_LEN: usize = 65;
+
+/// `encrypt_with_key` output starts with the big-endian key generation used
+pub const KEY_GENERATION_TAG_LEN: usize = 4;
+
+/// Longest prefix accepted by `generate_id`
+pub const MAX_ID_PREFIX_LEN: usize = 192;
+
+/// Base62 digits needed for 128 bits
+const ID_SUFFIX_LEN: usize = 22;
+
+const BASE62_ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// How long `self_test` benchmarks each algorithm
+const BENCHMARK_DURATION: Duration = Duration::from_millis(50);
+
+/// Message size for hash and cipher throughput benchmarks
+const BENCHMARK_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Outcome of one algorithm's self-test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    Passed,
+    Failed,
+    /// No implementation to test is built in
+    Skipped,
+}
+
+/// Throughput of an algorithm's main operation on this hardware
+#[derive(Debug, Clone, Serialize)]
+pub struct Benchmark {
+    pub operation: &'static str,
+    pub ops_per_second: f64,
+    /// For hashes and ciphers, over `BENCHMARK_BLOCK_SIZE` messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mb_per_second: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestResult {
+    pub algorithm: &'static str,
+    pub status: SelfTestStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub benchmark: Option<Benchmark>,
+}
+
+/// Report produced by `CryptoService::self_test`
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// False if any known-answer test failed
+    pub passed: bool,
+    pub results: Vec<SelfTestResult>,
+    pub duration_ms: u128,
+}
+
+impl SelfTestReport {
+    /// "algorithm: error" for every failed test
+    pub fn failures(&self) -> Vec<String> {
+        self.results.iter()
+            .filter(|result| result.status == SelfTestStatus::Failed)
+            .map(|result| format!("{}: {}", result.algorithm, result.error.as_deref().unwrap_or("failed")))
+            .collect()
+    }
+}
+
+/// Compare two byte buffers without leaking where they differ through timing.
+/// Use this for MACs, checksums and any other secret-dependent comparison.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
 /// Key metadata structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyMetadata {
@@ -32,15 +187,121 @@ pub struct KeyMetadata {
     pub exportable: bool,
     pub created_at: u64,
     pub description: String,
+    /// Raw public key; SEC1 compressed for ECDSA keys, SubjectPublicKeyInfo DER for RSA keys
     pub public_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub signature_scheme: Option<String>,
+    /// Whether the key was generated with `attestation_quote` committing to its public key
+    #[serde(default)]
+    pub attestation_bound: bool,
+    /// Quote whose report data is [`crate::attestation::key_report_data`] of this key's id,
+    /// type, generation and `public_key`, proving the key was generated inside this enclave
+    #[serde(default)]
+    pub attestation_quote: Option<Vec<u8>>,
+    /// Unix time after which the key refuses to sign, verify or encrypt; decryption of
+    /// existing ciphertexts keeps working
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Snapshot of the key's usage counters, filled in by `get_key_metadata`
+    #[serde(default)]
+    pub usage_stats: KeyUsageStats,
+    /// Active generation, starting at 1 and incremented by `rotate_key`; older
+    /// generations can only decrypt and verify
+    #[serde(default = "first_key_generation")]
+    pub generation: u32,
+    /// When the key was last rotated
+    #[serde(default)]
+    pub rotated_at: Option<u64>,
+}
+
+fn first_key_generation() -> u32 {
+    1
+}
+
+impl KeyMetadata {
+    /// Whether `expires_at` has been reached
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| unix_now() >= expires_at)
+    }
+}
+
+/// How often a key has been used since it was created or imported
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyUsageStats {
+    pub sign_count: u64,
+    pub verify_count: u64,
+    pub encrypt_count: u64,
+    pub decrypt_count: u64,
+    pub last_used_at: Option<u64>,
+}
+
+/// Live usage counters, updated under the keystore read lock
+#[derive(Debug, Default)]
+struct KeyUsage {
+    sign_count: Counter,
+    verify_count: Counter,
+    encrypt_count: Counter,
+    decrypt_count: Counter,
+    /// Unix time of the last successful operation, 0 if never used
+    last_used_at: AtomicU64,
+}
+
+impl KeyUsage {
+    fn snapshot(&self) -> KeyUsageStats {
+        let last_used_at = self.last_used_at.load(Ordering::Relaxed);
+        KeyUsageStats {
+            sign_count: self.sign_count.get(),
+            verify_count: self.verify_count.get(),
+            encrypt_count: self.encrypt_count.get(),
+            decrypt_count: self.decrypt_count.get(),
+            last_used_at: (last_used_at > 0).then_some(last_used_at),
+        }
+    }
+}
+
+/// Secret material for one generation of a key
+enum KeyMaterial {
+    Symmetric(Zeroizing<Vec<u8>>),
+    /// Private and public key
+    Asymmetric(Zeroizing<Vec<u8>>, Vec<u8>),
+}
+
+impl KeyMaterial {
+    fn public_key_bytes(&self) -> Option<&[u8]> {
+        match self {
+            KeyMaterial::Symmetric(_) => None,
+            KeyMaterial::Asymmetric(_, public_key) => Some(public_key),
+        }
+    }
+    
+    /// Make this the active material of `key_id`, returning the material it replaces
+    fn activate(self, key_store: &mut KeyStore, key_id: &str) -> Option<KeyMaterial> {
+        match self {
+            KeyMaterial::Symmetric(key) => key_store.symmetric_keys
+                .insert(key_id.to_string(), key)
+                .map(KeyMaterial::Symmetric),
+            KeyMaterial::Asymmetric(private_key, public_key) => key_store.asymmetric_keys
+                .insert(key_id.to_string(), (private_key, public_key))
+                .map(|(private_key, public_key)| KeyMaterial::Asymmetric(private_key, public_key)),
+        }
+    }
+    
+    fn zeroize(&mut self) {
+        match self {
+            KeyMaterial::Symmetric(key) => key.zeroize(),
+            KeyMaterial::Asymmetric(private_key, _) => private_key.zeroize(),
+        }
+    }
 }
 
-/// Cryptographic key storage
-#[derive(Debug)]
+/// Cryptographic key storage. Secret key bytes are wiped when dropped.
 struct KeyStore {
-    symmetric_keys: HashMap<String, Vec<u8>>,
-    asymmetric_keys: HashMap<String, (Vec<u8>, Vec<u8>)>, // (private, public)
+    symmetric_keys: HashMap<String, Zeroizing<Vec<u8>>>,
+    asymmetric_keys: HashMap<String, (Zeroizing<Vec<u8>>, Vec<u8>)>, // (private, public)
+    /// Generations replaced by `rotate_key`, kept for decryption and verification only
+    retired: HashMap<String, BTreeMap<u32, KeyMaterial>>,
     metadata: HashMap<String, KeyMetadata>,
+    usage: HashMap<String, KeyUsage>,
 }
 
 impl KeyStore {
@@ -48,38 +309,65 @@ impl KeyStore {
         Self {
             symmetric_keys: HashMap::new(),
             asymmetric_keys: HashMap::new(),
+            retired: HashMap::new(),
             metadata: HashMap::new(),
+            usage: HashMap::new(),
+        }
+    }
+    
+    /// Bump one of `key_id`'s usage counters and its last-used time
+    fn record_use(&self, key_id: &str, counter: impl Fn(&KeyUsage) -> &Counter) {
+        if let Some(usage) = self.usage.get(key_id) {
+            counter(usage).incr();
+            usage.last_used_at.store(unix_now(), Ordering::Relaxed);
         }
     }
 }
 
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reject new operations with a key past its expiry
+fn ensure_not_expired(metadata: &KeyMetadata) -> Result<()> {
+    if metadata.is_expired() {
+        warn!("Rejected use of expired key '{}'", metadata.key_id);
+        return Err(EnclaveError::PermissionDenied(format!(
+            "Key '{}' expired at {}", metadata.key_id, metadata.expires_at.unwrap_or_default()
+        )).into());
+    }
+    Ok(())
+}
+
 /// Main cryptographic service for the enclave
 pub struct CryptoService {
     rng: SystemRandom,
     secp256k1: Secp256k1<secp256k1::All>,
     key_store: Arc<RwLock<KeyStore>>,
-    #[allow(dead_code)]
     supported_algorithms: Vec<CryptoAlgorithm>,
+    /// Reject secp256k1 signatures whose s is in the upper half of the curve order
+    require_low_s: bool,
+    metrics: CryptoMetrics,
+    audit_log: Arc<AuditLog>,
+    attestation_service: Arc<AttestationService>,
 }
 
 impl CryptoService {
     /// Create a new crypto service instance
-    pub async fn new(config: &EncaveConfig) -> Result<Self> {
+    pub async fn new(config: &EncaveConfig, audit_log: Arc<AuditLog>) -> Result<Self> {
         info!("Initializing CryptoService");
         
         let supported_algorithms = config.crypto_algorithms
             .iter()
-            .filter_map(|alg| match alg.as_str() {
-                "aes-256-gcm" => Some(CryptoAlgorithm::Aes256Gcm),
-                "chacha20-poly1305" => Some(CryptoAlgorithm::ChaCha20Poly1305),
-                "secp256k1" => Some(CryptoAlgorithm::Secp256k1),
-                "ed25519" => Some(CryptoAlgorithm::Ed25519),
-                "sha256" => Some(CryptoAlgorithm::Sha256),
-                "sha3-256" => Some(CryptoAlgorithm::Sha3_256),
-                _ => {
+            .filter_map(|alg| {
+                let parsed = CryptoAlgorithm::from_name(alg);
+                if parsed.is_none() {
                     warn!("Unsupported crypto algorithm: {}", alg);
-                    None
                 }
+                parsed
             })
             .collect();
         
@@ -88,21 +376,230 @@ impl CryptoService {
             secp256k1: Secp256k1::new(),
             key_store: Arc::new(RwLock::new(KeyStore::new())),
             supported_algorithms,
+            require_low_s: config.crypto_require_low_s,
+            metrics: CryptoMetrics::default(),
+            audit_log,
+            attestation_service: Arc::new(AttestationService::new(config)),
         })
     }
     
-    /// Generate a secure random number within range
+    /// Run known-answer tests and a short throughput benchmark for every configured
+    /// algorithm, returning the report as JSON
+    ///
+    /// SHA-256 and AES-256-GCM are always tested since storage depends on them. A failed
+    /// known-answer test means a broken or mis-linked crypto library; the report's `passed`
+    /// is false and the algorithm lists the error.
+    pub fn self_test(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.self_test_report())?)
+    }
+    
+    /// `self_test` as a report value
+    pub fn self_test_report(&self) -> SelfTestReport {
+        let started = Instant::now();
+        
+        let mut algorithms = vec![CryptoAlgorithm::Sha256, CryptoAlgorithm::Aes256Gcm];
+        for algorithm in &self.supported_algorithms {
+            if !algorithms.iter().any(|tested| tested.name() == algorithm.name()) {
+                algorithms.push(algorithm.clone());
+            }
+        }
+        
+        let results: Vec<SelfTestResult> = algorithms.iter()
+            .map(|algorithm| {
+                let (status, error, benchmark) = match self.test_algorithm(algorithm) {
+                    Ok(Some(benchmark)) => (SelfTestStatus::Passed, None, Some(benchmark)),
+                    Ok(None) => (SelfTestStatus::Skipped, Some("no implementation is built in".to_string()), None),
+                    Err(e) => {
+                        error!("Crypto self-test failed for {}: {}", algorithm.name(), e);
+                        (SelfTestStatus::Failed, Some(e.to_string()), None)
+                    }
+                };
+                SelfTestResult { algorithm: algorithm.name(), status, error, benchmark }
+            })
+            .collect();
+        
+        SelfTestReport {
+            passed: results.iter().all(|result| result.status != SelfTestStatus::Failed),
+            results,
+            duration_ms: started.elapsed().as_millis(),
+        }
+    }
+    
+    /// Known-answer test for one algorithm followed, if it passes, by its benchmark.
+    /// `None` when the algorithm has no implementation.
+    fn test_algorithm(&self, algorithm: &CryptoAlgorithm) -> Result<Option<Benchmark>> {
+        let benchmark = match algorithm {
+            CryptoAlgorithm::Sha256 => {
+                // FIPS 180-2 examples
+                expect_hex("SHA-256(\"abc\")", &Sha256::digest(b"abc"),
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")?;
+                expect_hex("SHA-256(\"\")", &Sha256::digest(b""),
+                    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")?;
+                
+                let block = vec![0u8; BENCHMARK_BLOCK_SIZE];
+                measure("hash", Some(BENCHMARK_BLOCK_SIZE), || {
+                    Sha256::digest(&block);
+                    Ok(())
+                })?
+            }
+            CryptoAlgorithm::Sha3_256 => return Ok(None),
+            CryptoAlgorithm::Aes256Gcm => {
+                // GCM specification test case 14
+                aead_known_answer(
+                    &aead::AES_256_GCM,
+                    &[0u8; 32],
+                    [0u8; 12],
+                    &[],
+                    &[0u8; 16],
+                    "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919",
+                )?;
+                aead_benchmark(&aead::AES_256_GCM)?
+            }
+            CryptoAlgorithm::ChaCha20Poly1305 => {
+                // RFC 8439 section 2.8.2
+                aead_known_answer(
+                    &aead::CHACHA20_POLY1305,
+                    &hex::decode("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f")?,
+                    [0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47],
+                    &hex::decode("50515253c0c1c2c3c4c5c6c7")?,
+                    b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.",
+                    "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b\
+                     1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc3f\
+                     f4def08e4b7a9de576d26586cec64b61161ae10b594f09e26a7e902ecbd0600691",
+                )?;
+                aead_benchmark(&aead::CHACHA20_POLY1305)?
+            }
+            CryptoAlgorithm::Secp256k1 => {
+                // Private key 1 has the generator as its public key; the signature is the
+                // widely published RFC 6979 vector for "Satoshi Nakamoto"
+                let mut private_key = [0u8; 32];
+                private_key[31] = 1;
+                let private_key = SecretKey::from_slice(&private_key)?;
+                let public_key = PublicKey::from_secret_key(&self.secp256k1, &private_key).serialize();
+                expect_hex("secp256k1 public key", &public_key,
+                    "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")?;
+                
+                let message = b"Satoshi Nakamoto";
+                let sign = || self.secp256k1
+                    .sign_ecdsa(&Message::from_digest(Sha256::digest(message).into()), &private_key)
+                    .serialize_compact();
+                expect_hex("secp256k1 signature", &sign(),
+                    "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                     2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5")?;
+                self.expect_verifies(algorithm, &public_key, message, &sign())?;
+                
+                measure("sign", None, || {
+                    sign();
+                    Ok(())
+                })?
+            }
+            CryptoAlgorithm::Secp256r1 => {
+                // RFC 6979 appendix A.2.5, SHA-256 over "sample"
+                let signing_key = P256SigningKey::from_slice(&hex::decode(
+                    "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721"
+                )?).map_err(|e| anyhow!("Invalid secp256r1 test key: {}", e))?;
+                let public_key = signing_key.verifying_key().to_encoded_point(false);
+                expect_hex("secp256r1 public key", public_key.as_bytes(),
+                    "0460fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6\
+                     7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299")?;
+                
+                let message = b"sample";
+                let signature: P256Signature = signing_key.sign(message);
+                expect_hex("secp256r1 signature", &signature.to_bytes(),
+                    "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+                     f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8")?;
+                self.expect_verifies(algorithm, public_key.as_bytes(), message, &signature.to_bytes())?;
+                
+                measure("sign", None, || {
+                    let _: P256Signature = signing_key.sign(message);
+                    Ok(())
+                })?
+            }
+            CryptoAlgorithm::Ed25519 => {
+                // RFC 8032 section 7.1, test 1
+                let signing_key = SigningKey::from_bytes(&hex::decode(
+                    "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+                )?.try_into().map_err(|_| anyhow!("Invalid Ed25519 test key"))?);
+                let public_key = signing_key.verifying_key().to_bytes();
+                expect_hex("Ed25519 public key", &public_key,
+                    "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")?;
+                
+                let signature = signing_key.sign(b"").to_bytes();
+                expect_hex("Ed25519 signature", &signature,
+                    "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555\
+                     fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b")?;
+                self.expect_verifies(algorithm, &public_key, b"", &signature)?;
+                
+                measure("sign", None, || {
+                    signing_key.sign(b"");
+                    Ok(())
+                })?
+            }
+            CryptoAlgorithm::Rsa2048 | CryptoAlgorithm::Rsa4096 => {
+                // Round trip only: there is no compact published vector, and a 2048-bit key
+                // exercises the same code as a 4096-bit one in a fraction of the time
+                let private_key = RsaPrivateKey::new(&mut OsRng, MIN_RSA_KEY_BITS)
+                    .map_err(|e| anyhow!("RSA key generation failed: {}", e))?;
+                let public_key = private_key.to_public_key();
+                let message = b"sample";
+                let sign = || private_key.sign(Pkcs1v15Sign::new::<Sha256>(), &Sha256::digest(message))
+                    .map_err(|e| anyhow!("RSA signing failed: {}", e));
+                
+                let mut signature = sign()?;
+                if !verify_rsa(&public_key, message, &signature) {
+                    return Err(anyhow!("RSA signature does not verify"));
+                }
+                signature[0] ^= 1;
+                if verify_rsa(&public_key, message, &signature) {
+                    return Err(anyhow!("RSA accepted a corrupted signature"));
+                }
+                
+                measure("sign", None, || sign().map(drop))?
+            }
+        };
+        Ok(Some(benchmark))
+    }
+    
+    /// Check that a signature verifies through `check_signature` and a corrupted copy does not
+    fn expect_verifies(&self, algorithm: &CryptoAlgorithm, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()> {
+        if !self.check_signature(algorithm, public_key, data, signature)? {
+            return Err(anyhow!("{} signature does not verify", algorithm.name()));
+        }
+        let mut corrupted = signature.to_vec();
+        corrupted[0] ^= 1;
+        if self.check_signature(algorithm, public_key, data, &corrupted)? {
+            return Err(anyhow!("{} accepted a corrupted signature", algorithm.name()));
+        }
+        Ok(())
+    }
+    
+    /// Attestation service used to bind generated keys to this enclave
+    pub fn attestation_service(&self) -> &Arc<AttestationService> {
+        &self.attestation_service
+    }
+    
+    /// Generate a uniformly distributed secure random number in `min..max`
     pub fn generate_random(&self, min: i32, max: i32) -> Result<i32> {
         if min >= max {
-            return Err(anyhow!("Min must be less than max"));
+            return Err(EnclaveError::InvalidInput("Min must be less than max".into()).into());
         }
         
-        let range = (max - min) as u32;
-        let mut bytes = vec![0u8; 4];
-        self.rng.fill(&mut bytes)?;
+        // Widen first: the span of the full i32 range does not fit in an i32
+        let range = (max as i64 - min as i64) as u64;
+        let result = (min as i64 + self.random_below(range)? as i64) as i32;
+        
+        debug!("Generated random number: {} (range: {} - {})", result, min, max);
+        Ok(result)
+    }
+    
+    /// Generate a uniformly distributed secure random number in `min..max` for ranges
+    /// wider than `i32`
+    pub fn generate_random_u64_range(&self, min: u64, max: u64) -> Result<u64> {
+        if min >= max {
+            return Err(EnclaveError::InvalidInput("Min must be less than max".into()).into());
+        }
         
-        let random_u32 = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        let result = min + (random_u32 % range) as i32;
+        let result = min + self.random_below(max - min)?;
         
         debug!("Generated random number: {} (range: {} - {})", result, min, max);
         Ok(result)
@@ -111,7 +608,7 @@ impl CryptoService {
     /// Generate secure random bytes
     pub fn generate_random_bytes(&self, length: usize) -> Result<Vec<u8>> {
         if length == 0 || length > 1024 * 1024 {
-            return Err(anyhow!("Invalid length: must be between 1 and 1MB"));
+            return Err(EnclaveError::InvalidInput("Invalid length: must be between 1 and 1MB".into()).into());
         }
         
         let mut bytes = vec![0u8; length];
@@ -121,6 +618,84 @@ impl CryptoService {
         Ok(bytes)
     }
     
+    /// Unique id made of `prefix`, an underscore and 128 secure random bits in base62,
+    /// e.g. `train_model1_4Vq0...`. The suffix is URL-safe and always 22 characters.
+    pub fn generate_id(&self, prefix: &str) -> Result<String> {
+        if prefix.len() > MAX_ID_PREFIX_LEN {
+            return Err(EnclaveError::InvalidInput(format!(
+                "Id prefix is {} bytes, at most {} allowed", prefix.len(), MAX_ID_PREFIX_LEN
+            )).into());
+        }
+        
+        let mut bytes = [0u8; 16];
+        self.rng.fill(&mut bytes)?;
+        let mut value = u128::from_be_bytes(bytes);
+        let mut suffix = [b'0'; ID_SUFFIX_LEN];
+        for digit in suffix.iter_mut().rev() {
+            *digit = BASE62_ALPHABET[(value % 62) as usize];
+            value /= 62;
+        }
+        let suffix = std::str::from_utf8(&suffix)?;
+        
+        if prefix.is_empty() {
+            Ok(suffix.to_string())
+        } else {
+            Ok(format!("{}_{}", prefix, suffix))
+        }
+    }
+    
+    /// Uniform random index in `0..bound`, drawn from the secure RNG without modulo bias
+    pub fn random_index(&self, bound: usize) -> Result<usize> {
+        if bound == 0 {
+            return Err(EnclaveError::InvalidInput("Bound must be greater than zero".into()).into());
+        }
+        
+        Ok(self.random_below(bound as u64)? as usize)
+    }
+    
+    /// Uniform random value in `0..bound` for a non-zero bound, using rejection sampling
+    fn random_below(&self, bound: u64) -> Result<u64> {
+        // Values below 2^64 mod bound would make low results more likely; redraw them
+        let threshold = bound.wrapping_neg() % bound;
+        let mut bytes = [0u8; 8];
+        loop {
+            self.rng.fill(&mut bytes)?;
+            let value = u64::from_le_bytes(bytes);
+            if value >= threshold {
+                return Ok(value % bound);
+            }
+        }
+    }
+    
+    /// Shuffle `items` in place with Fisher-Yates using the secure RNG
+    pub fn secure_shuffle<T>(&self, items: &mut [T]) -> Result<()> {
+        for i in (1..items.len()).rev() {
+            let j = self.random_index(i + 1)?;
+            items.swap(i, j);
+        }
+        Ok(())
+    }
+    
+    /// Sample `k` distinct indices from `0..n` in random order
+    pub fn secure_sample(&self, n: usize, k: usize) -> Result<Vec<usize>> {
+        if k > n {
+            return Err(anyhow!("Cannot sample {} distinct indices from {}", k, n));
+        }
+        
+        // Floyd's algorithm picks a uniform subset in O(k); the shuffle randomizes its order
+        let mut chosen = std::collections::HashSet::with_capacity(k);
+        let mut sample = Vec::with_capacity(k);
+        for j in n - k..n {
+            let candidate = self.random_index(j + 1)?;
+            let pick = if chosen.contains(&candidate) { j } else { candidate };
+            chosen.insert(pick);
+            sample.push(pick);
+        }
+        self.secure_shuffle(&mut sample)?;
+        
+        Ok(sample)
+    }
+    
     /// Generate a cryptographic key
     pub fn generate_key(
         &self,
@@ -131,73 +706,376 @@ impl CryptoService {
         description: &str,
     ) -> Result<KeyMetadata> {
         if key_id.is_empty() {
-            return Err(anyhow!("Key ID cannot be empty"));
+            return Err(EnclaveError::InvalidInput("Key ID cannot be empty".into()).into());
         }
         
-        let mut key_store = self.key_store.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut key_store = self.key_store.write_or_recover();
         
         if key_store.metadata.contains_key(key_id) {
-            return Err(anyhow!("Key with ID '{}' already exists", key_id));
+            return Err(EnclaveError::AlreadyExists(format!("Key with ID '{}' already exists", key_id)).into());
         }
         
-        let (public_key_bytes, created_at) = match key_type {
+        let material = self.new_key_material(&key_type)?;
+        let public_key_bytes = material.public_key_bytes().map(<[u8]>::to_vec);
+        material.activate(&mut key_store, key_id);
+        let created_at = unix_now();
+        
+        let signature_scheme = key_type.signature_scheme().map(str::to_string);
+        
+        let metadata = KeyMetadata {
+            key_id: key_id.to_string(),
+            key_type,
+            usage,
+            exportable,
+            created_at,
+            description: description.to_string(),
+            public_key: public_key_bytes,
+            signature_scheme,
+            attestation_bound: false,
+            attestation_quote: None,
+            expires_at: None,
+            usage_stats: KeyUsageStats::default(),
+            generation: first_key_generation(),
+            rotated_at: None,
+        };
+        
+        key_store.metadata.insert(key_id.to_string(), metadata.clone());
+        key_store.usage.insert(key_id.to_string(), KeyUsage::default());
+        drop(key_store);
+        
+        self.metrics.keys_generated.incr();
+        self.record_key_event("key_created", &metadata);
+        info!("Generated key '{}' of type {:?}", key_id, metadata.key_type);
+        Ok(metadata)
+    }
+    
+    /// Generate fresh secret material for a key of `key_type`
+    fn new_key_material(&self, key_type: &CryptoAlgorithm) -> Result<KeyMaterial> {
+        match key_type {
             CryptoAlgorithm::Aes256Gcm => {
-                let mut key = vec![0u8; 32]; // 256 bits
+                let mut key = Zeroizing::new(vec![0u8; 32]); // 256 bits
                 self.rng.fill(&mut key)?;
-                key_store.symmetric_keys.insert(key_id.to_string(), key);
-                (None, std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs())
+                Ok(KeyMaterial::Symmetric(key))
             }
             CryptoAlgorithm::Secp256k1 => {
-                let mut private_key_bytes = vec![0u8; 32];
+                let mut private_key_bytes = Zeroizing::new(vec![0u8; 32]);
                 self.rng.fill(&mut private_key_bytes)?;
                 
                 let private_key = SecretKey::from_slice(&private_key_bytes)?;
                 let public_key = PublicKey::from_secret_key(&self.secp256k1, &private_key);
-                let public_key_bytes = public_key.serialize().to_vec();
-                
-                key_store.asymmetric_keys.insert(
-                    key_id.to_string(),
-                    (private_key_bytes, public_key_bytes.clone())
-                );
-                
-                (Some(public_key_bytes), std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs())
+                Ok(KeyMaterial::Asymmetric(private_key_bytes, public_key.serialize().to_vec()))
+            }
+            CryptoAlgorithm::Secp256r1 => {
+                let signing_key = P256SigningKey::random(&mut OsRng);
+                let private_key_bytes = Zeroizing::new(signing_key.to_bytes().to_vec());
+                let public_key_bytes = signing_key.verifying_key()
+                    .to_encoded_point(true)
+                    .as_bytes()
+                    .to_vec();
+                Ok(KeyMaterial::Asymmetric(private_key_bytes, public_key_bytes))
             }
             CryptoAlgorithm::Ed25519 => {
-                let mut seed = [0u8; 32];
-                self.rng.fill(&mut seed)?;
+                let mut seed = Zeroizing::new([0u8; 32]);
+                self.rng.fill(&mut *seed)?;
                 
                 let keypair = SigningKey::from_bytes(&seed);
                 let public_key_bytes = keypair.verifying_key().to_bytes().to_vec();
-                let private_key_bytes = keypair.to_bytes().to_vec();
-                
-                key_store.asymmetric_keys.insert(
-                    key_id.to_string(),
-                    (private_key_bytes, public_key_bytes.clone())
-                );
-                
-                (Some(public_key_bytes), std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs())
+                let private_key_bytes = Zeroizing::new(keypair.to_bytes().to_vec());
+                Ok(KeyMaterial::Asymmetric(private_key_bytes, public_key_bytes))
+            }
+            CryptoAlgorithm::Rsa2048 | CryptoAlgorithm::Rsa4096 => {
+                let bits = key_type.rsa_key_bits().unwrap_or(MIN_RSA_KEY_BITS);
+                let private_key = RsaPrivateKey::new(&mut OsRng, bits)
+                    .map_err(|e| EnclaveError::Crypto(format!("RSA key generation failed: {}", e)))?;
+                let private_key_der = private_key.to_pkcs8_der()
+                    .map_err(|e| anyhow!("Failed to encode RSA private key: {}", e))?;
+                let public_key_bytes = private_key.to_public_key().to_public_key_der()
+                    .map_err(|e| anyhow!("Failed to encode RSA public key: {}", e))?
+                    .into_vec();
+                Ok(KeyMaterial::Asymmetric(Zeroizing::new(private_key_der.as_bytes().to_vec()), public_key_bytes))
+            }
+            _ => Err(anyhow!("Unsupported key type for generation: {:?}", key_type)),
+        }
+    }
+    
+    /// Replace the active material of `key_id` with a new generation of the same type
+    ///
+    /// The previous generation is retired: it still decrypts ciphertexts tagged with its
+    /// generation and verifies its signatures, but all new signing and encryption uses the
+    /// new one. Rotation clears `expires_at`, and attestation-bound keys get a fresh quote
+    /// for the new public key.
+    pub fn rotate_key(&self, key_id: &str) -> Result<KeyMetadata> {
+        let current = self.get_key_metadata(key_id)?;
+        let material = self.new_key_material(&current.key_type)?;
+        let attestation_quote = match (current.attestation_bound, material.public_key_bytes()) {
+            (true, Some(public_key)) => Some(
+                key_report_data(key_id, &current.key_type, current.generation + 1, public_key)
+                    .and_then(|report_data| self.attestation_service.generate_quote(&report_data))
+                    .map_err(|e| e.context(format!("Failed to attest rotated key '{}'", key_id)))?
+            ),
+            _ => None,
+        };
+        let public_key = material.public_key_bytes().map(<[u8]>::to_vec);
+        
+        let metadata = {
+            let mut key_store = self.key_store.write_or_recover();
+            let previous_generation = key_store.metadata.get(key_id)
+                .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?
+                .generation;
+            if previous_generation != current.generation {
+                return Err(anyhow!("Key '{}' was rotated concurrently", key_id));
+            }
+            
+            if let Some(previous) = material.activate(&mut key_store, key_id) {
+                key_store.retired.entry(key_id.to_string())
+                    .or_default()
+                    .insert(previous_generation, previous);
+            }
+            
+            let metadata = key_store.metadata.get_mut(key_id)
+                .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+            metadata.generation = previous_generation + 1;
+            metadata.rotated_at = Some(unix_now());
+            metadata.expires_at = None;
+            metadata.public_key = public_key;
+            if metadata.attestation_bound {
+                metadata.attestation_quote = attestation_quote;
+            }
+            metadata.clone()
+        };
+        
+        self.record_key_event("key_rotated", &metadata);
+        info!("Rotated key '{}' to generation {}", key_id, metadata.generation);
+        Ok(metadata)
+    }
+    
+    /// Generate an asymmetric key together with an attestation quote whose report data is
+    /// [`key_report_data`] for it, so clients can verify the key never left the enclave.
+    /// The key is discarded if no quote can be produced.
+    pub fn generate_attested_key(
+        &self,
+        key_id: &str,
+        key_type: CryptoAlgorithm,
+        usage: Vec<String>,
+        exportable: bool,
+        description: &str,
+    ) -> Result<KeyMetadata> {
+        if exportable {
+            return Err(EnclaveError::InvalidInput("Attestation-bound keys cannot be exportable".into()).into());
+        }
+        
+        let mut metadata = self.generate_key(key_id, key_type, usage, exportable, description)?;
+        let quote = metadata.public_key.as_deref()
+            .ok_or_else(|| anyhow!("Key type {:?} has no public key to attest", metadata.key_type))
+            .and_then(|public_key| key_report_data(key_id, &metadata.key_type, metadata.generation, public_key))
+            .and_then(|report_data| self.attestation_service.generate_quote(&report_data));
+        let quote = match quote {
+            Ok(quote) => quote,
+            Err(e) => {
+                self.delete_key(key_id)?;
+                return Err(e.context(format!("Failed to attest key '{}'", key_id)));
             }
-            _ => return Err(anyhow!("Unsupported key type for generation: {:?}", key_type)),
         };
         
+        metadata.attestation_bound = true;
+        metadata.attestation_quote = Some(quote);
+        {
+            let mut key_store = self.key_store.write_or_recover();
+            key_store.metadata.insert(key_id.to_string(), metadata.clone());
+        }
+        
+        self.record_key_event("key_attested", &metadata);
+        Ok(metadata)
+    }
+    
+    /// Import an existing 32-byte ECDSA private key. Scalars that are zero or not below
+    /// the curve order are rejected.
+    pub fn import_key(
+        &self,
+        key_id: &str,
+        key_type: CryptoAlgorithm,
+        private_key: &[u8],
+        usage: Vec<String>,
+        exportable: bool,
+        description: &str,
+    ) -> Result<KeyMetadata> {
+        if key_id.is_empty() {
+            return Err(EnclaveError::InvalidInput("Key ID cannot be empty".into()).into());
+        }
+        
+        let public_key_bytes = match key_type {
+            CryptoAlgorithm::Secp256k1 => {
+                let secret_key = SecretKey::from_slice(private_key)
+                    .map_err(|_| anyhow!("Private key is not a valid secp256k1 scalar"))?;
+                PublicKey::from_secret_key(&self.secp256k1, &secret_key).serialize().to_vec()
+            }
+            CryptoAlgorithm::Secp256r1 => {
+                // from_slice also accepts short, zero-padded scalars; require the full 32 bytes
+                let signing_key = Some(private_key)
+                    .filter(|key| key.len() == 32)
+                    .and_then(|key| P256SigningKey::from_slice(key).ok())
+                    .ok_or_else(|| anyhow!("Private key is not a valid secp256r1 scalar"))?;
+                signing_key.verifying_key().to_encoded_point(true).as_bytes().to_vec()
+            }
+            _ => return Err(anyhow!("Unsupported key type for import: {:?}", key_type)),
+        };
+        
+        let mut key_store = self.key_store.write_or_recover();
+        
+        if key_store.metadata.contains_key(key_id) {
+            return Err(EnclaveError::AlreadyExists(format!("Key with ID '{}' already exists", key_id)).into());
+        }
+        
+        key_store.asymmetric_keys.insert(
+            key_id.to_string(),
+            (Zeroizing::new(private_key.to_vec()), public_key_bytes.clone())
+        );
+        
+        let signature_scheme = key_type.signature_scheme().map(str::to_string);
+        
         let metadata = KeyMetadata {
             key_id: key_id.to_string(),
             key_type,
             usage,
             exportable,
-            created_at,
+            created_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
             description: description.to_string(),
-            public_key: public_key_bytes,
+            public_key: Some(public_key_bytes),
+            signature_scheme,
+            // An imported key existed outside the enclave, so attesting it would prove nothing
+            attestation_bound: false,
+            attestation_quote: None,
+            expires_at: None,
+            usage_stats: KeyUsageStats::default(),
+            generation: first_key_generation(),
+            rotated_at: None,
         };
         
         key_store.metadata.insert(key_id.to_string(), metadata.clone());
+        key_store.usage.insert(key_id.to_string(), KeyUsage::default());
+        drop(key_store);
         
-        info!("Generated key '{}' of type {:?}", key_id, metadata.key_type);
+        self.record_key_event("key_imported", &metadata);
+        info!("Imported key '{}' of type {:?}", key_id, metadata.key_type);
         Ok(metadata)
     }
     
+    /// Export the raw private key of an asymmetric key created or imported as exportable
+    pub fn export_private_key(&self, key_id: &str) -> Result<Zeroizing<Vec<u8>>> {
+        let key_store = self.key_store.read_or_recover();
+        
+        let metadata = key_store.metadata.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+        if !metadata.exportable {
+            return Err(EnclaveError::PermissionDenied(format!("Key '{}' is not exportable", key_id)).into());
+        }
+        
+        let (private_key, _) = key_store.asymmetric_keys.get(key_id)
+            .ok_or_else(|| anyhow!("Key '{}' has no private key to export", key_id))?;
+        
+        let private_key = private_key.clone();
+        drop(key_store);
+        
+        self.audit_log.record(AuditEvent::new("crypto", "key_exported", key_id));
+        warn!("Exporting private key '{}'", key_id);
+        Ok(private_key)
+    }
+    
     /// Encrypt data using AES-256-GCM
     pub fn encrypt_aes_gcm(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        self.seal_aes_gcm(data, key, &[])
+    }
+    
+    /// Decrypt data using AES-256-GCM
+    pub fn decrypt_aes_gcm(&self, encrypted_data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+        self.open_aes_gcm(encrypted_data, key, &[])
+    }
+    
+    /// Encrypt with a stored symmetric key without exposing the key material.
+    /// The same `aad` must be supplied to `decrypt_with_key`. Expired keys are rejected.
+    pub fn encrypt_with_key(&self, key_id: &str, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let key_store = self.key_store.read_or_recover();
+        let (key, generation) = Self::symmetric_key_for(&key_store, key_id, "Encrypt", None)?;
+        let sealed = self.seal_aes_gcm(plaintext, key, aad)?;
+        key_store.record_use(key_id, |usage| &usage.encrypt_count);
+        
+        let mut ciphertext = Vec::with_capacity(KEY_GENERATION_TAG_LEN + sealed.len());
+        ciphertext.extend_from_slice(&generation.to_be_bytes());
+        ciphertext.extend_from_slice(&sealed);
+        Ok(ciphertext)
+    }
+    
+    /// Decrypt data produced by `encrypt_with_key` using the same stored key and `aad`,
+    /// with whichever generation of the key it was encrypted under. Still allowed after
+    /// the key expires so existing ciphertexts stay readable.
+    ///
+    /// Ciphertexts written before generation tags existed start directly with the nonce
+    /// and were sealed under the first generation; they are opened with that generation
+    /// when the tagged reading fails.
+    pub fn decrypt_with_key(&self, key_id: &str, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let key_store = self.key_store.read_or_recover();
+        let plaintext = match self.open_tagged(&key_store, key_id, ciphertext, aad) {
+            Ok(plaintext) => plaintext,
+            Err(tagged_error) => {
+                let legacy = Self::symmetric_key_for(&key_store, key_id, "Decrypt", Some(first_key_generation()))
+                    .and_then(|(key, _)| self.open_aes_gcm(ciphertext, key, aad));
+                legacy.map_err(|_| tagged_error)?
+            }
+        };
+        key_store.record_use(key_id, |usage| &usage.decrypt_count);
+        Ok(plaintext)
+    }
+    
+    /// Open a ciphertext prefixed with the generation it was sealed under
+    fn open_tagged(&self, key_store: &KeyStore, key_id: &str, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < KEY_GENERATION_TAG_LEN {
+            return Err(anyhow!("Encrypted data too short"));
+        }
+        let (tag, sealed) = ciphertext.split_at(KEY_GENERATION_TAG_LEN);
+        let generation = u32::from_be_bytes(tag.try_into()?);
+        
+        let (key, _) = Self::symmetric_key_for(key_store, key_id, "Decrypt", Some(generation))?;
+        self.open_aes_gcm(sealed, key, aad)
+    }
+    
+    /// Symmetric key material of `key_id` for `usage`, at `generation` or the active one,
+    /// together with the generation returned
+    fn symmetric_key_for<'a>(
+        key_store: &'a KeyStore,
+        key_id: &str,
+        usage: &str,
+        generation: Option<u32>,
+    ) -> Result<(&'a [u8], u32)> {
+        let metadata = key_store.metadata.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+        
+        if !metadata.usage.iter().any(|u| u == usage) {
+            return Err(EnclaveError::PermissionDenied(format!("Key '{}' is not authorized for {}", key_id, usage)).into());
+        }
+        if usage == "Encrypt" {
+            ensure_not_expired(metadata)?;
+        }
+        
+        if !matches!(metadata.key_type, CryptoAlgorithm::Aes256Gcm) {
+            return Err(anyhow!("Key '{}' is not a symmetric key ({:?})", key_id, metadata.key_type));
+        }
+        
+        let generation = generation.unwrap_or(metadata.generation);
+        let key = if generation == metadata.generation {
+            key_store.symmetric_keys.get(key_id).map(|key| key.as_slice())
+        } else {
+            match key_store.retired.get(key_id).and_then(|generations| generations.get(&generation)) {
+                Some(KeyMaterial::Symmetric(key)) => Some(key.as_slice()),
+                _ => None,
+            }
+        };
+        key.map(|key| (key, generation))
+            .ok_or_else(|| EnclaveError::NotFound(format!(
+                "Symmetric key '{}' generation {} not found", key_id, generation
+            )).into())
+    }
+    
+    fn seal_aes_gcm(&self, data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         if key.len() != 32 {
             return Err(anyhow!("AES-256 key must be 32 bytes"));
         }
@@ -211,7 +1089,7 @@ impl CryptoService {
         let less_safe_key = aead::LessSafeKey::new(unbound_key);
         let encrypted_result = less_safe_key.seal_in_place_append_tag(
             aead::Nonce::assume_unique_for_key(nonce),
-            aead::Aad::empty(),
+            aead::Aad::from(aad),
             &mut in_out,
         )?;
         
@@ -222,12 +1100,12 @@ impl CryptoService {
         result.extend_from_slice(&nonce);
         result.extend_from_slice(&in_out);
         
+        self.metrics.encryptions.incr();
         debug!("Encrypted {} bytes with AES-256-GCM", data.len());
         Ok(result)
     }
     
-    /// Decrypt data using AES-256-GCM
-    pub fn decrypt_aes_gcm(&self, encrypted_data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    fn open_aes_gcm(&self, encrypted_data: &[u8], key: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         if key.len() != 32 {
             return Err(anyhow!("AES-256 key must be 32 bytes"));
         }
@@ -244,109 +1122,285 @@ impl CryptoService {
         let less_safe_key = aead::LessSafeKey::new(unbound_key);
         let plaintext = less_safe_key.open_in_place(
             aead::Nonce::try_assume_unique_for_key(nonce)?,
-            aead::Aad::empty(),
+            aead::Aad::from(aad),
             &mut in_out,
-        )?;
+        ).map_err(|_| EnclaveError::Crypto("Decryption failed: ciphertext or tag is invalid".into()))?;
         
+        self.metrics.decryptions.incr();
         debug!("Decrypted {} bytes with AES-256-GCM", plaintext.len());
         Ok(plaintext.to_vec())
     }
     
-    /// Sign data using a stored key
+    /// Sign data using a stored key, producing the same bytes as `sign_deterministic`
+    /// for every key type it supports
     pub fn sign_data(&self, key_id: &str, data: &[u8]) -> Result<Vec<u8>> {
-        let key_store = self.key_store.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let key_store = self.key_store.read_or_recover();
         
         let metadata = key_store.metadata.get(key_id)
-            .ok_or_else(|| anyhow!("Key '{}' not found", key_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
         
         if !metadata.usage.contains(&"Sign".to_string()) {
-            return Err(anyhow!("Key '{}' is not authorized for signing", key_id));
+            return Err(EnclaveError::PermissionDenied(format!("Key '{}' is not authorized for signing", key_id)).into());
         }
+        ensure_not_expired(metadata)?;
         
-        match metadata.key_type {
+        let signature = match metadata.key_type {
             CryptoAlgorithm::Secp256k1 => {
                 let (private_key_bytes, _) = key_store.asymmetric_keys.get(key_id)
-                    .ok_or_else(|| anyhow!("Private key '{}' not found", key_id))?;
+                    .ok_or_else(|| EnclaveError::NotFound(format!("Private key '{}' not found", key_id)))?;
                 
                 let private_key = SecretKey::from_slice(private_key_bytes)?;
                 let message_hash = Sha256::digest(data);
                 let message = Message::from_slice(&message_hash)?;
-                let signature = self.secp256k1.sign_ecdsa(&message, &private_key);
+                let mut signature = self.secp256k1.sign_ecdsa(&message, &private_key);
+                // Neo and Bitcoin reject high-S signatures as malleable
+                signature.normalize_s();
                 
+                self.metrics.signatures_created.incr();
                 debug!("Signed {} bytes with secp256k1 key '{}'", data.len(), key_id);
                 Ok(signature.serialize_compact().to_vec())
             }
+            CryptoAlgorithm::Secp256r1 => {
+                let (private_key_bytes, _) = key_store.asymmetric_keys.get(key_id)
+                    .ok_or_else(|| EnclaveError::NotFound(format!("Private key '{}' not found", key_id)))?;
+                
+                let signing_key = P256SigningKey::from_slice(private_key_bytes)
+                    .map_err(|e| anyhow!("Invalid secp256r1 private key: {}", e))?;
+                // Hashes with SHA-256 internally and returns the 64-byte r || s form Neo expects
+                let signature: P256Signature = signing_key.sign(data);
+                
+                self.metrics.signatures_created.incr();
+                debug!("Signed {} bytes with secp256r1 key '{}'", data.len(), key_id);
+                Ok(signature.to_bytes().to_vec())
+            }
             CryptoAlgorithm::Ed25519 => {
                 let (private_key_bytes, _) = key_store.asymmetric_keys.get(key_id)
-                    .ok_or_else(|| anyhow!("Private key '{}' not found", key_id))?;
+                    .ok_or_else(|| EnclaveError::NotFound(format!("Private key '{}' not found", key_id)))?;
                 
                 if private_key_bytes.len() != 32 {
                     return Err(anyhow!("Invalid key length for Ed25519"));
                 }
-                let mut key_bytes = [0u8; 32];
+                let mut key_bytes = Zeroizing::new([0u8; 32]);
                 key_bytes.copy_from_slice(&private_key_bytes[..32]);
                 let keypair = SigningKey::from_bytes(&key_bytes);
                 let signature = keypair.sign(data);
                 
+                self.metrics.signatures_created.incr();
                 debug!("Signed {} bytes with Ed25519 key '{}'", data.len(), key_id);
                 Ok(signature.to_bytes().to_vec())
             }
+            CryptoAlgorithm::Rsa2048 | CryptoAlgorithm::Rsa4096 => {
+                let (private_key_der, _) = key_store.asymmetric_keys.get(key_id)
+                    .ok_or_else(|| EnclaveError::NotFound(format!("Private key '{}' not found", key_id)))?;
+                
+                let private_key = RsaPrivateKey::from_pkcs8_der(private_key_der)
+                    .map_err(|e| anyhow!("Invalid RSA private key: {}", e))?;
+                let message_hash = Sha256::digest(data);
+                let signature = private_key.sign(Pkcs1v15Sign::new::<Sha256>(), &message_hash)
+                    .map_err(|e| EnclaveError::Crypto(format!("RSA signing failed: {}", e)))?;
+                
+                self.metrics.signatures_created.incr();
+                debug!("Signed {} bytes with {:?} key '{}'", data.len(), metadata.key_type, key_id);
+                Ok(signature)
+            }
             _ => Err(anyhow!("Key type {:?} does not support signing", metadata.key_type)),
+        }?;
+        
+        key_store.record_use(key_id, |usage| &usage.sign_count);
+        Ok(signature)
+    }
+    
+    /// Sign data without consuming any randomness, so the same key and data always yield
+    /// identical signatures
+    ///
+    /// ECDSA keys (secp256k1 and secp256r1) derive their nonce from the key and message
+    /// hash per RFC 6979, which rules out nonce reuse from a weak or repeated RNG output.
+    /// Ed25519 and RSA PKCS#1 v1.5 are deterministic by construction.
+    pub fn sign_deterministic(&self, key_id: &str, data: &[u8]) -> Result<Vec<u8>> {
+        match self.get_key_metadata(key_id)?.key_type {
+            // secp256k1's sign_ecdsa adds no extra entropy and p256's Signer uses RFC 6979
+            CryptoAlgorithm::Secp256k1
+            | CryptoAlgorithm::Secp256r1
+            | CryptoAlgorithm::Ed25519
+            | CryptoAlgorithm::Rsa2048
+            | CryptoAlgorithm::Rsa4096 => self.sign_data(key_id, data),
+            other => Err(anyhow!("Key type {:?} does not support deterministic signing", other)),
         }
     }
     
-    /// Verify a signature using a stored key
+    /// Sign SHA256(data) with a secp256k1 key, returning the 65-byte r || s || v form where
+    /// v is the recovery id (0-3), so the signer's public key can be recovered from it
+    pub fn sign_recoverable(&self, key_id: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let key_store = self.key_store.read_or_recover();
+        
+        let metadata = key_store.metadata.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+        
+        if !metadata.usage.contains(&"Sign".to_string()) {
+            return Err(EnclaveError::PermissionDenied(format!("Key '{}' is not authorized for signing", key_id)).into());
+        }
+        ensure_not_expired(metadata)?;
+        if !matches!(metadata.key_type, CryptoAlgorithm::Secp256k1) {
+            return Err(anyhow!("Recoverable signatures require a secp256k1 key, '{}' is {:?}", key_id, metadata.key_type));
+        }
+        
+        let (private_key_bytes, _) = key_store.asymmetric_keys.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Private key '{}' not found", key_id)))?;
+        
+        let private_key = SecretKey::from_slice(private_key_bytes)?;
+        let message = Message::from_digest(Sha256::digest(data).into());
+        let (recovery_id, compact) = self.secp256k1
+            .sign_ecdsa_recoverable(&message, &private_key)
+            .serialize_compact();
+        
+        let mut signature = Vec::with_capacity(RECOVERABLE_SIGNATURE_LEN);
+        signature.extend_from_slice(&compact);
+        signature.push(recovery_id.to_i32() as u8);
+        
+        key_store.record_use(key_id, |usage| &usage.sign_count);
+        self.metrics.signatures_created.incr();
+        debug!("Signed {} bytes with recoverable secp256k1 key '{}'", data.len(), key_id);
+        Ok(signature)
+    }
+    
+    /// Recover the compressed secp256k1 public key that produced a `sign_recoverable`
+    /// signature over SHA256(data). Ethereum-style recovery ids (27-30) are also accepted.
+    pub fn recover_public_key(&self, data: &[u8], recoverable_signature: &[u8]) -> Result<Vec<u8>> {
+        if recoverable_signature.len() != RECOVERABLE_SIGNATURE_LEN {
+            return Err(anyhow!(
+                "Recoverable signature must be {} bytes, got {}",
+                RECOVERABLE_SIGNATURE_LEN, recoverable_signature.len()
+            ));
+        }
+        
+        let (compact, v) = recoverable_signature.split_at(64);
+        let recovery_id = match v[0] {
+            v @ 0..=3 => v,
+            v @ 27..=30 => v - 27,
+            v => return Err(anyhow!("Invalid recovery id {}", v)),
+        };
+        let recovery_id = RecoveryId::from_i32(recovery_id as i32)?;
+        let signature = RecoverableSignature::from_compact(compact, recovery_id)
+            .map_err(|e| EnclaveError::Crypto(format!("Invalid recoverable signature: {}", e)))?;
+        let message = Message::from_digest(Sha256::digest(data).into());
+        
+        let public_key = self.secp256k1.recover_ecdsa(&message, &signature)
+            .map_err(|e| EnclaveError::Crypto(format!("Public key recovery failed: {}", e)))?;
+        Ok(public_key.serialize().to_vec())
+    }
+    
+    /// Verify a signature using a stored key. Signatures made by generations retired by
+    /// `rotate_key` still verify. High-S secp256k1 signatures are invalid unless
+    /// `crypto_require_low_s` is off.
     pub fn verify_signature(&self, key_id: &str, data: &[u8], signature: &[u8]) -> Result<bool> {
-        let key_store = self.key_store.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let key_store = self.key_store.read_or_recover();
         
         let metadata = key_store.metadata.get(key_id)
-            .ok_or_else(|| anyhow!("Key '{}' not found", key_id))?;
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
         
         if !metadata.usage.contains(&"Verify".to_string()) {
-            return Err(anyhow!("Key '{}' is not authorized for verification", key_id));
+            return Err(EnclaveError::PermissionDenied(format!("Key '{}' is not authorized for verification", key_id)).into());
+        }
+        ensure_not_expired(metadata)?;
+        if metadata.key_type.signature_scheme().is_none() {
+            return Err(anyhow!("Key type {:?} does not support verification", metadata.key_type));
         }
         
-        match metadata.key_type {
+        let (_, active_public_key) = key_store.asymmetric_keys.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Public key '{}' not found", key_id)))?;
+        // Newest generation first: most signatures are made with the active key
+        let retired_public_keys = key_store.retired.get(key_id)
+            .into_iter()
+            .flat_map(|generations| generations.values().rev())
+            .filter_map(KeyMaterial::public_key_bytes);
+        
+        let mut is_valid = false;
+        for public_key in std::iter::once(active_public_key.as_slice()).chain(retired_public_keys) {
+            if self.check_signature(&metadata.key_type, public_key, data, signature)? {
+                is_valid = true;
+                break;
+            }
+        }
+        
+        self.record_verification(is_valid);
+        key_store.record_use(key_id, |usage| &usage.verify_count);
+        debug!("Verified signature for {} bytes with {:?} key '{}': {}", data.len(), metadata.key_type, key_id, is_valid);
+        Ok(is_valid)
+    }
+    
+    /// Verify a signature against a raw public key that is not held in the key store
+    pub fn verify_with_public_key(
+        &self,
+        key_type: CryptoAlgorithm,
+        public_key: &[u8],
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        let is_valid = self.check_signature(&key_type, public_key, data, signature)?;
+        self.record_verification(is_valid);
+        Ok(is_valid)
+    }
+    
+    /// Check `signature` over `data` against `public_key`. Malformed signatures, and high-S
+    /// secp256k1 signatures when `require_low_s` is set, are reported as invalid; malformed
+    /// public keys are errors.
+    fn check_signature(
+        &self,
+        key_type: &CryptoAlgorithm,
+        public_key: &[u8],
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        match key_type {
             CryptoAlgorithm::Secp256k1 => {
-                let (_, public_key_bytes) = key_store.asymmetric_keys.get(key_id)
-                    .ok_or_else(|| anyhow!("Public key '{}' not found", key_id))?;
-                
-                let public_key = PublicKey::from_slice(public_key_bytes)?;
+                let public_key = PublicKey::from_slice(public_key)?;
                 let message_hash = Sha256::digest(data);
                 let message = Message::from_slice(&message_hash)?;
-                let signature = Signature::from_compact(signature)?;
+                let Ok(mut signature) = Signature::from_compact(signature) else {
+                    return Ok(false);
+                };
+                // libsecp256k1 only verifies low-S signatures, so accepting high-S ones
+                // means verifying their normalized twin
+                if !self.require_low_s {
+                    signature.normalize_s();
+                }
                 
-                let is_valid = self.secp256k1.verify_ecdsa(&message, &signature, &public_key).is_ok();
-                debug!("Verified signature for {} bytes with secp256k1 key '{}': {}", data.len(), key_id, is_valid);
-                Ok(is_valid)
+                Ok(self.secp256k1.verify_ecdsa(&message, &signature, &public_key).is_ok())
             }
+            CryptoAlgorithm::Secp256r1 => verify_p256(public_key, data, signature),
             CryptoAlgorithm::Ed25519 => {
-                let (_, public_key_bytes) = key_store.asymmetric_keys.get(key_id)
-                    .ok_or_else(|| anyhow!("Public key '{}' not found", key_id))?;
-                
-                if public_key_bytes.len() != 32 {
-                    return Err(anyhow!("Invalid public key length for Ed25519"));
-                }
-                let mut public_key_array = [0u8; 32];
-                public_key_array.copy_from_slice(&public_key_bytes[..32]);
+                let public_key_array: [u8; 32] = public_key.try_into()
+                    .map_err(|_| anyhow!("Invalid public key length for Ed25519"))?;
                 let public_key = VerifyingKey::from_bytes(&public_key_array)
                     .map_err(|e| anyhow!("Invalid Ed25519 public key: {}", e))?;
                 
-                if signature.len() != 64 {
-                    return Err(anyhow!("Invalid signature length for Ed25519"));
-                }
-                let mut signature_array = [0u8; 64];
-                signature_array.copy_from_slice(&signature[..64]);
+                let Ok(signature_array) = <[u8; 64]>::try_from(signature) else {
+                    return Ok(false);
+                };
                 let signature = Ed25519Signature::from_bytes(&signature_array);
                 
-                let is_valid = public_key.verify(data, &signature).is_ok();
-                debug!("Verified signature for {} bytes with Ed25519 key '{}': {}", data.len(), key_id, is_valid);
-                Ok(is_valid)
+                Ok(public_key.verify(data, &signature).is_ok())
             }
-            _ => Err(anyhow!("Key type {:?} does not support verification", metadata.key_type)),
+            CryptoAlgorithm::Rsa2048 | CryptoAlgorithm::Rsa4096 => {
+                let public_key = parse_rsa_public_key(key_type, public_key)?;
+                Ok(verify_rsa(&public_key, data, signature))
+            }
+            _ => Err(anyhow!("Key type {:?} does not support verification", key_type)),
+        }
+    }
+    
+    fn record_verification(&self, is_valid: bool) {
+        self.metrics.verifications.incr();
+        if !is_valid {
+            self.metrics.verification_failures.incr();
         }
     }
     
+    /// Operation counters for this service
+    pub fn metrics(&self) -> &CryptoMetrics {
+        &self.metrics
+    }
+    
     /// Hash data using SHA-256
     pub fn hash_sha256(&self, data: &[u8]) -> Vec<u8> {
         let hash = Sha256::digest(data);
@@ -354,34 +1408,851 @@ impl CryptoService {
         hash.to_vec()
     }
     
-    /// Get key metadata
+    /// Get key metadata, including current usage counters
     pub fn get_key_metadata(&self, key_id: &str) -> Result<KeyMetadata> {
-        let key_store = self.key_store.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let key_store = self.key_store.read_or_recover();
         
-        key_store.metadata.get(key_id)
+        let mut metadata = key_store.metadata.get(key_id)
             .cloned()
-            .ok_or_else(|| anyhow!("Key '{}' not found", key_id))
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+        if let Some(usage) = key_store.usage.get(key_id) {
+            metadata.usage_stats = usage.snapshot();
+        }
+        Ok(metadata)
+    }
+    
+    /// Set or clear the Unix time after which `key_id` stops signing, verifying and
+    /// encrypting. A time in the past expires the key immediately.
+    pub fn set_key_expiry(&self, key_id: &str, expires_at: Option<u64>) -> Result<KeyMetadata> {
+        let metadata = {
+            let mut key_store = self.key_store.write_or_recover();
+            let metadata = key_store.metadata.get_mut(key_id)
+                .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+            metadata.expires_at = expires_at;
+            metadata.clone()
+        };
+        
+        self.audit_log.record(
+            AuditEvent::new("crypto", "key_expiry_set", key_id)
+                .with_details(serde_json::json!({ "expires_at": expires_at }))
+        );
+        info!("Set expiry of key '{}' to {:?}", key_id, expires_at);
+        Ok(metadata)
+    }
+    
+    /// Public key of `key_id`, SEC1 compressed (33 bytes) or uncompressed (65 bytes,
+    /// `0x04 || x || y`) for ECDSA keys. Ed25519 and RSA keys have a single encoding and
+    /// are returned as stored regardless of `compressed`.
+    pub fn get_public_key(&self, key_id: &str, compressed: bool) -> Result<Vec<u8>> {
+        let metadata = self.get_key_metadata(key_id)?;
+        let public_key = metadata.public_key
+            .ok_or_else(|| anyhow!("Key '{}' has no public key", key_id))?;
+        
+        match metadata.key_type {
+            CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Secp256r1 => {
+                encode_ec_public_key(&metadata.key_type, &public_key, compressed)
+            }
+            _ => Ok(public_key),
+        }
+    }
+    
+    /// Export an RSA public key as a PEM-encoded SubjectPublicKeyInfo
+    pub fn export_public_key_pem(&self, key_id: &str) -> Result<String> {
+        let key_store = self.key_store.read_or_recover();
+        
+        let metadata = key_store.metadata.get(key_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key_id)))?;
+        let (_, public_key_der) = key_store.asymmetric_keys.get(key_id)
+            .ok_or_else(|| anyhow!("Key '{}' has no public key", key_id))?;
+        
+        if metadata.key_type.rsa_key_bits().is_none() {
+            return Err(anyhow!("PEM export is only supported for RSA keys, not {:?}", metadata.key_type));
+        }
+        
+        parse_rsa_public_key(&metadata.key_type, public_key_der)?
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|e| anyhow!("Failed to encode public key as PEM: {}", e))
     }
     
     /// List all stored keys
     pub fn list_keys(&self) -> Result<Vec<String>> {
-        let key_store = self.key_store.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let key_store = self.key_store.read_or_recover();
         Ok(key_store.metadata.keys().cloned().collect())
     }
     
+    /// IDs of stored keys whose expiry has passed, as candidates for rotation or deletion
+    pub fn expired_keys(&self) -> Result<Vec<String>> {
+        let key_store = self.key_store.read_or_recover();
+        Ok(key_store.metadata.values()
+            .filter(|metadata| metadata.is_expired())
+            .map(|metadata| metadata.key_id.clone())
+            .collect())
+    }
+    
+    /// Probe keystore availability and the random number generator
+    pub fn health_check(&self) -> ServiceHealth {
+        let (key_count, expired_keys) = match self.key_store.read() {
+            Ok(key_store) => (
+                key_store.metadata.len(),
+                key_store.metadata.values().filter(|metadata| metadata.is_expired()).count(),
+            ),
+            Err(_) => return ServiceHealth::unhealthy("crypto", "Keystore lock poisoned"),
+        };
+        
+        if let Err(e) = self.generate_random_bytes(16) {
+            return ServiceHealth::unhealthy("crypto", format!("Random number generator failed: {}", e));
+        }
+        
+        ServiceHealth::healthy("crypto", serde_json::json!({
+            "key_count": key_count,
+            "expired_keys": expired_keys,
+        }))
+    }
+    
     /// Delete a key
     pub fn delete_key(&self, key_id: &str) -> Result<()> {
-        let mut key_store = self.key_store.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut key_store = self.key_store.write_or_recover();
         
         if !key_store.metadata.contains_key(key_id) {
-            return Err(anyhow!("Key '{}' not found", key_id));
+            return Err(EnclaveError::NotFound(format!("Key '{}' not found", key_id)).into());
         }
         
         key_store.metadata.remove(key_id);
-        key_store.symmetric_keys.remove(key_id);
-        key_store.asymmetric_keys.remove(key_id);
+        key_store.usage.remove(key_id);
+        
+        // Wipe explicitly rather than relying on the drop order of the removed entries
+        if let Some(mut key) = key_store.symmetric_keys.remove(key_id) {
+            key.zeroize();
+        }
+        if let Some((mut private_key, _)) = key_store.asymmetric_keys.remove(key_id) {
+            private_key.zeroize();
+        }
+        for (_, mut material) in key_store.retired.remove(key_id).into_iter().flatten() {
+            material.zeroize();
+        }
+        drop(key_store);
         
+        self.audit_log.record(AuditEvent::new("crypto", "key_deleted", key_id));
         info!("Deleted key '{}'", key_id);
         Ok(())
     }
-} 
\ No newline at end of file
+    
+    /// Record a key lifecycle event; only public metadata goes into the audit log
+    fn record_key_event(&self, action: &str, metadata: &KeyMetadata) {
+        self.audit_log.record(
+            AuditEvent::new("crypto", action, &metadata.key_id).with_details(serde_json::json!({
+                "key_type": format!("{:?}", metadata.key_type),
+                "usage": metadata.usage,
+                "exportable": metadata.exportable,
+                "generation": metadata.generation,
+                "public_key": metadata.public_key.as_ref().map(hex::encode),
+            }))
+        );
+    }
+}
+
+/// Encode an ECDSA public key in SEC1 compressed form (33 bytes)
+///
+/// Accepts compressed, uncompressed (`0x04 || x || y`) or raw 64-byte `x || y` keys. The
+/// point is decoded and checked to lie on the curve, so the result always decompresses
+/// back to the same point.
+pub fn compress_public_key(curve: &CryptoAlgorithm, public_key: &[u8]) -> Result<Vec<u8>> {
+    encode_ec_public_key(curve, public_key, true)
+}
+
+/// Encode an ECDSA public key in SEC1 uncompressed form (65 bytes, `0x04 || x || y`),
+/// recomputing y from x for compressed input. Accepts the same inputs as `compress_public_key`.
+pub fn decompress_public_key(curve: &CryptoAlgorithm, public_key: &[u8]) -> Result<Vec<u8>> {
+    encode_ec_public_key(curve, public_key, false)
+}
+
+fn encode_ec_public_key(curve: &CryptoAlgorithm, public_key: &[u8], compressed: bool) -> Result<Vec<u8>> {
+    // Raw x || y coordinates, as produced by the SGX key functions
+    let sec1 = if public_key.len() == 64 {
+        [&[0x04][..], public_key].concat()
+    } else {
+        public_key.to_vec()
+    };
+    
+    match curve {
+        CryptoAlgorithm::Secp256k1 => {
+            let key = PublicKey::from_slice(&sec1)
+                .map_err(|e| anyhow!("Invalid secp256k1 public key: {}", e))?;
+            Ok(if compressed {
+                key.serialize().to_vec()
+            } else {
+                key.serialize_uncompressed().to_vec()
+            })
+        }
+        CryptoAlgorithm::Secp256r1 => {
+            let key = P256VerifyingKey::from_sec1_bytes(&sec1)
+                .map_err(|e| anyhow!("Invalid secp256r1 public key: {}", e))?;
+            Ok(key.to_encoded_point(compressed).as_bytes().to_vec())
+        }
+        other => Err(anyhow!("{:?} is not an elliptic-curve key type", other)),
+    }
+}
+
+/// Verify a 64-byte r || s secp256r1 signature over SHA-256(data).
+/// A malformed public key is an error; a malformed signature is simply invalid.
+fn verify_p256(public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<bool> {
+    let verifying_key = P256VerifyingKey::from_sec1_bytes(public_key)
+        .map_err(|e| anyhow!("Invalid secp256r1 public key: {}", e))?;
+    
+    match P256Signature::from_slice(signature) {
+        Ok(signature) => Ok(verifying_key.verify(data, &signature).is_ok()),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Decode a SubjectPublicKeyInfo DER RSA key and check its modulus matches the declared size
+fn parse_rsa_public_key(key_type: &CryptoAlgorithm, der: &[u8]) -> Result<RsaPublicKey> {
+    let expected_bits = key_type.rsa_key_bits()
+        .ok_or_else(|| anyhow!("{:?} is not an RSA key type", key_type))?;
+    
+    let public_key = RsaPublicKey::from_public_key_der(der)
+        .map_err(|e| anyhow!("Invalid RSA public key: {}", e))?;
+    
+    let bits = public_key.size() * 8;
+    if bits < MIN_RSA_KEY_BITS {
+        return Err(anyhow!("RSA key size {} is below the minimum of {} bits", bits, MIN_RSA_KEY_BITS));
+    }
+    if bits != expected_bits {
+        return Err(anyhow!("RSA key size {} does not match {:?}", bits, key_type));
+    }
+    
+    Ok(public_key)
+}
+
+fn verify_rsa(public_key: &RsaPublicKey, data: &[u8], signature: &[u8]) -> bool {
+    let message_hash = Sha256::digest(data);
+    public_key.verify(Pkcs1v15Sign::new::<Sha256>(), &message_hash, signature).is_ok()
+}
+
+fn expect_hex(what: &str, actual: &[u8], expected: &str) -> Result<()> {
+    if hex::encode(actual) != expected {
+        return Err(EnclaveError::Crypto(format!("{} does not match the known answer", what)).into());
+    }
+    Ok(())
+}
+
+/// Seal `plaintext` and compare ciphertext || tag with `expected`, then check that it
+/// opens again and that a corrupted tag is rejected
+fn aead_known_answer(
+    algorithm: &'static aead::Algorithm,
+    key: &[u8],
+    nonce: [u8; 12],
+    aad: &[u8],
+    plaintext: &[u8],
+    expected: &str,
+) -> Result<()> {
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(algorithm, key)?);
+    
+    let mut sealed = plaintext.to_vec();
+    key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::from(aad), &mut sealed)?;
+    expect_hex("Ciphertext", &sealed, expected)?;
+    
+    let mut opened = sealed.clone();
+    let decrypted = key.open_in_place(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::from(aad), &mut opened)
+        .map_err(|_| EnclaveError::Crypto("Known-answer ciphertext does not decrypt".into()))?;
+    if decrypted != plaintext {
+        return Err(EnclaveError::Crypto("Decryption does not return the plaintext".into()).into());
+    }
+    
+    let last = sealed.len() - 1;
+    sealed[last] ^= 1;
+    if key.open_in_place(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::from(aad), &mut sealed).is_ok() {
+        return Err(EnclaveError::Crypto("A corrupted tag was accepted".into()).into());
+    }
+    Ok(())
+}
+
+fn aead_benchmark(algorithm: &'static aead::Algorithm) -> Result<Benchmark> {
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(algorithm, &[7u8; 32])?);
+    let mut block = vec![0u8; BENCHMARK_BLOCK_SIZE];
+    let mut counter = 0u64;
+    measure("encrypt", Some(BENCHMARK_BLOCK_SIZE), || {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        counter += 1;
+        let _tag = key.seal_in_place_separate_tag(aead::Nonce::assume_unique_for_key(nonce), aead::Aad::empty(), &mut block)?;
+        Ok(())
+    })
+}
+
+/// Run `operation` repeatedly for `BENCHMARK_DURATION`
+fn measure(operation: &'static str, block_size: Option<usize>, mut run: impl FnMut() -> Result<()>) -> Result<Benchmark> {
+    let started = Instant::now();
+    let mut ops = 0u64;
+    while ops == 0 || started.elapsed() < BENCHMARK_DURATION {
+        run()?;
+        ops += 1;
+    }
+    
+    let seconds = started.elapsed().as_secs_f64();
+    Ok(Benchmark {
+        operation,
+        ops_per_second: ops as f64 / seconds,
+        mb_per_second: block_size.map(|size| (ops * size as u64) as f64 / seconds / 1_000_000.0),
+    })
+}
+
+/// Base58 and Base58Check encoding with the Bitcoin/Neo alphabet
+pub mod base58 {
+    use anyhow::{Result, anyhow};
+    use sha2::{Sha256, Digest};
+
+    const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+    /// Length of the double-SHA256 checksum appended by `encode_check`
+    pub const CHECKSUM_LEN: usize = 4;
+
+    /// Encode bytes as Base58; each leading zero byte becomes a leading '1'
+    pub fn encode(input: &[u8]) -> String {
+        let zeros = input.iter().take_while(|&&byte| byte == 0).count();
+
+        // Base58 digits, least significant first; log(256)/log(58) < 1.38
+        let mut digits: Vec<u8> = Vec::with_capacity((input.len() - zeros) * 138 / 100 + 1);
+        for &byte in &input[zeros..] {
+            let mut carry = byte as u32;
+            for digit in digits.iter_mut() {
+                carry += (*digit as u32) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+
+        let mut output = String::with_capacity(zeros + digits.len());
+        output.extend(std::iter::repeat('1').take(zeros));
+        output.extend(digits.iter().rev().map(|&digit| ALPHABET[digit as usize] as char));
+        output
+    }
+
+    /// Decode a Base58 string; each leading '1' becomes a leading zero byte
+    pub fn decode(input: &str) -> Result<Vec<u8>> {
+        let zeros = input.chars().take_while(|&ch| ch == '1').count();
+
+        // Output bytes, least significant first
+        let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+        for ch in input.chars().skip(zeros) {
+            let mut carry = ALPHABET.iter()
+                .position(|&symbol| symbol as char == ch)
+                .ok_or_else(|| anyhow!("Invalid Base58 character: {}", ch))? as u32;
+            for byte in bytes.iter_mut() {
+                carry += (*byte as u32) * 58;
+                *byte = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push(carry as u8);
+                carry >>= 8;
+            }
+        }
+
+        bytes.extend(std::iter::repeat(0).take(zeros));
+        bytes.reverse();
+        Ok(bytes)
+    }
+
+    /// Encode `payload` followed by the first four bytes of SHA256(SHA256(payload))
+    pub fn encode_check(payload: &[u8]) -> String {
+        let mut data = Vec::with_capacity(payload.len() + CHECKSUM_LEN);
+        data.extend_from_slice(payload);
+        data.extend_from_slice(&checksum(payload));
+        encode(&data)
+    }
+
+    /// Decode a Base58Check string and return the payload with the checksum removed
+    pub fn decode_check(input: &str) -> Result<Vec<u8>> {
+        let mut data = decode(input)?;
+        if data.len() < CHECKSUM_LEN {
+            return Err(anyhow!("Base58Check data too short: {} bytes", data.len()));
+        }
+
+        let payload_len = data.len() - CHECKSUM_LEN;
+        if !super::constant_time_eq(&data[payload_len..], &checksum(&data[..payload_len])) {
+            return Err(anyhow!("Invalid Base58Check checksum"));
+        }
+
+        data.truncate(payload_len);
+        Ok(data)
+    }
+
+    fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+        let hash = Sha256::digest(Sha256::digest(payload));
+        let mut checksum = [0u8; CHECKSUM_LEN];
+        checksum.copy_from_slice(&hash[..CHECKSUM_LEN]);
+        checksum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_compares_contents() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"same bytes", b"same bytes"));
+        assert!(!constant_time_eq(b"same bytes", b"same byteS"));
+        assert!(!constant_time_eq(b"\x00same", b"\x01same"));
+        // Different lengths never compare equal, even with a common prefix
+        assert!(!constant_time_eq(b"prefix", b"prefix and more"));
+        assert!(!constant_time_eq(b"", b"\x00"));
+    }
+
+    #[test]
+    fn key_report_data_hashes_the_canonical_statement() {
+        let public_key = [0x02, 0xab, 0xcd];
+        let report_data = key_report_data("signer", &CryptoAlgorithm::Secp256k1, 3, &public_key).unwrap();
+        // A verifier's own serialization, keys in another order and with whitespace
+        let theirs = r#"{ "public_key": "02abcd", "key_type": "Secp256k1",
+            "key_id": "signer", "generation": 3 }"#;
+        let canonical = crate::canonical::canonicalize(theirs).unwrap();
+        assert_eq!(report_data, sha2::Sha256::digest(canonical.as_bytes()).to_vec());
+        assert_ne!(report_data, key_report_data("signer", &CryptoAlgorithm::Secp256k1, 4, &public_key).unwrap());
+    }
+
+    #[tokio::test]
+    async fn attested_key_quotes_verify_against_the_key_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let metadata = crypto
+            .generate_attested_key("attested", CryptoAlgorithm::Secp256k1, vec!["sign".into()], false, "")
+            .unwrap();
+        let expected = |metadata: &KeyMetadata| {
+            key_report_data(&metadata.key_id, &metadata.key_type, metadata.generation,
+                metadata.public_key.as_deref().unwrap()).unwrap()
+        };
+        crate::attestation::inspect_quote(metadata.attestation_quote.as_deref().unwrap(), &expected(&metadata)).unwrap();
+
+        let rotated = crypto.rotate_key("attested").unwrap();
+        crate::attestation::inspect_quote(rotated.attestation_quote.as_deref().unwrap(), &expected(&rotated)).unwrap();
+        assert!(crate::attestation::inspect_quote(rotated.attestation_quote.as_deref().unwrap(), &expected(&metadata)).is_err());
+    }
+
+    #[tokio::test]
+    async fn ciphertexts_from_before_generation_tags_still_decrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("data", CryptoAlgorithm::Aes256Gcm, vec!["Encrypt".into(), "Decrypt".into()], false, "").unwrap();
+        // Untagged nonce || ciphertext || tag, as encrypt_with_key used to return
+        let legacy = {
+            let key_store = crypto.key_store.read_or_recover();
+            crypto.seal_aes_gcm(b"written long ago", key_store.symmetric_keys.get("data").unwrap(), b"aad").unwrap()
+        };
+        assert_eq!(crypto.decrypt_with_key("data", &legacy, b"aad").unwrap(), b"written long ago");
+        assert!(crypto.decrypt_with_key("data", &legacy, b"other aad").is_err());
+        
+        crypto.rotate_key("data").unwrap();
+        assert_eq!(crypto.decrypt_with_key("data", &legacy, b"aad").unwrap(), b"written long ago");
+        let current = crypto.encrypt_with_key("data", b"written today", b"aad").unwrap();
+        assert_eq!(&current[..KEY_GENERATION_TAG_LEN], &2u32.to_be_bytes());
+        assert_eq!(crypto.decrypt_with_key("data", &current, b"aad").unwrap(), b"written today");
+    }
+
+    /// Compiles only for types that wipe their contents when dropped
+    fn wiped_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+
+    #[tokio::test]
+    async fn deleting_a_key_wipes_every_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("secret", CryptoAlgorithm::Aes256Gcm, vec!["Encrypt".into(), "Decrypt".into()], false, "").unwrap();
+        crypto.generate_key("signer", CryptoAlgorithm::Secp256r1, vec!["Sign".into()], false, "").unwrap();
+        crypto.rotate_key("secret").unwrap();
+        crypto.rotate_key("signer").unwrap();
+        {
+            let key_store = crypto.key_store.read_or_recover();
+            wiped_on_drop(key_store.symmetric_keys.get("secret").unwrap());
+            wiped_on_drop(&key_store.asymmetric_keys.get("signer").unwrap().0);
+            assert_eq!(key_store.retired["secret"].len(), 1);
+            assert_eq!(key_store.retired["signer"].len(), 1);
+        }
+
+        crypto.delete_key("secret").unwrap();
+        crypto.delete_key("signer").unwrap();
+        let key_store = crypto.key_store.read_or_recover();
+        for key_id in ["secret", "signer"] {
+            assert!(!key_store.symmetric_keys.contains_key(key_id));
+            assert!(!key_store.asymmetric_keys.contains_key(key_id));
+            assert!(!key_store.retired.contains_key(key_id));
+            assert!(!key_store.metadata.contains_key(key_id));
+        }
+    }
+
+    #[test]
+    fn zeroizing_key_material_drops_the_secret_bytes() {
+        let mut symmetric = KeyMaterial::Symmetric(Zeroizing::new(vec![0x5a; 32]));
+        symmetric.zeroize();
+        assert!(matches!(&symmetric, KeyMaterial::Symmetric(key) if key.is_empty()));
+
+        let mut asymmetric = KeyMaterial::Asymmetric(Zeroizing::new(vec![0x5a; 32]), vec![0x02; 33]);
+        asymmetric.zeroize();
+        let KeyMaterial::Asymmetric(private_key, public_key) = &asymmetric else { unreachable!() };
+        assert!(private_key.is_empty());
+        assert_eq!(public_key, &vec![0x02; 33]);
+    }
+
+    #[tokio::test]
+    async fn rsa_keys_sign_and_verify_with_pkcs1_v1_5() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let metadata = crypto
+            .generate_key("rsa", CryptoAlgorithm::Rsa2048, vec!["Sign".into(), "Verify".into()], false, "")
+            .unwrap();
+        assert_eq!(metadata.signature_scheme.as_deref(), Some(RSA_SIGNATURE_SCHEME));
+        let public_key = metadata.public_key.unwrap();
+        assert_eq!(parse_rsa_public_key(&CryptoAlgorithm::Rsa2048, &public_key).unwrap().size() * 8, 2048);
+        assert!(parse_rsa_public_key(&CryptoAlgorithm::Rsa4096, &public_key).is_err());
+        
+        let signature = crypto.sign_data("rsa", b"payload").unwrap();
+        assert_eq!(signature.len(), 256);
+        assert!(crypto.verify_signature("rsa", b"payload", &signature).unwrap());
+        assert!(!crypto.verify_signature("rsa", b"payloaD", &signature).unwrap());
+        assert!(!crypto.verify_signature("rsa", b"payload", &signature[1..]).unwrap());
+        assert!(crypto.verify_with_public_key(CryptoAlgorithm::Rsa2048, &public_key, b"payload", &signature).unwrap());
+        
+        let pem = crypto.export_public_key_pem("rsa").unwrap();
+        assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----"));
+        assert_eq!(RsaPublicKey::from_public_key_pem(&pem).unwrap().to_public_key_der().unwrap().as_bytes(), public_key);
+    }
+
+    #[tokio::test]
+    async fn secp256r1_signatures_are_raw_r_s_over_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("neo", CryptoAlgorithm::Secp256r1, vec!["Sign".into(), "Verify".into()], false, "").unwrap();
+        
+        let signature = crypto.sign_data("neo", b"transaction").unwrap();
+        assert_eq!(signature.len(), 64);
+        assert!(crypto.verify_signature("neo", b"transaction", &signature).unwrap());
+        assert!(!crypto.verify_signature("neo", b"transactioN", &signature).unwrap());
+        assert!(!crypto.verify_signature("neo", b"transaction", &[0u8; 64]).unwrap());
+        
+        // Both SEC1 encodings verify, and so does an independent p256 verifier
+        let compressed = crypto.get_public_key("neo", true).unwrap();
+        let uncompressed = crypto.get_public_key("neo", false).unwrap();
+        assert_eq!((compressed.len(), uncompressed.len()), (33, 65));
+        for public_key in [&compressed, &uncompressed] {
+            assert!(crypto.verify_with_public_key(CryptoAlgorithm::Secp256r1, public_key, b"transaction", &signature).unwrap());
+        }
+        let verifying_key = P256VerifyingKey::from_sec1_bytes(&compressed).unwrap();
+        verifying_key.verify(b"transaction", &P256Signature::from_slice(&signature).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn base58_matches_reference_vectors() {
+        let vectors: [(&str, &str); 6] = [
+            ("", ""),
+            ("61", "2g"),
+            ("516b6fcd0f", "ABnLTmg"),
+            ("73696d706c792061206c6f6e6720737472696e67", "2cFupjhnEsSn59qHXstmK2ffpLv2"),
+            ("00eb15231dfceb60925886b67d065299925915aeb172c06647", "1NS17iag9jJgTHD1VXjvLCEnZuQ3rJDE9L"),
+            ("00000000000000000000", "1111111111"),
+        ];
+        for (bytes, encoded) in vectors {
+            assert_eq!(base58::encode(&hex::decode(bytes).unwrap()), encoded);
+            assert_eq!(hex::encode(base58::decode(encoded).unwrap()), bytes);
+        }
+        for invalid in ["0", "O", "I", "l", "2g "] {
+            assert!(base58::decode(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn base58check_round_trips_neo_addresses_and_rejects_bad_checksums() {
+        // Neo N3 address of the key pair in account's test vectors: version 0x35 and a script hash
+        let address = "NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV";
+        let payload = base58::decode_check(address).unwrap();
+        assert_eq!((payload.len(), payload[0]), (21, 0x35));
+        assert_eq!(base58::encode_check(&payload), address);
+        
+        // A leading zero version byte survives as a leading '1'
+        let genesis = hex::decode("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert_eq!(base58::encode_check(&genesis), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+        assert_eq!(base58::decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap(), genesis);
+        
+        let corrupted = address.replace("GV", "GW");
+        assert!(base58::decode_check(&corrupted).unwrap_err().to_string().contains("checksum"));
+        assert!(base58::decode_check("2g").is_err());
+    }
+
+    #[tokio::test]
+    async fn recovered_public_keys_match_the_signing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("k1", CryptoAlgorithm::Secp256k1, vec!["Sign".into(), "Verify".into()], false, "").unwrap();
+        crypto.generate_key("r1", CryptoAlgorithm::Secp256r1, vec!["Sign".into()], false, "").unwrap();
+        let public_key = crypto.get_public_key("k1", true).unwrap();
+        
+        let signature = crypto.sign_recoverable("k1", b"transfer").unwrap();
+        assert_eq!(signature.len(), RECOVERABLE_SIGNATURE_LEN);
+        assert!(signature[64] <= 3);
+        assert_eq!(crypto.recover_public_key(b"transfer", &signature).unwrap(), public_key);
+        // The r || s prefix is an ordinary compact signature
+        assert!(crypto.verify_signature("k1", b"transfer", &signature[..64]).unwrap());
+        
+        let mut ethereum = signature.clone();
+        ethereum[64] += 27;
+        assert_eq!(crypto.recover_public_key(b"transfer", &ethereum).unwrap(), public_key);
+        
+        // Other data recovers some other key, never the signer's
+        assert_ne!(crypto.recover_public_key(b"transfeR", &signature).ok(), Some(public_key));
+        let mut bad_id = signature.clone();
+        bad_id[64] = 4;
+        assert!(crypto.recover_public_key(b"transfer", &bad_id).is_err());
+        assert!(crypto.recover_public_key(b"transfer", &signature[..64]).is_err());
+        assert!(crypto.sign_recoverable("r1", b"transfer").is_err());
+    }
+
+    #[tokio::test]
+    async fn deterministic_signatures_repeat_and_match_rfc_6979() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        for (key_id, algorithm) in [("k1", CryptoAlgorithm::Secp256k1), ("r1", CryptoAlgorithm::Secp256r1), ("ed", CryptoAlgorithm::Ed25519)] {
+            crypto.generate_key(key_id, algorithm, vec!["Sign".into(), "Verify".into()], false, "").unwrap();
+            let first = crypto.sign_deterministic(key_id, b"message").unwrap();
+            assert_eq!(crypto.sign_deterministic(key_id, b"message").unwrap(), first, "{}", key_id);
+            assert_ne!(crypto.sign_deterministic(key_id, b"other message").unwrap(), first, "{}", key_id);
+            assert!(crypto.verify_signature(key_id, b"message", &first).unwrap());
+        }
+        
+        // RFC 6979 appendix A.2.5: P-256 with SHA-256, message "sample"
+        let private_key = hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721").unwrap();
+        crypto.import_key("rfc6979", CryptoAlgorithm::Secp256r1, &private_key, vec!["Sign".into()], false, "").unwrap();
+        assert_eq!(hex::encode(crypto.sign_deterministic("rfc6979", b"sample").unwrap()), concat!(
+            "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+            "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+        ));
+        
+        crypto.generate_key("aes", CryptoAlgorithm::Aes256Gcm, vec!["Sign".into()], false, "").unwrap();
+        assert!(crypto.sign_deterministic("aes", b"message").is_err());
+    }
+
+    /// Pearson's chi-squared statistic of `counts` against a uniform distribution
+    fn chi_squared(counts: &[u64]) -> f64 {
+        let expected = counts.iter().sum::<u64>() as f64 / counts.len() as f64;
+        counts.iter().map(|&count| (count as f64 - expected).powi(2) / expected).sum()
+    }
+
+    #[tokio::test]
+    async fn random_ranges_are_uniform() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        
+        let mut faces = [0u64; 6];
+        for _ in 0..60_000 {
+            let value = crypto.generate_random(-3, 3).unwrap();
+            assert!((-3..3).contains(&value));
+            faces[(value + 3) as usize] += 1;
+        }
+        // 5 degrees of freedom; a uniform source exceeds 35.9 with probability 1e-6
+        assert!(chi_squared(&faces) < 35.9, "{:?}", faces);
+        
+        // Reducing a u64 modulo 3 * 2^62 would put half of all draws in the lowest third
+        let max = 3u64 << 62;
+        let mut thirds = [0u64; 3];
+        for _ in 0..30_000 {
+            let value = crypto.generate_random_u64_range(0, max).unwrap();
+            thirds[(value >> 62) as usize] += 1;
+        }
+        // 2 degrees of freedom; exceeded with probability 1e-6
+        assert!(chi_squared(&thirds) < 27.6, "{:?}", thirds);
+        
+        assert!((i32::MIN..i32::MAX).contains(&crypto.generate_random(i32::MIN, i32::MAX).unwrap()));
+        assert_eq!(crypto.generate_random_u64_range(u64::MAX - 1, u64::MAX).unwrap(), u64::MAX - 1);
+        assert!(crypto.generate_random(3, 3).is_err());
+        assert!(crypto.generate_random_u64_range(5, 4).is_err());
+    }
+
+    #[tokio::test]
+    async fn keys_count_their_uses_and_refuse_work_once_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("signer", CryptoAlgorithm::Secp256r1, vec!["Sign".into(), "Verify".into()], false, "").unwrap();
+        crypto.generate_key("sealer", CryptoAlgorithm::Aes256Gcm, vec!["Encrypt".into(), "Decrypt".into()], false, "").unwrap();
+        assert!(crypto.get_key_metadata("signer").unwrap().usage_stats.last_used_at.is_none());
+        
+        let signature = crypto.sign_data("signer", b"data").unwrap();
+        crypto.sign_data("signer", b"more data").unwrap();
+        assert!(crypto.verify_signature("signer", b"data", &signature).unwrap());
+        let ciphertext = crypto.encrypt_with_key("sealer", b"secret", b"").unwrap();
+        crypto.decrypt_with_key("sealer", &ciphertext, b"").unwrap();
+        
+        let usage = crypto.get_key_metadata("signer").unwrap().usage_stats;
+        assert_eq!((usage.sign_count, usage.verify_count), (2, 1));
+        assert!(usage.last_used_at.is_some());
+        let usage = crypto.get_key_metadata("sealer").unwrap().usage_stats;
+        assert_eq!((usage.encrypt_count, usage.decrypt_count), (1, 1));
+        
+        let next_year = unix_now() + 365 * 24 * 60 * 60;
+        crypto.set_key_expiry("sealer", Some(next_year)).unwrap();
+        assert!(crypto.encrypt_with_key("sealer", b"secret", b"").is_ok());
+        
+        crypto.set_key_expiry("signer", Some(1)).unwrap();
+        assert!(crypto.get_key_metadata("signer").unwrap().is_expired());
+        assert!(crypto.sign_data("signer", b"data").unwrap_err().to_string().contains("expired"));
+        assert!(crypto.sign_deterministic("signer", b"data").is_err());
+        assert!(crypto.verify_signature("signer", b"data", &signature).is_err());
+        assert_eq!(crypto.expired_keys().unwrap(), vec!["signer".to_string()]);
+        assert_eq!(crypto.get_key_metadata("signer").unwrap().usage_stats.sign_count, 2);
+        
+        crypto.set_key_expiry("signer", None).unwrap();
+        assert!(crypto.sign_data("signer", b"data").is_ok());
+        assert!(crypto.expired_keys().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn self_test_passes_every_built_in_algorithm() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::test_support::test_config(dir.path());
+        config.crypto_algorithms = ["aes-256-gcm", "chacha20-poly1305", "secp256k1", "secp256r1", "ed25519", "rsa-2048", "sha3-256"]
+            .iter().map(|name| name.to_string()).collect();
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        
+        let report = crypto.self_test_report();
+        assert!(report.passed, "{:?}", report.failures());
+        let tested: Vec<&str> = report.results.iter().map(|result| result.algorithm).collect();
+        assert_eq!(tested, ["sha256", "aes-256-gcm", "chacha20-poly1305", "secp256k1", "secp256r1", "ed25519", "rsa-2048", "sha3-256"]);
+        for result in &report.results {
+            if result.algorithm == "sha3-256" {
+                assert_eq!(result.status, SelfTestStatus::Skipped);
+                assert!(result.benchmark.is_none());
+            } else {
+                assert_eq!(result.status, SelfTestStatus::Passed, "{}", result.algorithm);
+                assert!(result.benchmark.as_ref().unwrap().ops_per_second > 0.0);
+            }
+        }
+        
+        let json: serde_json::Value = serde_json::from_str(&crypto.self_test().unwrap()).unwrap();
+        assert_eq!(json["passed"], true);
+        assert!(json["results"][1]["benchmark"]["mb_per_second"].as_f64().unwrap() > 0.0);
+    }
+    
+    #[test]
+    fn known_answer_checks_reject_wrong_answers() {
+        // GCM specification test case 13: empty plaintext, so the output is just the tag
+        let tag = "530f8afbc74536b9a963b4f1c4cb738b";
+        aead_known_answer(&aead::AES_256_GCM, &[0u8; 32], [0u8; 12], &[], &[], tag).unwrap();
+        let wrong_tag = "530f8afbc74536b9a963b4f1c4cb738c";
+        assert!(aead_known_answer(&aead::AES_256_GCM, &[0u8; 32], [0u8; 12], &[], &[], wrong_tag).is_err());
+        // The same inputs under another cipher do not produce the GCM answer
+        assert!(aead_known_answer(&aead::CHACHA20_POLY1305, &[0u8; 32], [0u8; 12], &[], &[], tag).is_err());
+        
+        expect_hex("SHA-256(\"abc\")", &Sha256::digest(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap();
+        let error = expect_hex("SHA-256(\"abd\")", &Sha256::digest(b"abd"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap_err();
+        assert!(error.to_string().contains("SHA-256(\"abd\") does not match the known answer"), "{}", error);
+    }
+
+    /// `n - s` for a compact secp256k1 signature: the high-S twin of a low-S signature
+    fn flip_s(signature: &[u8]) -> Vec<u8> {
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+        let mut flipped = signature.to_vec();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let difference = order[i] as i16 - signature[32 + i] as i16 - borrow;
+            borrow = (difference < 0) as i16;
+            flipped[32 + i] = difference.rem_euclid(256) as u8;
+        }
+        flipped
+    }
+    
+    #[tokio::test]
+    async fn secp256k1_signatures_are_low_s_and_high_s_is_enforced() {
+        let half_order = hex::decode("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        crypto.generate_key("k1", CryptoAlgorithm::Secp256k1, vec!["Sign".into(), "Verify".into()], false, "").unwrap();
+        
+        // Unnormalized, about half of these would be high-S
+        for i in 0..32u8 {
+            let signature = crypto.sign_data("k1", &[i]).unwrap();
+            assert!(signature[32..] <= half_order[..], "high-S signature for message {}", i);
+            assert!(crypto.verify_signature("k1", &[i], &signature).unwrap());
+        }
+        
+        let signature = crypto.sign_data("k1", b"payload").unwrap();
+        let high_s = flip_s(&signature);
+        assert!(high_s[32..] > half_order[..]);
+        assert!(!crypto.verify_signature("k1", b"payload", &high_s).unwrap());
+        
+        // With enforcement off the malleated twin is accepted as the same signature
+        let lenient_dir = tempfile::tempdir().unwrap();
+        let mut lenient_config = crate::test_support::test_config(lenient_dir.path());
+        lenient_config.crypto_require_low_s = false;
+        let (_, _, lenient) = crate::test_support::core_services(&lenient_config).await;
+        let public_key = crypto.get_public_key("k1", true).unwrap();
+        assert!(lenient.check_signature(&CryptoAlgorithm::Secp256k1, &public_key, b"payload", &high_s).unwrap());
+        assert!(!lenient.check_signature(&CryptoAlgorithm::Secp256k1, &public_key, b"other", &high_s).unwrap());
+    }
+
+    /// RSA-2048 key generated with `openssl genpkey`; OPENSSL_SIGNATURE is
+    /// `openssl dgst -sha256 -sign` over OPENSSL_MESSAGE with its private half
+    const OPENSSL_RSA_2048_PUBLIC_KEY: &str = concat!(
+        "-----BEGIN PUBLIC KEY-----\n",
+        "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAy/pCzHFotTyXCYqaDqsy\n",
+        "x2ghWWuutT2JFWpOtsJTfn103WtPYYWY+Mcot96lq2Ikk5TxJTkj3UCV8PWvQ8iC\n",
+        "EKQdykN+2cEKc+1rp8DmivF2nWwhFtiOwtV4TKgO79gfuGE9uR92bd4pKnbaoKf4\n",
+        "IGZ+bCD1sEbdmrIxTC2ASwV2qdIJwFaxObl+38vVtLYeJIZjpWgKXMXJav5L9qLQ\n",
+        "u3rKi/9wTxh7xQDeKsHD0I9EHRzJ4CEpBOQbuke6Bt9dvLd87/V1elZBJWGw8/CN\n",
+        "NLSh1MEUMlyYJHP4W3OUJ8csp447Dl1M0wo8B7x70kiFjF6VV5acAezt6uUdF/aZ\n",
+        "fQIDAQAB\n",
+        "-----END PUBLIC KEY-----\n",
+    );
+    const OPENSSL_MESSAGE: &[u8] = b"Signed by OpenSSL";
+    const OPENSSL_SIGNATURE: &str = concat!(
+        "4f0a1262aaa1597523f628f60e871bc4a815d665d36bfb7be6c24dfb2ed53f6c7bb06a5f8f03ac88ac77de6f76ead66616f6577285d94814f0a8b5b71bc81b63",
+        "d9bdbdedd8354c95458971c4c5db9255aa49e77953ae8086e96bc0478d0ee8ba7ee4e15335550c245618146857ce403dbbee481430b661c12f0bef14b9facc8f",
+        "1d06f127357d0c8235a48cf17d8efb39bcb64ec9d35d01aea1e2f5f2979800dd5a2370cf0538f39513f521f6d555ff0c6459acba953461e9963020d01cd69c68",
+        "4a48cfd843cd4c120419da59c11d36538b785410ffd5e51e5a2b75f54a879d7539d77e670515613914abb47261b9b0cef281d0afbac5d1a77c44ac917b15eea4",
+    );
+    
+    #[tokio::test]
+    async fn openssl_rsa_signatures_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let public_key = RsaPublicKey::from_public_key_pem(OPENSSL_RSA_2048_PUBLIC_KEY).unwrap().to_public_key_der().unwrap();
+        let mut signature = hex::decode(OPENSSL_SIGNATURE).unwrap();
+        
+        assert!(crypto.verify_with_public_key(CryptoAlgorithm::Rsa2048, public_key.as_bytes(), OPENSSL_MESSAGE, &signature).unwrap());
+        assert!(!crypto.verify_with_public_key(CryptoAlgorithm::Rsa2048, public_key.as_bytes(), b"Signed by OpenSSl", &signature).unwrap());
+        signature[100] ^= 0x01;
+        assert!(!crypto.verify_with_public_key(CryptoAlgorithm::Rsa2048, public_key.as_bytes(), OPENSSL_MESSAGE, &signature).unwrap());
+    }
+    
+    #[test]
+    fn rsa_keys_below_2048_bits_are_rejected() {
+        // `openssl genpkey -algorithm RSA -pkeyopt rsa_keygen_bits:1024`, public half
+        let weak = RsaPublicKey::from_public_key_pem(concat!(
+            "-----BEGIN PUBLIC KEY-----\n",
+            "MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQC01n8yHFunEpGJiAUCw2J75So+\n",
+            "FKegub9caQhyS0a9ZB5yzg8oAlUPsXNmKbJzEW7PbzwkepkI7wkJIwCydiDWlpKc\n",
+            "GmevoEhwGTQ/c5EBJ9cFswpUsGt5LeBn5eB39X+9j6gVrWqd6X/qqwcpfDwoTGJ1\n",
+            "5zbvqI2sq+9sGWyfVQIDAQAB\n",
+            "-----END PUBLIC KEY-----\n",
+        )).unwrap().to_public_key_der().unwrap();
+        
+        for key_type in [CryptoAlgorithm::Rsa2048, CryptoAlgorithm::Rsa4096] {
+            let error = parse_rsa_public_key(&key_type, weak.as_bytes()).unwrap_err();
+            assert!(error.to_string().contains("RSA key size 1024 is below the minimum of 2048 bits"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,631 @@
+use anyhow::{Result, anyhow};
+use log::{debug, warn};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::ai::AIService;
+use crate::audit::AuditEvent;
+use crate::computation::CachePolicy;
+use crate::crypto::CryptoAlgorithm;
+use crate::error::{error_code, EnclaveError};
+use crate::storage::{StorageAcl, StoreOptions, SYSTEM_PRINCIPAL};
+use crate::EncaveRuntime;
+use crate::locks::MutexExt;
+
+/// A method callable through [`EncaveRuntime::dispatch`]
+pub struct DispatchMethod {
+    pub name: &'static str,
+    pub description: &'static str,
+    handler: fn(&EncaveRuntime, &Value) -> Result<Value>,
+}
+
+/// Every method the dispatcher routes, by name. Binary values are hex strings and
+/// numeric vectors are JSON arrays of numbers.
+pub static METHODS: &[DispatchMethod] = &[
+    DispatchMethod { name: "system.methods", description: "List the methods available through dispatch", handler: system_methods },
+    DispatchMethod { name: "system.health", description: "Health report for all services", handler: system_health },
+    DispatchMethod { name: "system.metrics", description: "Operation counters for all services", handler: system_metrics },
+    DispatchMethod { name: "system.reload_auth_policy", description: "Re-read the auth policy file", handler: system_reload_auth_policy },
+    DispatchMethod { name: "crypto.generate_key", description: "Generate a key: key_id, key_type, usage, exportable?, description?", handler: crypto_generate_key },
+    DispatchMethod { name: "crypto.key_metadata", description: "Metadata and usage counters of key_id", handler: crypto_key_metadata },
+    DispatchMethod { name: "crypto.rotate_key", description: "Rotate key_id to a new generation", handler: crypto_rotate_key },
+    DispatchMethod { name: "crypto.sign", description: "Sign hex data with key_id", handler: crypto_sign },
+    DispatchMethod { name: "crypto.verify", description: "Verify a hex signature over hex data with key_id", handler: crypto_verify },
+    DispatchMethod { name: "crypto.random_bytes", description: "length secure random bytes as hex", handler: crypto_random_bytes },
+    DispatchMethod { name: "crypto.generate_id", description: "Unique id: prefix? followed by 128 random bits in base62", handler: crypto_generate_id },
+    DispatchMethod { name: "crypto.self_test", description: "Run the crypto known-answer tests and benchmark", handler: crypto_self_test },
+    DispatchMethod { name: "storage.store", description: "Store hex data under key for principal: encryption_key, compress?, acl?, secure_delete?, pinned?", handler: storage_store },
+    DispatchMethod { name: "storage.retrieve", description: "Retrieve key as hex for principal: encryption_key", handler: storage_retrieve },
+    DispatchMethod { name: "storage.delete", description: "Delete key for principal", handler: storage_delete },
+    DispatchMethod { name: "storage.delete_many", description: "Delete keys for principal, reporting failures", handler: storage_delete_many },
+    DispatchMethod { name: "storage.delete_prefix", description: "Delete every key starting with prefix for principal", handler: storage_delete_prefix },
+    DispatchMethod { name: "storage.list_keys", description: "Page of the keys principal may read: principal, limit?, offset?", handler: storage_list_keys },
+    DispatchMethod { name: "ai.train", description: "Train model_id of model_type on data with parameters?", handler: ai_train },
+    DispatchMethod { name: "ai.predict", description: "Predict with model_id on input", handler: ai_predict },
+    DispatchMethod { name: "ai.feature_importance", description: "Features of model_id ranked by importance", handler: ai_feature_importance },
+    DispatchMethod { name: "ai.import_model", description: "Import a trained model as exported by model info", handler: ai_import_model },
+    DispatchMethod { name: "account.create", description: "Create account_id with account_data?", handler: account_create },
+    DispatchMethod { name: "account.info", description: "Public information about account_id", handler: account_info },
+    DispatchMethod { name: "account.sign_transaction", description: "Sign transaction_data with account_id", handler: account_sign_transaction },
+    DispatchMethod { name: "computation.execute", description: "Run JavaScript code with args? and deterministic? (opts in to result caching)", handler: computation_execute },
+    DispatchMethod { name: "oracle.latest", description: "Latest value of oracle subscription_id", handler: oracle_latest },
+];
+
+/// Look up a registered method by name
+pub fn find_method(name: &str) -> Option<&'static DispatchMethod> {
+    METHODS.iter().find(|method| method.name == name)
+}
+
+/// JSON-RPC style envelope for a dispatch outcome: `{"id", "result"}` on success,
+/// `{"id", "error": {"code", "message"}}` with the stable FFI code on failure
+pub fn response(id: Value, outcome: &Result<Value>) -> Value {
+    match outcome {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(e) => json!({
+            "id": id,
+            "error": { "code": error_code(e), "message": e.to_string() },
+        }),
+    }
+}
+
+/// Upper bound on results opened with `occlum_result_open` and not yet closed
+pub const MAX_OPEN_RESULTS: usize = 64;
+
+/// How long a result may go unread before it is dropped
+pub const OPEN_RESULT_TTL: Duration = Duration::from_secs(300);
+
+/// A held result and when the host last touched it
+struct OpenResult {
+    data: Vec<u8>,
+    last_read: Instant,
+}
+
+/// Serialized call results the host reads in chunks, keyed by the handle returned to it
+///
+/// A result stays in memory until `close` is called or it has not been read for the
+/// TTL, so a host that never closes its handles cannot exhaust the slots.
+pub struct OpenResults {
+    next_handle: AtomicU64,
+    results: Mutex<HashMap<u64, OpenResult>>,
+    ttl: Duration,
+}
+
+impl Default for OpenResults {
+    fn default() -> Self {
+        Self::with_ttl(OPEN_RESULT_TTL)
+    }
+}
+
+impl OpenResults {
+    /// Holder whose results are dropped after going unread for `ttl`
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            next_handle: AtomicU64::new(0),
+            results: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+    
+    /// Hold `data` and return its handle
+    pub fn open(&self, data: String) -> Result<u64> {
+        let mut results = self.results.lock_or_recover();
+        let before = results.len();
+        results.retain(|_, result| result.last_read.elapsed() < self.ttl);
+        if results.len() < before {
+            warn!("Dropped {} results left unread for {:?}", before - results.len(), self.ttl);
+        }
+        if results.len() >= MAX_OPEN_RESULTS {
+            return Err(EnclaveError::ResourceLimit(format!("Too many open results ({})", MAX_OPEN_RESULTS)).into());
+        }
+        
+        // Handles start at 1 so that 0 is never a valid handle
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed) + 1;
+        results.insert(handle, OpenResult { data: data.into_bytes(), last_read: Instant::now() });
+        Ok(handle)
+    }
+    
+    /// Copy the bytes starting at `offset` into `buffer`, returning how many were copied;
+    /// 0 once `offset` reaches the end of the result
+    pub fn read(&self, handle: u64, offset: usize, buffer: &mut [u8]) -> Result<usize> {
+        let mut results = self.results.lock_or_recover();
+        let result = results.get_mut(&handle)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Unknown result handle {}", handle)))?;
+        result.last_read = Instant::now();
+        let data = &result.data;
+        if offset > data.len() {
+            return Err(EnclaveError::InvalidInput(format!(
+                "Offset {} is past the end of result {} ({} bytes)", offset, handle, data.len()
+            )).into());
+        }
+        
+        let chunk = &data[offset..data.len().min(offset + buffer.len())];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        Ok(chunk.len())
+    }
+    
+    /// Drop a result
+    pub fn close(&self, handle: u64) -> Result<()> {
+        self.results.lock_or_recover()
+            .remove(&handle)
+            .map(|_| ())
+            .ok_or_else(|| EnclaveError::NotFound(format!("Unknown result handle {}", handle)).into())
+    }
+}
+
+impl EncaveRuntime {
+    /// Handle a serialized `{"method", "params", "token", "id"}` request, returning the
+    /// [`response`] envelope and its stable code (0 on success)
+    pub fn call(&self, request: &str) -> (String, c_int) {
+        let (id, outcome) = match serde_json::from_str::<Value>(request) {
+            Ok(mut request) => {
+                let id = request["id"].take();
+                let params = request["params"].take();
+                let outcome = match request["method"].as_str() {
+                    Some(method) => self.dispatch_as(request["token"].as_str(), method, params),
+                    None => Err(EnclaveError::InvalidInput("Request has no method".into()).into()),
+                };
+                (id, outcome)
+            }
+            Err(e) => (
+                Value::Null,
+                Err(EnclaveError::InvalidInput(format!("Invalid request JSON: {}", e)).into()),
+            ),
+        };
+        
+        let code = match &outcome {
+            Ok(_) => 0,
+            Err(e) => {
+                debug!("Dispatched call failed: {}", e);
+                error_code(e)
+            }
+        };
+        (response(id, &outcome).to_string(), code)
+    }
+    
+    /// Route `method` (e.g. `crypto.sign`) to the service that implements it, without a
+    /// token; fails for every method once an auth policy is configured
+    pub fn dispatch(&self, method: &str, params: Value) -> Result<Value> {
+        self.dispatch_as(None, method, params)
+    }
+    
+    /// Route `method` for the caller holding `token`
+    ///
+    /// `params` must be a JSON object, or null for methods without parameters. Calls the
+    /// auth policy does not allow fail with `PermissionDenied` before any service is
+    /// touched, and an authenticated caller's principal replaces any `principal` param.
+    /// Errors carry the same classification as the dedicated FFI exports.
+    pub fn dispatch_as(&self, token: Option<&str>, method: &str, params: Value) -> Result<Value> {
+        let handler = find_method(method)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Unknown method '{}'", method)))?
+            .handler;
+        let mut params = match params {
+            Value::Null => Value::Object(Default::default()),
+            Value::Object(_) => params,
+            _ => return Err(EnclaveError::InvalidInput("params must be a JSON object".into()).into()),
+        };
+        
+        match self.authorizer().authorize(token, method) {
+            Ok(Some(principal)) => params["principal"] = Value::String(principal),
+            Ok(None) => {}
+            Err(e) => {
+                self.audit_log().record(AuditEvent::new("dispatch", "call_denied", method)
+                    .with_details(json!({ "reason": e.to_string() })));
+                return Err(e);
+            }
+        }
+        handler(self, &params)
+    }
+}
+
+fn param<'a>(params: &'a Value, name: &str) -> Result<&'a Value> {
+    params.get(name)
+        .filter(|value| !value.is_null())
+        .ok_or_else(|| EnclaveError::InvalidInput(format!("Missing parameter '{}'", name)).into())
+}
+
+fn param_str<'a>(params: &'a Value, name: &str) -> Result<&'a str> {
+    param(params, name)?
+        .as_str()
+        .ok_or_else(|| EnclaveError::InvalidInput(format!("Parameter '{}' must be a string", name)).into())
+}
+
+fn optional_str<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => param_str(params, name).map(Some),
+    }
+}
+
+fn param_hex(params: &Value, name: &str) -> Result<Vec<u8>> {
+    hex::decode(param_str(params, name)?)
+        .map_err(|e| EnclaveError::InvalidInput(format!("Parameter '{}' is not valid hex: {}", name, e)).into())
+}
+
+fn param_u64(params: &Value, name: &str) -> Result<u64> {
+    param(params, name)?
+        .as_u64()
+        .ok_or_else(|| EnclaveError::InvalidInput(format!("Parameter '{}' must be a non-negative integer", name)).into())
+}
+
+fn optional_usize(params: &Value, name: &str) -> Result<Option<usize>> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(_) => Ok(Some(param_u64(params, name)? as usize)),
+    }
+}
+
+fn optional_bool(params: &Value, name: &str) -> Result<bool> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(false),
+        Some(value) => value.as_bool()
+            .ok_or_else(|| EnclaveError::InvalidInput(format!("Parameter '{}' must be a boolean", name)).into()),
+    }
+}
+
+fn param_as<T: serde::de::DeserializeOwned>(params: &Value, name: &str) -> Result<T> {
+    serde_json::from_value(param(params, name)?.clone())
+        .map_err(|e| EnclaveError::InvalidInput(format!("Invalid parameter '{}': {}", name, e)).into())
+}
+
+/// Principal a host request acts for; the enclave's own principal cannot be claimed
+fn param_principal(params: &Value) -> Result<&str> {
+    let principal = param_str(params, "principal")?;
+    if principal.is_empty() || principal == SYSTEM_PRINCIPAL {
+        return Err(EnclaveError::PermissionDenied(format!("Principal '{}' is reserved", principal)).into());
+    }
+    Ok(principal)
+}
+
+/// Services return JSON documents as strings; embed them as JSON rather than as text
+fn service_json(result: String) -> Value {
+    serde_json::from_str(&result).unwrap_or(Value::String(result))
+}
+
+fn ai_service(runtime: &EncaveRuntime) -> Result<&Arc<AIService>> {
+    runtime.ai_service().ok_or_else(|| anyhow!("AI service is disabled"))
+}
+
+fn system_methods(_runtime: &EncaveRuntime, _params: &Value) -> Result<Value> {
+    Ok(METHODS.iter()
+        .map(|method| json!({ "name": method.name, "description": method.description }))
+        .collect())
+}
+
+fn system_health(runtime: &EncaveRuntime, _params: &Value) -> Result<Value> {
+    Ok(serde_json::to_value(runtime.health_check())?)
+}
+
+fn system_metrics(runtime: &EncaveRuntime, _params: &Value) -> Result<Value> {
+    Ok(service_json(runtime.metrics_snapshot()?))
+}
+
+fn system_reload_auth_policy(runtime: &EncaveRuntime, _params: &Value) -> Result<Value> {
+    let principals = runtime.authorizer().reload()?;
+    Ok(json!({ "principals": principals }))
+}
+
+fn crypto_generate_key(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let key_type = param_str(params, "key_type")?;
+    let key_type = CryptoAlgorithm::from_name(key_type)
+        .ok_or_else(|| EnclaveError::InvalidInput(format!("Unknown key type '{}'", key_type)))?;
+    let metadata = runtime.crypto_service().generate_key(
+        param_str(params, "key_id")?,
+        key_type,
+        param_as(params, "usage")?,
+        optional_bool(params, "exportable")?,
+        optional_str(params, "description")?.unwrap_or_default(),
+    )?;
+    Ok(serde_json::to_value(metadata)?)
+}
+
+fn crypto_key_metadata(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let metadata = runtime.crypto_service().get_key_metadata(param_str(params, "key_id")?)?;
+    Ok(serde_json::to_value(metadata)?)
+}
+
+fn crypto_rotate_key(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let metadata = runtime.crypto_service().rotate_key(param_str(params, "key_id")?)?;
+    Ok(serde_json::to_value(metadata)?)
+}
+
+fn crypto_sign(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let signature = runtime.crypto_service().sign_data(param_str(params, "key_id")?, &param_hex(params, "data")?)?;
+    Ok(json!({ "signature": hex::encode(signature) }))
+}
+
+fn crypto_verify(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let valid = runtime.crypto_service().verify_signature(
+        param_str(params, "key_id")?,
+        &param_hex(params, "data")?,
+        &param_hex(params, "signature")?,
+    )?;
+    Ok(json!({ "valid": valid }))
+}
+
+fn crypto_random_bytes(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let length = usize::try_from(param_u64(params, "length")?)?;
+    let bytes = runtime.crypto_service().generate_random_bytes(length)?;
+    Ok(json!({ "bytes": hex::encode(bytes) }))
+}
+
+fn crypto_generate_id(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let prefix = optional_str(params, "prefix")?.unwrap_or_default();
+    Ok(json!({ "id": runtime.crypto_service().generate_id(prefix)? }))
+}
+
+fn crypto_self_test(runtime: &EncaveRuntime, _params: &Value) -> Result<Value> {
+    Ok(service_json(runtime.crypto_service().self_test()?))
+}
+
+fn storage_store(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let acl: StorageAcl = match params.get("acl") {
+        None | Some(Value::Null) => StorageAcl::default(),
+        Some(_) => param_as(params, "acl")?,
+    };
+    let options = StoreOptions {
+        compress: optional_bool(params, "compress")?,
+        acl,
+        secure_delete: optional_bool(params, "secure_delete")?,
+        pinned: optional_bool(params, "pinned")?,
+    };
+    let metadata = runtime.storage_service().store_data(
+        param_str(params, "key")?,
+        &param_hex(params, "data")?,
+        param_str(params, "encryption_key")?,
+        param_principal(params)?,
+        options,
+    )?;
+    Ok(service_json(metadata))
+}
+
+fn storage_retrieve(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let data = runtime.storage_service().retrieve_data(
+        param_str(params, "key")?,
+        param_str(params, "encryption_key")?,
+        param_principal(params)?,
+    )?;
+    Ok(json!({ "data": hex::encode(data) }))
+}
+
+fn storage_delete(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let result = runtime.storage_service().delete_data(param_str(params, "key")?, param_principal(params)?)?;
+    Ok(service_json(result))
+}
+
+fn storage_delete_many(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let keys: Vec<String> = param_as(params, "keys")?;
+    let result = runtime.storage_service().delete_many(&keys, param_principal(params)?)?;
+    Ok(service_json(result))
+}
+
+fn storage_delete_prefix(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let result = runtime.storage_service().delete_prefix(param_str(params, "prefix")?, param_principal(params)?)?;
+    Ok(service_json(result))
+}
+
+fn storage_list_keys(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let limit = optional_usize(params, "limit")?;
+    let offset = optional_usize(params, "offset")?;
+    Ok(service_json(runtime.storage_service().list_keys(param_principal(params)?, limit, offset)?))
+}
+
+fn ai_train(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let data: Vec<f64> = param_as(params, "data")?;
+    let parameters = match params.get("parameters") {
+        None | Some(Value::Null) => String::new(),
+        Some(parameters) => parameters.to_string(),
+    };
+    let model = ai_service(runtime)?.train_model(
+        param_str(params, "model_id")?,
+        param_str(params, "model_type")?,
+        &data,
+        &parameters,
+    )?;
+    Ok(service_json(model))
+}
+
+fn ai_predict(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let input: Vec<f64> = param_as(params, "input")?;
+    let (output, metadata) = ai_service(runtime)?.predict(param_str(params, "model_id")?, &input)?;
+    Ok(json!({ "output": output, "metadata": service_json(metadata) }))
+}
+
+fn ai_feature_importance(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let result = ai_service(runtime)?.feature_importance(param_str(params, "model_id")?)?;
+    Ok(service_json(result))
+}
+
+fn ai_import_model(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let model = param(params, "model")?.to_string();
+    Ok(service_json(ai_service(runtime)?.import_model(&model)?))
+}
+
+fn account_create(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let account_data = match params.get("account_data") {
+        None | Some(Value::Null) => "{}".to_string(),
+        Some(Value::String(data)) => data.clone(),
+        Some(data) => data.to_string(),
+    };
+    let account = runtime.account_service().create_account(param_str(params, "account_id")?, &account_data)?;
+    Ok(service_json(account))
+}
+
+fn account_info(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    Ok(service_json(runtime.account_service().get_account_info(param_str(params, "account_id")?)?))
+}
+
+fn account_sign_transaction(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let signed = runtime.account_service().sign_transaction(
+        param_str(params, "account_id")?,
+        param_str(params, "transaction_data")?,
+    )?;
+    Ok(service_json(signed))
+}
+
+fn computation_execute(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    let args = match params.get("args") {
+        None | Some(Value::Null) => "{}".to_string(),
+        Some(Value::String(args)) => args.clone(),
+        Some(args) => args.to_string(),
+    };
+    let cache_policy = if optional_bool(params, "deterministic")? { CachePolicy::Deterministic } else { CachePolicy::Bypass };
+    let code = param_str(params, "code")?;
+    let result = runtime.tokio_handle()
+        .block_on(runtime.computation_service().execute_javascript_with_policy(code, &args, cache_policy))?;
+    Ok(service_json(result))
+}
+
+fn oracle_latest(runtime: &EncaveRuntime, params: &Value) -> Result<Value> {
+    Ok(service_json(runtime.oracle_subscription_latest(param_str(params, "subscription_id")?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{runtime, test_config, token_sha256, write_signed_policy};
+
+    fn is_permission_denied(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::PermissionDenied(_)))
+    }
+
+    /// A runtime whose policy lets "monitor" call system methods and storage.list_keys
+    fn policy_runtime(dir: &std::path::Path) -> EncaveRuntime {
+        let (path, key) = write_signed_policy(dir, &json!({
+            "principals": {
+                "monitor": { "token_sha256": token_sha256("monitor-token"), "methods": ["system.*", "storage.list_keys"] },
+            }
+        }));
+        let mut config = test_config(dir);
+        config.auth_policy_path = path;
+        config.auth_policy_public_key = key;
+        runtime(config)
+    }
+
+    #[test]
+    fn policy_allows_and_denies_dispatched_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = policy_runtime(dir.path());
+
+        assert!(runtime.dispatch_as(Some("monitor-token"), "system.methods", Value::Null).is_ok());
+        assert!(runtime.dispatch_as(Some("monitor-token"), "storage.list_keys", json!({ "principal": "monitor" })).is_ok());
+
+        let denied = runtime.dispatch_as(Some("monitor-token"), "crypto.random_bytes", json!({ "length": 8 }));
+        assert!(is_permission_denied(&denied.unwrap_err()));
+        assert!(is_permission_denied(&runtime.dispatch_as(Some("other-token"), "system.methods", Value::Null).unwrap_err()));
+        assert!(is_permission_denied(&runtime.dispatch("system.methods", Value::Null).unwrap_err()));
+    }
+
+    #[test]
+    fn dedicated_exports_are_refused_under_a_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = policy_runtime(dir.path());
+        assert!(is_permission_denied(&runtime.check_direct_export().unwrap_err()));
+
+        let open = tempfile::tempdir().unwrap();
+        let runtime = crate::test_support::runtime(test_config(open.path()));
+        assert!(runtime.check_direct_export().is_ok());
+    }
+
+    #[test]
+    fn results_read_in_chunks_until_closed() {
+        let results = OpenResults::default();
+        let handle = results.open("abcdef".to_string()).unwrap();
+        let mut buffer = [0u8; 4];
+        assert_eq!(results.read(handle, 0, &mut buffer).unwrap(), 4);
+        assert_eq!(&buffer, b"abcd");
+        assert_eq!(results.read(handle, 4, &mut buffer).unwrap(), 2);
+        assert_eq!(results.read(handle, 6, &mut buffer).unwrap(), 0);
+        assert!(results.read(handle, 7, &mut buffer).is_err());
+
+        results.close(handle).unwrap();
+        assert!(results.read(handle, 0, &mut buffer).is_err());
+        assert!(results.close(handle).is_err());
+    }
+
+    #[test]
+    fn unread_results_expire_and_free_their_slots() {
+        let results = OpenResults::with_ttl(Duration::from_millis(20));
+        let handles: Vec<u64> = (0..MAX_OPEN_RESULTS).map(|_| results.open("result".to_string()).unwrap()).collect();
+        assert!(results.open("result".to_string()).is_err());
+
+        std::thread::sleep(Duration::from_millis(40));
+        let handle = results.open("fresh".to_string()).unwrap();
+        let mut buffer = [0u8; 8];
+        assert!(results.read(handles[0], 0, &mut buffer).is_err());
+        assert_eq!(results.read(handle, 0, &mut buffer).unwrap(), 5);
+    }
+
+    fn is_invalid_input(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::InvalidInput(_)))
+    }
+
+    #[test]
+    fn registry_lists_every_method_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = runtime(test_config(dir.path()));
+        let listed = runtime.dispatch("system.methods", Value::Null).unwrap();
+        let names: Vec<&str> = listed.as_array().unwrap().iter().map(|method| method["name"].as_str().unwrap()).collect();
+        assert_eq!(names, METHODS.iter().map(|method| method.name).collect::<Vec<_>>());
+        let unique: std::collections::HashSet<&str> = names.iter().copied().collect();
+        assert_eq!(unique.len(), names.len());
+    }
+
+    #[test]
+    fn crypto_calls_route_to_the_crypto_service() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = runtime(test_config(dir.path()));
+        let metadata = runtime.dispatch("crypto.generate_key", json!({
+            "key_id": "dispatch-signer", "key_type": "secp256r1", "usage": ["Sign", "Verify"],
+        })).unwrap();
+        assert_eq!(metadata["key_id"], "dispatch-signer");
+
+        let signed = runtime.dispatch("crypto.sign", json!({ "key_id": "dispatch-signer", "data": "c0ffee" })).unwrap();
+        let signature = signed["signature"].as_str().unwrap();
+        let verify = |data: &str| runtime.dispatch("crypto.verify", json!({
+            "key_id": "dispatch-signer", "data": data, "signature": signature,
+        })).unwrap()["valid"].clone();
+        assert_eq!(verify("c0ffee"), json!(true));
+        assert_eq!(verify("c0ffef"), json!(false));
+
+        let bytes = runtime.dispatch("crypto.random_bytes", json!({ "length": 16 })).unwrap();
+        assert_eq!(bytes["bytes"].as_str().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn storage_calls_route_to_the_storage_service() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = runtime(test_config(dir.path()));
+        let params = json!({ "key": "note", "data": "68656c6c6f", "encryption_key": "k", "principal": "alice" });
+        runtime.dispatch("storage.store", params.clone()).unwrap();
+        let retrieved = runtime.dispatch("storage.retrieve", params.clone()).unwrap();
+        assert_eq!(retrieved["data"], "68656c6c6f");
+        assert_eq!(runtime.storage_service().retrieve_data("note", "k", "alice").unwrap(), b"hello");
+
+        let mut as_enclave = params;
+        as_enclave["principal"] = json!(SYSTEM_PRINCIPAL);
+        assert!(is_permission_denied(&runtime.dispatch("storage.retrieve", as_enclave).unwrap_err()));
+    }
+
+    #[test]
+    fn malformed_calls_fail_with_the_ffi_error_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = runtime(test_config(dir.path()));
+
+        let unknown = runtime.dispatch("crypto.no_such_method", Value::Null).unwrap_err();
+        assert!(matches!(unknown.downcast_ref::<EnclaveError>(), Some(EnclaveError::NotFound(_))));
+        assert!(is_invalid_input(&runtime.dispatch("crypto.random_bytes", json!([16])).unwrap_err()));
+        assert!(is_invalid_input(&runtime.dispatch("crypto.random_bytes", Value::Null).unwrap_err()));
+        assert!(is_invalid_input(&runtime.dispatch("crypto.sign", json!({ "key_id": "k", "data": "not hex" })).unwrap_err()));
+
+        let outcome = runtime.dispatch("crypto.no_such_method", Value::Null);
+        let envelope = response(json!(7), &outcome);
+        assert_eq!(envelope["id"], 7);
+        assert_eq!(envelope["error"]["code"], json!(error_code(outcome.as_ref().unwrap_err())));
+        assert!(envelope.get("result").is_none());
+        assert_eq!(response(json!(8), &Ok(json!(1))), json!({ "id": 8, "result": 1 }));
+    }
+}
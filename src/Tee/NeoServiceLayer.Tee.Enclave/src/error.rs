@@ -0,0 +1,81 @@
+use std::os::raw::c_int;
+
+use crate::computation::WorkerPoolError;
+use crate::storage::StorageError;
+
+/// Catch-all FFI code for failures that are not classified below
+pub const ENCLAVE_ERROR_UNKNOWN: c_int = -1;
+pub const ENCLAVE_ERROR_NOT_FOUND: c_int = -3001;
+pub const ENCLAVE_ERROR_ALREADY_EXISTS: c_int = -3002;
+pub const ENCLAVE_ERROR_INVALID_INPUT: c_int = -3003;
+pub const ENCLAVE_ERROR_PERMISSION_DENIED: c_int = -3004;
+pub const ENCLAVE_ERROR_RESOURCE_LIMIT: c_int = -3005;
+pub const ENCLAVE_ERROR_CRYPTO: c_int = -3006;
+pub const ENCLAVE_ERROR_IO: c_int = -3007;
+
+/// Failure categories the host can react to programmatically
+///
+/// Services return these wrapped in `anyhow::Error`; the FFI layer finds them in the
+/// error chain with [`error_code`] and reports the matching stable code.
+#[derive(Debug, thiserror::Error)]
+pub enum EnclaveError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    AlreadyExists(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// A size, count, time or quota limit was hit
+    #[error("{0}")]
+    ResourceLimit(String),
+    /// Key material, signature or ciphertext was rejected by a cryptographic operation
+    #[error("{0}")]
+    Crypto(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl EnclaveError {
+    /// Stable FFI code for this error
+    pub fn code(&self) -> c_int {
+        match self {
+            EnclaveError::NotFound(_) => ENCLAVE_ERROR_NOT_FOUND,
+            EnclaveError::AlreadyExists(_) => ENCLAVE_ERROR_ALREADY_EXISTS,
+            EnclaveError::InvalidInput(_) => ENCLAVE_ERROR_INVALID_INPUT,
+            EnclaveError::PermissionDenied(_) => ENCLAVE_ERROR_PERMISSION_DENIED,
+            EnclaveError::ResourceLimit(_) => ENCLAVE_ERROR_RESOURCE_LIMIT,
+            EnclaveError::Crypto(_) => ENCLAVE_ERROR_CRYPTO,
+            EnclaveError::Io(_) => ENCLAVE_ERROR_IO,
+        }
+    }
+}
+
+/// FFI code for a service error, taken from the outermost classified cause
+///
+/// The module-specific storage and worker pool errors map onto the matching categories,
+/// and bare I/O errors propagated with `?` report `ENCLAVE_ERROR_IO`.
+pub fn error_code(error: &anyhow::Error) -> c_int {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<EnclaveError>() {
+            return e.code();
+        }
+        if let Some(e) = cause.downcast_ref::<StorageError>() {
+            return match e {
+                StorageError::AccessDenied { .. } => ENCLAVE_ERROR_PERMISSION_DENIED,
+                StorageError::QuotaExceeded { .. } => ENCLAVE_ERROR_RESOURCE_LIMIT,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<WorkerPoolError>() {
+            return match e {
+                WorkerPoolError::QueueFull { .. } => ENCLAVE_ERROR_RESOURCE_LIMIT,
+                WorkerPoolError::Closed => ENCLAVE_ERROR_UNKNOWN,
+            };
+        }
+        if cause.downcast_ref::<std::io::Error>().is_some() {
+            return ENCLAVE_ERROR_IO;
+        }
+    }
+    ENCLAVE_ERROR_UNKNOWN
+}
@@ -1,6 +1,11 @@
-// Stub account FFI functions for future implementation
-use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int};
+// Account FFI functions; creation, signing and guardian management are still stubs
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_uint};
+
+// SGX error codes
+const SGX_SUCCESS: c_uint = 0x00000000;
+const SGX_ERROR_INVALID_PARAMETER: c_uint = 0x00000002;
+const SGX_ERROR_OUT_OF_MEMORY: c_uint = 0x00000003;
 
 /// Create abstract account (stub)
 #[no_mangle]
@@ -11,6 +16,9 @@ pub extern "C" fn occlum_account_create(
     _result_size: usize,
     _actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     0 // Success stub
 }
 
@@ -23,9 +31,72 @@ pub extern "C" fn occlum_account_sign_transaction(
     _result_size: usize,
     _actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     0 // Success stub
 }
 
+/// Check a Base58Check Neo address against the enclave's configured network
+///
+/// Writes 1 to `is_valid` for a valid address and 0 otherwise; a malformed address is
+/// not an error.
+#[no_mangle]
+pub extern "C" fn occlum_validate_neo_address(
+    address: *const c_char,
+    is_valid: *mut u8,
+) -> c_int {
+    if address.is_null() || is_valid.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let address_str = match unsafe { CStr::from_ptr(address) }.to_str() {
+        Ok(s) => s,
+        Err(_) => {
+            unsafe { *is_valid = 0; }
+            return SGX_SUCCESS as c_int;
+        }
+    };
+    
+    crate::with_runtime(|runtime| {
+        let valid = runtime.account_service().validate_neo_address(address_str)?;
+        unsafe { *is_valid = valid as u8; }
+        Ok(())
+    })
+}
+
+/// Derive the Neo address of a secp256r1 public key (33, 64 or 65 bytes)
+#[no_mangle]
+pub extern "C" fn occlum_address_from_public_key(
+    public_key: *const u8,
+    public_key_len: usize,
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if public_key.is_null() || public_key_len == 0 || result.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let public_key_hex = hex::encode(unsafe { std::slice::from_raw_parts(public_key, public_key_len) });
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        write_status = match runtime.account_service().address_from_public_key(&public_key_hex) {
+            Ok(address) => write_string_result(&address, result, result_size, actual_size),
+            // An invalid key is the caller's input error, not a runtime failure
+            Err(_) => SGX_ERROR_INVALID_PARAMETER as c_int,
+        };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
 /// Add guardian (stub)
 #[no_mangle]
 pub extern "C" fn occlum_account_add_guardian(
@@ -35,5 +106,17 @@ pub extern "C" fn occlum_account_add_guardian(
     _result_size: usize,
     _actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     0 // Success stub
-} 
\ No newline at end of file
+}
+
+/// Write a string result following the `write_result_to_buffer` contract, reporting a
+/// buffer that is too small as `SGX_ERROR_OUT_OF_MEMORY`
+fn write_string_result(value: &str, result: *mut c_char, result_size: usize, actual_size: *mut usize) -> c_int {
+    match unsafe { crate::write_result_to_buffer(value, result, result_size, actual_size) } {
+        0 => 0,
+        _ => SGX_ERROR_OUT_OF_MEMORY as c_int,
+    }
+}
@@ -14,6 +14,9 @@ pub extern "C" fn occlum_ai_train_model(
     _result_size: usize,
     _actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     0 // Success stub
 }
 
@@ -31,4 +34,84 @@ pub extern "C" fn occlum_ai_predict(
     _actual_metadata_size: *mut usize,
 ) -> c_int {
     0 // Success stub
-} 
\ No newline at end of file
+} 
+
+/// Start training in the background and return a job handle for polling
+#[no_mangle]
+pub extern "C" fn occlum_ai_train_async(
+    model_id: *const c_char,
+    model_type: *const c_char,
+    training_data: *const f64,
+    data_size: usize,
+    parameters: *const c_char,
+    job_handle: *mut c_char,
+    handle_size: usize,
+    actual_handle_size: *mut usize,
+) -> c_int {
+    if model_id.is_null() || model_type.is_null() || training_data.is_null() || data_size == 0 {
+        return -1;
+    }
+    
+    let mut write_status = 0;
+    let status = crate::with_runtime(|runtime| {
+        let ai = runtime.ai_service()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("AI service is disabled"))?;
+        
+        let (model_id, model_type, parameters) = unsafe {
+            (
+                crate::c_str_to_string(model_id)?,
+                crate::c_str_to_string(model_type)?,
+                crate::c_str_to_string(parameters)?,
+            )
+        };
+        // Copy the data so the background job does not borrow host memory
+        let data = unsafe { std::slice::from_raw_parts(training_data, data_size) }.to_vec();
+        
+        let job_id = ai.queue_training_job(&model_id)?;
+        let background_job_id = job_id.clone();
+        runtime.tokio_handle().spawn_blocking(move || {
+            if let Err(e) = ai.run_training_job(&background_job_id, &model_type, &data, &parameters) {
+                log::error!("Training job {} failed: {}", background_job_id, e);
+            }
+        });
+        
+        write_status = unsafe { crate::write_result_to_buffer(&job_id, job_handle, handle_size, actual_handle_size) };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Get JSON progress for a job started with `occlum_ai_train_async`
+#[no_mangle]
+pub extern "C" fn occlum_ai_train_status(
+    job_handle: *const c_char,
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if job_handle.is_null() {
+        return -1;
+    }
+    
+    let mut write_status = 0;
+    let status = crate::with_runtime(|runtime| {
+        let ai = runtime.ai_service().ok_or_else(|| anyhow::anyhow!("AI service is disabled"))?;
+        let job_id = unsafe { crate::c_str_to_string(job_handle)? };
+        
+        let status_json = ai.get_training_status(&job_id)?;
+        write_status = unsafe { crate::write_result_to_buffer(&status_json, result, result_size, actual_size) };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
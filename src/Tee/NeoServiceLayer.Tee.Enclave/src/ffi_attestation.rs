@@ -0,0 +1,50 @@
+// Attestation FFI functions
+use std::os::raw::{c_int, c_uint};
+
+// SGX error codes
+const SGX_SUCCESS: c_uint = 0x00000000;
+const SGX_ERROR_INVALID_PARAMETER: c_uint = 0x00000002;
+const SGX_ERROR_OUT_OF_MEMORY: c_uint = 0x00000003;
+
+/// Generate an attestation quote committing to `report_data` (at most 64 bytes, zero-padded)
+///
+/// The quote is written to `quote` as raw bytes. If `quote_size` is too small,
+/// `actual_size` receives the required size and `SGX_ERROR_OUT_OF_MEMORY` is returned.
+#[no_mangle]
+pub extern "C" fn occlum_get_attestation(
+    report_data: *const u8,
+    report_data_len: usize,
+    quote: *mut u8,
+    quote_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if (report_data.is_null() && report_data_len != 0) || quote.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let report_data = if report_data_len == 0 {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(report_data, report_data_len) }
+    };
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let generated = runtime.attestation_service().generate_quote(report_data)?;
+        unsafe {
+            *actual_size = generated.len();
+            if quote_size < generated.len() {
+                write_status = SGX_ERROR_OUT_OF_MEMORY as c_int;
+            } else {
+                std::ptr::copy_nonoverlapping(generated.as_ptr(), quote, generated.len());
+            }
+        }
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
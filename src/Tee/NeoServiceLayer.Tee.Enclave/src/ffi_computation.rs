@@ -1,6 +1,5 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
-use std::ptr;
 
 /// Execute JavaScript code
 #[no_mangle]
@@ -13,18 +12,12 @@ pub extern "C" fn occlum_execute_js(
     result_size: usize,
     actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     // Stub implementation
     let response = r#"{"result":"js_executed","timestamp":1234567890}"#;
-    unsafe {
-        if !result.is_null() && result_size > response.len() {
-            ptr::copy_nonoverlapping(response.as_ptr(), result as *mut u8, response.len());
-            *result.add(response.len()) = 0; // Null terminator
-        }
-        if !actual_result_size.is_null() {
-            *actual_result_size = response.len();
-        }
-    }
-    0 // Success
+    unsafe { crate::write_result_to_buffer(response, result, result_size, actual_result_size) }
 }
 
 /// Execute computation
@@ -37,16 +30,10 @@ pub extern "C" fn occlum_compute_execute(
     result_size: usize,
     actual_result_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     // Stub implementation
     let response = r#"{"result":"computation_completed","timestamp":1234567890}"#;
-    unsafe {
-        if !result.is_null() && result_size > response.len() {
-            ptr::copy_nonoverlapping(response.as_ptr(), result as *mut u8, response.len());
-            *result.add(response.len()) = 0; // Null terminator
-        }
-        if !actual_result_size.is_null() {
-            *actual_result_size = response.len();
-        }
-    }
-    0 // Success
+    unsafe { crate::write_result_to_buffer(response, result, result_size, actual_result_size) }
 } 
\ No newline at end of file
@@ -115,29 +115,40 @@ pub extern "C" fn occlum_generate_random(
     max: c_int,
     result: *mut c_int,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if result.is_null() || min >= max {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
     
-    unsafe {
-        // Generate 4 bytes of secure random data using SGX
-        let mut random_bytes = [0u8; 4];
-        let sgx_result = sgx_read_rand(random_bytes.as_mut_ptr(), 4);
-        
-        if sgx_result != SGX_SUCCESS {
-            // Fallback to entropy if random fails
-            let entropy_result = sgx_get_entropy(random_bytes.as_mut_ptr(), 4);
-            if entropy_result != SGX_SUCCESS {
-                return SGX_ERROR_UNEXPECTED as c_int;
+    // Widen first: the span of the full c_int range does not fit in a c_int
+    let range = (max as i64 - min as i64) as u64;
+    
+    // Draws below 2^64 mod range would make low values more likely; redraw them
+    let threshold = range.wrapping_neg() % range;
+    let value = loop {
+        let mut random_bytes = [0u8; 8];
+        unsafe {
+            let sgx_result = sgx_read_rand(random_bytes.as_mut_ptr(), random_bytes.len());
+            
+            if sgx_result != SGX_SUCCESS {
+                // Fallback to entropy if random fails
+                let entropy_result = sgx_get_entropy(random_bytes.as_mut_ptr(), random_bytes.len());
+                if entropy_result != SGX_SUCCESS {
+                    return SGX_ERROR_UNEXPECTED as c_int;
+                }
             }
         }
         
-        // Convert bytes to u32 and scale to range
-        let random_u32 = u32::from_le_bytes(random_bytes);
-        let range = (max - min) as u32;
-        let scaled_value = (random_u32 % range) as c_int + min;
-        
-        *result = scaled_value;
+        let random_u64 = u64::from_le_bytes(random_bytes);
+        if random_u64 >= threshold {
+            break random_u64 % range;
+        }
+    };
+    
+    unsafe {
+        *result = (min as i64 + value as i64) as c_int;
     }
     
     SGX_SUCCESS as c_int
@@ -149,6 +160,9 @@ pub extern "C" fn occlum_generate_random_bytes(
     buffer: *mut u8,
     length: usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if buffer.is_null() || length == 0 {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -183,6 +197,9 @@ pub extern "C" fn occlum_generate_key_material(
     result_size: usize,
     actual_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key_type.is_null() || result.is_null() || actual_size.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -228,6 +245,9 @@ pub extern "C" fn occlum_derive_key(
     derived_key: *mut u8,
     derived_key_len: usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if master_key.is_null() || derived_key.is_null() || 
        master_key_len == 0 || derived_key_len == 0 {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
@@ -366,6 +386,9 @@ pub extern "C" fn occlum_generate_ecdsa_keypair(
     private_key: *mut u8,
     public_key: *mut u8,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if private_key.is_null() || public_key.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -404,6 +427,9 @@ pub extern "C" fn occlum_ecdsa_sign(
     private_key: *const u8,
     signature: *mut u8,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if data.is_null() || private_key.is_null() || signature.is_null() || data_len == 0 {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -442,6 +468,9 @@ pub extern "C" fn occlum_ecdsa_verify(
     signature: *const u8,
     is_valid: *mut u8,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if data.is_null() || public_key.is_null() || signature.is_null() || is_valid.is_null() || data_len == 0 {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -494,6 +523,9 @@ pub extern "C" fn occlum_aes_gcm_encrypt(
     ciphertext: *mut u8,
     mac: *mut u8,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key.is_null() || plaintext.is_null() || iv.is_null() || ciphertext.is_null() || mac.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -541,6 +573,9 @@ pub extern "C" fn occlum_aes_gcm_decrypt(
     mac: *const u8,
     plaintext: *mut u8,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key.is_null() || ciphertext.is_null() || iv.is_null() || mac.is_null() || plaintext.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -571,6 +606,11 @@ pub extern "C" fn occlum_aes_gcm_decrypt(
 }
 
 /// Generate Neo-compatible address from public key using SGX cryptographic functions
+///
+/// `public_key` is the 64-byte P-256 key written by `occlum_generate_ecdsa_keypair`
+/// (SGX layout: little-endian x then y). Neo N3 accounts are secp256r1, so the key is
+/// checked to be on that curve and the address is the hash of its CheckSig verification
+/// script, matching what `AccountService` derives for the same key.
 #[no_mangle]
 pub extern "C" fn occlum_generate_neo_address(
     public_key: *const u8,
@@ -582,9 +622,21 @@ pub extern "C" fn occlum_generate_neo_address(
     }
     
     unsafe {
-        // Neo address generation: SHA256(public_key) -> RIPEMD160 -> Base58Check
+        // SGX stores each coordinate little-endian; SEC1 wants big-endian
+        let mut coordinates = [0u8; 64];
+        std::ptr::copy_nonoverlapping(public_key, coordinates.as_mut_ptr(), 64);
+        coordinates[..32].reverse();
+        coordinates[32..].reverse();
+        
+        let compressed_key = match crate::crypto::compress_public_key(&crate::crypto::CryptoAlgorithm::Secp256r1, &coordinates) {
+            Ok(key) => key,
+            Err(_) => return SGX_ERROR_INVALID_PARAMETER as c_int,
+        };
+        let verification_script = crate::account::single_sig_verification_script(&compressed_key);
+        
+        // Neo address generation: SHA256(script) -> RIPEMD160 -> Base58Check
         let mut sha256_hash = [0u8; 32];
-        let sha_result = sgx_sha256_msg(public_key, 65, &mut sha256_hash); // 65 bytes for uncompressed public key
+        let sha_result = sgx_sha256_msg(verification_script.as_ptr(), verification_script.len(), &mut sha256_hash);
         if sha_result != SGX_SUCCESS {
             return sha_result as c_int;
         }
@@ -595,9 +647,9 @@ pub extern "C" fn occlum_generate_neo_address(
             return ripemd_result as c_int;
         }
         
-        // Add version byte (0x17 for Neo mainnet)
+        // Add the Neo N3 version byte
         let mut versioned_hash = [0u8; 21];
-        versioned_hash[0] = 0x17;
+        versioned_hash[0] = crate::account::NEO_MAINNET_ADDRESS_VERSION;
         std::ptr::copy_nonoverlapping(ripemd_hash.as_ptr(), versioned_hash.as_mut_ptr().add(1), 20);
         
         // Calculate checksum (first 4 bytes of SHA256(SHA256(versioned_hash)))
@@ -629,4 +681,92 @@ pub extern "C" fn occlum_generate_neo_address(
     }
     
     SGX_SUCCESS as c_int
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+    #[test]
+    fn generates_n3_address_for_known_key() {
+        // Key pair of the Neo wallet test vectors
+        let public_key = p256::PublicKey::from_sec1_bytes(
+            &hex::decode("035a928f201639204e06b4368b1a93365462a8ebbff0b8818151b74faab3a2b61a").unwrap(),
+        ).unwrap();
+        let point = public_key.to_encoded_point(false);
+        let mut sgx_key = [0u8; 64];
+        sgx_key[..32].copy_from_slice(point.x().unwrap());
+        sgx_key[32..].copy_from_slice(point.y().unwrap());
+        sgx_key[..32].reverse();
+        sgx_key[32..].reverse();
+
+        let mut address = [0u8; 25];
+        let mut address_len = address.len();
+        let result = occlum_generate_neo_address(sgx_key.as_ptr(), address.as_mut_ptr(), &mut address_len);
+
+        assert_eq!(result, SGX_SUCCESS as c_int);
+        assert_eq!(address_len, 25);
+        assert_eq!(crate::crypto::base58::encode(&address), "NMACuhqEaNAeDSQVipcUPYiJ9TVgVyUxGV");
+    }
+
+    #[tokio::test]
+    async fn ecdsa_signatures_cross_verify_with_the_crypto_service() {
+        // RFC 6979 A.2.5 key; the SDK stand-in signs deterministically, so the FFI
+        // output is also checked against the RFC signature for "sample"
+        let private_key = hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721").unwrap();
+        let expected = hex::decode(
+            "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716\
+             f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8",
+        ).unwrap();
+        let message = b"sample";
+
+        let dir = tempfile::tempdir().unwrap();
+        let (_storage, _audit, crypto) = crate::test_support::core_services(&crate::test_support::test_config(dir.path())).await;
+        crypto.import_key(
+            "ffi-kat", crate::crypto::CryptoAlgorithm::Secp256r1, &private_key,
+            vec!["Sign".to_string(), "Verify".to_string()], false, "",
+        ).unwrap();
+        let public_key = crypto.get_public_key("ffi-kat", true).unwrap();
+
+        // SGX keeps the scalar, the coordinates and both signature halves little-endian
+        let sgx_private_key: Vec<u8> = private_key.iter().rev().copied().collect();
+        let point = p256::PublicKey::from_sec1_bytes(&public_key).unwrap().to_encoded_point(false);
+        let mut sgx_public_key = [0u8; 64];
+        sgx_public_key[..32].copy_from_slice(point.x().unwrap());
+        sgx_public_key[32..].copy_from_slice(point.y().unwrap());
+        sgx_public_key[..32].reverse();
+        sgx_public_key[32..].reverse();
+        let swap = |signature: &[u8]| {
+            let mut swapped = signature.to_vec();
+            swapped[..32].reverse();
+            swapped[32..].reverse();
+            swapped
+        };
+
+        let mut sgx_signature = [0u8; 64];
+        let result = occlum_ecdsa_sign(message.as_ptr(), message.len(), sgx_private_key.as_ptr(), sgx_signature.as_mut_ptr());
+        assert_eq!(result, SGX_SUCCESS as c_int);
+        let ffi_signature = swap(&sgx_signature);
+        assert_eq!(ffi_signature, expected);
+        assert!(crypto.verify_with_public_key(
+            crate::crypto::CryptoAlgorithm::Secp256r1, &public_key, message, &ffi_signature,
+        ).unwrap());
+
+        let service_signature = crypto.sign_data("ffi-kat", message).unwrap();
+        assert_eq!(service_signature, expected);
+        let ffi_verify = |signature: &[u8]| {
+            let mut is_valid = u8::MAX;
+            let result = occlum_ecdsa_verify(
+                message.as_ptr(), message.len(), sgx_public_key.as_ptr(), signature.as_ptr(), &mut is_valid,
+            );
+            assert_eq!(result, SGX_SUCCESS as c_int);
+            is_valid
+        };
+        assert_eq!(ffi_verify(&swap(&service_signature)), 0);
+
+        let mut tampered = service_signature.clone();
+        tampered[63] ^= 1;
+        assert_ne!(ffi_verify(&swap(&tampered)), 0);
+    }
+}
@@ -1,9 +1,10 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_uint};
-use std::ptr;
 use std::time::{SystemTime, Duration};
 use std::collections::HashMap;
 
+use crate::oracle::FetchPoll;
+
 // Import SGX functions for secure operations
 extern "C" {
     fn sgx_read_rand(rand: *mut u8, length: usize) -> c_uint;
@@ -20,6 +21,10 @@ const ORACLE_ERROR_INVALID_URL: c_int = -2002;
 const ORACLE_ERROR_TIMEOUT: c_int = -2003;
 const ORACLE_ERROR_INVALID_RESPONSE: c_int = -2004;
 const ORACLE_ERROR_SECURITY_VIOLATION: c_int = -2005;
+const ORACLE_ERROR_FETCH_FAILED: c_int = -2006;
+
+/// Returned by `occlum_oracle_fetch_result` while the fetch is still in flight
+pub const ORACLE_FETCH_PENDING: c_int = 1;
 
 /// Fetch oracle data from external sources with security validation
 #[no_mangle]
@@ -32,6 +37,9 @@ pub extern "C" fn occlum_oracle_fetch_data(
     result_size: usize,
     actual_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if url.is_null() || result.is_null() || actual_size.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -85,18 +93,8 @@ pub extern "C" fn occlum_oracle_fetch_data(
         
         let final_response = format_oracle_response(&processed_data, output_fmt);
         
-        // Copy result
-        if result_size > final_response.len() {
-            ptr::copy_nonoverlapping(final_response.as_ptr(), result as *mut u8, final_response.len());
-            *result.add(final_response.len()) = 0; // Null terminator
-            *actual_size = final_response.len();
-        } else {
-            *actual_size = final_response.len();
-            return SGX_ERROR_OUT_OF_MEMORY as c_int;
-        }
+        copy_result(&final_response, result, result_size, actual_size)
     }
-    
-    SGX_SUCCESS as c_int
 }
 
 /// Validate multiple oracle sources and aggregate results
@@ -109,6 +107,9 @@ pub extern "C" fn occlum_oracle_aggregate_sources(
     result_size: usize,
     actual_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if urls.is_null() || url_count == 0 || result.is_null() || actual_size.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -156,22 +157,234 @@ pub extern "C" fn occlum_oracle_aggregate_sources(
         
         let aggregated_response = aggregate_oracle_data(&oracle_results, aggregation);
         
-        // Copy result
-        if result_size > aggregated_response.len() {
-            ptr::copy_nonoverlapping(aggregated_response.as_ptr(), result as *mut u8, aggregated_response.len());
-            *result.add(aggregated_response.len()) = 0;
-            *actual_size = aggregated_response.len();
+        copy_result(&aggregated_response, result, result_size, actual_size)
+    }
+}
+
+/// Start an oracle fetch in the background and return immediately
+///
+/// C signature:
+/// `int occlum_oracle_fetch_async(const char* url, const char* headers,
+///                                const char* processing_script, uint64_t* handle);`
+///
+/// `headers` ("Name: value" lines) and `processing_script` may be null. On success
+/// `*handle` identifies the fetch for `occlum_oracle_fetch_result`.
+#[no_mangle]
+pub extern "C" fn occlum_oracle_fetch_async(
+    url: *const c_char,
+    headers: *const c_char,
+    processing_script: *const c_char,
+    handle: *mut u64,
+) -> c_int {
+    if url.is_null() || handle.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let (url_str, parsed_headers, script) = unsafe {
+        let url_str = match CStr::from_ptr(url).to_str() {
+            Ok(s) => s.to_string(),
+            Err(_) => return SGX_ERROR_INVALID_PARAMETER as c_int,
+        };
+        let parsed_headers = if !headers.is_null() {
+            match CStr::from_ptr(headers).to_str() {
+                Ok(h) => Some(parse_headers(h)),
+                Err(_) => return SGX_ERROR_INVALID_PARAMETER as c_int,
+            }
+        } else {
+            None
+        };
+        let script = if !processing_script.is_null() {
+            match CStr::from_ptr(processing_script).to_str() {
+                Ok(s) => Some(s.to_string()),
+                Err(_) => return SGX_ERROR_INVALID_PARAMETER as c_int,
+            }
         } else {
-            *actual_size = aggregated_response.len();
-            return SGX_ERROR_OUT_OF_MEMORY as c_int;
+            None
+        };
+        (url_str, parsed_headers, script)
+    };
+    
+    crate::with_runtime(|runtime| {
+        let fetch_handle = runtime.spawn_oracle_fetch(url_str, parsed_headers, script)?;
+        unsafe { *handle = fetch_handle; }
+        Ok(())
+    })
+}
+
+/// Poll a fetch started with `occlum_oracle_fetch_async`
+///
+/// C signature:
+/// `int occlum_oracle_fetch_result(uint64_t handle, char* result, size_t result_size,
+///                                 size_t* actual_size);`
+///
+/// Returns `ORACLE_FETCH_PENDING` while in flight. Once finished it returns `SGX_SUCCESS`
+/// with the response body, or `ORACLE_ERROR_FETCH_FAILED` with the error message, and the
+/// handle is released. If the buffer is too small, `*actual_size` is set to the required
+/// length (excluding the terminator), `SGX_ERROR_OUT_OF_MEMORY` is returned and the handle
+/// stays valid so the call can be retried with a larger buffer.
+#[no_mangle]
+pub extern "C" fn occlum_oracle_fetch_result(
+    handle: u64,
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if result.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let mut poll_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let (data, done_status) = match runtime.poll_oracle_fetch(handle) {
+            Ok(FetchPoll::Pending) => {
+                poll_status = ORACLE_FETCH_PENDING;
+                return Ok(());
+            }
+            Ok(FetchPoll::Ready(body)) => (body, SGX_SUCCESS as c_int),
+            Ok(FetchPoll::Failed(message)) => (message, ORACLE_ERROR_FETCH_FAILED),
+            Err(_) => {
+                poll_status = SGX_ERROR_INVALID_PARAMETER as c_int;
+                return Ok(());
+            }
+        };
+        
+        poll_status = copy_result(&data, result, result_size, actual_size);
+        if poll_status == SGX_SUCCESS as c_int {
+            runtime.release_oracle_fetch(handle)?;
+            poll_status = done_status;
         }
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        poll_status
+    }
+}
+
+/// Stop a fetch started with `occlum_oracle_fetch_async` and release its handle
+///
+/// C signature:
+/// `int occlum_oracle_fetch_cancel(uint64_t handle);`
+///
+/// Returns `SGX_ERROR_INVALID_PARAMETER` for unknown or already released handles.
+/// Fetches that are never collected are cancelled automatically after ten minutes.
+#[no_mangle]
+pub extern "C" fn occlum_oracle_fetch_cancel(handle: u64) -> c_int {
+    let mut cancel_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        if runtime.cancel_oracle_fetch(handle).is_err() {
+            cancel_status = SGX_ERROR_INVALID_PARAMETER as c_int;
+        }
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        cancel_status
+    }
+}
+
+/// Refresh a URL in the background every `interval_seconds`
+///
+/// C signature:
+/// `int occlum_oracle_subscribe(const char* url, const char* processing_script,
+///                              uint64_t interval_seconds, char* subscription_id,
+///                              size_t subscription_id_size, size_t* actual_size);`
+///
+/// `processing_script` may be null. The id is written NUL-terminated; 32 bytes always
+/// suffice. Read values with `occlum_oracle_get_latest`.
+#[no_mangle]
+pub extern "C" fn occlum_oracle_subscribe(
+    url: *const c_char,
+    processing_script: *const c_char,
+    interval_seconds: u64,
+    subscription_id: *mut c_char,
+    subscription_id_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if url.is_null() || subscription_id.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
     
-    SGX_SUCCESS as c_int
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let url = unsafe { crate::c_str_to_string(url)? };
+        let script = if processing_script.is_null() {
+            None
+        } else {
+            Some(unsafe { crate::c_str_to_string(processing_script)? })
+        };
+        
+        let id = runtime.subscribe_oracle(&url, Duration::from_secs(interval_seconds), script.as_deref())?;
+        write_status = copy_result(&id, subscription_id, subscription_id_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Write a subscription's latest value and staleness as JSON
+///
+/// C signature:
+/// `int occlum_oracle_get_latest(const char* subscription_id, char* result, size_t result_size,
+///                               size_t* actual_size);`
+#[no_mangle]
+pub extern "C" fn occlum_oracle_get_latest(
+    subscription_id: *const c_char,
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if subscription_id.is_null() || result.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let subscription_id = unsafe { crate::c_str_to_string(subscription_id)? };
+        let latest = runtime.oracle_subscription_latest(&subscription_id)?;
+        write_status = copy_result(&latest, result, result_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Stop a subscription started with `occlum_oracle_subscribe`
+#[no_mangle]
+pub extern "C" fn occlum_oracle_unsubscribe(subscription_id: *const c_char) -> c_int {
+    if subscription_id.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    crate::with_runtime(|runtime| {
+        let subscription_id = unsafe { crate::c_str_to_string(subscription_id)? };
+        runtime.unsubscribe_oracle(&subscription_id)
+    })
 }
 
 // Helper functions for production oracle functionality
 
+/// Write a string result following the `write_result_to_buffer` contract, reporting a
+/// buffer that is too small as `SGX_ERROR_OUT_OF_MEMORY`
+fn copy_result(data: &str, result: *mut c_char, result_size: usize, actual_size: *mut usize) -> c_int {
+    match unsafe { crate::write_result_to_buffer(data, result, result_size, actual_size) } {
+        0 => SGX_SUCCESS as c_int,
+        _ => SGX_ERROR_OUT_OF_MEMORY as c_int,
+    }
+}
+
 fn validate_oracle_url(url: &str) -> Result<(), c_int> {
     // Security validation
     if url.len() > 2048 {
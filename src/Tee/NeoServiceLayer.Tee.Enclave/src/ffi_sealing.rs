@@ -0,0 +1,126 @@
+// Sealing FFI functions
+use std::os::raw::{c_int, c_uint};
+
+use crate::sealing::SealDomain;
+
+// SGX error codes
+const SGX_SUCCESS: c_uint = 0x00000000;
+const SGX_ERROR_INVALID_PARAMETER: c_uint = 0x00000002;
+const SGX_ERROR_OUT_OF_MEMORY: c_uint = 0x00000003;
+
+/// Seal `data` to the enclave identity so it can be kept on untrusted storage
+///
+/// If `sealed_size` is too small, `actual_size` receives the required size and
+/// `SGX_ERROR_OUT_OF_MEMORY` is returned.
+#[no_mangle]
+pub extern "C" fn occlum_seal_data(
+    data: *const u8,
+    data_size: usize,
+    sealed: *mut u8,
+    sealed_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if data.is_null() || data_size == 0 || sealed.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let data = unsafe { std::slice::from_raw_parts(data, data_size) };
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let blob = runtime.sealing_service().seal_data(SealDomain::External, data)?;
+        write_status = write_bytes_result(&blob, sealed, sealed_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Recover data sealed with `occlum_seal_data` by this enclave
+///
+/// Blobs the enclave sealed for itself, such as the storage master key, are refused with
+/// `ENCLAVE_ERROR_PERMISSION_DENIED`.
+///
+/// If `data_size` is too small, `actual_size` receives the required size and
+/// `SGX_ERROR_OUT_OF_MEMORY` is returned.
+#[no_mangle]
+pub extern "C" fn occlum_unseal_data(
+    sealed: *const u8,
+    sealed_size: usize,
+    data: *mut u8,
+    data_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if sealed.is_null() || sealed_size == 0 || data.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let sealed = unsafe { std::slice::from_raw_parts(sealed, sealed_size) };
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let plaintext = zeroize::Zeroizing::new(runtime.sealing_service().unseal_data(SealDomain::External, sealed)?);
+        write_status = write_bytes_result(&plaintext, data, data_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Copy a binary result, reporting the required size when the buffer is too small
+fn write_bytes_result(bytes: &[u8], buffer: *mut u8, buffer_size: usize, actual_size: *mut usize) -> c_int {
+    unsafe {
+        *actual_size = bytes.len();
+        if buffer_size < bytes.len() {
+            return SGX_ERROR_OUT_OF_MEMORY as c_int;
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    }
+    SGX_SUCCESS as c_int
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    use crate::test_support::install_ffi_runtime;
+
+    fn unseal(blob: &[u8]) -> (c_int, Vec<u8>) {
+        let mut data = vec![0u8; blob.len()];
+        let mut actual_size = 0usize;
+        let status = occlum_unseal_data(blob.as_ptr(), blob.len(), data.as_mut_ptr(), data.len(), &mut actual_size);
+        data.truncate(actual_size);
+        (status, data)
+    }
+
+    #[test]
+    fn host_blobs_round_trip() {
+        install_ffi_runtime();
+        let mut sealed = vec![0u8; 256];
+        let mut sealed_size = 0usize;
+        let status = occlum_seal_data(b"host data".as_ptr(), 9, sealed.as_mut_ptr(), sealed.len(), &mut sealed_size);
+        assert_eq!(status, SGX_SUCCESS as c_int);
+        sealed.truncate(sealed_size);
+
+        assert_eq!(unseal(&sealed), (SGX_SUCCESS as c_int, b"host data".to_vec()));
+    }
+
+    #[test]
+    fn internal_blobs_cannot_be_unsealed() {
+        install_ffi_runtime();
+        let runtime = crate::current_runtime().unwrap();
+        let internal = runtime.lock().unwrap().sealing_service().seal_data(SealDomain::Internal, &[0x42; 32]).unwrap();
+
+        let (status, data) = unseal(&internal);
+        assert_eq!(status, ENCLAVE_ERROR_PERMISSION_DENIED);
+        assert!(data.iter().all(|&b| b == 0));
+    }
+}
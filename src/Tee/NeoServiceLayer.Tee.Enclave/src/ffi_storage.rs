@@ -56,6 +56,9 @@ pub extern "C" fn occlum_storage_store(
     result_size: usize,
     actual_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key.is_null() || data.is_null() || data_size == 0 || result.is_null() || actual_size.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -147,17 +150,8 @@ pub extern "C" fn occlum_storage_store(
             key_str, final_data.len(), compress != 0, !encryption_key.is_null(), timestamp
         );
         
-        if result_size > response.len() {
-            ptr::copy_nonoverlapping(response.as_ptr(), result as *mut u8, response.len());
-            *result.add(response.len()) = 0; // Null terminator
-            *actual_size = response.len();
-        } else {
-            *actual_size = response.len();
-            return SGX_ERROR_OUT_OF_MEMORY as c_int;
-        }
+        write_json_result(&response, result, result_size, actual_size)
     }
-    
-    SGX_SUCCESS as c_int
 }
 
 /// Retrieve data from secure storage with decryption and decompression
@@ -169,6 +163,9 @@ pub extern "C" fn occlum_storage_retrieve(
     result_size: usize,
     actual_size: *mut usize,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key.is_null() || result.is_null() || actual_size.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -234,6 +231,9 @@ pub extern "C" fn occlum_storage_retrieve(
 pub extern "C" fn occlum_storage_delete(
     key: *const c_char,
 ) -> c_int {
+    if crate::direct_export_denied() {
+        return crate::error::ENCLAVE_ERROR_PERMISSION_DENIED;
+    }
     if key.is_null() {
         return SGX_ERROR_INVALID_PARAMETER as c_int;
     }
@@ -256,6 +256,68 @@ pub extern "C" fn occlum_storage_delete(
     }
 }
 
+/// Get storage usage statistics as JSON
+#[no_mangle]
+pub extern "C" fn occlum_storage_stats(
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if result.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let stats = runtime.storage_service().get_usage_stats()?;
+        write_status = write_json_result(&stats, result, result_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Run storage optimization and return its report as JSON
+#[no_mangle]
+pub extern "C" fn occlum_storage_optimize(
+    result: *mut c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if result.is_null() || actual_size.is_null() {
+        return SGX_ERROR_INVALID_PARAMETER as c_int;
+    }
+    
+    let mut write_status = SGX_SUCCESS as c_int;
+    let status = crate::with_runtime(|runtime| {
+        let storage = runtime.storage_service().clone();
+        let report = runtime.tokio_handle().block_on(async move {
+            storage.optimize_storage().await
+        })?;
+        write_status = write_json_result(&report, result, result_size, actual_size);
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Write a JSON result following the `write_result_to_buffer` contract, reporting a
+/// buffer that is too small as `SGX_ERROR_OUT_OF_MEMORY`
+fn write_json_result(json: &str, result: *mut c_char, result_size: usize, actual_size: *mut usize) -> c_int {
+    match unsafe { crate::write_result_to_buffer(json, result, result_size, actual_size) } {
+        0 => SGX_SUCCESS as c_int,
+        _ => SGX_ERROR_OUT_OF_MEMORY as c_int,
+    }
+}
+
 // Helper functions for encryption, compression, and hashing
 
 fn hash_key(key: &[u8]) -> String {
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Health state of a single service or of the enclave as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+    Disabled,
+}
+
+/// Result of probing a single service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub service: String,
+    pub status: HealthStatus,
+    pub message: Option<String>,
+    pub details: serde_json::Value,
+}
+
+impl ServiceHealth {
+    pub fn healthy(service: &str, details: serde_json::Value) -> Self {
+        Self {
+            service: service.to_string(),
+            status: HealthStatus::Healthy,
+            message: None,
+            details,
+        }
+    }
+
+    pub fn degraded(service: &str, message: impl Into<String>, details: serde_json::Value) -> Self {
+        Self {
+            service: service.to_string(),
+            status: HealthStatus::Degraded,
+            message: Some(message.into()),
+            details,
+        }
+    }
+
+    pub fn unhealthy(service: &str, message: impl Into<String>) -> Self {
+        Self {
+            service: service.to_string(),
+            status: HealthStatus::Unhealthy,
+            message: Some(message.into()),
+            details: serde_json::Value::Null,
+        }
+    }
+
+    pub fn disabled(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            status: HealthStatus::Disabled,
+            message: Some("Disabled by configuration".to_string()),
+            details: serde_json::Value::Null,
+        }
+    }
+}
+
+/// Aggregated health of all enclave services
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub services: Vec<ServiceHealth>,
+    pub disabled_services: Vec<String>,
+    pub timestamp: u64,
+}
+
+impl HealthReport {
+    /// Build a report, deriving the overall status from the enabled services
+    pub fn from_services(services: Vec<ServiceHealth>) -> Self {
+        let status = if services.iter().any(|s| s.status == HealthStatus::Unhealthy) {
+            HealthStatus::Unhealthy
+        } else if services.iter().any(|s| s.status == HealthStatus::Degraded) {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+
+        let disabled_services = services.iter()
+            .filter(|s| s.status == HealthStatus::Disabled)
+            .map(|s| s.service.clone())
+            .collect();
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            status,
+            services,
+            disabled_services,
+            timestamp,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+}
@@ -1,26 +1,55 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::os::raw::c_int;
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::runtime::Runtime;
-use log::{info, warn, error};
+use log::{info, warn, error, debug};
 
+pub mod attestation;
+pub mod audit;
+pub mod auth;
+pub mod canonical;
+pub mod error;
 pub mod crypto;
 pub mod storage;
 pub mod oracle;
 pub mod computation;
 pub mod ai;
 pub mod account;
+pub mod sealing;
+pub mod storage_backend;
+pub mod redact;
+pub mod dispatch;
+pub mod health;
+pub mod locks;
+pub mod metrics;
+pub mod pagination;
 
+use attestation::AttestationService;
+use audit::AuditLog;
+use auth::Authorizer;
 use crypto::CryptoService;
 use storage::StorageService;
-use oracle::OracleService;
+use sealing::SealingService;
+use oracle::{FetchPoll, OracleService, PendingFetches};
 use computation::ComputationService;
 use ai::AIService;
 use account::AccountService;
+use health::{HealthReport, HealthStatus, ServiceHealth};
+use metrics::Metrics;
+
+/// Interval between periodic health checks in the runtime loop
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Storage key of the job records saved at shutdown and reloaded at startup
+const JOB_RECORDS_KEY: &str = "runtime_job_records";
 
 /// Enclave configuration structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EncaveConfig {
     pub mode: String,
     pub log_level: String,
@@ -31,6 +60,109 @@ pub struct EncaveConfig {
     pub crypto_algorithms: Vec<String>,
     pub enable_ai: bool,
     pub enable_oracle: bool,
+    /// Neo network used for address generation ("mainnet" or "testnet").
+    pub neo_network: String,
+    /// Seconds to wait for in-flight jobs to finish during shutdown.
+    pub shutdown_grace_period_seconds: u64,
+    /// Keystore id of the secp256r1 key that signs oracle responses; generated if missing.
+    pub oracle_signing_key_id: String,
+    /// Rule set for computation code analysis ("strict" or "permissive").
+    pub computation_security_level: String,
+    /// Extra denied token patterns for computation code, e.g. "setTimeout (".
+    pub computation_denied_patterns: Vec<String>,
+    /// Computation jobs allowed to execute at the same time.
+    pub computation_max_concurrent_jobs: usize,
+    /// Jobs allowed to wait for a free slot before submissions are rejected; 0 disables queueing.
+    pub computation_queue_depth: usize,
+    /// How long results of computations declared deterministic are reused; 0 disables the cache.
+    pub computation_cache_ttl_seconds: u64,
+    /// Most computation results kept in the cache at once.
+    pub computation_cache_max_entries: usize,
+    /// Ceiling for per-request oracle timeout overrides.
+    pub oracle_max_timeout_seconds: u64,
+    /// Let any principal access storage entries written before ownership was tracked.
+    pub storage_open_unowned_entries: bool,
+    /// Decrypt entries in the legacy version-0 format, whose ciphertext is not bound to its
+    /// storage key. Only for migrating old stores; updating an entry rewrites it as version 1.
+    pub storage_accept_legacy_format: bool,
+    /// Cap on the total stored bytes across all entries; 0 means unlimited.
+    pub storage_max_total_bytes: u64,
+    /// What to do when a write would exceed the quota ("lru" or "reject").
+    pub storage_eviction_policy: String,
+    /// Let oracle requests reach loopback, private and link-local addresses.
+    pub oracle_allow_private_hosts: bool,
+    /// Upper bound on active oracle subscriptions; 0 disables subscriptions.
+    pub oracle_max_subscriptions: usize,
+    /// User-Agent sent with oracle requests; empty sends none.
+    pub oracle_user_agent: String,
+    /// Headers sent with every oracle request unless the request sets the same header.
+    /// Values of credential-like headers are redacted in logs.
+    pub oracle_default_headers: BTreeMap<String, String>,
+    /// Verify oracle TLS certificates against the web PKI roots. Turning this off leaves
+    /// only the pins below standing between the enclave and a man in the middle.
+    pub oracle_ssl_verification: bool,
+    /// Pinned keys per oracle domain (subdomains included), each "spki-sha256:<hex>" or
+    /// "cert-sha256:<hex>" for the server certificate, or "ca-spki-sha256:<hex>" for a CA
+    /// in its chain. Connections to a pinned domain fail unless the server certificate
+    /// matches a pin or verifies up to a pinned CA. CA pins need `oracle_ssl_verification`.
+    pub oracle_pinned_keys: BTreeMap<String, Vec<String>>,
+    /// Generate the oracle and account signing keys with an attestation quote over their
+    /// public keys; key generation fails if no quote can be produced.
+    pub attest_signing_keys: bool,
+    /// Log keys, addresses, storage key names and URL queries in full instead of as
+    /// fingerprints; only honored when `log_level` is "debug" or "trace".
+    pub log_secrets: bool,
+    /// Keep starting when the oracle or AI service fails to start, running without it,
+    /// instead of aborting startup.
+    pub degrade_optional_services: bool,
+    /// Where storage keeps its objects: "filesystem" (under `storage_path`) or "memory"
+    /// (lost on restart).
+    pub storage_backend: String,
+    /// JSON policy mapping principals to the dispatch methods they may call; empty
+    /// leaves dispatch unauthenticated. While a policy is configured the dedicated FFI
+    /// exports are refused, since they carry no token.
+    pub auth_policy_path: String,
+    /// Hex Ed25519 public key whose signature, in `<auth_policy_path>.sig`, the policy
+    /// file must carry. Required with `auth_policy_path`.
+    pub auth_policy_public_key: String,
+    /// Overwrite every deleted or evicted storage object before removing it, not just
+    /// entries stored with `secure_delete`.
+    pub storage_secure_delete: bool,
+    /// Entries smaller than this are stored uncompressed.
+    pub storage_compression_min_bytes: u64,
+    /// Entries at least this large that are rarely read are compressed densely (zstd)
+    /// instead of with fast lz4; 0 always uses lz4.
+    pub storage_dense_compression_min_bytes: u64,
+    /// Input anomaly score (0-1) above which a prediction is reported as degraded.
+    pub ai_anomaly_warn_threshold: f64,
+    /// Input anomaly score (0-1) above which a prediction is refused; 0 only warns.
+    pub ai_anomaly_reject_threshold: f64,
+    /// Run the crypto known-answer tests and benchmark at startup; a failed test aborts
+    /// startup.
+    pub crypto_self_test: bool,
+    /// Treat secp256k1 signatures with a high S value as invalid, as Neo and Bitcoin do;
+    /// when off they are normalized before verification.
+    pub crypto_require_low_s: bool,
+    /// Serve imported models but refuse training, model updates and hyperparameter tuning.
+    pub ai_inference_only: bool,
+    /// Upper bound on threads tokio starts for blocking work (training, file I/O) on top
+    /// of the `max_threads` workers.
+    pub max_blocking_threads: usize,
+    /// Runtime threads are named "<prefix>-<n>"; Linux shows the first 15 bytes.
+    pub thread_name_prefix: String,
+    /// Threads of the dedicated pool that parallel training loops run on with the
+    /// `parallel` feature; 0 keeps training on the calling thread.
+    pub ai_training_threads: usize,
+    /// Threads the enclave image allows: `resource_limits.max_num_of_threads` in
+    /// Occlum.json, or TCSNum for a plain SGX enclave. `max_threads`, `max_blocking_threads`,
+    /// `ai_training_threads` and `RESERVED_ENCLAVE_THREADS` must fit, since spawning past
+    /// the limit fails hard inside the enclave. 0 skips the check when running outside an
+    /// enclave.
+    pub enclave_thread_limit: usize,
+    /// ISV SVN that new sealed blobs are bound to: `metadata.security_version` in
+    /// Occlum.json. It must not exceed the enclave's actual SVN; blobs sealed at a lower
+    /// SVN stay readable and are re-sealed at this one.
+    pub seal_isv_svn: u16,
 }
 
 impl Default for EncaveConfig {
@@ -45,42 +177,615 @@ impl Default for EncaveConfig {
             crypto_algorithms: vec![
                 "aes-256-gcm".to_string(),
                 "secp256k1".to_string(),
+                "secp256r1".to_string(),
                 "ed25519".to_string(),
             ],
             enable_ai: true,
             enable_oracle: true,
+            neo_network: "mainnet".to_string(),
+            shutdown_grace_period_seconds: 30,
+            oracle_signing_key_id: "oracle_signing_key".to_string(),
+            computation_security_level: "strict".to_string(),
+            computation_denied_patterns: Vec::new(),
+            computation_max_concurrent_jobs: 16,
+            computation_queue_depth: 64,
+            computation_cache_ttl_seconds: 300,
+            computation_cache_max_entries: 1024,
+            oracle_max_timeout_seconds: 120,
+            storage_open_unowned_entries: true,
+            storage_accept_legacy_format: false,
+            storage_max_total_bytes: 0,
+            storage_eviction_policy: "reject".to_string(),
+            oracle_allow_private_hosts: false,
+            oracle_max_subscriptions: 32,
+            oracle_user_agent: concat!("neo-service-enclave/", env!("CARGO_PKG_VERSION")).to_string(),
+            oracle_default_headers: BTreeMap::new(),
+            oracle_ssl_verification: true,
+            oracle_pinned_keys: BTreeMap::new(),
+            attest_signing_keys: false,
+            log_secrets: false,
+            degrade_optional_services: false,
+            storage_backend: "filesystem".to_string(),
+            auth_policy_path: String::new(),
+            auth_policy_public_key: String::new(),
+            storage_secure_delete: false,
+            storage_compression_min_bytes: 512,
+            storage_dense_compression_min_bytes: 1024 * 1024,
+            ai_anomaly_warn_threshold: 0.8,
+            ai_anomaly_reject_threshold: 0.0,
+            crypto_self_test: false,
+            crypto_require_low_s: true,
+            ai_inference_only: false,
+            max_blocking_threads: 8,
+            thread_name_prefix: "nsl-enclave".to_string(),
+            ai_training_threads: 0,
+            // Matches max_num_of_threads in the production Occlum.json
+            enclave_thread_limit: 64,
+            // Matches security_version in the production Occlum.json
+            seal_isv_svn: 1,
+        }
+    }
+}
+
+/// Enclave threads outside the tokio pools: the main thread and one host caller.
+pub const RESERVED_ENCLAVE_THREADS: usize = 2;
+
+/// Recognized values for `EncaveConfig::mode`.
+pub const VALID_MODES: &[&str] = &["production", "development", "simulation"];
+
+/// Recognized values for `EncaveConfig::log_level`.
+pub const VALID_LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// Recognized values for `EncaveConfig::computation_security_level`.
+pub const VALID_COMPUTATION_SECURITY_LEVELS: &[&str] = &["strict", "permissive"];
+
+/// Recognized values for `EncaveConfig::storage_eviction_policy`.
+pub const VALID_STORAGE_EVICTION_POLICIES: &[&str] = &["lru", "reject"];
+
+/// A single configuration problem found during validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigViolation {
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// All configuration problems found during validation.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("invalid enclave configuration ({} problem(s)): {}", .violations.len(), format_violations(.violations))]
+pub struct ConfigValidationError {
+    pub violations: Vec<ConfigViolation>,
+}
+
+fn format_violations(violations: &[ConfigViolation]) -> String {
+    violations.iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Partially specified enclave configuration.
+///
+/// Only fields that were explicitly present in the source are `Some`, so a partial
+/// config can be layered over defaults without clobbering unrelated settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartialEncaveConfig {
+    pub mode: Option<String>,
+    pub log_level: Option<String>,
+    pub sgx_simulation_mode: Option<bool>,
+    pub max_threads: Option<usize>,
+    pub storage_path: Option<String>,
+    pub network_timeout_seconds: Option<u64>,
+    pub crypto_algorithms: Option<Vec<String>>,
+    pub enable_ai: Option<bool>,
+    pub enable_oracle: Option<bool>,
+    pub neo_network: Option<String>,
+    pub shutdown_grace_period_seconds: Option<u64>,
+    pub oracle_signing_key_id: Option<String>,
+    pub computation_security_level: Option<String>,
+    pub computation_denied_patterns: Option<Vec<String>>,
+    pub computation_max_concurrent_jobs: Option<usize>,
+    pub computation_queue_depth: Option<usize>,
+    pub computation_cache_ttl_seconds: Option<u64>,
+    pub computation_cache_max_entries: Option<usize>,
+    pub oracle_max_timeout_seconds: Option<u64>,
+    pub storage_open_unowned_entries: Option<bool>,
+    pub storage_accept_legacy_format: Option<bool>,
+    pub storage_max_total_bytes: Option<u64>,
+    pub storage_eviction_policy: Option<String>,
+    pub oracle_allow_private_hosts: Option<bool>,
+    pub oracle_max_subscriptions: Option<usize>,
+    pub oracle_user_agent: Option<String>,
+    pub oracle_default_headers: Option<BTreeMap<String, String>>,
+    pub oracle_ssl_verification: Option<bool>,
+    pub oracle_pinned_keys: Option<BTreeMap<String, Vec<String>>>,
+    pub attest_signing_keys: Option<bool>,
+    pub log_secrets: Option<bool>,
+    pub degrade_optional_services: Option<bool>,
+    pub storage_backend: Option<String>,
+    pub auth_policy_path: Option<String>,
+    pub auth_policy_public_key: Option<String>,
+    pub storage_secure_delete: Option<bool>,
+    pub storage_compression_min_bytes: Option<u64>,
+    pub storage_dense_compression_min_bytes: Option<u64>,
+    pub ai_anomaly_warn_threshold: Option<f64>,
+    pub ai_anomaly_reject_threshold: Option<f64>,
+    pub crypto_self_test: Option<bool>,
+    pub crypto_require_low_s: Option<bool>,
+    pub ai_inference_only: Option<bool>,
+    pub max_blocking_threads: Option<usize>,
+    pub thread_name_prefix: Option<String>,
+    pub ai_training_threads: Option<usize>,
+    pub enclave_thread_limit: Option<usize>,
+    pub seal_isv_svn: Option<u16>,
+}
+
+impl PartialEncaveConfig {
+    /// Load a partial configuration from a JSON or TOML file (chosen by extension).
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read config file {:?}: {}", path, e))?;
+        
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Invalid TOML config {:?}: {}", path, e)),
+            _ => serde_json::from_str(&contents)
+                .map_err(|e| anyhow::anyhow!("Invalid JSON config {:?}: {}", path, e)),
         }
     }
+    
+    /// Load a partial configuration from `NSL_*` environment variables.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(std::env::vars())
+    }
+    
+    /// Build a partial configuration from `NSL_*` key/value pairs.
+    pub fn from_vars<I: IntoIterator<Item = (String, String)>>(vars: I) -> Result<Self> {
+        fn parse_bool(key: &str, value: &str) -> Result<bool> {
+            match value.trim().to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(true),
+                "0" | "false" | "no" | "off" => Ok(false),
+                _ => Err(anyhow::anyhow!("{} must be a boolean, got '{}'", key, value)),
+            }
+        }
+        
+        fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T> {
+            value.trim().parse::<T>()
+                .map_err(|_| anyhow::anyhow!("{} must be a number, got '{}'", key, value))
+        }
+        
+        let mut partial = Self::default();
+        
+        for (key, value) in vars {
+            match key.as_str() {
+                "NSL_MODE" => partial.mode = Some(value),
+                "NSL_LOG_LEVEL" => partial.log_level = Some(value),
+                "NSL_SGX_SIMULATION_MODE" => partial.sgx_simulation_mode = Some(parse_bool(&key, &value)?),
+                "NSL_MAX_THREADS" => partial.max_threads = Some(parse_number(&key, &value)?),
+                "NSL_STORAGE_PATH" => partial.storage_path = Some(value),
+                "NSL_NETWORK_TIMEOUT_SECONDS" => partial.network_timeout_seconds = Some(parse_number(&key, &value)?),
+                "NSL_CRYPTO_ALGORITHMS" => partial.crypto_algorithms = Some(
+                    value.split(',')
+                        .map(|alg| alg.trim().to_string())
+                        .filter(|alg| !alg.is_empty())
+                        .collect()
+                ),
+                "NSL_ENABLE_AI" => partial.enable_ai = Some(parse_bool(&key, &value)?),
+                "NSL_ENABLE_ORACLE" => partial.enable_oracle = Some(parse_bool(&key, &value)?),
+                "NSL_NEO_NETWORK" => partial.neo_network = Some(value),
+                "NSL_SHUTDOWN_GRACE_PERIOD_SECONDS" => partial.shutdown_grace_period_seconds = Some(parse_number(&key, &value)?),
+                "NSL_ORACLE_SIGNING_KEY_ID" => partial.oracle_signing_key_id = Some(value),
+                "NSL_COMPUTATION_SECURITY_LEVEL" => partial.computation_security_level = Some(value),
+                "NSL_COMPUTATION_DENIED_PATTERNS" => partial.computation_denied_patterns = Some(
+                    value.split(',')
+                        .map(|pattern| pattern.trim().to_string())
+                        .filter(|pattern| !pattern.is_empty())
+                        .collect()
+                ),
+                "NSL_COMPUTATION_MAX_CONCURRENT_JOBS" => partial.computation_max_concurrent_jobs = Some(parse_number(&key, &value)?),
+                "NSL_COMPUTATION_QUEUE_DEPTH" => partial.computation_queue_depth = Some(parse_number(&key, &value)?),
+                "NSL_COMPUTATION_CACHE_TTL_SECONDS" => partial.computation_cache_ttl_seconds = Some(parse_number(&key, &value)?),
+                "NSL_COMPUTATION_CACHE_MAX_ENTRIES" => partial.computation_cache_max_entries = Some(parse_number(&key, &value)?),
+                "NSL_ORACLE_MAX_TIMEOUT_SECONDS" => partial.oracle_max_timeout_seconds = Some(parse_number(&key, &value)?),
+                "NSL_STORAGE_OPEN_UNOWNED_ENTRIES" => partial.storage_open_unowned_entries = Some(parse_bool(&key, &value)?),
+                "NSL_STORAGE_ACCEPT_LEGACY_FORMAT" => partial.storage_accept_legacy_format = Some(parse_bool(&key, &value)?),
+                "NSL_STORAGE_MAX_TOTAL_BYTES" => partial.storage_max_total_bytes = Some(parse_number(&key, &value)?),
+                "NSL_STORAGE_EVICTION_POLICY" => partial.storage_eviction_policy = Some(value),
+                "NSL_ORACLE_ALLOW_PRIVATE_HOSTS" => partial.oracle_allow_private_hosts = Some(parse_bool(&key, &value)?),
+                "NSL_ORACLE_MAX_SUBSCRIPTIONS" => partial.oracle_max_subscriptions = Some(parse_number(&key, &value)?),
+                "NSL_ORACLE_USER_AGENT" => partial.oracle_user_agent = Some(value),
+                "NSL_ORACLE_DEFAULT_HEADERS" => partial.oracle_default_headers = Some(
+                    serde_json::from_str(&value)
+                        .map_err(|_| anyhow::anyhow!("{} must be a JSON object of header names to values", key))?
+                ),
+                "NSL_ORACLE_SSL_VERIFICATION" => partial.oracle_ssl_verification = Some(parse_bool(&key, &value)?),
+                "NSL_ORACLE_PINNED_KEYS" => partial.oracle_pinned_keys = Some(
+                    serde_json::from_str(&value)
+                        .map_err(|_| anyhow::anyhow!("{} must be a JSON object of domains to lists of pins", key))?
+                ),
+                "NSL_ATTEST_SIGNING_KEYS" => partial.attest_signing_keys = Some(parse_bool(&key, &value)?),
+                "NSL_LOG_SECRETS" => partial.log_secrets = Some(parse_bool(&key, &value)?),
+                "NSL_DEGRADE_OPTIONAL_SERVICES" => partial.degrade_optional_services = Some(parse_bool(&key, &value)?),
+                "NSL_STORAGE_BACKEND" => partial.storage_backend = Some(value),
+                "NSL_AUTH_POLICY_PATH" => partial.auth_policy_path = Some(value),
+                "NSL_AUTH_POLICY_PUBLIC_KEY" => partial.auth_policy_public_key = Some(value),
+                "NSL_STORAGE_SECURE_DELETE" => partial.storage_secure_delete = Some(parse_bool(&key, &value)?),
+                "NSL_STORAGE_COMPRESSION_MIN_BYTES" => partial.storage_compression_min_bytes = Some(parse_number(&key, &value)?),
+                "NSL_STORAGE_DENSE_COMPRESSION_MIN_BYTES" => partial.storage_dense_compression_min_bytes = Some(parse_number(&key, &value)?),
+                "NSL_AI_ANOMALY_WARN_THRESHOLD" => partial.ai_anomaly_warn_threshold = Some(parse_number(&key, &value)?),
+                "NSL_AI_ANOMALY_REJECT_THRESHOLD" => partial.ai_anomaly_reject_threshold = Some(parse_number(&key, &value)?),
+                "NSL_CRYPTO_SELF_TEST" => partial.crypto_self_test = Some(parse_bool(&key, &value)?),
+                "NSL_CRYPTO_REQUIRE_LOW_S" => partial.crypto_require_low_s = Some(parse_bool(&key, &value)?),
+                "NSL_AI_INFERENCE_ONLY" => partial.ai_inference_only = Some(parse_bool(&key, &value)?),
+                "NSL_MAX_BLOCKING_THREADS" => partial.max_blocking_threads = Some(parse_number(&key, &value)?),
+                "NSL_THREAD_NAME_PREFIX" => partial.thread_name_prefix = Some(value),
+                "NSL_AI_TRAINING_THREADS" => partial.ai_training_threads = Some(parse_number(&key, &value)?),
+                "NSL_ENCLAVE_THREAD_LIMIT" => partial.enclave_thread_limit = Some(parse_number(&key, &value)?),
+                "NSL_SEAL_ISV_SVN" => partial.seal_isv_svn = Some(parse_number(&key, &value)?),
+                _ => {}
+            }
+        }
+        
+        Ok(partial)
+    }
 }
 
 impl EncaveConfig {
-    pub fn merge(&mut self, other: EncaveConfig) {
-        self.mode = other.mode;
-        self.log_level = other.log_level;
-        self.sgx_simulation_mode = other.sgx_simulation_mode;
-        self.max_threads = other.max_threads;
-        self.storage_path = other.storage_path;
-        self.network_timeout_seconds = other.network_timeout_seconds;
-        self.crypto_algorithms = other.crypto_algorithms;
-        self.enable_ai = other.enable_ai;
-        self.enable_oracle = other.enable_oracle;
-    }
-    
-    pub fn validate(&self) -> Result<()> {
+    /// Load configuration from a file layered over the defaults.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut config = Self::default();
+        config.merge(PartialEncaveConfig::from_file(path)?);
+        Ok(config)
+    }
+    
+    /// Load configuration from `NSL_*` environment variables layered over the defaults.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        config.merge(PartialEncaveConfig::from_env()?);
+        Ok(config)
+    }
+    
+    /// Load the layered configuration: defaults, then the optional file, then the
+    /// environment. The merged result is validated before being returned.
+    pub fn load(config_file: Option<&Path>) -> Result<Self> {
+        let mut config = Self::default();
+        
+        if let Some(path) = config_file {
+            config.merge(PartialEncaveConfig::from_file(path)?);
+        }
+        config.merge(PartialEncaveConfig::from_env()?);
+        
+        config.validate()?;
+        Ok(config)
+    }
+    
+    /// Override only the fields explicitly present in `other`.
+    pub fn merge(&mut self, other: PartialEncaveConfig) {
+        if let Some(mode) = other.mode {
+            self.mode = mode;
+        }
+        if let Some(log_level) = other.log_level {
+            self.log_level = log_level;
+        }
+        if let Some(sgx_simulation_mode) = other.sgx_simulation_mode {
+            self.sgx_simulation_mode = sgx_simulation_mode;
+        }
+        if let Some(max_threads) = other.max_threads {
+            self.max_threads = max_threads;
+        }
+        if let Some(storage_path) = other.storage_path {
+            self.storage_path = storage_path;
+        }
+        if let Some(network_timeout_seconds) = other.network_timeout_seconds {
+            self.network_timeout_seconds = network_timeout_seconds;
+        }
+        if let Some(crypto_algorithms) = other.crypto_algorithms {
+            self.crypto_algorithms = crypto_algorithms;
+        }
+        if let Some(enable_ai) = other.enable_ai {
+            self.enable_ai = enable_ai;
+        }
+        if let Some(enable_oracle) = other.enable_oracle {
+            self.enable_oracle = enable_oracle;
+        }
+        if let Some(neo_network) = other.neo_network {
+            self.neo_network = neo_network;
+        }
+        if let Some(shutdown_grace_period_seconds) = other.shutdown_grace_period_seconds {
+            self.shutdown_grace_period_seconds = shutdown_grace_period_seconds;
+        }
+        if let Some(oracle_signing_key_id) = other.oracle_signing_key_id {
+            self.oracle_signing_key_id = oracle_signing_key_id;
+        }
+        if let Some(computation_security_level) = other.computation_security_level {
+            self.computation_security_level = computation_security_level;
+        }
+        if let Some(computation_denied_patterns) = other.computation_denied_patterns {
+            self.computation_denied_patterns = computation_denied_patterns;
+        }
+        if let Some(computation_max_concurrent_jobs) = other.computation_max_concurrent_jobs {
+            self.computation_max_concurrent_jobs = computation_max_concurrent_jobs;
+        }
+        if let Some(computation_queue_depth) = other.computation_queue_depth {
+            self.computation_queue_depth = computation_queue_depth;
+        }
+        if let Some(computation_cache_ttl_seconds) = other.computation_cache_ttl_seconds {
+            self.computation_cache_ttl_seconds = computation_cache_ttl_seconds;
+        }
+        if let Some(computation_cache_max_entries) = other.computation_cache_max_entries {
+            self.computation_cache_max_entries = computation_cache_max_entries;
+        }
+        if let Some(oracle_max_timeout_seconds) = other.oracle_max_timeout_seconds {
+            self.oracle_max_timeout_seconds = oracle_max_timeout_seconds;
+        }
+        if let Some(storage_open_unowned_entries) = other.storage_open_unowned_entries {
+            self.storage_open_unowned_entries = storage_open_unowned_entries;
+        }
+        if let Some(storage_accept_legacy_format) = other.storage_accept_legacy_format {
+            self.storage_accept_legacy_format = storage_accept_legacy_format;
+        }
+        if let Some(storage_max_total_bytes) = other.storage_max_total_bytes {
+            self.storage_max_total_bytes = storage_max_total_bytes;
+        }
+        if let Some(storage_eviction_policy) = other.storage_eviction_policy {
+            self.storage_eviction_policy = storage_eviction_policy;
+        }
+        if let Some(oracle_allow_private_hosts) = other.oracle_allow_private_hosts {
+            self.oracle_allow_private_hosts = oracle_allow_private_hosts;
+        }
+        if let Some(oracle_max_subscriptions) = other.oracle_max_subscriptions {
+            self.oracle_max_subscriptions = oracle_max_subscriptions;
+        }
+        if let Some(oracle_user_agent) = other.oracle_user_agent {
+            self.oracle_user_agent = oracle_user_agent;
+        }
+        if let Some(oracle_default_headers) = other.oracle_default_headers {
+            self.oracle_default_headers = oracle_default_headers;
+        }
+        if let Some(oracle_ssl_verification) = other.oracle_ssl_verification {
+            self.oracle_ssl_verification = oracle_ssl_verification;
+        }
+        if let Some(oracle_pinned_keys) = other.oracle_pinned_keys {
+            self.oracle_pinned_keys = oracle_pinned_keys;
+        }
+        if let Some(attest_signing_keys) = other.attest_signing_keys {
+            self.attest_signing_keys = attest_signing_keys;
+        }
+        if let Some(log_secrets) = other.log_secrets {
+            self.log_secrets = log_secrets;
+        }
+        if let Some(degrade_optional_services) = other.degrade_optional_services {
+            self.degrade_optional_services = degrade_optional_services;
+        }
+        if let Some(storage_backend) = other.storage_backend {
+            self.storage_backend = storage_backend;
+        }
+        if let Some(auth_policy_path) = other.auth_policy_path {
+            self.auth_policy_path = auth_policy_path;
+        }
+        if let Some(auth_policy_public_key) = other.auth_policy_public_key {
+            self.auth_policy_public_key = auth_policy_public_key;
+        }
+        if let Some(storage_secure_delete) = other.storage_secure_delete {
+            self.storage_secure_delete = storage_secure_delete;
+        }
+        if let Some(storage_compression_min_bytes) = other.storage_compression_min_bytes {
+            self.storage_compression_min_bytes = storage_compression_min_bytes;
+        }
+        if let Some(storage_dense_compression_min_bytes) = other.storage_dense_compression_min_bytes {
+            self.storage_dense_compression_min_bytes = storage_dense_compression_min_bytes;
+        }
+        if let Some(ai_anomaly_warn_threshold) = other.ai_anomaly_warn_threshold {
+            self.ai_anomaly_warn_threshold = ai_anomaly_warn_threshold;
+        }
+        if let Some(ai_anomaly_reject_threshold) = other.ai_anomaly_reject_threshold {
+            self.ai_anomaly_reject_threshold = ai_anomaly_reject_threshold;
+        }
+        if let Some(crypto_self_test) = other.crypto_self_test {
+            self.crypto_self_test = crypto_self_test;
+        }
+        if let Some(crypto_require_low_s) = other.crypto_require_low_s {
+            self.crypto_require_low_s = crypto_require_low_s;
+        }
+        if let Some(ai_inference_only) = other.ai_inference_only {
+            self.ai_inference_only = ai_inference_only;
+        }
+        if let Some(max_blocking_threads) = other.max_blocking_threads {
+            self.max_blocking_threads = max_blocking_threads;
+        }
+        if let Some(thread_name_prefix) = other.thread_name_prefix {
+            self.thread_name_prefix = thread_name_prefix;
+        }
+        if let Some(ai_training_threads) = other.ai_training_threads {
+            self.ai_training_threads = ai_training_threads;
+        }
+        if let Some(enclave_thread_limit) = other.enclave_thread_limit {
+            self.enclave_thread_limit = enclave_thread_limit;
+        }
+        if let Some(seal_isv_svn) = other.seal_isv_svn {
+            self.seal_isv_svn = seal_isv_svn;
+        }
+    }
+    
+    /// Check that the runtime's threads fit in `enclave_thread_limit`
+    pub fn check_thread_limit(&self) -> std::result::Result<(), String> {
+        let required = self.max_threads
+            .saturating_add(self.max_blocking_threads)
+            .saturating_add(self.ai_training_threads)
+            .saturating_add(RESERVED_ENCLAVE_THREADS);
+        if self.enclave_thread_limit > 0 && required > self.enclave_thread_limit {
+            return Err(format!(
+                "{} worker, {} blocking and {} training threads plus {} reserved need {} threads, more than the enclave allows ({}); \
+                 lower them or raise max_num_of_threads/TCSNum and enclave_thread_limit together",
+                self.max_threads, self.max_blocking_threads, self.ai_training_threads,
+                RESERVED_ENCLAVE_THREADS, required, self.enclave_thread_limit
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Validate the configuration, reporting every violation at once.
+    pub fn validate(&self) -> std::result::Result<(), ConfigValidationError> {
+        let mut violations = Vec::new();
+        let mut violation = |field: &str, message: String| {
+            violations.push(ConfigViolation {
+                field: field.to_string(),
+                message,
+            });
+        };
+        
+        if !VALID_MODES.contains(&self.mode.as_str()) {
+            violation("mode", format!("unknown mode '{}', expected one of {:?}", self.mode, VALID_MODES));
+        }
+        
+        if !VALID_LOG_LEVELS.contains(&self.log_level.to_lowercase().as_str()) {
+            violation("log_level", format!("unknown log level '{}', expected one of {:?}", self.log_level, VALID_LOG_LEVELS));
+        }
+        
         if self.max_threads == 0 {
-            return Err(anyhow::anyhow!("max_threads must be greater than 0"));
+            violation("max_threads", "must be greater than 0".to_string());
+        }
+        if self.max_blocking_threads == 0 {
+            violation("max_blocking_threads", "must be greater than 0".to_string());
+        }
+        if let Err(message) = self.check_thread_limit() {
+            violation("max_threads", message);
+        }
+        if self.thread_name_prefix.is_empty() || self.thread_name_prefix.contains('\0') {
+            violation("thread_name_prefix", "must be non-empty and contain no NUL bytes".to_string());
         }
         
         if self.network_timeout_seconds == 0 {
-            return Err(anyhow::anyhow!("network_timeout_seconds must be greater than 0"));
+            violation("network_timeout_seconds", "must be greater than 0".to_string());
         }
         
-        Ok(())
+        if !storage_backend::VALID_STORAGE_BACKENDS.contains(&self.storage_backend.as_str()) {
+            violation("storage_backend", format!(
+                "unknown backend '{}', expected one of {:?}",
+                self.storage_backend, storage_backend::VALID_STORAGE_BACKENDS
+            ));
+        }
+        
+        // The in-memory backend never touches storage_path
+        if self.storage_backend != "memory" {
+            if self.storage_path.trim().is_empty() {
+                violation("storage_path", "must not be empty".to_string());
+            } else if let Err(e) = Self::check_storage_writable(Path::new(&self.storage_path)) {
+                violation("storage_path", format!("'{}' is not writable: {}", self.storage_path, e));
+            }
+        }
+        
+        if !self.auth_policy_path.is_empty() {
+            match auth::policy_verifying_key(&self.auth_policy_public_key) {
+                Ok(key) => if let Err(e) = auth::AuthPolicy::from_file(&self.auth_policy_path, &key) {
+                    violation("auth_policy_path", e.to_string());
+                },
+                Err(e) => violation("auth_policy_public_key", e.to_string()),
+            }
+        }
+        
+        if self.crypto_algorithms.is_empty() {
+            violation("crypto_algorithms", "at least one algorithm must be enabled".to_string());
+        }
+        for alg in &self.crypto_algorithms {
+            if crypto::CryptoAlgorithm::from_name(alg).is_none() {
+                violation("crypto_algorithms", format!("unsupported algorithm '{}'", alg));
+            }
+        }
+        
+        if let Err(e) = account::address_version_for_network(&self.neo_network) {
+            violation("neo_network", e.to_string());
+        }
+        
+        if self.oracle_signing_key_id.trim().is_empty() {
+            violation("oracle_signing_key_id", "must not be empty".to_string());
+        }
+        
+        if self.oracle_max_timeout_seconds < self.network_timeout_seconds {
+            violation("oracle_max_timeout_seconds", format!(
+                "must be at least network_timeout_seconds ({})", self.network_timeout_seconds
+            ));
+        }
+        if reqwest::header::HeaderValue::from_str(&self.oracle_user_agent).is_err() {
+            violation("oracle_user_agent", "is not a valid header value".to_string());
+        }
+        for (name, value) in &self.oracle_default_headers {
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                violation("oracle_default_headers", format!("'{}' is not a valid header name", name));
+            } else if reqwest::header::HeaderValue::from_str(value).is_err() {
+                violation("oracle_default_headers", format!("value of '{}' is not a valid header value", name));
+            }
+        }
+        for (domain, pins) in &self.oracle_pinned_keys {
+            if domain.trim().is_empty() {
+                violation("oracle_pinned_keys", "domains must not be empty".to_string());
+            } else if pins.is_empty() {
+                violation("oracle_pinned_keys", format!("'{}' has no pins", domain));
+            }
+            for pin in pins {
+                match oracle::CertificatePin::parse(pin) {
+                    Err(e) => violation("oracle_pinned_keys", format!("'{}': {}", domain, e)),
+                    Ok(pin) if !pin.is_end_entity() && !self.oracle_ssl_verification => violation(
+                        "oracle_pinned_keys",
+                        format!("'{}': CA pins need oracle_ssl_verification", domain),
+                    ),
+                    Ok(_) => {}
+                }
+            }
+        }
+        
+        if !VALID_STORAGE_EVICTION_POLICIES.contains(&self.storage_eviction_policy.as_str()) {
+            violation("storage_eviction_policy", format!(
+                "unknown policy '{}', expected one of {:?}",
+                self.storage_eviction_policy, VALID_STORAGE_EVICTION_POLICIES
+            ));
+        }
+        
+        if !VALID_COMPUTATION_SECURITY_LEVELS.contains(&self.computation_security_level.as_str()) {
+            violation("computation_security_level", format!(
+                "unknown level '{}', expected one of {:?}",
+                self.computation_security_level, VALID_COMPUTATION_SECURITY_LEVELS
+            ));
+        }
+        if self.computation_denied_patterns.iter().any(|pattern| pattern.trim().is_empty()) {
+            violation("computation_denied_patterns", "patterns must not be empty".to_string());
+        }
+        if self.computation_max_concurrent_jobs == 0 {
+            violation("computation_max_concurrent_jobs", "must be greater than 0".to_string());
+        }
+        
+        if !(0.0..=1.0).contains(&self.ai_anomaly_warn_threshold) {
+            violation("ai_anomaly_warn_threshold", "must be between 0 and 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.ai_anomaly_reject_threshold) {
+            violation("ai_anomaly_reject_threshold", "must be between 0 and 1".to_string());
+        }
+        
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigValidationError { violations })
+        }
+    }
+    
+    /// Ensure the storage directory exists (creating it if needed) and accepts writes
+    fn check_storage_writable(path: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(path)?;
+        
+        let probe = path.join(".nsl_write_probe");
+        std::fs::write(&probe, b"probe")?;
+        std::fs::remove_file(&probe)
     }
     
     pub fn get_number(&self, key: &str) -> Result<usize> {
         match key {
-            "computation.max_concurrent_jobs" => Ok(self.max_threads),
+            "computation.max_concurrent_jobs" => Ok(self.computation_max_concurrent_jobs),
             "ai.max_model_size_mb" => Ok(1024), // Default 1GB
             "ai.max_training_data_mb" => Ok(512), // Default 512MB
             _ => Err(anyhow::anyhow!("Unknown config key: {}", key))
@@ -93,89 +798,260 @@ pub struct EncaveRuntime {
     config: EncaveConfig,
     crypto_service: Arc<CryptoService>,
     storage_service: Arc<StorageService>,
+    audit_log: Arc<AuditLog>,
     oracle_service: Option<Arc<OracleService>>,
     computation_service: Arc<ComputationService>,
     ai_service: Option<Arc<AIService>>,
     account_service: Arc<AccountService>,
+    attestation_service: Arc<AttestationService>,
+    pending_fetches: PendingFetches,
+    open_results: dispatch::OpenResults,
+    authorizer: Authorizer,
     tokio_runtime: Runtime,
+    started_at: std::time::Instant,
+    /// Set once `start` has brought up every required service, cleared on shutdown
+    ready: bool,
+    /// Optional services that failed to start and were dropped
+    failed_services: Vec<&'static str>,
 }
 
 impl EncaveRuntime {
     pub async fn new(config: EncaveConfig) -> Result<Self> {
         info!("Initializing Neo Service Layer Enclave Runtime");
         
-        // Create Tokio runtime
+        let log_secrets = config.log_secrets
+            && matches!(config.log_level.to_lowercase().as_str(), "debug" | "trace");
+        if config.log_secrets && !log_secrets {
+            warn!("log_secrets is ignored unless log_level is debug or trace");
+        } else if log_secrets {
+            warn!("Secret logging is enabled; keys and addresses will appear in logs");
+        }
+        redact::set_log_secrets(log_secrets);
+        
+        // Create Tokio runtime, refusing up front to start more threads than the enclave has
+        config.check_thread_limit().map_err(|message| anyhow::anyhow!(message))?;
+        let thread_name_prefix = config.thread_name_prefix.clone();
+        let thread_index = AtomicUsize::new(0);
         let tokio_runtime = tokio::runtime::Builder::new_multi_thread()
             .worker_threads(config.max_threads)
+            .max_blocking_threads(config.max_blocking_threads)
+            .thread_name_fn(move || {
+                format!("{}-{}", thread_name_prefix, thread_index.fetch_add(1, Ordering::Relaxed))
+            })
             .enable_all()
             .build()?;
         
         // Initialize services
-        let crypto_service = Arc::new(CryptoService::new(&config).await?);
+        // Storage comes first so the audit log can be shared with every other service
         let storage_service = Arc::new(StorageService::new(&config).await?);
+        let audit_log = Arc::new(AuditLog::new(storage_service.clone())?);
+        let crypto_service = Arc::new(CryptoService::new(&config, audit_log.clone()).await?);
         
         let oracle_service = if config.enable_oracle {
-            Some(Arc::new(OracleService::new(&config).await?))
+            Some(Arc::new(OracleService::new(&config, crypto_service.clone()).await?))
         } else {
             None
         };
         
-        let computation_service = Arc::new(ComputationService::new(&config).await?);
+        let computation_service = Arc::new(ComputationService::new(&config, crypto_service.clone()).await?);
         
         let ai_service = if config.enable_ai {
-            Some(Arc::new(AIService::new(&config).await?))
+            Some(Arc::new(AIService::new(&config, crypto_service.clone(), audit_log.clone()).await?))
         } else {
             None
         };
         
-        let account_service = Arc::new(AccountService::new(&config, crypto_service.clone()).await?);
+        let account_service = Arc::new(AccountService::new(&config, crypto_service.clone(), storage_service.clone(), audit_log.clone()).await?);
+        let attestation_service = crypto_service.attestation_service().clone();
+        let authorizer = Authorizer::new(&config.auth_policy_path, &config.auth_policy_public_key)?;
         
-        Ok(Self {
+        let runtime = Self {
             config,
             crypto_service,
             storage_service,
+            audit_log,
             oracle_service,
             computation_service,
             ai_service,
             account_service,
+            attestation_service,
+            pending_fetches: PendingFetches::default(),
+            open_results: dispatch::OpenResults::default(),
+            authorizer,
             tokio_runtime,
-        })
+            started_at: std::time::Instant::now(),
+            ready: false,
+            failed_services: Vec::new(),
+        };
+        runtime.restore_job_records()?;
+        Ok(runtime)
     }
     
+    /// Start services in dependency order, checking that each reports ready before the next
+    ///
+    /// Storage, crypto, account and computation are required; a failure in any of them aborts
+    /// startup. The oracle and AI services are optional: with `degrade_optional_services` set
+    /// a failed one is dropped and reported as degraded, otherwise it aborts startup too.
     pub async fn start(&mut self) -> Result<()> {
         info!("Starting enclave services");
+        self.ready = false;
+        self.failed_services.clear();
         
-        // Start storage service
-        self.storage_service.start().await?;
+        self.storage_service.start().await
+            .map_err(|e| anyhow::anyhow!("Required service 'storage' failed to start: {}", e))?;
+        Self::ensure_ready(self.storage_service.health_check())?;
+        if self.config.crypto_self_test {
+            let report = self.crypto_service.self_test_report();
+            let failures = report.failures();
+            if !failures.is_empty() {
+                return Err(anyhow::anyhow!("Required service 'crypto' failed its self-test: {}", failures.join("; ")));
+            }
+            info!("Crypto self-test passed in {} ms: {}", report.duration_ms, serde_json::to_string(&report.results)?);
+        }
+        Self::ensure_ready(self.crypto_service.health_check())?;
+        Self::ensure_ready(self.account_service.health_check())?;
+        Self::ensure_ready(self.computation_service.health_check())?;
         
-        // Start oracle service if enabled
-        if let Some(oracle) = &self.oracle_service {
-            oracle.start().await?;
+        if let Some(oracle) = self.oracle_service.clone() {
+            let started = match oracle.start().await {
+                Ok(()) => Self::ensure_ready(oracle.health_check()),
+                Err(e) => Err(anyhow::anyhow!("Service 'oracle' failed to start: {}", e)),
+            };
+            if let Err(e) = started {
+                self.degrade_optional_service("oracle", e)?;
+                self.oracle_service = None;
+            }
         }
         
-        // Start AI service if enabled
-        if let Some(ai) = &self.ai_service {
-            ai.start().await?;
+        if let Some(ai) = self.ai_service.clone() {
+            let started = match ai.start().await {
+                Ok(()) => Self::ensure_ready(ai.health_check()),
+                Err(e) => Err(anyhow::anyhow!("Service 'ai' failed to start: {}", e)),
+            };
+            if let Err(e) = started {
+                self.degrade_optional_service("ai", e)?;
+                self.ai_service = None;
+            }
         }
         
-        info!("All enclave services started successfully");
+        self.ready = true;
+        if self.failed_services.is_empty() {
+            info!("All enclave services started successfully");
+        } else {
+            warn!("Enclave services started without: {}", self.failed_services.join(", "));
+        }
         Ok(())
     }
     
+    /// Fail unless a freshly started service reports healthy or degraded
+    fn ensure_ready(health: ServiceHealth) -> Result<()> {
+        if health.status == HealthStatus::Unhealthy {
+            return Err(anyhow::anyhow!(
+                "Service '{}' is not ready: {}",
+                health.service,
+                health.message.as_deref().unwrap_or("no details")
+            ));
+        }
+        debug!("Service '{}' is ready", health.service);
+        Ok(())
+    }
+    
+    /// Record an optional service startup failure, or abort if degrading is not allowed
+    fn degrade_optional_service(&mut self, service: &'static str, error: anyhow::Error) -> Result<()> {
+        if !self.config.degrade_optional_services {
+            return Err(error.context("Optional service failed and degrade_optional_services is disabled"));
+        }
+        error!("{}; continuing without it", error);
+        self.failed_services.push(service);
+        Ok(())
+    }
+    
+    /// Whether `start` completed and the runtime has not been shut down
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+    
     pub async fn run(&self) -> Result<()> {
+        if !self.is_ready() {
+            return Err(anyhow::anyhow!("Enclave runtime is not ready; start() must succeed before run()"));
+        }
         info!("Running enclave runtime");
         
         // Main runtime loop - this will run indefinitely until shutdown
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS));
         loop {
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            interval.tick().await;
             
-            // Health checks and maintenance tasks can go here
-            // For now, just keep the runtime alive
+            let report = self.health_check();
+            for service in &report.services {
+                match service.status {
+                    HealthStatus::Degraded => warn!(
+                        "Service '{}' degraded: {}",
+                        service.service,
+                        service.message.as_deref().unwrap_or("no details")
+                    ),
+                    HealthStatus::Unhealthy => error!(
+                        "Service '{}' unhealthy: {}",
+                        service.service,
+                        service.message.as_deref().unwrap_or("no details")
+                    ),
+                    HealthStatus::Healthy | HealthStatus::Disabled => {}
+                }
+            }
         }
     }
     
+    /// Probe every service and aggregate the results
+    pub fn health_check(&self) -> HealthReport {
+        let mut services = vec![
+            self.crypto_service.health_check(),
+            self.storage_service.health_check(),
+        ];
+        
+        services.push(match &self.oracle_service {
+            Some(oracle) => oracle.health_check(),
+            None => ServiceHealth::disabled("oracle"),
+        });
+        
+        services.push(self.computation_service.health_check());
+        services.push(self.account_service.health_check());
+        
+        services.push(match &self.ai_service {
+            Some(ai) => ai.health_check(),
+            None => ServiceHealth::disabled("ai"),
+        });
+        
+        for service in &mut services {
+            if service.status == HealthStatus::Disabled && self.failed_services.contains(&service.service.as_str()) {
+                *service = ServiceHealth::degraded(&service.service, "Failed to start; running without it", serde_json::Value::Null);
+            }
+        }
+        
+        // A recovered lock means some operation panicked mid-update; the data may be stale
+        let recoveries = locks::recoveries();
+        if !recoveries.is_empty() {
+            let total: u64 = recoveries.values().sum();
+            services.push(ServiceHealth::degraded(
+                "locks",
+                format!("{} poisoned lock(s) recovered since startup; restart advisable", total),
+                serde_json::json!(recoveries),
+            ));
+        }
+        
+        HealthReport::from_services(services)
+    }
+    
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down enclave runtime");
+        self.ready = false;
+        
+        // Stop accepting new work, then give in-flight jobs a chance to finish
+        self.computation_service.stop_accepting();
+        if let Some(ai) = &self.ai_service {
+            ai.stop_accepting();
+        }
+        self.drain_jobs().await?;
+        self.persist_job_records()?;
         
         // Shutdown services in reverse order
         if let Some(ai) = &self.ai_service {
@@ -192,6 +1068,99 @@ impl EncaveRuntime {
         Ok(())
     }
     
+    /// Snapshot every service's counters as JSON
+    pub fn metrics_snapshot(&self) -> Result<String> {
+        let metrics = Metrics {
+            timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
+            uptime_seconds: self.started_at.elapsed().as_secs(),
+            crypto: self.crypto_service.metrics().clone(),
+            storage: self.storage_service.metrics().clone(),
+            oracle: self.oracle_service.as_ref().map(|oracle| oracle.metrics().clone()),
+            computation: self.computation_service.metrics().clone(),
+            computation_pool: self.computation_service.pool_metrics(),
+            ai: self.ai_service.as_ref().map(|ai| ai.metrics().clone()),
+        };
+        
+        Ok(serde_json::to_string(&metrics)?)
+    }
+    
+    /// Wait up to the configured grace period for running jobs to reach a terminal
+    /// state, then cancel whatever is still in flight
+    async fn drain_jobs(&self) -> Result<()> {
+        let grace_period = tokio::time::Duration::from_secs(self.config.shutdown_grace_period_seconds);
+        let deadline = tokio::time::Instant::now() + grace_period;
+        
+        loop {
+            let mut active = self.computation_service.active_job_count()?;
+            if let Some(ai) = &self.ai_service {
+                active += ai.active_training_job_count()?;
+            }
+            
+            if active == 0 {
+                info!("All in-flight jobs drained");
+                return Ok(());
+            }
+            
+            if tokio::time::Instant::now() >= deadline {
+                warn!("{} jobs still running after {}s grace period", active, grace_period.as_secs());
+                break;
+            }
+            
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        
+        let reason = format!(
+            "Cancelled during shutdown after {}s grace period",
+            grace_period.as_secs()
+        );
+        self.computation_service.cancel_active_jobs(&reason)?;
+        if let Some(ai) = &self.ai_service {
+            ai.cancel_active_training_jobs(&reason)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Save every computation and training job record so they survive a restart
+    fn persist_job_records(&self) -> Result<()> {
+        let records = serde_json::json!({
+            "computation": self.computation_service.export_jobs(),
+            "training": match &self.ai_service {
+                Some(ai) => ai.export_training_jobs()?,
+                None => serde_json::Value::Array(Vec::new()),
+            },
+        });
+        let data = serde_json::to_vec(&records)?;
+        
+        if self.storage_service.contains_key(JOB_RECORDS_KEY) {
+            self.storage_service.update_data(JOB_RECORDS_KEY, &data, JOB_RECORDS_KEY, true, storage::SYSTEM_PRINCIPAL)?;
+        } else {
+            let options = storage::StoreOptions { compress: true, pinned: true, ..storage::StoreOptions::default() };
+            self.storage_service.store_data(JOB_RECORDS_KEY, &data, JOB_RECORDS_KEY, storage::SYSTEM_PRINCIPAL, options)?;
+        }
+        info!("Persisted job records");
+        Ok(())
+    }
+    
+    /// Reload the job records saved by the last shutdown, if any
+    fn restore_job_records(&self) -> Result<()> {
+        if !self.storage_service.contains_key(JOB_RECORDS_KEY) {
+            return Ok(());
+        }
+        let data = self.storage_service.retrieve_data(JOB_RECORDS_KEY, JOB_RECORDS_KEY, storage::SYSTEM_PRINCIPAL)?;
+        let mut records: serde_json::Value = serde_json::from_slice(&data)?;
+        
+        let computation = serde_json::from_value(records["computation"].take())
+            .map_err(|e| anyhow::anyhow!("Invalid computation job records: {}", e))?;
+        let restored = self.computation_service.import_jobs(computation);
+        let restored_training = match &self.ai_service {
+            Some(ai) => ai.import_training_jobs(records["training"].take())?,
+            None => 0,
+        };
+        info!("Restored {} computation and {} training job records", restored, restored_training);
+        Ok(())
+    }
+    
     // Getter methods for services
     pub fn crypto_service(&self) -> &Arc<CryptoService> {
         &self.crypto_service
@@ -201,6 +1170,10 @@ impl EncaveRuntime {
         &self.storage_service
     }
     
+    pub fn audit_log(&self) -> &Arc<AuditLog> {
+        &self.audit_log
+    }
+    
     pub fn oracle_service(&self) -> Option<&Arc<OracleService>> {
         self.oracle_service.as_ref()
     }
@@ -216,26 +1189,180 @@ impl EncaveRuntime {
     pub fn account_service(&self) -> &Arc<AccountService> {
         &self.account_service
     }
+    
+    pub fn attestation_service(&self) -> &Arc<AttestationService> {
+        &self.attestation_service
+    }
+    
+    pub fn sealing_service(&self) -> &Arc<SealingService> {
+        self.storage_service.sealing_service()
+    }
+    
+    /// Start an oracle fetch on the runtime's executor and return a handle for
+    /// `poll_oracle_fetch`
+    pub fn spawn_oracle_fetch(
+        &self,
+        url: String,
+        headers: Option<std::collections::HashMap<String, String>>,
+        processing_script: Option<String>,
+    ) -> Result<u64> {
+        let oracle = self.oracle_service.clone()
+            .ok_or_else(|| anyhow::anyhow!("Oracle service is disabled"))?;
+        let (handle, sender) = self.pending_fetches.register()?;
+        
+        let task = self.tokio_runtime.spawn(async move {
+            let result = oracle.fetch_data(&url, headers, processing_script.as_deref()).await
+                .map_err(|e| e.to_string());
+            // The receiver is gone if the caller released the handle early
+            let _ = sender.send(result);
+        });
+        self.pending_fetches.track(handle, task.abort_handle());
+        
+        Ok(handle)
+    }
+    
+    /// State of a fetch started with `spawn_oracle_fetch`; the outcome is kept until released
+    pub fn poll_oracle_fetch(&self, handle: u64) -> Result<FetchPoll> {
+        self.pending_fetches.poll(handle)
+    }
+    
+    /// Drop a fetch handle and its outcome
+    pub fn release_oracle_fetch(&self, handle: u64) -> Result<()> {
+        self.pending_fetches.release(handle)
+    }
+    
+    /// Stop a fetch that is still running and drop its handle
+    pub fn cancel_oracle_fetch(&self, handle: u64) -> Result<()> {
+        self.pending_fetches.cancel(handle)
+    }
+    
+    /// Run a serialized request with [`call`](Self::call) and hold its response for
+    /// `read_result`, returning the handle, the response length and the call's code
+    pub fn open_result(&self, request: &str) -> Result<(u64, usize, c_int)> {
+        let (response, code) = self.call(request);
+        let len = response.len();
+        Ok((self.open_results.open(response)?, len, code))
+    }
+    
+    /// Copy part of an open result into `buffer`, returning the number of bytes copied
+    pub fn read_result(&self, handle: u64, offset: usize, buffer: &mut [u8]) -> Result<usize> {
+        self.open_results.read(handle, offset, buffer)
+    }
+    
+    /// Drop an open result
+    pub fn close_result(&self, handle: u64) -> Result<()> {
+        self.open_results.close(handle)
+    }
+    
+    /// Register a recurring oracle fetch refreshed on the runtime's executor
+    pub fn subscribe_oracle(
+        &self,
+        url: &str,
+        interval: std::time::Duration,
+        processing_script: Option<&str>,
+    ) -> Result<String> {
+        let oracle = self.oracle_service.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Oracle service is disabled"))?;
+        let _guard = self.tokio_runtime.enter();
+        oracle.subscribe(url, interval, processing_script)
+    }
+    
+    /// Latest value and staleness of an oracle subscription as JSON
+    pub fn oracle_subscription_latest(&self, subscription_id: &str) -> Result<String> {
+        self.oracle_service.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Oracle service is disabled"))?
+            .get_latest(subscription_id)
+    }
+    
+    /// Stop an oracle subscription
+    pub fn unsubscribe_oracle(&self, subscription_id: &str) -> Result<()> {
+        self.oracle_service.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Oracle service is disabled"))?
+            .unsubscribe(subscription_id)
+    }
+    
+    /// Checks dispatched calls against the configured auth policy
+    pub fn authorizer(&self) -> &Authorizer {
+        &self.authorizer
+    }
+    
+    /// Dedicated FFI exports carry no auth token, so they are refused while an auth policy
+    /// is configured; callers then go through `occlum_call`, which checks the policy
+    pub fn check_direct_export(&self) -> Result<()> {
+        if self.authorizer.is_enabled() {
+            return Err(error::EnclaveError::PermissionDenied(
+                "Dedicated exports are disabled while an auth policy is configured; use occlum_call".into()
+            ).into());
+        }
+        Ok(())
+    }
+    
+    /// Handle to the runtime's executor for work that must outlive a single FFI call
+    pub fn tokio_handle(&self) -> tokio::runtime::Handle {
+        self.tokio_runtime.handle().clone()
+    }
 }
 
-// Global runtime instance for C FFI
-use std::sync::OnceLock;
-static RUNTIME: OnceLock<Arc<Mutex<EncaveRuntime>>> = OnceLock::new();
+// Global runtime instance for C FFI. The slot is emptied on destroy so the
+// enclave can be re-initialized afterwards.
+static RUNTIME: RwLock<Option<Arc<Mutex<EncaveRuntime>>>> = RwLock::new(None);
+
+/// Clone the current runtime handle, if initialized.
+fn current_runtime() -> Option<Arc<Mutex<EncaveRuntime>>> {
+    match RUNTIME.read() {
+        Ok(slot) => slot.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
 
 /// Initialize the Occlum enclave runtime.
 #[no_mangle]
 pub extern "C" fn occlum_init() -> c_int {
     std::panic::catch_unwind(|| {
-        let config = EncaveConfig::default();
+        if current_runtime().is_some() {
+            warn!("Runtime already initialized");
+            return 0;
+        }
         
-        let rt = tokio::runtime::Runtime::new().unwrap();
+        // Layer defaults, the optional config file, and NSL_* environment overrides
+        let config_file = std::env::var("NSL_CONFIG_FILE").ok();
+        let config = match EncaveConfig::load(config_file.as_deref().map(Path::new)) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Invalid enclave configuration: {}", e);
+                return -1;
+            }
+        };
+        
+        // Construction runs on this host thread, one of RESERVED_ENCLAVE_THREADS, so the
+        // only pool threads alive are those of the runtime it builds to config limits
+        let rt = match tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                error!("Failed to create the enclave construction runtime: {}", e);
+                return -1;
+            }
+        };
         let runtime = rt.block_on(async {
             EncaveRuntime::new(config).await
         });
         
         match runtime {
             Ok(rt) => {
-                let _ = RUNTIME.set(Arc::new(Mutex::new(rt)));
+                let mut slot = match RUNTIME.write() {
+                    Ok(slot) => slot,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                if slot.is_some() {
+                    // Lost a race with a concurrent init; keep the existing runtime
+                    warn!("Runtime already initialized");
+                } else {
+                    *slot = Some(Arc::new(Mutex::new(rt)));
+                }
                 0 // Success
             }
             Err(e) => {
@@ -250,19 +1377,26 @@ pub extern "C" fn occlum_init() -> c_int {
 #[no_mangle]
 pub extern "C" fn occlum_destroy() -> c_int {
     std::panic::catch_unwind(|| {
-        if let Some(runtime) = RUNTIME.get() {
-            // Properly shutdown the runtime and all services
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                if let Ok(mut runtime_guard) = runtime.lock() {
-                    // Shutdown all services gracefully
-                    if let Err(e) = runtime_guard.shutdown().await {
-                        error!("Error during runtime shutdown: {}", e);
-                    }
-                }
-            });
+        let taken = match RUNTIME.write() {
+            Ok(mut slot) => slot.take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        
+        if let Some(runtime) = taken {
+            // Properly shutdown the runtime and all services. The guard is only held by
+            // this blocking call, never across an await point.
+            let mut runtime_guard = match runtime.lock() {
+                Ok(guard) => guard,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let handle = runtime_guard.tokio_handle();
+            if let Err(e) = handle.block_on(runtime_guard.shutdown()) {
+                error!("Error during runtime shutdown: {}", e);
+            }
+            drop(runtime_guard);
             
-            // Runtime will be dropped when program exits
+            // The slot is now empty, so the runtime is dropped here and a
+            // subsequent occlum_init can start a fresh one
             info!("Enclave runtime destroyed successfully");
             0 // Success
         } else {
@@ -275,37 +1409,203 @@ pub extern "C" fn occlum_destroy() -> c_int {
     })
 }
 
-/// Helper function to safely get runtime reference.
-#[allow(dead_code)]
+/// Run a health check across all enclave services and write the JSON report.
+#[no_mangle]
+pub extern "C" fn occlum_health_check(
+    result: *mut std::os::raw::c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    let mut write_status = 0;
+    let status = with_runtime(|runtime| {
+        let report = serde_json::to_string(&runtime.health_check())?;
+        write_status = unsafe { write_result_to_buffer(&report, result, result_size, actual_size) };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Write a JSON snapshot of all service metrics.
+#[no_mangle]
+pub extern "C" fn occlum_get_metrics(
+    result: *mut std::os::raw::c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    let mut write_status = 0;
+    let status = with_runtime(|runtime| {
+        let snapshot = runtime.metrics_snapshot()?;
+        write_status = unsafe { write_result_to_buffer(&snapshot, result, result_size, actual_size) };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        write_status
+    }
+}
+
+/// Call a service method by name through [`EncaveRuntime::dispatch`].
+///
+/// `request` is a JSON object `{"method": "...", "params": {...}, "token": "...", "id": ...}`,
+/// where `token` authenticates the caller when an auth policy is configured; the JSON
+/// response written to `result` carries either `result` or `error` with the stable code
+/// and message, and the same code is returned.
+#[no_mangle]
+pub extern "C" fn occlum_call(
+    request: *const std::os::raw::c_char,
+    result: *mut std::os::raw::c_char,
+    result_size: usize,
+    actual_size: *mut usize,
+) -> c_int {
+    if request.is_null() {
+        return error::ENCLAVE_ERROR_INVALID_INPUT;
+    }
+    
+    let mut write_status = 0;
+    let mut call_status = 0;
+    let status = with_dispatch_runtime(|runtime| {
+        let request = unsafe { c_str_to_string(request)? };
+        let (response, code) = runtime.call(&request);
+        call_status = code;
+        write_status = unsafe { write_result_to_buffer(&response, result, result_size, actual_size) };
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else if write_status != 0 {
+        write_status
+    } else {
+        call_status
+    }
+}
+
+/// Run a request like [`occlum_call`] and keep the response for reading in chunks.
+///
+/// `*handle` receives the handle for [`occlum_result_read`] and `*total_size` the
+/// response length in bytes. The response is held until [`occlum_result_close`], even
+/// when the call itself failed, or until it has gone unread for five minutes. The call's
+/// code is returned as from `occlum_call`.
+#[no_mangle]
+pub extern "C" fn occlum_result_open(
+    request: *const std::os::raw::c_char,
+    handle: *mut u64,
+    total_size: *mut usize,
+) -> c_int {
+    if request.is_null() || handle.is_null() || total_size.is_null() {
+        return error::ENCLAVE_ERROR_INVALID_INPUT;
+    }
+    
+    let mut call_status = 0;
+    let status = with_dispatch_runtime(|runtime| {
+        let request = unsafe { c_str_to_string(request)? };
+        let (opened, len, code) = runtime.open_result(&request)?;
+        unsafe {
+            *handle = opened;
+            *total_size = len;
+        }
+        call_status = code;
+        Ok(())
+    });
+    
+    if status != 0 {
+        status
+    } else {
+        call_status
+    }
+}
+
+/// Copy up to `buffer_size` bytes of an open result, starting at `offset`.
+///
+/// The bytes are not nul-terminated and a chunk may end inside a UTF-8 sequence, so
+/// the host should join chunks before decoding. `*written` is 0 once `offset` reaches
+/// `total_size`.
+#[no_mangle]
+pub extern "C" fn occlum_result_read(
+    handle: u64,
+    offset: usize,
+    buffer: *mut u8,
+    buffer_size: usize,
+    written: *mut usize,
+) -> c_int {
+    if buffer.is_null() || written.is_null() {
+        return error::ENCLAVE_ERROR_INVALID_INPUT;
+    }
+    
+    with_dispatch_runtime(|runtime| {
+        let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, buffer_size) };
+        let copied = runtime.read_result(handle, offset, buffer)?;
+        unsafe { *written = copied };
+        Ok(())
+    })
+}
+
+/// Release a result opened with [`occlum_result_open`].
+#[no_mangle]
+pub extern "C" fn occlum_result_close(handle: u64) -> c_int {
+    with_dispatch_runtime(|runtime| runtime.close_result(handle))
+}
+
+/// Helper function to safely get runtime reference for a dedicated export.
+///
+/// Failures are reported with the stable code from [`error::error_code`]; the export is
+/// refused while an auth policy is configured, see [`EncaveRuntime::check_direct_export`].
 fn with_runtime<F, R>(f: F) -> c_int 
 where
-    F: FnOnce(&EncaveRuntime) -> Result<R, Box<dyn std::error::Error>>,
+    F: FnOnce(&EncaveRuntime) -> Result<R>,
 {
-    if let Some(runtime_arc) = RUNTIME.get() {
-            match runtime_arc.lock() {
-                Ok(runtime) => {
-                    match f(&*runtime) {
-                        Ok(_) => 0,
-                        Err(e) => {
-                            error!("Runtime operation failed: {}", e);
-                            -1
-                        }
+    with_dispatch_runtime(|runtime| {
+        runtime.check_direct_export()?;
+        f(runtime)
+    })
+}
+
+/// Like [`with_runtime`], for the exports that authorize each request themselves
+fn with_dispatch_runtime<F, R>(f: F) -> c_int 
+where
+    F: FnOnce(&EncaveRuntime) -> Result<R>,
+{
+    if let Some(runtime_arc) = current_runtime() {
+        match runtime_arc.lock() {
+            Ok(runtime) => {
+                match f(&*runtime) {
+                    Ok(_) => 0,
+                    Err(e) => {
+                        error!("Runtime operation failed: {}", e);
+                        error::error_code(&e)
                     }
                 }
-                Err(e) => {
-                    error!("Failed to acquire runtime lock: {}", e);
-                    -2
-                }
             }
+            Err(e) => {
+                error!("Failed to acquire runtime lock: {}", e);
+                -2
+            }
+        }
     } else {
         error!("Runtime not initialized");
         -3
     }
 }
 
+/// Whether a dedicated export that works without the runtime must be refused, because
+/// the runtime has an auth policy configured
+fn direct_export_denied() -> bool {
+    current_runtime().is_some_and(|runtime| match runtime.lock() {
+        Ok(runtime) => runtime.check_direct_export().is_err(),
+        Err(poisoned) => poisoned.into_inner().check_direct_export().is_err(),
+    })
+}
+
 /// Helper function to convert C string to Rust string.
 #[allow(dead_code)]
-unsafe fn c_str_to_string(ptr: *const std::os::raw::c_char) -> Result<String, Box<dyn std::error::Error>> {
+unsafe fn c_str_to_string(ptr: *const std::os::raw::c_char) -> Result<String> {
     use std::ffi::CStr;
     if ptr.is_null() {
         return Ok(String::new());
@@ -314,8 +1614,18 @@ unsafe fn c_str_to_string(ptr: *const std::os::raw::c_char) -> Result<String, Bo
     Ok(c_str.to_str()?.to_string())
 }
 
-/// Helper function to write result to C buffer.
-#[allow(dead_code)]
+/// Returned by [`write_result_to_buffer`] when the result does not fit
+pub const FFI_BUFFER_TOO_SMALL: c_int = 1;
+
+/// Write a string result to a C buffer, all or nothing.
+///
+/// This is the contract for every FFI function that returns a string:
+/// - `*actual_size` (when non-null) is set to the result length in bytes, excluding the
+///   nul terminator, so a retry needs a buffer of at least `actual_size + 1` bytes.
+/// - When the buffer is smaller than that, none of the result is written: the buffer
+///   is set to the empty string and [`FFI_BUFFER_TOO_SMALL`] is returned, so a caller
+///   that ignores the status can never parse a truncated JSON document.
+/// - Otherwise the whole result and its terminator are written and 0 is returned.
 unsafe fn write_result_to_buffer(
     result: &str,
     buffer: *mut std::os::raw::c_char,
@@ -324,22 +1634,21 @@ unsafe fn write_result_to_buffer(
 ) -> c_int {
     use std::ptr;
     let result_bytes = result.as_bytes();
-    let write_size = std::cmp::min(result_bytes.len(), buffer_size.saturating_sub(1));
-    
-    if !buffer.is_null() && buffer_size > 0 {
-        ptr::copy_nonoverlapping(result_bytes.as_ptr(), buffer as *mut u8, write_size);
-        *(buffer.offset(write_size as isize)) = 0; // Null terminator
-    }
     
     if !actual_size.is_null() {
         *actual_size = result_bytes.len();
     }
     
-    if result_bytes.len() > buffer_size.saturating_sub(1) {
-        1 // Buffer too small
-    } else {
-        0 // Success
+    if buffer.is_null() || buffer_size <= result_bytes.len() {
+        if !buffer.is_null() && buffer_size > 0 {
+            *buffer = 0;
+        }
+        return FFI_BUFFER_TOO_SMALL;
     }
+    
+    ptr::copy_nonoverlapping(result_bytes.as_ptr(), buffer as *mut u8, result_bytes.len());
+    *buffer.add(result_bytes.len()) = 0;
+    0
 }
 
 // Export the C FFI functions
@@ -350,6 +1659,8 @@ mod ffi_oracle;
 mod ffi_computation;
 mod ffi_ai;
 mod ffi_account;
+mod ffi_attestation;
+mod ffi_sealing;
 
 // Re-export FFI functions
 pub use ffi_crypto::*;
@@ -357,4 +1668,48 @@ pub use ffi_storage::*;
 pub use ffi_oracle::*;
 pub use ffi_computation::*;
 pub use ffi_ai::*;
-pub use ffi_account::*; 
\ No newline at end of file
+pub use ffi_account::*;
+pub use ffi_attestation::*;
+pub use ffi_sealing::*; 
+
+#[cfg(test)]
+mod test_support;
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{runtime, test_config};
+
+    #[test]
+    fn job_records_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = runtime(test_config(dir.path()));
+        let handle = first.tokio_handle();
+        handle.block_on(first.computation_service().execute_computation("price", "return 1", "{}")).unwrap();
+        handle.block_on(first.shutdown()).unwrap();
+        drop(first);
+
+        let second = runtime(test_config(dir.path()));
+        let jobs: serde_json::Value = serde_json::from_str(&second.computation_service().list_jobs(None, None).unwrap()).unwrap();
+        assert_eq!(jobs["total"], 1);
+        assert!(jobs["items"][0]["id"].as_str().unwrap().starts_with("price_"));
+        assert_eq!(jobs["items"][0]["status"], "Completed");
+    }
+
+    #[test]
+    fn thread_limit_counts_the_training_pool() {
+        let mut config = crate::EncaveConfig {
+            max_threads: 16,
+            max_blocking_threads: 8,
+            enclave_thread_limit: 32,
+            ..crate::EncaveConfig::default()
+        };
+        assert!(config.check_thread_limit().is_ok());
+
+        config.ai_training_threads = 6;
+        assert!(config.check_thread_limit().is_ok());
+        config.ai_training_threads = 7;
+        let error = config.check_thread_limit().unwrap_err();
+        assert!(error.contains("7 training threads"), "{}", error);
+        assert!(config.validate().is_err());
+    }
+}
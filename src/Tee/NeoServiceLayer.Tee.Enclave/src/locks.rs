@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use log::warn;
+
+/// Poisoned locks recovered since startup, by the name of the data they guard
+static RECOVERIES: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+/// Note that the lock guarding `what` was poisoned by a panic and has been taken over
+pub fn record_recovery(what: &'static str) {
+    warn!("Recovered lock on {} poisoned by a panicking thread", what);
+    *RECOVERIES.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(what)
+        .or_default() += 1;
+}
+
+/// Recovery counts per guarded type; non-empty means some operation panicked mid-update
+/// and a restart is advisable
+pub fn recoveries() -> BTreeMap<&'static str, u64> {
+    RECOVERIES.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// Lock access that survives poisoning
+///
+/// A panic while holding a std lock poisons it, and every later `read`/`write` fails.
+/// These take the lock anyway, clear the poison so the next caller is not affected and
+/// record the event for the health check. Use them for data whose invariants hold between
+/// individual statements (maps of independent entries and the like); data that a panic
+/// can leave half updated needs rebuilding instead, as the storage index does.
+pub trait RwLockExt<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T>;
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RwLockExt<T> for RwLock<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|poisoned| {
+            self.clear_poison();
+            record_recovery(std::any::type_name::<T>());
+            poisoned.into_inner()
+        })
+    }
+
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|poisoned| {
+            self.clear_poison();
+            record_recovery(std::any::type_name::<T>());
+            poisoned.into_inner()
+        })
+    }
+}
+
+/// `Mutex` counterpart of [`RwLockExt`]
+pub trait MutexExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> MutexExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned| {
+            self.clear_poison();
+            record_recovery(std::any::type_name::<T>());
+            poisoned.into_inner()
+        })
+    }
+}
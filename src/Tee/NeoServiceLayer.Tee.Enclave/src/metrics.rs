@@ -0,0 +1,110 @@
+use serde::{Serialize, Serializer};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Monotonic counter that can be shared across threads and serialized as a plain number
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// Increment by one, returning the previous value
+    pub fn incr(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Clone for Counter {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.get()))
+    }
+}
+
+impl Serialize for Counter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.get())
+    }
+}
+
+/// Crypto service counters
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CryptoMetrics {
+    pub keys_generated: Counter,
+    pub signatures_created: Counter,
+    pub verifications: Counter,
+    pub verification_failures: Counter,
+    pub encryptions: Counter,
+    pub decryptions: Counter,
+}
+
+/// Storage service counters
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct StorageMetrics {
+    pub writes: Counter,
+    pub reads: Counter,
+    pub deletes: Counter,
+    pub bytes_written: Counter,
+    pub bytes_read: Counter,
+    /// Entries removed to make room under the storage quota
+    pub evictions: Counter,
+}
+
+/// Oracle service counters
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct OracleMetrics {
+    pub requests: Counter,
+    pub failures: Counter,
+    /// Requests answered from the response cache without contacting the source
+    pub cache_hits: Counter,
+}
+
+/// Computation service counters
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ComputationMetrics {
+    pub javascript_executions: Counter,
+    pub jobs_completed: Counter,
+    pub jobs_failed: Counter,
+    pub jobs_cancelled: Counter,
+    pub jobs_rejected: Counter,
+    /// Executions answered from the result cache
+    pub cache_hits: Counter,
+    /// Cacheable executions that had to run
+    pub cache_misses: Counter,
+}
+
+/// Point-in-time occupancy of the computation worker pool
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct WorkerPoolMetrics {
+    pub capacity: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub queue_depth: usize,
+}
+
+/// AI service counters
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct AIMetrics {
+    pub models_trained: Counter,
+    pub inferences: Counter,
+    /// Predictions refused because the input anomaly score passed the reject threshold
+    pub anomalous_inputs_rejected: Counter,
+}
+
+/// Point-in-time snapshot of every service's counters
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub timestamp: u64,
+    pub uptime_seconds: u64,
+    pub crypto: CryptoMetrics,
+    pub storage: StorageMetrics,
+    pub oracle: Option<OracleMetrics>,
+    pub computation: ComputationMetrics,
+    pub computation_pool: WorkerPoolMetrics,
+    pub ai: Option<AIMetrics>,
+}
@@ -1,34 +1,148 @@
 use anyhow::{Result, anyhow};
-use reqwest::{Client, header::{HeaderMap, HeaderName, HeaderValue}, Method};
+use reqwest::{Client, ClientBuilder, header::{HeaderMap, HeaderName, HeaderValue}, Method};
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use hyper::client::connect::dns::Name;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ServerName};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::time::timeout;
 use log::{info, warn, error, debug};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
 use crate::EncaveConfig;
+use crate::canonical::canonicalize;
+use crate::crypto::{CryptoAlgorithm, CryptoService};
+use crate::error::EnclaveError;
+use crate::health::ServiceHealth;
+use crate::metrics::OracleMetrics;
+use crate::redact::{redact, redact_url};
+use crate::locks::{MutexExt, RwLockExt};
 
 /// Oracle service for secure external data fetching with production HTTP client
 pub struct OracleService {
     client: Client,
     timeout_duration: Duration,
+    max_timeout_duration: Duration,
     allowed_domains: Vec<String>,
-    request_count: std::sync::atomic::AtomicU64,
+    metrics: OracleMetrics,
     response_cache: Arc<RwLock<HashMap<String, CachedResponse>>>,
     rate_limiter: Arc<RwLock<HashMap<String, RateLimitInfo>>>,
     max_response_size: usize,
     ssl_verification: bool,
+    /// Domains with TLS pins in `oracle_pinned_keys`
+    pinned_domains: usize,
+    resolver: PinnedResolver,
+    crypto_service: Arc<CryptoService>,
+    signing_key_id: String,
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+    max_subscriptions: usize,
 }
 
-/// Cached response structure for performance optimization
+/// Oracle payload signed by the enclave oracle key.
+///
+/// The signature is ECDSA secp256r1 over
+/// `SHA256(url || timestamp as 8-byte big-endian || payload)`. A JSON payload is signed in
+/// canonical form (see [`crate::canonical`]), so a verifier that parsed and re-encoded it
+/// can recover the signed bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedOracleResponse {
+    pub url: String,
+    pub timestamp: u64,
+    pub payload: String,
+    /// Hex-encoded 64-byte r || s signature
+    pub signature: String,
+    /// Hex-encoded compressed public key of the signing key
+    pub public_key: String,
+    pub key_id: String,
+    pub signature_scheme: String,
+}
+
+impl SignedOracleResponse {
+    /// Bytes covered by the signature
+    pub fn signed_message(url: &str, timestamp: u64, payload: &str) -> Vec<u8> {
+        let mut message = Vec::with_capacity(url.len() + 8 + payload.len());
+        message.extend_from_slice(url.as_bytes());
+        message.extend_from_slice(&timestamp.to_be_bytes());
+        message.extend_from_slice(payload.as_bytes());
+        message
+    }
+}
+
+/// Response body kept for as long as the source's `Cache-Control: max-age` allows
 #[derive(Debug, Clone)]
 struct CachedResponse {
-    data: String,
-    timestamp: u64,
-    ttl_seconds: u64,
-    etag: Option<String>,
-    cache_control: Option<String>,
+    body: String,
+    content_type: Option<String>,
+    stored_at: Instant,
+    ttl: Duration,
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        self.stored_at.elapsed() < self.ttl
+    }
+}
+
+/// Most responses held in the cache at once; further cacheable responses are not stored
+/// until entries expire
+const MAX_CACHED_RESPONSES: usize = 256;
+
+/// Ceiling on how long a response is cached, whatever `max-age` the source sends
+const MAX_CACHED_RESPONSE_TTL: Duration = Duration::from_secs(300);
+
+/// How long a response may be cached according to its `Cache-Control` header; `None` when
+/// the header is missing, forbids caching or has no positive `max-age`
+fn cache_ttl(cache_control: &str) -> Option<Duration> {
+    let mut max_age = None;
+    for directive in cache_control.split(',').map(|directive| directive.trim().to_ascii_lowercase()) {
+        match directive.as_str() {
+            "no-store" | "no-cache" | "private" => return None,
+            _ => {
+                if let Some(seconds) = directive.strip_prefix("max-age=") {
+                    max_age = seconds.trim_matches('"').parse::<u64>().ok();
+                }
+            }
+        }
+    }
+    max_age.filter(|&seconds| seconds > 0)
+        .map(|seconds| Duration::from_secs(seconds).min(MAX_CACHED_RESPONSE_TTL))
+}
+
+/// Shortest refresh interval a subscription may use
+pub const MIN_SUBSCRIPTION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A recurring fetch registered with `OracleService::subscribe`
+struct Subscription {
+    url: String,
+    interval: Duration,
+    processing_script: Option<String>,
+    /// Last successfully fetched and processed value; kept when later fetches fail
+    latest: Option<String>,
+    updated_at: Option<u64>,
+    last_attempt_at: Option<u64>,
+    last_error: Option<String>,
+    consecutive_failures: u64,
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    /// A value is stale when the last refresh failed, none has succeeded yet, or the last
+    /// success is older than two intervals
+    fn is_stale(&self, now: u64) -> bool {
+        match self.updated_at {
+            Some(updated_at) => {
+                self.last_error.is_some() || now.saturating_sub(updated_at) > 2 * self.interval.as_secs().max(1)
+            }
+            None => true,
+        }
+    }
 }
 
 /// Rate limiting information per domain
@@ -40,14 +154,507 @@ struct RateLimitInfo {
     last_request: u64,
 }
 
+/// DNS resolver that vets every resolved address before the HTTP client connects
+///
+/// The client connects only to the addresses returned here, so the address that was
+/// checked is the address that is used: a rebinding DNS server cannot swap in an internal
+/// IP between validation and connection. Redirect targets resolve through it as well.
+#[derive(Debug, Clone, Copy)]
+pub struct PinnedResolver {
+    allow_private_hosts: bool,
+}
+
+impl PinnedResolver {
+    pub fn new(allow_private_hosts: bool) -> Self {
+        Self { allow_private_hosts }
+    }
+    
+    /// Resolve `host`, failing if any address is non-public while private hosts are disallowed
+    ///
+    /// One bad address rejects the whole host rather than being filtered out, since a
+    /// mixed answer is itself a sign of rebinding.
+    pub async fn resolve_host(&self, host: &str) -> Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await
+            .map_err(|e| anyhow!("Failed to resolve '{}': {}", host, e))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(anyhow!("Host '{}' did not resolve to any address", host));
+        }
+        
+        if !self.allow_private_hosts {
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+                return Err(anyhow!("Host '{}' resolves to non-public address {}", host, addr.ip()));
+            }
+        }
+        
+        Ok(addrs)
+    }
+}
+
+impl Resolve for PinnedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = *self;
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Addrs = Box::new(resolver.resolve_host(&host).await?.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether `ip` is routable on the public internet, i.e. not loopback, private,
+/// link-local, site-local, carrier-grade NAT, benchmarking, reserved, unspecified,
+/// broadcast or multicast. IPv6 forms that embed an IPv4 address (mapped, compatible,
+/// NAT64 and 6to4) are judged by the embedded address.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || octets[0] == 0
+                // 100.64.0.0/10 shared address space
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+                // 192.0.0.0/24 IETF protocol assignments
+                || (octets[0] == 192 && octets[1] == 0 && octets[2] == 0)
+                // 198.18.0.0/15 benchmarking
+                || (octets[0] == 198 && (octets[1] & 0xfe) == 18)
+                // 240.0.0.0/4 reserved
+                || (octets[0] & 0xf0) == 240)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = embedded_ipv4(&v6) {
+                return is_public_address(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 link-local
+                || (first & 0xffc0) == 0xfe80
+                // fec0::/10 deprecated site-local
+                || (first & 0xffc0) == 0xfec0)
+        }
+    }
+}
+
+/// The IPv4 address an IPv6 address leads to: `::ffff:a.b.c.d` (mapped), `::a.b.c.d`
+/// (compatible), `64:ff9b::a.b.c.d` (NAT64) or `2002:aabb:ccdd::/48` (6to4)
+fn embedded_ipv4(v6: &std::net::Ipv6Addr) -> Option<std::net::Ipv4Addr> {
+    let segments = v6.segments();
+    let octets = v6.octets();
+    if v6.is_loopback() || v6.is_unspecified() {
+        return None;
+    }
+    if let Some(v4) = v6.to_ipv4() {
+        return Some(v4);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        return Some(std::net::Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15]));
+    }
+    if segments[0] == 0x2002 {
+        return Some(std::net::Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5]));
+    }
+    None
+}
+
+/// Whether a header carries credentials, so its value is marked sensitive and not logged
+fn is_sensitive_header(name: &HeaderName) -> bool {
+    let name = name.as_str();
+    matches!(name, "authorization" | "proxy-authorization" | "cookie")
+        || ["key", "token", "secret", "auth", "password"].iter().any(|word| name.contains(word))
+}
+
+/// Client-wide headers from `oracle_default_headers`; a request setting the same header
+/// replaces the default
+fn default_headers(configured: &BTreeMap<String, String>) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in configured {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| EnclaveError::InvalidInput(format!("Invalid oracle header name '{}'", name)))?;
+        let mut header_value = HeaderValue::from_str(value)
+            .map_err(|_| EnclaveError::InvalidInput(format!("Invalid value for oracle header '{}'", name)))?;
+        if is_sensitive_header(&name) {
+            header_value.set_sensitive(true);
+            info!("Oracle default header {}: {}", name, redact(value));
+        } else {
+            info!("Oracle default header {}: {}", name, value);
+        }
+        headers.insert(name, header_value);
+    }
+    Ok(headers)
+}
+
+/// A pinned oracle certificate or public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertificatePin {
+    /// SHA-256 of the server certificate's DER SubjectPublicKeyInfo, "spki-sha256:<hex>";
+    /// survives renewal of a certificate for the same key
+    Spki([u8; 32]),
+    /// SHA-256 of the whole DER server certificate, "cert-sha256:<hex>"
+    Certificate([u8; 32]),
+    /// SHA-256 of the SubjectPublicKeyInfo of a CA certificate in the presented chain,
+    /// "ca-spki-sha256:<hex>". Matches only if the server certificate verifies up to that
+    /// CA, and needs `oracle_ssl_verification`.
+    CaSpki([u8; 32]),
+}
+
+impl CertificatePin {
+    pub fn parse(pin: &str) -> Result<Self> {
+        let (kind, digest) = pin.split_once(':')
+            .ok_or_else(|| anyhow!("pin '{}' must look like spki-sha256:<hex>, cert-sha256:<hex> or ca-spki-sha256:<hex>", pin))?;
+        let pin_type: fn([u8; 32]) -> Self = match kind.trim() {
+            "spki-sha256" => Self::Spki,
+            "cert-sha256" => Self::Certificate,
+            "ca-spki-sha256" => Self::CaSpki,
+            other => return Err(anyhow!("unknown pin type '{}', expected spki-sha256, cert-sha256 or ca-spki-sha256", other)),
+        };
+        let digest: [u8; 32] = hex::decode(digest.trim()).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow!("pin '{}' must carry a 64-digit hex SHA-256 digest", pin))?;
+        Ok(pin_type(digest))
+    }
+    
+    /// Whether the pin names the server certificate itself rather than an issuing CA
+    pub fn is_end_entity(&self) -> bool {
+        !matches!(self, Self::CaSpki(_))
+    }
+    
+    /// Whether an end-entity pin matches the server certificate; CA pins never do
+    fn matches_end_entity(&self, certificate: &[u8]) -> bool {
+        match self {
+            Self::Spki(digest) => spki_digest_is(certificate, digest),
+            Self::Certificate(digest) => *digest == <[u8; 32]>::from(Sha256::digest(certificate)),
+            Self::CaSpki(_) => false,
+        }
+    }
+}
+
+fn spki_digest_is(certificate: &[u8], digest: &[u8; 32]) -> bool {
+    certificate_spki(certificate).is_some_and(|spki| *digest == <[u8; 32]>::from(Sha256::digest(spki)))
+}
+
+/// Split the DER element at the front of `input` into (tag, whole element, contents, rest)
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8], &[u8])> {
+    let tag = *input.first()?;
+    let first = *input.get(1)? as usize;
+    let (header, length) = if first < 0x80 {
+        (2, first)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let length = input.get(2..2 + count)?.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (2 + count, length)
+    };
+    let end = header.checked_add(length)?;
+    let element = input.get(..end)?;
+    Some((tag, element, &element[header..], &input[end..]))
+}
+
+/// The DER SubjectPublicKeyInfo of an X.509 certificate
+fn certificate_spki(certificate: &[u8]) -> Option<&[u8]> {
+    let (_, _, certificate, _) = der_element(certificate)?;
+    let (_, _, mut tbs, _) = der_element(certificate)?;
+    // Optional explicit [0] version, then serial number, signature algorithm, issuer,
+    // validity and subject precede the key
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_element(tbs)?.3;
+    }
+    for _ in 0..5 {
+        tbs = der_element(tbs)?.3;
+    }
+    let (tag, spki, _, _) = der_element(tbs)?;
+    (tag == 0x30).then_some(spki)
+}
+
+/// Certificate verifier enforcing `oracle_pinned_keys` on top of (optional) web PKI checks
+///
+/// A pinned domain's chain is accepted only if the server certificate matches an
+/// end-entity pin, or verifies up to a presented CA certificate matching a CA pin. Merely
+/// presenting a pinned CA certificate is not enough, since anyone can append one.
+struct PinningVerifier {
+    /// `None` when `oracle_ssl_verification` is off
+    webpki: Option<WebPkiVerifier>,
+    pins: Vec<(String, Vec<CertificatePin>)>,
+}
+
+impl PinningVerifier {
+    /// Pins of the most specific configured domain covering `host`
+    fn pins_for(&self, host: &str) -> Option<&[CertificatePin]> {
+        self.pins.iter()
+            .filter(|(domain, _)| host == domain || host.ends_with(&format!(".{}", domain)))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, pins)| pins.as_slice())
+    }
+    
+    /// Whether `end_entity` verifies up to one of the `intermediates` whose key matches `digest`
+    fn chains_to_pinned_ca(
+        digest: &[u8; 32],
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> bool {
+        intermediates.iter()
+            .filter(|ca| spki_digest_is(&ca.0, digest))
+            .any(|ca| {
+                // Trusting only the pinned CA makes path building prove it issued the chain
+                let mut roots = rustls::RootCertStore::empty();
+                roots.add(ca).is_ok()
+                    && WebPkiVerifier::new(roots, None)
+                        .verify_server_cert(end_entity, intermediates, server_name, &mut std::iter::empty(), ocsp_response, now)
+                        .is_ok()
+            })
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        if let Some(webpki) = &self.webpki {
+            webpki.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        }
+        
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+            ServerName::IpAddress(ip) => ip.to_string(),
+            _ => String::new(),
+        };
+        if let Some(pins) = self.pins_for(&host) {
+            let matched = pins.iter().any(|pin| match pin {
+                CertificatePin::CaSpki(digest) => self.webpki.is_some()
+                    && Self::chains_to_pinned_ca(digest, end_entity, intermediates, server_name, ocsp_response, now),
+                pin => pin.matches_end_entity(&end_entity.0),
+            });
+            if !matched {
+                warn!("TLS certificate chain for {} matches none of its {} pins", host, pins.len());
+                return Err(rustls::Error::General(format!("certificate for {} does not match its pinned keys", host)));
+            }
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Apply `oracle_ssl_verification` and `oracle_pinned_keys` to the oracle HTTP client
+fn configure_tls(builder: ClientBuilder, config: &EncaveConfig) -> Result<ClientBuilder> {
+    if !config.oracle_ssl_verification {
+        warn!("Oracle TLS certificate verification is disabled");
+    }
+    if config.oracle_pinned_keys.is_empty() {
+        return Ok(builder.danger_accept_invalid_certs(!config.oracle_ssl_verification));
+    }
+    
+    let mut pins = Vec::new();
+    for (domain, domain_pins) in &config.oracle_pinned_keys {
+        let parsed = domain_pins.iter()
+            .map(|pin| CertificatePin::parse(pin))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| EnclaveError::InvalidInput(format!("Invalid pin for '{}': {}", domain, e)))?;
+        // Without web PKI checks nothing verifies the chain up to a pinned CA
+        if !config.oracle_ssl_verification && parsed.iter().any(|pin| !pin.is_end_entity()) {
+            return Err(EnclaveError::InvalidInput(format!(
+                "CA pins for '{}' need oracle_ssl_verification; pin the server key instead", domain
+            )).into());
+        }
+        pins.push((domain.trim().to_ascii_lowercase(), parsed));
+    }
+    info!("Oracle TLS pins configured for {} domain(s)", pins.len());
+    
+    let webpki = config.oracle_ssl_verification.then(|| {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        WebPkiVerifier::new(roots, None)
+    });
+    let tls = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinningVerifier { webpki, pins }))
+        .with_no_client_auth();
+    Ok(builder.use_preconfigured_tls(tls))
+}
+
+/// Upper bound on fetches started through `PendingFetches` whose results have not been collected
+pub const MAX_PENDING_FETCHES: usize = 256;
+
+/// How long an uncollected fetch keeps its slot before it is cancelled and dropped
+pub const PENDING_FETCH_TTL: Duration = Duration::from_secs(600);
+
+/// State of a fetch started with `PendingFetches::register`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchPoll {
+    Pending,
+    Ready(String),
+    Failed(String),
+}
+
+/// Outcome slot of a single background fetch
+struct PendingFetch {
+    receiver: oneshot::Receiver<std::result::Result<String, String>>,
+    outcome: Option<FetchPoll>,
+    /// The task producing the outcome, aborted when the fetch is cancelled or expires
+    task: Option<tokio::task::AbortHandle>,
+    registered_at: std::time::Instant,
+}
+
+impl Drop for PendingFetch {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// Background oracle fetches keyed by the handle returned to the caller
+///
+/// Each fetch reports its outcome through a oneshot channel, so polling never blocks.
+/// An outcome stays available until `release` is called, letting a caller retry with a
+/// larger buffer after a truncated read. Fetches never collected are dropped after the
+/// TTL, so callers that go away cannot hold every slot.
+pub struct PendingFetches {
+    next_handle: AtomicU64,
+    fetches: Mutex<HashMap<u64, PendingFetch>>,
+    ttl: Duration,
+}
+
+impl Default for PendingFetches {
+    fn default() -> Self {
+        Self::with_ttl(PENDING_FETCH_TTL)
+    }
+}
+
+impl PendingFetches {
+    /// Tracker whose uncollected fetches are dropped after `ttl`
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            next_handle: AtomicU64::new(0),
+            fetches: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+    
+    /// Reserve a handle for a fetch that will report through the returned sender
+    pub fn register(&self) -> Result<(u64, oneshot::Sender<std::result::Result<String, String>>)> {
+        let mut fetches = self.fetches.lock_or_recover();
+        let before = fetches.len();
+        fetches.retain(|_, fetch| fetch.registered_at.elapsed() < self.ttl);
+        if fetches.len() < before {
+            warn!("Dropped {} oracle fetches that were not collected within {:?}", before - fetches.len(), self.ttl);
+        }
+        if fetches.len() >= MAX_PENDING_FETCHES {
+            return Err(EnclaveError::ResourceLimit(format!("Too many pending oracle fetches ({})", MAX_PENDING_FETCHES)).into());
+        }
+        
+        // Handles start at 1 so that 0 is never a valid handle
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed) + 1;
+        let (sender, receiver) = oneshot::channel();
+        fetches.insert(handle, PendingFetch { receiver, outcome: None, task: None, registered_at: std::time::Instant::now() });
+        Ok((handle, sender))
+    }
+    
+    /// Attach the task running a registered fetch so that cancelling the fetch stops it
+    pub fn track(&self, handle: u64, task: tokio::task::AbortHandle) {
+        match self.fetches.lock_or_recover().get_mut(&handle) {
+            Some(fetch) => fetch.task = Some(task),
+            None => task.abort(),
+        }
+    }
+    
+    /// Current state of a fetch without blocking
+    pub fn poll(&self, handle: u64) -> Result<FetchPoll> {
+        let mut fetches = self.fetches.lock_or_recover();
+        let fetch = fetches.get_mut(&handle)
+            .ok_or_else(|| anyhow!("Unknown oracle fetch handle {}", handle))?;
+        
+        if fetch.outcome.is_none() {
+            fetch.outcome = match fetch.receiver.try_recv() {
+                Ok(Ok(body)) => Some(FetchPoll::Ready(body)),
+                Ok(Err(e)) => Some(FetchPoll::Failed(e)),
+                Err(oneshot::error::TryRecvError::Empty) => None,
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    Some(FetchPoll::Failed("Oracle fetch task ended without a result".to_string()))
+                }
+            };
+        }
+        
+        Ok(fetch.outcome.clone().unwrap_or(FetchPoll::Pending))
+    }
+    
+    /// Forget a fetch, stopping it if it is still running
+    pub fn release(&self, handle: u64) -> Result<()> {
+        self.fetches.lock_or_recover().remove(&handle);
+        Ok(())
+    }
+    
+    /// Stop and forget a fetch, failing for handles that are unknown or already released
+    pub fn cancel(&self, handle: u64) -> Result<()> {
+        self.fetches.lock_or_recover().remove(&handle)
+            .ok_or_else(|| anyhow!("Unknown oracle fetch handle {}", handle))?;
+        debug!("Cancelled oracle fetch {}", handle);
+        Ok(())
+    }
+
+}
+
 impl OracleService {
     /// Create a new oracle service instance
-    pub async fn new(config: &EncaveConfig) -> Result<Self> {
+    pub async fn new(config: &EncaveConfig, crypto_service: Arc<CryptoService>) -> Result<Self> {
         info!("Initializing OracleService");
         
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.network_timeout_seconds))
-            .build()?;
+        let signing_key_id = config.oracle_signing_key_id.clone();
+        if crypto_service.get_key_metadata(&signing_key_id).is_err() {
+            let generate = if config.attest_signing_keys {
+                CryptoService::generate_attested_key
+            } else {
+                CryptoService::generate_key
+            };
+            generate(
+                &crypto_service,
+                &signing_key_id,
+                CryptoAlgorithm::Secp256r1,
+                vec!["Sign".to_string(), "Verify".to_string()],
+                false,
+                "Enclave oracle response signing key",
+            )?;
+        }
+        
+        // The client-wide timeout is the ceiling; each request sets its own effective timeout
+        let max_timeout_duration = Duration::from_secs(
+            config.oracle_max_timeout_seconds.max(config.network_timeout_seconds)
+        );
+        let resolver = PinnedResolver::new(config.oracle_allow_private_hosts);
+        if config.oracle_allow_private_hosts {
+            warn!("Oracle requests may reach private and loopback addresses");
+        }
+        let mut builder = Client::builder()
+            .timeout(max_timeout_duration)
+            .dns_resolver(Arc::new(resolver))
+            .default_headers(default_headers(&config.oracle_default_headers)?);
+        builder = configure_tls(builder, config)?;
+        if !config.oracle_user_agent.is_empty() {
+            builder = builder.user_agent(&config.oracle_user_agent);
+        }
+        let client = builder.build()?;
         
         let allowed_domains = vec![
             "api.neo.org".to_string(),
@@ -58,12 +665,19 @@ impl OracleService {
         Ok(Self {
             client,
             timeout_duration: Duration::from_secs(config.network_timeout_seconds),
+            max_timeout_duration,
             allowed_domains,
-            request_count: std::sync::atomic::AtomicU64::new(0),
+            metrics: OracleMetrics::default(),
             response_cache: Arc::new(RwLock::new(HashMap::new())),
             rate_limiter: Arc::new(RwLock::new(HashMap::new())),
             max_response_size: 1024 * 1024, // 1MB default
-            ssl_verification: true,
+            ssl_verification: config.oracle_ssl_verification,
+            pinned_domains: config.oracle_pinned_keys.len(),
+            resolver,
+            crypto_service,
+            signing_key_id,
+            subscriptions: Mutex::new(HashMap::new()),
+            max_subscriptions: config.oracle_max_subscriptions,
         })
     }
     
@@ -76,9 +690,62 @@ impl OracleService {
     /// Shutdown the oracle service
     pub async fn shutdown(&self) -> Result<()> {
         info!("Shutting down OracleService");
+        let mut subscriptions = self.subscriptions.lock_or_recover();
+        for (_, subscription) in subscriptions.drain() {
+            subscription.task.abort();
+        }
         Ok(())
     }
     
+    /// Probe the HTTP client state, response cache and rate limiter
+    pub fn health_check(&self) -> ServiceHealth {
+        let cached_responses = match self.response_cache.read() {
+            Ok(cache) => cache.len(),
+            Err(_) => return ServiceHealth::unhealthy("oracle", "Response cache lock poisoned"),
+        };
+        
+        let tracked_domains = match self.rate_limiter.read() {
+            Ok(limiter) => limiter.len(),
+            Err(_) => return ServiceHealth::unhealthy("oracle", "Rate limiter lock poisoned"),
+        };
+        
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let (subscription_count, stale_subscriptions) = match self.subscriptions.lock() {
+            Ok(subscriptions) => (
+                subscriptions.len(),
+                subscriptions.values().filter(|s| s.is_stale(now)).count(),
+            ),
+            Err(_) => return ServiceHealth::unhealthy("oracle", "Subscription registry lock poisoned"),
+        };
+        
+        let details = serde_json::json!({
+            "request_count": self.metrics.requests.get(),
+            "cached_responses": cached_responses,
+            "rate_limited_domains": tracked_domains,
+            "allowed_domains": self.allowed_domains.len(),
+            "timeout_seconds": self.timeout_duration.as_secs(),
+            "max_timeout_seconds": self.max_timeout_duration.as_secs(),
+            "allow_private_hosts": self.resolver.allow_private_hosts,
+            "subscriptions": subscription_count,
+            "stale_subscriptions": stale_subscriptions,
+            "ssl_verification": self.ssl_verification,
+            "pinned_domains": self.pinned_domains,
+        });
+        
+        if self.allowed_domains.is_empty() {
+            ServiceHealth::degraded("oracle", "No allowed domains configured", details)
+        } else if !self.ssl_verification {
+            ServiceHealth::degraded("oracle", "TLS certificate verification is disabled", details)
+        } else {
+            ServiceHealth::healthy("oracle", details)
+        }
+    }
+    
+    /// Operation counters for this service
+    pub fn metrics(&self) -> &OracleMetrics {
+        &self.metrics
+    }
+    
     /// Fetch data from external URL
     pub async fn fetch_data(
         &self,
@@ -86,12 +753,310 @@ impl OracleService {
         headers: Option<HashMap<String, String>>,
         processing_script: Option<&str>,
     ) -> Result<String> {
-        self.validate_url(url)?;
+        self.fetch_data_with_timeout(url, headers, processing_script, None).await
+    }
+    
+    /// Fetch data with a per-request timeout instead of the configured default.
+    /// Overrides above `oracle_max_timeout_seconds` are clamped to it.
+    pub async fn fetch_data_with_timeout(
+        &self,
+        url: &str,
+        headers: Option<HashMap<String, String>>,
+        processing_script: Option<&str>,
+        timeout_override: Option<Duration>,
+    ) -> Result<String> {
+        let request_id = self.metrics.requests.incr();
+        
+        let result = match self.effective_timeout(timeout_override) {
+            Ok(timeout_duration) => {
+                self.execute_fetch(request_id, url, headers, processing_script, timeout_duration).await
+            }
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.metrics.failures.incr();
+        }
+        result
+    }
+    
+    /// Fetch and process data, then sign the result with the enclave oracle key so
+    /// downstream verifiers can check it came from this enclave
+    pub async fn fetch_data_signed(
+        &self,
+        url: &str,
+        headers: Option<HashMap<String, String>>,
+        processing_script: Option<&str>,
+    ) -> Result<String> {
+        self.fetch_data_signed_with_timeout(url, headers, processing_script, None).await
+    }
+    
+    /// `fetch_data_signed` with a per-request timeout, bounded as in `fetch_data_with_timeout`
+    pub async fn fetch_data_signed_with_timeout(
+        &self,
+        url: &str,
+        headers: Option<HashMap<String, String>>,
+        processing_script: Option<&str>,
+        timeout_override: Option<Duration>,
+    ) -> Result<String> {
+        let payload = self.fetch_data_with_timeout(url, headers, processing_script, timeout_override).await?;
+        let payload = canonicalize(&payload).unwrap_or(payload);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        
+        let message = SignedOracleResponse::signed_message(url, timestamp, &payload);
+        let signature = self.crypto_service.sign_data(&self.signing_key_id, &message)?;
+        let metadata = self.crypto_service.get_key_metadata(&self.signing_key_id)?;
+        let public_key = metadata.public_key
+            .ok_or_else(|| anyhow!("Oracle signing key '{}' has no public key", self.signing_key_id))?;
+        
+        let response = SignedOracleResponse {
+            url: url.to_string(),
+            timestamp,
+            payload,
+            signature: hex::encode(signature),
+            public_key: hex::encode(public_key),
+            key_id: self.signing_key_id.clone(),
+            signature_scheme: metadata.signature_scheme.unwrap_or_default(),
+        };
+        
+        Ok(serde_json::to_string(&response)?)
+    }
+    
+    /// Refresh `url` every `interval` in the background, keeping the latest result for
+    /// `get_latest`, and return the subscription id (`sub_` followed by 22 base62 characters)
+    ///
+    /// Must be called from within a Tokio runtime, which runs the refresh task. The first
+    /// fetch starts immediately. A failed refresh keeps the previous value and marks the
+    /// subscription stale until a later refresh succeeds.
+    pub fn subscribe(self: &Arc<Self>, url: &str, interval: Duration, processing_script: Option<&str>) -> Result<String> {
+        if interval < MIN_SUBSCRIPTION_INTERVAL {
+            return Err(EnclaveError::InvalidInput(format!(
+                "Subscription interval must be at least {:?}", MIN_SUBSCRIPTION_INTERVAL
+            )).into());
+        }
+        url::Url::parse(url).map_err(|_| EnclaveError::InvalidInput("Invalid URL format".into()))?;
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| anyhow!("Oracle subscriptions must be created from within the Tokio runtime"))?;
+        
+        let mut subscriptions = self.subscriptions.lock_or_recover();
+        if subscriptions.len() >= self.max_subscriptions {
+            return Err(EnclaveError::ResourceLimit(format!(
+                "Too many oracle subscriptions ({})", self.max_subscriptions
+            )).into());
+        }
+        
+        let id = self.crypto_service.generate_id("sub")?;
+        let task = runtime.spawn(Self::run_subscription(Arc::downgrade(self), id.clone()));
+        subscriptions.insert(id.clone(), Subscription {
+            url: url.to_string(),
+            interval,
+            processing_script: processing_script.map(str::to_string),
+            latest: None,
+            updated_at: None,
+            last_attempt_at: None,
+            last_error: None,
+            consecutive_failures: 0,
+            task,
+        });
+        
+        info!("Oracle subscription {} refreshes {} every {:?}", id, redact_url(url), interval);
+        Ok(id)
+    }
+    
+    /// JSON with a subscription's latest value (null until the first success) and its
+    /// staleness
+    pub fn get_latest(&self, subscription_id: &str) -> Result<String> {
+        let subscriptions = self.subscriptions.lock_or_recover();
+        let subscription = subscriptions.get(subscription_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Unknown oracle subscription {}", subscription_id)))?;
+        
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(serde_json::json!({
+            "subscription_id": subscription_id,
+            "url": subscription.url,
+            "interval_seconds": subscription.interval.as_secs(),
+            "value": subscription.latest,
+            "updated_at": subscription.updated_at,
+            "age_seconds": subscription.updated_at.map(|updated_at| now.saturating_sub(updated_at)),
+            "stale": subscription.is_stale(now),
+            "last_attempt_at": subscription.last_attempt_at,
+            "last_error": subscription.last_error,
+            "consecutive_failures": subscription.consecutive_failures,
+        }).to_string())
+    }
+    
+    /// Stop refreshing a subscription and drop its value
+    pub fn unsubscribe(&self, subscription_id: &str) -> Result<()> {
+        let subscription = self.subscriptions.lock_or_recover()
+            .remove(subscription_id)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Unknown oracle subscription {}", subscription_id)))?;
+        subscription.task.abort();
+        info!("Removed oracle subscription {}", subscription_id);
+        Ok(())
+    }
+    
+    /// Refresh loop of a subscription; ends once it is removed or the service is dropped
+    async fn run_subscription(service: Weak<Self>, id: String) {
+        let interval = match service.upgrade().and_then(|s| s.subscription_target(&id)) {
+            Some((_, interval, _)) => interval,
+            None => return,
+        };
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        
+        loop {
+            ticker.tick().await;
+            let Some(service) = service.upgrade() else { return };
+            let Some((url, _, script)) = service.subscription_target(&id) else { return };
+            
+            let result = service.fetch_data(&url, None, script.as_deref()).await;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            
+            let Ok(mut subscriptions) = service.subscriptions.lock() else { return };
+            let Some(subscription) = subscriptions.get_mut(&id) else { return };
+            subscription.last_attempt_at = Some(now);
+            match result {
+                Ok(value) => {
+                    subscription.latest = Some(value);
+                    subscription.updated_at = Some(now);
+                    subscription.last_error = None;
+                    subscription.consecutive_failures = 0;
+                }
+                Err(e) => {
+                    subscription.consecutive_failures += 1;
+                    warn!("Oracle subscription {} refresh failed ({} in a row): {}", id, subscription.consecutive_failures, e);
+                    subscription.last_error = Some(e.to_string());
+                }
+            }
+        }
+    }
+    
+    /// URL, interval and script of a live subscription
+    fn subscription_target(&self, id: &str) -> Option<(String, Duration, Option<String>)> {
+        let subscriptions = self.subscriptions.lock().ok()?;
+        let subscription = subscriptions.get(id)?;
+        Some((subscription.url.clone(), subscription.interval, subscription.processing_script.clone()))
+    }
+    
+    /// Check a response produced by `fetch_data_signed` against the public key it carries.
+    /// Callers should also confirm that key belongs to a trusted enclave.
+    pub fn verify_signed_response(&self, response: &str) -> Result<bool> {
+        let response: SignedOracleResponse = serde_json::from_str(response)
+            .map_err(|e| anyhow!("Invalid signed oracle response: {}", e))?;
+        
+        let public_key = hex::decode(&response.public_key)
+            .map_err(|_| anyhow!("Invalid public key hex in signed oracle response"))?;
+        let signature = match hex::decode(&response.signature) {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+        
+        let message = SignedOracleResponse::signed_message(&response.url, response.timestamp, &response.payload);
+        self.crypto_service.verify_with_public_key(CryptoAlgorithm::Secp256r1, &public_key, &message, &signature)
+    }
+    
+    /// Validate JSON data against a JSON Schema document. Returns whether the data is
+    /// valid and, for each violation, the JSON Pointer of the offending value (empty for
+    /// the root), the schema keyword path and a message. Remote `$ref`s are not fetched.
+    pub fn validate_against_schema(&self, data: &str, schema: &str) -> Result<String> {
+        let schema: serde_json::Value = serde_json::from_str(schema)
+            .map_err(|e| anyhow!("Invalid JSON schema document: {}", e))?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| anyhow!("Invalid JSON Schema: {}", e))?;
+        
+        let instance: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid JSON data: {}", e))?;
+        
+        let mut error_count = 0;
+        let mut errors = Vec::new();
+        for error in validator.iter_errors(&instance) {
+            error_count += 1;
+            if errors.len() < MAX_SCHEMA_ERRORS {
+                errors.push(serde_json::json!({
+                    "path": error.instance_path.to_string(),
+                    "schema_path": error.schema_path.to_string(),
+                    "message": error.to_string(),
+                }));
+            }
+        }
+        
+        Ok(serde_json::json!({
+            "valid": error_count == 0,
+            "error_count": error_count,
+            "errors": errors,
+            "truncated": error_count > errors.len(),
+        }).to_string())
+    }
+    
+    /// Resolve a caller's timeout override against the default and the configured ceiling
+    fn effective_timeout(&self, timeout_override: Option<Duration>) -> Result<Duration> {
+        match timeout_override {
+            None => Ok(self.timeout_duration),
+            Some(requested) if requested.is_zero() => Err(anyhow!("Timeout override must be greater than zero")),
+            Some(requested) if requested > self.max_timeout_duration => {
+                warn!("Oracle timeout override {:?} exceeds maximum, using {:?}", requested, self.max_timeout_duration);
+                Ok(self.max_timeout_duration)
+            }
+            Some(requested) => Ok(requested),
+        }
+    }
+    
+    async fn execute_fetch(
+        &self,
+        request_id: u64,
+        url: &str,
+        headers: Option<HashMap<String, String>>,
+        processing_script: Option<&str>,
+        timeout_duration: Duration,
+    ) -> Result<String> {
+        self.validate_url(url).await?;
         
-        let request_id = self.request_count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-        debug!("Oracle request #{}: {}", request_id, url);
+        // Custom headers can change the response, so only plain requests use the cache
+        let cacheable = headers.is_none();
+        let cached = cacheable
+            .then(|| self.response_cache.read_or_recover().get(url).filter(|cached| cached.is_fresh()).cloned())
+            .flatten();
+        let (body, content_type) = match cached {
+            Some(cached) => {
+                self.metrics.cache_hits.incr();
+                debug!("Oracle request #{}: {} answered from cache", request_id, redact_url(url));
+                (cached.body, cached.content_type)
+            }
+            None => {
+                let (body, content_type, ttl) = self.send_request(request_id, url, headers, timeout_duration).await?;
+                if let (true, Some(ttl)) = (cacheable, ttl) {
+                    self.cache_response(url, &body, &content_type, ttl);
+                }
+                (body, content_type)
+            }
+        };
         
-        let mut request = self.client.get(url);
+        // Without an explicit script, CSV and XML bodies are converted to JSON
+        let detected = content_type.as_deref().and_then(transform_for_content_type);
+        let result = match (processing_script, detected) {
+            (Some(script), _) => self.process_data(&body, script)?,
+            (None, Some(transform)) => {
+                debug!("Oracle request #{}: applying {} for content type {:?}", request_id, transform, content_type);
+                self.apply_transform(&body, transform)?
+            }
+            (None, None) => body,
+        };
+        
+        debug!("Oracle request #{} completed successfully", request_id);
+        Ok(result)
+    }
+    
+    /// Send the HTTP request, returning the body, its content type and how long the
+    /// source allows it to be cached
+    async fn send_request(
+        &self,
+        request_id: u64,
+        url: &str,
+        headers: Option<HashMap<String, String>>,
+        timeout_duration: Duration,
+    ) -> Result<(String, Option<String>, Option<Duration>)> {
+        debug!("Oracle request #{}: {} (timeout {:?})", request_id, redact_url(url), timeout_duration);
+        
+        let mut request = self.client.get(url).timeout(timeout_duration);
         
         if let Some(headers) = headers {
             for (key, value) in headers {
@@ -99,49 +1064,119 @@ impl OracleService {
             }
         }
         
-        let response = timeout(self.timeout_duration, request.send()).await??;
+        let response = timeout(timeout_duration, request.send()).await
+            .map_err(|_| anyhow!("Oracle request timed out after {:?}", timeout_duration))??;
         let status = response.status();
+        let header = |name| response.headers().get(name).and_then(|value: &HeaderValue| value.to_str().ok()).map(str::to_ascii_lowercase);
+        let content_type = header(reqwest::header::CONTENT_TYPE);
+        let ttl = header(reqwest::header::CACHE_CONTROL).as_deref().and_then(cache_ttl);
         let body = response.text().await?;
         
         if !status.is_success() {
             return Err(anyhow!("HTTP request failed with status: {}", status));
         }
-        
-        let result = if let Some(script) = processing_script {
-            self.process_data(&body, script)?
-        } else {
-            body
-        };
-        
-        debug!("Oracle request #{} completed successfully", request_id);
-        Ok(result)
+        Ok((body, content_type, ttl))
+    }
+    
+    /// Keep a successful response for `ttl`, dropping expired entries to make room
+    fn cache_response(&self, url: &str, body: &str, content_type: &Option<String>, ttl: Duration) {
+        let mut cache = self.response_cache.write_or_recover();
+        if cache.len() >= MAX_CACHED_RESPONSES {
+            cache.retain(|_, cached| cached.is_fresh());
+        }
+        if cache.len() < MAX_CACHED_RESPONSES || cache.contains_key(url) {
+            cache.insert(url.to_string(), CachedResponse {
+                body: body.to_string(),
+                content_type: content_type.clone(),
+                stored_at: Instant::now(),
+                ttl,
+            });
+        }
     }
     
-    /// Validate URL against allowed domains
-    fn validate_url(&self, url: &str) -> Result<()> {
+    /// Validate URL against allowed domains and check where its host resolves
+    ///
+    /// The resolution here gives an early, descriptive error; the client's `PinnedResolver`
+    /// repeats the check for the addresses it actually connects to.
+    async fn validate_url(&self, url: &str) -> Result<()> {
         let parsed = url::Url::parse(url)
             .map_err(|_| anyhow!("Invalid URL format"))?;
         
-        if let Some(host) = parsed.host_str() {
-            if self.allowed_domains.iter().any(|domain| {
+        let allowed = match parsed.host() {
+            Some(url::Host::Domain(host)) => self.allowed_domains.iter().any(|domain| {
                 host == domain || host.ends_with(&format!(".{}", domain))
-            }) {
-                return Ok(());
+            }),
+            // IP literals bypass DNS, so the address check has to happen here
+            Some(url::Host::Ipv4(ip)) => {
+                self.check_ip_literal(IpAddr::V4(ip))?;
+                self.allowed_domains.iter().any(|domain| domain == &ip.to_string())
+            }
+            Some(url::Host::Ipv6(ip)) => {
+                self.check_ip_literal(IpAddr::V6(ip))?;
+                self.allowed_domains.iter().any(|domain| domain == &ip.to_string())
             }
+            None => false,
+        };
+        if !allowed {
+            return Err(anyhow!("URL not in allowed domains list"));
         }
         
-        Err(anyhow!("URL not in allowed domains list"))
+        if let Some(url::Host::Domain(host)) = parsed.host() {
+            self.resolver.resolve_host(host).await?;
+        }
+        Ok(())
     }
     
-    /// Process fetched data with secure data processing capabilities
+    fn check_ip_literal(&self, ip: IpAddr) -> Result<()> {
+        if !self.resolver.allow_private_hosts && !is_public_address(ip) {
+            return Err(anyhow!("URL targets non-public address {}", ip));
+        }
+        Ok(())
+    }
+    
+    /// Process fetched data with secure data processing capabilities.
+    /// The script is a single transform or a `|`-separated pipeline of them, run left to right.
     fn process_data(&self, data: &str, script: &str) -> Result<String> {
         // Production-ready data processing with security validation
         if script.len() > 10000 {
             return Err(anyhow!("Processing script too large (max 10KB)"));
         }
         
+        let steps = split_pipeline(script);
+        if steps.len() > 1 {
+            return self.run_pipeline(data, &steps);
+        }
+        
+        self.apply_transform(data, script.trim())
+    }
+    
+    /// Run each pipeline step on the previous step's output, failing on the first bad step
+    fn run_pipeline(&self, data: &str, steps: &[&str]) -> Result<String> {
+        if steps.len() > MAX_PIPELINE_STEPS {
+            return Err(anyhow!("Processing pipeline too long (max {} steps)", MAX_PIPELINE_STEPS));
+        }
+        
+        let mut current = data.to_string();
+        for (index, step) in steps.iter().enumerate() {
+            if !is_transform_step(step) {
+                return Err(anyhow!("Pipeline step {} ('{}') is not a known transform", index + 1, step));
+            }
+            
+            // jq errors are normally reported in-band; inside a pipeline they must stop it
+            let output = match step.strip_prefix("jq:") {
+                Some(query) => self.process_jq_strict(&current, query),
+                None => self.apply_transform(&current, step),
+            };
+            current = output.map_err(|e| anyhow!("Pipeline step {} ('{}') failed: {}", index + 1, step, e))?;
+        }
+        
+        Ok(current)
+    }
+    
+    /// Apply a single named transform
+    fn apply_transform(&self, data: &str, script: &str) -> Result<String> {
         // Parse script commands and execute securely
-        match script.trim() {
+        match script {
             "extract_json" => self.extract_json_fields(data),
             "parse_price" => self.parse_price_data(data),
             "validate_schema" => self.validate_json_schema(data),
@@ -151,6 +1186,9 @@ impl OracleService {
             "clean_whitespace" => Ok(data.trim().to_string()),
             "to_uppercase" => Ok(data.to_uppercase()),
             "to_lowercase" => Ok(data.to_lowercase()),
+            "parse_csv" => self.parse_csv(data, b','),
+            "parse_xml" => self.parse_xml(data),
+            script if script.starts_with("parse_csv:") => self.parse_csv(data, csv_delimiter(&script[10..])?),
             script if script.starts_with("jq:") => self.process_jq_like(data, &script[3..]),
             script if script.starts_with("regex:") => self.process_regex(data, &script[6..]),
             _ => {
@@ -162,6 +1200,45 @@ impl OracleService {
         }
     }
     
+    /// Parse CSV with a header row into an array of objects keyed by column name.
+    /// Quoted fields may contain the delimiter, quotes (doubled) and newlines.
+    fn parse_csv(&self, data: &str, delimiter: u8) -> Result<String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(data.as_bytes());
+        
+        let headers = reader.headers()
+            .map_err(|e| anyhow!("Invalid CSV header: {}", e))?
+            .clone();
+        
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| anyhow!("Invalid CSV: {}", e))?;
+            let row: serde_json::Map<String, serde_json::Value> = headers.iter()
+                .zip(record.iter())
+                .map(|(column, value)| (column.to_string(), serde_json::Value::String(value.to_string())))
+                .collect();
+            rows.push(serde_json::Value::Object(row));
+        }
+        
+        Ok(serde_json::Value::Array(rows).to_string())
+    }
+    
+    /// Flatten an XML document into JSON: `{root: element}`, where an element becomes its
+    /// text if it has no attributes or children, and otherwise an object with `@attribute`
+    /// keys, one key per child tag (an array when the tag repeats) and `#text` for text
+    fn parse_xml(&self, data: &str) -> Result<String> {
+        // roxmltree rejects DTDs by default, which rules out entity expansion attacks
+        let document = roxmltree::Document::parse(data)
+            .map_err(|e| anyhow!("Invalid XML: {}", e))?;
+        
+        let root = document.root_element();
+        let mut object = serde_json::Map::new();
+        object.insert(root.tag_name().name().to_string(), xml_element_to_json(root, 0)?);
+        
+        Ok(serde_json::Value::Object(object).to_string())
+    }
+    
     /// Extract JSON fields from data
     fn extract_json_fields(&self, data: &str) -> Result<String> {
         let parsed: serde_json::Value = serde_json::from_str(data)
@@ -223,12 +1300,14 @@ impl OracleService {
         Err(anyhow!("Could not parse price from data"))
     }
     
-    /// Validate JSON schema
+    /// Validate data against `DEFAULT_ORACLE_SCHEMA`, keeping the field presence flags
+    /// and completeness score that existing pipelines read
     fn validate_json_schema(&self, data: &str) -> Result<String> {
         let parsed: serde_json::Value = serde_json::from_str(data)
             .map_err(|e| anyhow!("Invalid JSON: {}", e))?;
         
-        let mut validation_result = serde_json::Map::new();
+        let mut validation_result: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&self.validate_against_schema(data, DEFAULT_ORACLE_SCHEMA)?)?;
         validation_result.insert("valid_json".to_string(), serde_json::Value::Bool(true));
         
         // Check for required fields based on common oracle schemas
@@ -347,6 +1426,15 @@ impl OracleService {
         }
     }
     
+    /// JQ-like processing that returns query failures as errors
+    fn process_jq_strict(&self, data: &str, query: &str) -> Result<String> {
+        let parsed: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| anyhow!("Invalid JSON for jq processing: {}", e))?;
+        
+        let result = self.execute_jq_query(&parsed, query.trim())?;
+        Ok(serde_json::to_string(&result)?)
+    }
+    
     /// Execute JQ-like query with full production support
     fn execute_jq_query(&self, data: &serde_json::Value, query: &str) -> Result<serde_json::Value> {
         match query {
@@ -870,4 +1958,633 @@ impl OracleService {
             "pattern": pattern
         }).to_string())
     }
-} 
\ No newline at end of file
+}
+
+/// Transforms that can be named in a processing script
+const NAMED_TRANSFORMS: &[&str] = &[
+    "extract_json",
+    "parse_price",
+    "validate_schema",
+    "filter_numbers",
+    "transform_to_array",
+    "aggregate_values",
+    "clean_whitespace",
+    "to_uppercase",
+    "to_lowercase",
+    "parse_csv",
+    "parse_xml",
+];
+
+/// Schema applied by the `validate_schema` transform: a price quote with a numeric
+/// price and timestamp and a string symbol
+const DEFAULT_ORACLE_SCHEMA: &str = r#"{
+    "type": "object",
+    "required": ["price", "timestamp", "symbol"],
+    "properties": {
+        "price": {"type": "number"},
+        "timestamp": {"type": "number"},
+        "symbol": {"type": "string"}
+    }
+}"#;
+
+/// Violations listed by `validate_against_schema`; the total is always reported
+const MAX_SCHEMA_ERRORS: usize = 100;
+
+/// Upper bound on steps in one processing pipeline
+const MAX_PIPELINE_STEPS: usize = 16;
+
+fn is_transform_step(step: &str) -> bool {
+    NAMED_TRANSFORMS.contains(&step)
+        || step.starts_with("jq:")
+        || step.starts_with("regex:")
+        || step.starts_with("parse_csv:")
+}
+
+/// Deepest XML element nesting converted by `parse_xml`
+const MAX_XML_DEPTH: usize = 128;
+
+/// Transform applied to a response with this Content-Type when no script is given
+fn transform_for_content_type(content_type: &str) -> Option<&'static str> {
+    let media_type = content_type.split(';').next().unwrap_or("").trim();
+    match media_type {
+        "text/csv" | "application/csv" => Some("parse_csv"),
+        "text/tab-separated-values" => Some("parse_csv:tab"),
+        _ if media_type.ends_with("/xml") || media_type.ends_with("+xml") => Some("parse_xml"),
+        _ => None,
+    }
+}
+
+/// Delimiter for a `parse_csv:<delimiter>` step: a single ASCII character, or `tab`
+fn csv_delimiter(spec: &str) -> Result<u8> {
+    match spec {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if spec.len() == 1 && spec.is_ascii() && !matches!(spec, "\"" | "\n" | "\r") => Ok(spec.as_bytes()[0]),
+        _ => Err(EnclaveError::InvalidInput(format!("Invalid CSV delimiter '{}': expected a single character or 'tab'", spec)).into()),
+    }
+}
+
+fn xml_element_to_json(node: roxmltree::Node, depth: usize) -> Result<serde_json::Value> {
+    if depth > MAX_XML_DEPTH {
+        return Err(EnclaveError::ResourceLimit(format!("XML nesting exceeds {} levels", MAX_XML_DEPTH)).into());
+    }
+    
+    let mut object = serde_json::Map::new();
+    for attribute in node.attributes() {
+        object.insert(format!("@{}", attribute.name()), serde_json::Value::String(attribute.value().to_string()));
+    }
+    
+    let mut text = String::new();
+    for child in node.children() {
+        if child.is_element() {
+            let value = xml_element_to_json(child, depth + 1)?;
+            match object.entry(child.tag_name().name()) {
+                serde_json::map::Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+                // Elements never convert to arrays, so an array here means a repeated tag
+                serde_json::map::Entry::Occupied(mut entry) => match entry.get_mut() {
+                    serde_json::Value::Array(items) => items.push(value),
+                    existing => {
+                        let first = existing.take();
+                        *existing = serde_json::Value::Array(vec![first, value]);
+                    }
+                },
+            }
+        } else if let Some(fragment) = child.text().filter(|_| child.is_text()) {
+            text.push_str(fragment);
+        }
+    }
+    
+    let text = text.trim();
+    if object.is_empty() {
+        return Ok(serde_json::Value::String(text.to_string()));
+    }
+    if !text.is_empty() {
+        object.insert("#text".to_string(), serde_json::Value::String(text.to_string()));
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+/// Split a processing script into pipeline steps.
+///
+/// A `|` only separates steps when a transform follows it, so pipes inside
+/// `jq:` queries and alternations inside `regex:` patterns are left intact.
+fn split_pipeline(script: &str) -> Vec<&str> {
+    let mut steps = Vec::new();
+    let mut start = 0;
+    
+    for (index, _) in script.match_indices('|') {
+        let rest = script[index + 1..].trim_start();
+        let next = rest.split('|').next().unwrap_or("").trim();
+        if rest.starts_with("jq:") || rest.starts_with("regex:") || NAMED_TRANSFORMS.contains(&next) {
+            steps.push(script[start..index].trim());
+            start = index + 1;
+        }
+    }
+    
+    steps.push(script[start..].trim());
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa};
+
+    const HOST: &str = "oracle.example";
+
+    fn ca(name: &str) -> rcgen::Certificate {
+        let mut params = CertificateParams::new(Vec::new());
+        params.distinguished_name.push(rcgen::DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        rcgen::Certificate::from_params(params).unwrap()
+    }
+
+    fn leaf() -> rcgen::Certificate {
+        rcgen::Certificate::from_params(CertificateParams::new(vec![HOST.to_string()])).unwrap()
+    }
+
+    fn der(certificate: &rcgen::Certificate, issuer: Option<&rcgen::Certificate>) -> Certificate {
+        Certificate(match issuer {
+            Some(issuer) => certificate.serialize_der_with_signer(issuer).unwrap(),
+            None => certificate.serialize_der().unwrap(),
+        })
+    }
+
+    fn spki_pin(certificate: &Certificate) -> [u8; 32] {
+        Sha256::digest(certificate_spki(&certificate.0).unwrap()).into()
+    }
+
+    /// A verifier trusting `roots` with `pins` for HOST
+    fn verifier(roots: &[&Certificate], pins: Vec<CertificatePin>) -> PinningVerifier {
+        let mut store = rustls::RootCertStore::empty();
+        for root in roots {
+            store.add(root).unwrap();
+        }
+        PinningVerifier {
+            webpki: Some(WebPkiVerifier::new(store, None)),
+            pins: vec![(HOST.to_string(), pins)],
+        }
+    }
+
+    fn verify(verifier: &PinningVerifier, end_entity: &Certificate, intermediates: &[Certificate]) -> bool {
+        let server_name = ServerName::try_from(HOST).unwrap();
+        verifier.verify_server_cert(end_entity, intermediates, &server_name, &mut std::iter::empty(), &[], SystemTime::now()).is_ok()
+    }
+
+    /// Root, intermediate and server certificates of the genuine chain, plus a server
+    /// certificate for the same host from an unrelated but trusted root
+    struct Chains {
+        root: Certificate,
+        intermediate: Certificate,
+        server: Certificate,
+        other_root: Certificate,
+        other_server: Certificate,
+    }
+
+    fn chains() -> Chains {
+        let (root, intermediate, server) = (ca("Pinned Root"), ca("Pinned Intermediate"), leaf());
+        let (other_root, other_server) = (ca("Other Root"), leaf());
+        Chains {
+            intermediate: der(&intermediate, Some(&root)),
+            server: der(&server, Some(&intermediate)),
+            root: der(&root, None),
+            other_server: der(&other_server, Some(&other_root)),
+            other_root: der(&other_root, None),
+        }
+    }
+
+    #[test]
+    fn server_key_pin_matches() {
+        let c = chains();
+        let spki = verifier(&[&c.root], vec![CertificatePin::Spki(spki_pin(&c.server))]);
+        assert!(verify(&spki, &c.server, std::slice::from_ref(&c.intermediate)));
+        let cert = verifier(&[&c.root], vec![CertificatePin::Certificate(Sha256::digest(&c.server.0).into())]);
+        assert!(verify(&cert, &c.server, std::slice::from_ref(&c.intermediate)));
+    }
+
+    #[test]
+    fn pin_mismatch_is_rejected() {
+        let c = chains();
+        let verifier = verifier(&[&c.root, &c.other_root], vec![CertificatePin::Spki(spki_pin(&c.server))]);
+        assert!(!verify(&verifier, &c.other_server, &[]));
+    }
+
+    #[test]
+    fn appended_pinned_certificates_are_not_trusted() {
+        let c = chains();
+        // Another trusted CA issued a certificate for the host; appending the genuine
+        // server certificate or the pinned intermediate must not make it acceptable
+        let end_entity_pin = verifier(&[&c.root, &c.other_root], vec![CertificatePin::Spki(spki_pin(&c.server))]);
+        assert!(!verify(&end_entity_pin, &c.other_server, std::slice::from_ref(&c.server)));
+
+        let ca_pin = verifier(&[&c.root, &c.other_root], vec![CertificatePin::CaSpki(spki_pin(&c.intermediate))]);
+        assert!(!verify(&ca_pin, &c.other_server, std::slice::from_ref(&c.intermediate)));
+        assert!(verify(&ca_pin, &c.server, std::slice::from_ref(&c.intermediate)));
+    }
+
+    #[test]
+    fn ca_pins_need_ssl_verification() {
+        let mut config = EncaveConfig::default();
+        config.oracle_pinned_keys.insert(HOST.to_string(), vec![format!("ca-spki-sha256:{}", "ab".repeat(32))]);
+        assert!(configure_tls(Client::builder(), &config).is_ok());
+        config.oracle_ssl_verification = false;
+        assert!(configure_tls(Client::builder(), &config).is_err());
+        assert!(config.validate().is_err());
+
+        config.oracle_pinned_keys.insert(HOST.to_string(), vec![format!("spki-sha256:{}", "ab".repeat(32))]);
+        assert!(configure_tls(Client::builder(), &config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_fetch_stops_its_task() {
+        let fetches = PendingFetches::default();
+        let (handle, sender) = fetches.register().unwrap();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            let _ = sender.send(Ok("late".to_string()));
+        });
+        fetches.track(handle, task.abort_handle());
+
+        fetches.cancel(handle).unwrap();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert!(fetches.poll(handle).is_err());
+        assert!(fetches.cancel(handle).is_err());
+    }
+
+    #[tokio::test]
+    async fn uncollected_fetches_expire_and_free_their_slots() {
+        let fetches = PendingFetches::with_ttl(Duration::from_millis(20));
+        let senders: Vec<_> = (0..MAX_PENDING_FETCHES).map(|_| fetches.register().unwrap()).collect();
+        assert!(fetches.register().is_err());
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let (handle, sender) = fetches.register().unwrap();
+        assert!(fetches.poll(senders[0].0).is_err());
+        sender.send(Ok("body".to_string())).unwrap();
+        assert_eq!(fetches.poll(handle).unwrap(), FetchPoll::Ready("body".to_string()));
+    }
+
+    #[test]
+    fn public_address_table() {
+        let cases = [
+            ("8.8.8.8", true),
+            ("1.1.1.1", true),
+            ("127.0.0.1", false),
+            ("10.1.2.3", false),
+            ("172.16.0.1", false),
+            ("192.168.1.1", false),
+            ("169.254.169.254", false),
+            ("100.64.0.1", false),
+            ("0.1.2.3", false),
+            ("192.0.0.8", false),
+            ("198.18.0.1", false),
+            ("198.19.255.255", false),
+            ("198.20.0.1", true),
+            ("240.0.0.1", false),
+            ("255.255.255.255", false),
+            ("224.0.0.1", false),
+            ("2606:4700:4700::1111", true),
+            ("::1", false),
+            ("::", false),
+            ("::ffff:127.0.0.1", false),
+            ("::ffff:8.8.8.8", true),
+            ("::127.0.0.1", false),
+            ("::10.0.0.1", false),
+            ("64:ff9b::7f00:1", false),
+            ("64:ff9b::a9fe:a9fe", false),
+            ("64:ff9b::808:808", true),
+            ("2002:7f00:1::", false),
+            ("2002:c0a8:101::1", false),
+            ("2002:808:808::1", true),
+            ("fc00::1", false),
+            ("fe80::1", false),
+            ("fec0::1", false),
+            ("ff02::1", false),
+        ];
+        for (address, public) in cases {
+            assert_eq!(is_public_address(address.parse().unwrap()), public, "{}", address);
+        }
+    }
+
+    #[tokio::test]
+    async fn subscription_ids_are_random_strings() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let oracle = Arc::new(OracleService::new(&config, crypto).await.unwrap());
+
+        let url = format!("https://{}/price", HOST);
+        let first = oracle.subscribe(&url, MIN_SUBSCRIPTION_INTERVAL, None).unwrap();
+        let second = oracle.subscribe(&url, MIN_SUBSCRIPTION_INTERVAL, None).unwrap();
+        assert_ne!(first, second);
+        for id in [&first, &second] {
+            let suffix = id.strip_prefix("sub_").unwrap();
+            assert_eq!(suffix.len(), 22);
+            assert!(suffix.bytes().all(|b| b.is_ascii_alphanumeric()));
+        }
+
+        let latest: serde_json::Value = serde_json::from_str(&oracle.get_latest(&first).unwrap()).unwrap();
+        assert_eq!(latest["subscription_id"], first.as_str());
+        oracle.unsubscribe(&first).unwrap();
+        assert!(oracle.get_latest(&first).is_err());
+        oracle.unsubscribe(&second).unwrap();
+    }
+
+    #[test]
+    fn cache_control_sets_the_response_ttl() {
+        assert_eq!(cache_ttl("max-age=30"), Some(Duration::from_secs(30)));
+        assert_eq!(cache_ttl("public, Max-Age=86400"), Some(MAX_CACHED_RESPONSE_TTL));
+        assert_eq!(cache_ttl("max-age=0"), None);
+        assert_eq!(cache_ttl("no-store, max-age=30"), None);
+        assert_eq!(cache_ttl("private, max-age=30"), None);
+        assert_eq!(cache_ttl("public"), None);
+    }
+
+    /// Serve `body` as JSON from a loopback port, returning the address and a count of
+    /// the requests served
+    async fn serve_json(body: &'static str) -> (std::net::SocketAddr, Arc<AtomicU64>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let served = Arc::new(AtomicU64::new(0));
+        let server_count = served.clone();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            while let Ok((mut stream, _)) = listener.accept().await {
+                server_count.fetch_add(1, Ordering::SeqCst);
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nCache-Control: max-age=60\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (address, served)
+    }
+
+    /// An oracle that may fetch from loopback addresses
+    async fn loopback_oracle(dir: &std::path::Path) -> OracleService {
+        loopback_oracle_with(crate::test_support::test_config(dir)).await
+    }
+
+    async fn loopback_oracle_with(mut config: EncaveConfig) -> OracleService {
+        config.oracle_allow_private_hosts = true;
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let mut oracle = OracleService::new(&config, crypto).await.unwrap();
+        oracle.allowed_domains.push("127.0.0.1".to_string());
+        oracle
+    }
+
+    #[tokio::test]
+    async fn cached_responses_count_as_cache_hits() {
+        let (address, served) = serve_json(r#"{"price":42}"#).await;
+        let dir = tempfile::tempdir().unwrap();
+        let oracle = loopback_oracle(dir.path()).await;
+
+        let url = format!("http://{}/price", address);
+        assert_eq!(oracle.fetch_data(&url, None, None).await.unwrap(), r#"{"price":42}"#);
+        assert_eq!(oracle.fetch_data(&url, None, None).await.unwrap(), r#"{"price":42}"#);
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+        assert_eq!(oracle.metrics().cache_hits.get(), 1);
+        assert_eq!(oracle.metrics().requests.get(), 2);
+
+        // Requests with their own headers always reach the source
+        let headers = HashMap::from([("X-Api-Key".to_string(), "secret".to_string())]);
+        oracle.fetch_data(&url, Some(headers), None).await.unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+        assert_eq!(oracle.metrics().cache_hits.get(), 1);
+    }
+
+    #[test]
+    fn pipes_inside_jq_and_regex_steps_do_not_split_the_pipeline() {
+        assert_eq!(split_pipeline("jq:.data | parse_price"), vec!["jq:.data", "parse_price"]);
+        assert_eq!(split_pipeline("regex:BTC|ETH | to_lowercase"), vec!["regex:BTC|ETH", "to_lowercase"]);
+        assert_eq!(split_pipeline("jq:.items | length"), vec!["jq:.items | length"]);
+        assert_eq!(split_pipeline("clean_whitespace|to_uppercase|regex:[A-Z]+"), vec!["clean_whitespace", "to_uppercase", "regex:[A-Z]+"]);
+    }
+
+    #[tokio::test]
+    async fn pipeline_steps_feed_each_other_left_to_right() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let oracle = OracleService::new(&config, crypto).await.unwrap();
+        let feed = r#"{"data": {"price": 12.5, "symbol": "NEO"}}"#;
+
+        let parsed: serde_json::Value = serde_json::from_str(&oracle.process_data(feed, "jq:.data | parse_price").unwrap()).unwrap();
+        assert_eq!(parsed["price"], 12.5);
+        assert_eq!(parsed["parsed_from"], "json");
+        assert_eq!(oracle.process_data(feed, "jq:.data | parse_price | jq:.price").unwrap(), "12.5");
+        assert_eq!(oracle.process_data("  neo  ", "clean_whitespace | to_uppercase").unwrap(), "NEO");
+
+        let error = oracle.process_data(feed, "jq:.data | jq:.[0] | parse_price").unwrap_err().to_string();
+        assert!(error.starts_with("Pipeline step 2 ('jq:.[0]') failed"), "{}", error);
+        let error = oracle.process_data("no digits", "to_lowercase | parse_price").unwrap_err().to_string();
+        assert!(error.starts_with("Pipeline step 2 ('parse_price') failed"), "{}", error);
+
+        let too_long = vec!["to_lowercase"; MAX_PIPELINE_STEPS + 1].join(" | ");
+        assert!(oracle.process_data("x", &too_long).is_err());
+    }
+
+    #[tokio::test]
+    async fn signed_responses_verify_and_detect_tampering() {
+        use p256::ecdsa::signature::Verifier;
+        let (address, _) = serve_json(r#"{ "symbol": "NEO", "price": 12.5 }"#).await;
+        let dir = tempfile::tempdir().unwrap();
+        let oracle = loopback_oracle(dir.path()).await;
+
+        let url = format!("http://{}/price", address);
+        let signed = oracle.fetch_data_signed(&url, None, None).await.unwrap();
+        assert!(oracle.verify_signed_response(&signed).unwrap());
+
+        let response: SignedOracleResponse = serde_json::from_str(&signed).unwrap();
+        assert_eq!(response.url, url);
+        assert_eq!(response.payload, r#"{"price":12.5,"symbol":"NEO"}"#);
+        assert_eq!(response.key_id, "oracle_signing_key");
+        assert_eq!(response.signature_scheme, "ECDSA-secp256r1-SHA256");
+        let key = oracle.crypto_service.get_key_metadata("oracle_signing_key").unwrap();
+        assert_eq!(hex::decode(&response.public_key).unwrap(), key.public_key.unwrap());
+
+        // An independent verifier needs only the documented message layout
+        let verifying_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&hex::decode(&response.public_key).unwrap()).unwrap();
+        let signature = p256::ecdsa::Signature::from_slice(&hex::decode(&response.signature).unwrap()).unwrap();
+        let message = [url.as_bytes(), &response.timestamp.to_be_bytes(), response.payload.as_bytes()].concat();
+        verifying_key.verify(&message, &signature).unwrap();
+
+        let tampered = |edit: fn(&mut SignedOracleResponse)| {
+            let mut response = response.clone();
+            edit(&mut response);
+            oracle.verify_signed_response(&serde_json::to_string(&response).unwrap()).unwrap()
+        };
+        assert!(!tampered(|r| r.payload = r.payload.replace("12.5", "13.5")));
+        assert!(!tampered(|r| r.timestamp += 1));
+        assert!(!tampered(|r| r.url.push('?')));
+    }
+
+    #[tokio::test]
+    async fn schema_violations_are_reported_with_their_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let oracle = OracleService::new(&config, crypto).await.unwrap();
+        let schema = r#"{
+            "type": "object",
+            "required": ["symbol", "quote"],
+            "properties": {
+                "symbol": {"type": "string"},
+                "quote": {
+                    "type": "object",
+                    "required": ["price"],
+                    "properties": {"price": {"type": "number", "minimum": 0}}
+                }
+            }
+        }"#;
+        let validate = |data: &str| -> serde_json::Value {
+            serde_json::from_str(&oracle.validate_against_schema(data, schema).unwrap()).unwrap()
+        };
+        let paths = |result: &serde_json::Value| -> Vec<(String, String)> {
+            result["errors"].as_array().unwrap().iter()
+                .map(|e| (e["path"].as_str().unwrap().to_string(), e["schema_path"].as_str().unwrap().to_string()))
+                .collect()
+        };
+
+        let valid = validate(r#"{"symbol": "NEO", "quote": {"price": 12.5}}"#);
+        assert_eq!((valid["valid"].as_bool(), valid["error_count"].as_u64()), (Some(true), Some(0)));
+
+        let missing = validate(r#"{"symbol": "NEO"}"#);
+        assert_eq!(missing["valid"], false);
+        assert_eq!(paths(&missing), vec![(String::new(), "/required".to_string())]);
+        assert!(missing["errors"][0]["message"].as_str().unwrap().contains("quote"));
+
+        let mistyped = validate(r#"{"symbol": 7, "quote": {"price": "12.5"}}"#);
+        let mut mistyped = paths(&mistyped);
+        mistyped.sort();
+        assert_eq!(mistyped, vec![
+            ("/quote/price".to_string(), "/properties/quote/properties/price/type".to_string()),
+            ("/symbol".to_string(), "/properties/symbol/type".to_string()),
+        ]);
+
+        let nested = validate(r#"{"symbol": "NEO", "quote": {"price": -1}}"#);
+        assert_eq!(paths(&nested), vec![("/quote/price".to_string(), "/properties/quote/properties/price/minimum".to_string())]);
+
+        assert!(oracle.validate_against_schema("{", schema).is_err());
+        assert!(oracle.validate_against_schema("{}", r#"{"type": 5}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn validate_schema_script_uses_the_default_oracle_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let oracle = OracleService::new(&config, crypto).await.unwrap();
+        let check = |data: &str| -> serde_json::Value {
+            serde_json::from_str(&oracle.process_data(data, "validate_schema").unwrap()).unwrap()
+        };
+
+        let complete = check(r#"{"price": 12.5, "timestamp": 1700000000, "symbol": "NEO"}"#);
+        assert_eq!(complete["valid"], true);
+        assert_eq!(complete["completeness_score"], 1.0);
+
+        let partial = check(r#"{"price": "12.5", "symbol": "NEO"}"#);
+        assert_eq!(partial["valid"], false);
+        assert_eq!(partial["error_count"], 2);
+        assert_eq!(partial["has_price"], true);
+        assert_eq!(partial["has_timestamp"], false);
+    }
+
+    #[tokio::test]
+    async fn oracle_signing_key_is_attestation_bound_when_configured() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::test_support::test_config(dir.path());
+        config.attest_signing_keys = true;
+        config.oracle_signing_key_id = "attested_oracle".to_string();
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let oracle = OracleService::new(&config, crypto.clone()).await.unwrap();
+        assert_eq!(oracle.signing_key_id, "attested_oracle");
+        crate::test_support::assert_attested(&crypto, "attested_oracle");
+    }
+
+    /// Serve `{}` and keep the head of every request received, lowercased
+    async fn record_requests() -> (std::net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                log.lock().unwrap().push(String::from_utf8_lossy(&request[..read]).to_ascii_lowercase());
+                let response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (address, received)
+    }
+
+    #[tokio::test]
+    async fn configured_user_agent_and_default_headers_are_sent() {
+        let (address, received) = record_requests().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::test_support::test_config(dir.path());
+        config.oracle_user_agent = "neo-oracle/1.0".to_string();
+        config.oracle_default_headers = BTreeMap::from([
+            ("Accept".to_string(), "application/json".to_string()),
+            ("X-Api-Key".to_string(), "provider-secret".to_string()),
+        ]);
+        let oracle = loopback_oracle_with(config).await;
+
+        oracle.fetch_data(&format!("http://{}/defaults", address), None, None).await.unwrap();
+        let overrides = HashMap::from([("Accept".to_string(), "text/csv".to_string())]);
+        oracle.fetch_data(&format!("http://{}/overridden", address), Some(overrides), None).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        for request in received.iter() {
+            assert!(request.contains("\r\nuser-agent: neo-oracle/1.0\r\n"), "{}", request);
+            assert!(request.contains("\r\nx-api-key: provider-secret\r\n"), "{}", request);
+        }
+        assert!(received[0].contains("\r\naccept: application/json\r\n"), "{}", received[0]);
+        assert!(received[1].contains("\r\naccept: text/csv\r\n"), "{}", received[1]);
+        assert!(!received[1].contains("application/json"), "{}", received[1]);
+    }
+
+    #[test]
+    fn credential_default_headers_are_marked_sensitive() {
+        let headers = default_headers(&BTreeMap::from([
+            ("Accept".to_string(), "application/json".to_string()),
+            ("Authorization".to_string(), "Bearer token".to_string()),
+            ("X-Api-Key".to_string(), "provider-secret".to_string()),
+        ])).unwrap();
+        assert!(!headers["accept"].is_sensitive());
+        assert!(headers["authorization"].is_sensitive());
+        assert!(headers["x-api-key"].is_sensitive());
+
+        assert!(default_headers(&BTreeMap::from([("Bad Header".to_string(), "x".to_string())])).is_err());
+        assert!(default_headers(&BTreeMap::from([("X-Ok".to_string(), "line\nbreak".to_string())])).is_err());
+    }
+
+    #[tokio::test]
+    async fn localhost_is_rejected_unless_private_hosts_are_allowed() {
+        assert!(PinnedResolver::new(false).resolve_host("localhost").await.is_err());
+        let addrs = PinnedResolver::new(true).resolve_host("localhost").await.unwrap();
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = crate::test_support::test_config(dir.path());
+        let (_, _, crypto) = crate::test_support::core_services(&config).await;
+        let mut oracle = OracleService::new(&config, crypto.clone()).await.unwrap();
+        oracle.allowed_domains.push("localhost".to_string());
+        let error = oracle.validate_url("http://localhost/price").await.unwrap_err();
+        assert!(error.to_string().contains("non-public address"), "{}", error);
+
+        let mut private_config = config.clone();
+        private_config.oracle_allow_private_hosts = true;
+        let mut oracle = OracleService::new(&private_config, crypto).await.unwrap();
+        oracle.allowed_domains.push("localhost".to_string());
+        oracle.validate_url("http://localhost/price").await.unwrap();
+    }
+}
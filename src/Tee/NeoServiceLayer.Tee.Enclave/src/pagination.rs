@@ -0,0 +1,73 @@
+use serde::Serialize;
+
+/// Page size when the caller does not pass a limit
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+
+/// Largest page a listing returns; bigger limits are clamped to it
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// One page of a listing in the envelope shared by every list endpoint
+///
+/// `items` are the entries starting at `offset`, at most `limit` of them, taken from a
+/// listing of `total` matching entries in a stable order. `next_offset` is the offset of
+/// the following page, or null on the last one. An offset past the end yields no items
+/// but still reports the total.
+pub fn paginate<T: Serialize>(
+    items: impl IntoIterator<Item = T>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    total: usize,
+) -> serde_json::Value {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+    let offset = offset.unwrap_or(0);
+    let page: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+    let end = offset.saturating_add(page.len());
+    let next_offset = (end < total && !page.is_empty()).then_some(end);
+
+    serde_json::json!({
+        "items": page,
+        "total": total,
+        "limit": limit,
+        "offset": offset,
+        "next_offset": next_offset,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_share_one_envelope() {
+        let page = paginate(0..5, Some(2), Some(2), 5);
+        assert_eq!(page, serde_json::json!({ "items": [2, 3], "total": 5, "limit": 2, "offset": 2, "next_offset": 4 }));
+
+        let last = paginate(0..5, Some(2), Some(4), 5);
+        assert_eq!(last["items"], serde_json::json!([4]));
+        assert_eq!(last["next_offset"], serde_json::Value::Null);
+
+        let defaults = paginate(0..DEFAULT_PAGE_LIMIT + 1, None, None, DEFAULT_PAGE_LIMIT + 1);
+        assert_eq!(defaults["items"].as_array().unwrap().len(), DEFAULT_PAGE_LIMIT);
+        assert_eq!(defaults["offset"], 0);
+        assert_eq!(defaults["next_offset"], DEFAULT_PAGE_LIMIT);
+    }
+
+    #[test]
+    fn page_boundaries() {
+        // Past the end: no items, but the total still tells the client where the end is
+        let beyond = paginate(0..5, Some(2), Some(10), 5);
+        assert_eq!(beyond, serde_json::json!({ "items": [], "total": 5, "limit": 2, "offset": 10, "next_offset": null }));
+
+        let exact = paginate(0..4, Some(2), Some(2), 4);
+        assert_eq!(exact["next_offset"], serde_json::Value::Null);
+
+        let empty = paginate(Vec::<u8>::new(), Some(2), None, 0);
+        assert_eq!(empty["items"], serde_json::json!([]));
+        assert_eq!(empty["total"], 0);
+
+        // A zero limit never reports a next page it cannot advance to
+        assert_eq!(paginate(0..5, Some(0), None, 5)["next_offset"], serde_json::Value::Null);
+        assert_eq!(paginate(0..5, Some(MAX_PAGE_LIMIT + 1), None, 5)["limit"], MAX_PAGE_LIMIT);
+        assert_eq!(paginate(0..5, Some(2), Some(usize::MAX), 5)["items"], serde_json::json!([]));
+    }
+}
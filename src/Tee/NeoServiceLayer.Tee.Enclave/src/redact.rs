@@ -0,0 +1,65 @@
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether sensitive values are logged in full; off unless the operator opts in
+static LOG_SECRETS: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable full logging of values wrapped with [`redact`] and friends
+pub fn set_log_secrets(enabled: bool) {
+    LOG_SECRETS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn log_secrets() -> bool {
+    LOG_SECRETS.load(Ordering::Relaxed)
+}
+
+/// Sensitive value formatted for logs
+///
+/// Shown in full only when secret logging is enabled. Otherwise it is replaced by a short
+/// SHA-256 fingerprint, so log lines about the same value can still be correlated.
+pub struct Redacted<'a>(Sensitive<'a>);
+
+enum Sensitive<'a> {
+    Text(&'a str),
+    Bytes(&'a [u8]),
+    Url(&'a str),
+}
+
+/// Redact a string such as an address or storage key name
+pub fn redact(value: &str) -> Redacted<'_> {
+    Redacted(Sensitive::Text(value))
+}
+
+/// Redact binary data such as a public key; shown as hex when secret logging is enabled
+pub fn redact_bytes(value: &[u8]) -> Redacted<'_> {
+    Redacted(Sensitive::Bytes(value))
+}
+
+/// Redact the query and fragment of a URL, which often carry API keys; the scheme, host
+/// and path stay visible
+pub fn redact_url(url: &str) -> Redacted<'_> {
+    Redacted(Sensitive::Url(url))
+}
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secrets = log_secrets();
+        match self.0 {
+            Sensitive::Text(value) if secrets => f.write_str(value),
+            Sensitive::Bytes(value) if secrets => f.write_str(&hex::encode(value)),
+            Sensitive::Url(url) if secrets => f.write_str(url),
+            Sensitive::Text(value) => write_fingerprint(f, value.as_bytes()),
+            Sensitive::Bytes(value) => write_fingerprint(f, value),
+            Sensitive::Url(url) => match url.find(['?', '#']) {
+                Some(index) => f.write_str(&url[..=index])
+                    .and_then(|_| write_fingerprint(f, &url.as_bytes()[index + 1..])),
+                None => f.write_str(url),
+            },
+        }
+    }
+}
+
+fn write_fingerprint(f: &mut fmt::Formatter<'_>, value: &[u8]) -> fmt::Result {
+    write!(f, "<redacted:{}>", hex::encode(&Sha256::digest(value)[..4]))
+}
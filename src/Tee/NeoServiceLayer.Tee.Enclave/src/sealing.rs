@@ -0,0 +1,398 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs::{self, File};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use ring::{aead, hmac};
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::EncaveConfig;
+use crate::error::EnclaveError;
+use crate::storage_backend::PathSandbox;
+
+/// Occlum device exposing `EGETKEY` through an ioctl
+const SGX_DEVICE_PATH: &str = "/dev/sgx";
+
+/// `_IOWR('s', 11, IoctlGetKeyArg)`: derive a 128-bit key from the enclave identity
+const SGXIOC_GET_KEY: u64 = 0xc010_730b;
+
+/// `sgx_key_request_t::key_name` for a seal key
+const SGX_KEYSELECT_SEAL: u16 = 0x0004;
+/// `sgx_key_request_t::key_policy`: bind the key to the enclave measurement, so only the
+/// same enclave build can unseal. Only read from blobs sealed before seal domains.
+const SGX_KEYPOLICY_MRENCLAVE: u16 = 0x0001;
+/// `sgx_key_request_t::key_policy`: bind the key to the enclave signer, so later builds
+/// with an equal or higher ISV SVN can unseal
+const SGX_KEYPOLICY_MRSIGNER: u16 = 0x0002;
+
+/// Sealed blob layout: magic, key policy (u16 LE), ISV SVN (u16 LE), seal domain, key id,
+/// AES-128-GCM nonce, then the ciphertext with its tag. The header is authenticated as
+/// associated data.
+const SEALED_MAGIC: &[u8; 8] = b"NSLSEAL2";
+const KEY_ID_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const SEALED_HEADER_SIZE: usize = SEALED_MAGIC.len() + 2 + 2 + 1 + KEY_ID_SIZE;
+
+/// Blobs from before seal domains: magic, MRENCLAVE policy and key id. They are only
+/// unsealed for the enclave itself, which re-seals them in the current format.
+const LEGACY_SEALED_MAGIC: &[u8; 8] = b"NSLSEAL1";
+const LEGACY_SEALED_HEADER_SIZE: usize = LEGACY_SEALED_MAGIC.len() + 2 + KEY_ID_SIZE;
+
+/// File holding the stand-in sealing secret used in simulation mode
+const SIMULATION_SEAL_SECRET_FILE: &str = ".simulation_seal_secret";
+
+/// `sgx_key_request_t`
+#[repr(C)]
+struct SgxKeyRequest {
+    key_name: u16,
+    key_policy: u16,
+    isv_svn: u16,
+    reserved1: u16,
+    cpu_svn: [u8; 16],
+    attribute_mask_flags: u64,
+    attribute_mask_xfrm: u64,
+    key_id: [u8; KEY_ID_SIZE],
+    misc_mask: u32,
+    config_svn: u16,
+    reserved2: [u8; 434],
+}
+
+#[repr(C)]
+struct IoctlGetKeyArg {
+    key_request: *const SgxKeyRequest,
+    key: *mut [u8; 16],
+}
+
+/// Owner of a sealed blob. A blob only unseals in the domain it was sealed in, so data
+/// the host seals through the FFI can never be exchanged for the enclave's own secrets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SealDomain {
+    /// Secrets the enclave keeps for itself, such as the storage master key
+    Internal,
+    /// Data sealed and unsealed on behalf of the host
+    External,
+}
+
+impl SealDomain {
+    fn tag(self) -> u8 {
+        match self {
+            SealDomain::Internal => 1,
+            SealDomain::External => 2,
+        }
+    }
+
+    /// Label mixed into the seal key id, so each domain uses different keys
+    fn key_label(self) -> &'static [u8] {
+        match self {
+            SealDomain::Internal => b"nsl-seal-internal",
+            SealDomain::External => b"nsl-seal-external",
+        }
+    }
+}
+
+/// Seals data to the enclave signer so it can be kept on untrusted storage
+///
+/// On hardware each blob is encrypted under an SGX seal key bound to MRSIGNER and the
+/// configured ISV SVN, so upgraded builds keep access while older builds cannot read newer
+/// blobs. The key id is derived from a random value stored in the blob and the seal domain.
+/// In simulation mode there is no seal key, so keys are derived from a secret kept next to
+/// the storage directory instead; such blobs offer no protection against anyone who can
+/// read that directory.
+pub struct SealingService {
+    simulation_secret: Option<Zeroizing<Vec<u8>>>,
+    isv_svn: u16,
+    rng: SystemRandom,
+}
+
+impl SealingService {
+    pub fn new(config: &EncaveConfig) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let simulation_secret = if config.sgx_simulation_mode {
+            warn!("Sealing running in simulation mode; sealed data is not bound to an enclave");
+            if config.storage_backend == "memory" {
+                // Nothing sealed outlives the process, so the secret need not either
+                let mut secret = Zeroizing::new(vec![0u8; 32]);
+                rng.fill(&mut secret).map_err(|_| anyhow!("Failed to generate simulation seal secret"))?;
+                Some(secret)
+            } else {
+                Some(load_simulation_secret(Path::new(&config.storage_path), &rng)?)
+            }
+        } else {
+            None
+        };
+        Ok(Self { simulation_secret, isv_svn: config.seal_isv_svn, rng })
+    }
+
+    /// Encrypt `data` so that only this enclave, or a later build from the same signer,
+    /// can recover it with [`SealingService::unseal_data`] in the same `domain`
+    pub fn seal_data(&self, domain: SealDomain, data: &[u8]) -> Result<Vec<u8>> {
+        let mut key_id = [0u8; KEY_ID_SIZE];
+        self.rng.fill(&mut key_id).map_err(|_| anyhow!("Failed to generate seal key id"))?;
+        let mut nonce = [0u8; NONCE_SIZE];
+        self.rng.fill(&mut nonce).map_err(|_| anyhow!("Failed to generate seal nonce"))?;
+
+        let mut sealed = Vec::with_capacity(SEALED_HEADER_SIZE + NONCE_SIZE + data.len() + aead::AES_128_GCM.tag_len());
+        sealed.extend_from_slice(SEALED_MAGIC);
+        sealed.extend_from_slice(&SGX_KEYPOLICY_MRSIGNER.to_le_bytes());
+        sealed.extend_from_slice(&self.isv_svn.to_le_bytes());
+        sealed.push(domain.tag());
+        sealed.extend_from_slice(&key_id);
+
+        let key = self.seal_key(SGX_KEYPOLICY_MRSIGNER, self.isv_svn, &domain_key_id(domain, &key_id))?;
+        let mut ciphertext = data.to_vec();
+        key.seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key(nonce),
+            aead::Aad::from(&sealed[..SEALED_HEADER_SIZE]),
+            &mut ciphertext,
+        ).map_err(|_| EnclaveError::Crypto("Sealing failed".into()))?;
+
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Recover data sealed in `domain` by this enclave or an earlier build from its signer
+    ///
+    /// Blobs from before seal domains are only accepted in [`SealDomain::Internal`]; callers
+    /// should re-seal them when [`SealingService::needs_reseal`] says so.
+    pub fn unseal_data(&self, domain: SealDomain, sealed: &[u8]) -> Result<Vec<u8>> {
+        let min_size = |header_size: usize| header_size + NONCE_SIZE + aead::AES_128_GCM.tag_len();
+
+        let (policy, isv_svn, header_size, key_id) = if sealed.starts_with(SEALED_MAGIC) && sealed.len() >= min_size(SEALED_HEADER_SIZE) {
+            let policy = u16::from_le_bytes([sealed[8], sealed[9]]);
+            if policy != SGX_KEYPOLICY_MRSIGNER {
+                return Err(EnclaveError::InvalidInput(format!("Unsupported seal key policy {:#x}", policy)).into());
+            }
+            if sealed[12] != domain.tag() {
+                return Err(EnclaveError::PermissionDenied("Sealed blob belongs to another seal domain".into()).into());
+            }
+            let isv_svn = u16::from_le_bytes([sealed[10], sealed[11]]);
+            if isv_svn > self.isv_svn {
+                return Err(EnclaveError::InvalidInput(format!(
+                    "Blob was sealed at ISV SVN {}, above this enclave's {}", isv_svn, self.isv_svn
+                )).into());
+            }
+            let key_id: [u8; KEY_ID_SIZE] = sealed[SEALED_HEADER_SIZE - KEY_ID_SIZE..SEALED_HEADER_SIZE].try_into()?;
+            (policy, isv_svn, SEALED_HEADER_SIZE, domain_key_id(domain, &key_id))
+        } else if sealed.starts_with(LEGACY_SEALED_MAGIC) && sealed.len() >= min_size(LEGACY_SEALED_HEADER_SIZE) {
+            // Legacy blobs carry no domain, so the host could pass in the enclave's own
+            if domain != SealDomain::Internal {
+                return Err(EnclaveError::PermissionDenied("Blobs sealed before seal domains cannot be unsealed by the host".into()).into());
+            }
+            let policy = u16::from_le_bytes([sealed[8], sealed[9]]);
+            if policy != SGX_KEYPOLICY_MRENCLAVE {
+                return Err(EnclaveError::InvalidInput(format!("Unsupported seal key policy {:#x}", policy)).into());
+            }
+            let key_id: [u8; KEY_ID_SIZE] = sealed[LEGACY_SEALED_HEADER_SIZE - KEY_ID_SIZE..LEGACY_SEALED_HEADER_SIZE].try_into()?;
+            (policy, 0, LEGACY_SEALED_HEADER_SIZE, key_id)
+        } else {
+            return Err(EnclaveError::InvalidInput("Not a sealed blob".into()).into());
+        };
+
+        let (header, rest) = sealed.split_at(header_size);
+        let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+
+        let key = if header_size == LEGACY_SEALED_HEADER_SIZE {
+            self.legacy_seal_key(&key_id)?
+        } else {
+            self.seal_key(policy, isv_svn, &key_id)?
+        };
+        let mut plaintext = ciphertext.to_vec();
+        let len = key.open_in_place(
+            aead::Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid seal nonce"))?,
+            aead::Aad::from(header),
+            &mut plaintext,
+        ).map_err(|_| EnclaveError::Crypto("Unsealing failed: data was sealed by a different enclave or modified".into()))?.len();
+        plaintext.truncate(len);
+        Ok(plaintext)
+    }
+
+    /// Whether `sealed` should be sealed again in the current format: it predates seal
+    /// domains or was sealed at a lower ISV SVN than this enclave's
+    pub fn needs_reseal(&self, sealed: &[u8]) -> bool {
+        if sealed.starts_with(LEGACY_SEALED_MAGIC) {
+            return true;
+        }
+        sealed.starts_with(SEALED_MAGIC)
+            && sealed.len() >= SEALED_HEADER_SIZE
+            && u16::from_le_bytes([sealed[10], sealed[11]]) < self.isv_svn
+    }
+
+    fn seal_key(&self, policy: u16, isv_svn: u16, key_id: &[u8; KEY_ID_SIZE]) -> Result<aead::LessSafeKey> {
+        let key_bytes = match &self.simulation_secret {
+            Some(secret) => {
+                let mut input = Vec::with_capacity(4 + KEY_ID_SIZE);
+                input.extend_from_slice(&policy.to_le_bytes());
+                input.extend_from_slice(&isv_svn.to_le_bytes());
+                input.extend_from_slice(key_id);
+                simulation_seal_key(secret, &input)
+            }
+            None => sgx_seal_key(policy, isv_svn, key_id)?,
+        };
+        aes_128_key(&key_bytes)
+    }
+
+    /// Seal key of blobs from before seal domains: MRENCLAVE policy at ISV SVN 0
+    fn legacy_seal_key(&self, key_id: &[u8; KEY_ID_SIZE]) -> Result<aead::LessSafeKey> {
+        let key_bytes = match &self.simulation_secret {
+            Some(secret) => simulation_seal_key(secret, key_id),
+            None => sgx_seal_key(SGX_KEYPOLICY_MRENCLAVE, 0, key_id)?,
+        };
+        aes_128_key(&key_bytes)
+    }
+}
+
+/// Key id for the seal key of a blob in `domain` with the random id `key_id`
+fn domain_key_id(domain: SealDomain, key_id: &[u8; KEY_ID_SIZE]) -> [u8; KEY_ID_SIZE] {
+    let mut hasher = Sha256::new();
+    hasher.update(domain.key_label());
+    hasher.update(key_id);
+    hasher.finalize().into()
+}
+
+fn simulation_seal_key(secret: &[u8], input: &[u8]) -> Zeroizing<[u8; 16]> {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret), input);
+    let mut key = Zeroizing::new([0u8; 16]);
+    key.copy_from_slice(&tag.as_ref()[..16]);
+    key
+}
+
+fn aes_128_key(key_bytes: &[u8; 16]) -> Result<aead::LessSafeKey> {
+    let unbound = aead::UnboundKey::new(&aead::AES_128_GCM, key_bytes)
+        .map_err(|_| anyhow!("Invalid seal key"))?;
+    Ok(aead::LessSafeKey::new(unbound))
+}
+
+/// Ask the CPU for the seal key with the given policy, ISV SVN and key id
+fn sgx_seal_key(policy: u16, isv_svn: u16, key_id: &[u8; KEY_ID_SIZE]) -> Result<Zeroizing<[u8; 16]>> {
+    let device = File::open(SGX_DEVICE_PATH)
+        .map_err(EnclaveError::Io)
+        .with_context(|| format!("SGX device {} is unavailable for sealing", SGX_DEVICE_PATH))?;
+
+    let request = SgxKeyRequest {
+        key_name: SGX_KEYSELECT_SEAL,
+        key_policy: policy,
+        isv_svn,
+        reserved1: 0,
+        cpu_svn: [0u8; 16],
+        // Bind to the INITTED and DEBUG attributes, as the SGX SDK's sealing does
+        attribute_mask_flags: 0xFF00_0000_0000_000B,
+        attribute_mask_xfrm: 0,
+        key_id: *key_id,
+        misc_mask: 0xF000_0000,
+        config_svn: 0,
+        reserved2: [0u8; 434],
+    };
+    let mut key = Zeroizing::new([0u8; 16]);
+    let mut arg = IoctlGetKeyArg { key_request: &request, key: &mut *key };
+    // SAFETY: both pointers refer to live, correctly sized values for the duration of the call
+    let ret = unsafe { libc::ioctl(device.as_raw_fd(), SGXIOC_GET_KEY as _, &mut arg as *mut IoctlGetKeyArg) };
+    if ret < 0 {
+        return Err(EnclaveError::Io(std::io::Error::last_os_error()).into());
+    }
+    Ok(key)
+}
+
+/// Load or create the simulation-mode stand-in for the hardware seal key
+fn load_simulation_secret(storage_dir: &Path, rng: &SystemRandom) -> Result<Zeroizing<Vec<u8>>> {
+    let secret_file: PathBuf = PathSandbox::new(storage_dir)?.resolve(SIMULATION_SEAL_SECRET_FILE)?;
+    if let Ok(secret) = fs::read(&secret_file) {
+        if secret.len() == 32 {
+            return Ok(Zeroizing::new(secret));
+        }
+    }
+
+    let mut secret = Zeroizing::new(vec![0u8; 32]);
+    rng.fill(&mut secret).map_err(|_| anyhow!("Failed to generate simulation seal secret"))?;
+    fs::write(&secret_file, &*secret)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&secret_file, fs::Permissions::from_mode(0o600))?;
+    }
+    info!("Generated simulation seal secret");
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::test_config;
+
+    fn sealing_service(dir: &Path) -> SealingService {
+        SealingService::new(&test_config(dir)).unwrap()
+    }
+
+    /// Seal `data` the way enclaves did before seal domains
+    fn legacy_seal(service: &SealingService, data: &[u8]) -> Vec<u8> {
+        let key_id = [7u8; KEY_ID_SIZE];
+        let mut sealed = LEGACY_SEALED_MAGIC.to_vec();
+        sealed.extend_from_slice(&SGX_KEYPOLICY_MRENCLAVE.to_le_bytes());
+        sealed.extend_from_slice(&key_id);
+        let mut ciphertext = data.to_vec();
+        service.legacy_seal_key(&key_id).unwrap().seal_in_place_append_tag(
+            aead::Nonce::assume_unique_for_key([1u8; NONCE_SIZE]),
+            aead::Aad::from(&sealed[..]),
+            &mut ciphertext,
+        ).unwrap();
+        sealed.extend_from_slice(&[1u8; NONCE_SIZE]);
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    fn is_permission_denied(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::PermissionDenied(_)))
+    }
+
+    #[test]
+    fn blobs_only_unseal_in_their_domain() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = sealing_service(dir.path());
+
+        let internal = service.seal_data(SealDomain::Internal, b"master key").unwrap();
+        let external = service.seal_data(SealDomain::External, b"host data").unwrap();
+        assert_eq!(service.unseal_data(SealDomain::Internal, &internal).unwrap(), b"master key");
+        assert_eq!(service.unseal_data(SealDomain::External, &external).unwrap(), b"host data");
+
+        assert!(is_permission_denied(&service.unseal_data(SealDomain::External, &internal).unwrap_err()));
+        assert!(is_permission_denied(&service.unseal_data(SealDomain::Internal, &external).unwrap_err()));
+
+        // Relabelling the blob does not help: the domain is part of the key and the AAD
+        let mut relabelled = internal.clone();
+        relabelled[12] = SealDomain::External.tag();
+        assert!(service.unseal_data(SealDomain::External, &relabelled).is_err());
+    }
+
+    #[test]
+    fn legacy_blobs_unseal_only_internally_and_need_resealing() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = sealing_service(dir.path());
+
+        let legacy = legacy_seal(&service, b"old master key");
+        assert!(service.needs_reseal(&legacy));
+        assert_eq!(service.unseal_data(SealDomain::Internal, &legacy).unwrap(), b"old master key");
+        assert!(is_permission_denied(&service.unseal_data(SealDomain::External, &legacy).unwrap_err()));
+
+        let resealed = service.seal_data(SealDomain::Internal, b"old master key").unwrap();
+        assert!(!service.needs_reseal(&resealed));
+    }
+
+    #[test]
+    fn blobs_from_a_higher_svn_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.seal_isv_svn = 2;
+        let newer = SealingService::new(&config).unwrap();
+        config.seal_isv_svn = 1;
+        let older = SealingService::new(&config).unwrap();
+
+        let from_older = older.seal_data(SealDomain::Internal, b"secret").unwrap();
+        assert_eq!(newer.unseal_data(SealDomain::Internal, &from_older).unwrap(), b"secret");
+        assert!(newer.needs_reseal(&from_older));
+
+        let from_newer = newer.seal_data(SealDomain::Internal, b"secret").unwrap();
+        assert!(older.unseal_data(SealDomain::Internal, &from_newer).is_err());
+    }
+}
@@ -1,20 +1,134 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write, Seek, SeekFrom};
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
-use flate2::{Compression, read::GzDecoder, write::GzEncoder};
-use lz4_flex::{compress_prepend_size, decompress_size_prepended};
+use flate2::read::GzDecoder;
+use lz4_flex::compress_prepend_size;
 use sha2::{Sha256, Digest};
 use log::{info, warn, error, debug};
+use zeroize::{Zeroize, Zeroizing};
 use ring::{aead, digest as ring_digest, rand};
 use ring::rand::SecureRandom;
 use ring::aead::BoundKey;
 
 use crate::EncaveConfig;
+use crate::error::EnclaveError;
+use crate::redact::redact;
+use crate::sealing::{SealDomain, SealingService};
+use crate::storage_backend::{self, BackendObject, StorageBackend};
+use crate::health::ServiceHealth;
+use crate::locks::{self, RwLockExt};
+use crate::metrics::StorageMetrics;
+use crate::pagination::paginate;
+
+/// Master key sealed to the enclave identity
+const SEALED_MASTER_KEY_FILE: &str = ".master_key.sealed";
+
+/// Plaintext master key written by earlier versions; migrated on startup
+const LEGACY_MASTER_KEY_FILE: &str = ".master_key";
+
+/// Free space below which storage reports itself as degraded
+const MIN_HEALTHY_FREE_SPACE: u64 = 100 * 1024 * 1024; // 100MB
+
+/// Ciphertext format version. Version 1 binds the storage key name into the AEAD
+/// associated data; version 0 (legacy entries) used empty associated data and is only
+/// decrypted when `storage_accept_legacy_format` is set.
+const STORAGE_FORMAT_VERSION: u32 = 1;
+
+/// Span of plaintext covered by each per-chunk integrity hash
+const INTEGRITY_CHUNK_SIZE: usize = 1024 * 1024; // 1MB
+
+/// Backend objects holding the index snapshot and its journal
+const INDEX_OBJECT: &str = "index.json";
+const INDEX_JOURNAL_OBJECT: &str = "index.journal";
+
+/// Journal records after which the index is compacted into a fresh snapshot
+const INDEX_COMPACTION_THRESHOLD: usize = 1000;
+
+/// Leading bytes of a backup archive written by `create_snapshot`
+const SNAPSHOT_MAGIC: &[u8; 8] = b"NSLSNAP\0";
+
+/// Backup archive format version
+const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// PBKDF2 rounds deriving the archive key from the backup passphrase
+const SNAPSHOT_KDF_ITERATIONS: u32 = 100_000;
+
+/// Largest KDF work factor accepted from an archive header
+const MAX_SNAPSHOT_KDF_ITERATIONS: u32 = 10_000_000;
+
+/// Largest encrypted record accepted while restoring
+const MAX_SNAPSHOT_RECORD_BYTES: usize = 256 * 1024 * 1024;
+
+/// Principal that enclave services use for their own records
+pub const SYSTEM_PRINCIPAL: &str = "enclave";
+
+/// ACL entry matching every principal
+pub const ANY_PRINCIPAL: &str = "*";
+
+/// Storage errors callers may need to tell apart from other failures
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum StorageError {
+    /// Also returned for missing keys, so callers cannot probe which keys exist
+    #[error("Access denied for key '{key}'")]
+    AccessDenied { key: String },
+    /// The write does not fit under `storage_max_total_bytes`, even after eviction
+    #[error("Storage quota exceeded: {needed} bytes needed, {available} available")]
+    QuotaExceeded { needed: u64, available: u64 },
+}
+
+/// How `store_data` makes room when the total-size quota would be exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvictionPolicy {
+    /// Evict least recently accessed unpinned entries
+    Lru,
+    /// Reject the write
+    Reject,
+}
+
+impl EvictionPolicy {
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "lru" => Ok(Self::Lru),
+            "reject" => Ok(Self::Reject),
+            other => Err(anyhow!("Unknown storage eviction policy: {}", other)),
+        }
+    }
+}
+
+/// Principals other than the owner that may access an entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageAcl {
+    /// May retrieve the entry
+    #[serde(default)]
+    pub readers: Vec<String>,
+    /// May retrieve, update and delete the entry
+    #[serde(default)]
+    pub writers: Vec<String>,
+}
+
+/// How `store_data` writes a new entry
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    /// Compress the data when the compression policy finds it worthwhile
+    pub compress: bool,
+    /// Principals given access in addition to the owner
+    pub acl: StorageAcl,
+    /// Overwrite the entry's object before removing it when it is deleted
+    pub secure_delete: bool,
+    /// Exclude the entry from quota eviction from the moment it is stored
+    pub pinned: bool,
+}
+
+/// Kind of access checked against an entry's owner and ACL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StorageAccess {
+    Read,
+    Write,
+}
 
 /// Storage metadata for files
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,13 +143,191 @@ pub struct StorageMetadata {
     pub encryption: bool,
     pub hash: String,
     pub access_count: u64,
+    /// Ciphertext format, see `STORAGE_FORMAT_VERSION`
+    #[serde(default)]
+    pub format_version: u32,
+    /// Principal that stored the entry; empty for entries written before ownership existed
+    #[serde(default)]
+    pub owner: String,
+    #[serde(default)]
+    pub acl: StorageAcl,
+    /// Pinned entries are never evicted to satisfy the storage quota
+    #[serde(default)]
+    pub pinned: bool,
+    /// Hashes of fixed-size plaintext chunks, recorded for entries larger than one chunk
+    /// so corruption can be located; absent for small and older entries
+    #[serde(default)]
+    pub chunk_hashes: Option<ChunkHashes>,
+    /// Overwrite the stored object with random bytes before removing it on delete or
+    /// eviction
+    #[serde(default)]
+    pub secure_delete: bool,
+    /// How `compression` was chosen; absent for entries written before the policy existed
+    #[serde(default)]
+    pub compression_policy: Option<CompressionPolicy>,
+}
+
+/// Per-chunk SHA-256 hashes of an entry's plaintext
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkHashes {
+    pub chunk_size: u64,
+    pub hashes: Vec<String>,
+}
+
+/// Hash `data` as a whole and, when it spans several chunks, chunk by chunk
+fn compute_integrity_hashes(data: &[u8]) -> (String, Option<ChunkHashes>) {
+    let hash = hex::encode(Sha256::digest(data));
+    let chunk_hashes = (data.len() > INTEGRITY_CHUNK_SIZE).then(|| ChunkHashes {
+        chunk_size: INTEGRITY_CHUNK_SIZE as u64,
+        hashes: data.chunks(INTEGRITY_CHUNK_SIZE)
+            .map(|chunk| hex::encode(Sha256::digest(chunk)))
+            .collect(),
+    });
+    (hash, chunk_hashes)
+}
+
+/// Checks plaintext against an entry's hashes as it is produced
+///
+/// The overall hash is computed incrementally, and when per-chunk hashes are recorded each
+/// chunk is checked as soon as it is complete, so a corrupt chunk fails the read without
+/// waiting for the rest of the entry.
+struct IntegrityVerifier<'a> {
+    metadata: &'a StorageMetadata,
+    overall: Sha256,
+    chunk: Sha256,
+    chunk_filled: usize,
+    chunk_index: usize,
+}
+
+impl<'a> IntegrityVerifier<'a> {
+    fn new(metadata: &'a StorageMetadata) -> Self {
+        Self {
+            metadata,
+            overall: Sha256::new(),
+            chunk: Sha256::new(),
+            chunk_filled: 0,
+            chunk_index: 0,
+        }
+    }
+    
+    fn update(&mut self, mut data: &[u8]) -> Result<()> {
+        self.overall.update(data);
+        let Some(chunk_hashes) = &self.metadata.chunk_hashes else {
+            return Ok(());
+        };
+        let chunk_size = chunk_hashes.chunk_size as usize;
+        
+        while !data.is_empty() {
+            let take = (chunk_size - self.chunk_filled).min(data.len());
+            self.chunk.update(&data[..take]);
+            self.chunk_filled += take;
+            data = &data[take..];
+            if self.chunk_filled == chunk_size {
+                self.check_chunk()?;
+            }
+        }
+        Ok(())
+    }
+    
+    fn check_chunk(&mut self) -> Result<()> {
+        let computed = hex::encode(std::mem::take(&mut self.chunk).finalize());
+        let expected = self.metadata.chunk_hashes.as_ref()
+            .and_then(|chunks| chunks.hashes.get(self.chunk_index));
+        let valid = expected.is_some_and(|expected| {
+            crate::crypto::constant_time_eq(computed.as_bytes(), expected.as_bytes())
+        });
+        if !valid {
+            return Err(anyhow!(
+                "Data integrity check failed for key '{}': chunk {} is corrupt",
+                self.metadata.key, self.chunk_index
+            ));
+        }
+        self.chunk_index += 1;
+        self.chunk_filled = 0;
+        Ok(())
+    }
+    
+    fn finish(mut self) -> Result<()> {
+        if self.metadata.chunk_hashes.is_some() && self.chunk_filled > 0 {
+            self.check_chunk()?;
+        }
+        let computed = hex::encode(self.overall.finalize());
+        if !crate::crypto::constant_time_eq(computed.as_bytes(), self.metadata.hash.as_bytes()) {
+            return Err(anyhow!("Data integrity check failed for key '{}'", self.metadata.key));
+        }
+        Ok(())
+    }
+}
+
+impl StorageMetadata {
+    /// Bytes counted against the storage quota
+    fn stored_size(&self) -> u64 {
+        self.compressed_size.unwrap_or(self.size)
+    }
+    
+    /// Whether `principal` may perform `access` on this entry. Unowned legacy entries are
+    /// open to everyone when `open_unowned` is set and to `SYSTEM_PRINCIPAL` otherwise.
+    fn permits(&self, principal: &str, access: StorageAccess, open_unowned: bool) -> bool {
+        if self.owner.is_empty() {
+            return open_unowned || principal == SYSTEM_PRINCIPAL;
+        }
+        if self.owner == principal {
+            return true;
+        }
+        
+        let listed = |principals: &[String]| principals.iter()
+            .any(|p| p == principal || p == ANY_PRINCIPAL);
+        match access {
+            StorageAccess::Read => listed(&self.acl.readers) || listed(&self.acl.writers),
+            StorageAccess::Write => listed(&self.acl.writers),
+        }
+    }
 }
 
 /// Supported compression types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum CompressionType {
+    /// Written by earlier versions for dense compression; decoded but no longer produced
     Gzip,
     Lz4,
+    Zstd,
+}
+
+/// Zstd level used for dense compression
+const DENSE_ZSTD_LEVEL: i32 = 19;
+
+/// Reads at which an entry counts as hot and keeps fast compression when rewritten
+const HOT_ACCESS_COUNT: u64 = 10;
+
+/// Leading bytes of formats that are already compressed and not worth compressing again
+const COMPRESSED_SIGNATURES: &[&[u8]] = &[
+    &[0x1f, 0x8b],                         // gzip
+    &[0x50, 0x4b, 0x03, 0x04],             // zip
+    &[0x28, 0xb5, 0x2f, 0xfd],             // zstd
+    &[0x04, 0x22, 0x4d, 0x18],             // lz4 frame
+    &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00], // xz
+    &[0x42, 0x5a, 0x68],                   // bzip2
+    &[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c], // 7z
+    &[0x89, 0x50, 0x4e, 0x47],             // png
+    &[0xff, 0xd8, 0xff],                   // jpeg
+];
+
+/// How an entry's compression was chosen, recorded in its metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionPolicy {
+    /// The caller did not ask for compression or it is turned off
+    Disabled,
+    /// Smaller than `storage_compression_min_bytes`
+    BelowThreshold,
+    /// Starts with the signature of an already-compressed format
+    AlreadyCompressed,
+    /// Lz4, for entries below the dense threshold or that are read often
+    Fast,
+    /// Zstd at a high level, for large entries that are rarely read
+    Dense,
+    /// Compression did not make the entry smaller, so it was stored as is
+    NotSmaller,
 }
 
 /// Storage statistics
@@ -49,57 +341,317 @@ pub struct StorageStats {
     pub used_space: u64,
 }
 
+/// Access statistics for one entry. Atomics let reads record an access while holding
+/// only the shared index lock; the values are folded back into `StorageMetadata` when
+/// the index is snapshotted or metadata is reported.
+#[derive(Debug, Default)]
+struct AccessStats {
+    accessed_at: AtomicU64,
+    access_count: AtomicU64,
+}
+
+impl AccessStats {
+    fn from_metadata(metadata: &StorageMetadata) -> Self {
+        Self {
+            accessed_at: AtomicU64::new(metadata.accessed_at),
+            access_count: AtomicU64::new(metadata.access_count),
+        }
+    }
+    
+    fn record(&self, now: u64) {
+        self.accessed_at.fetch_max(now, Ordering::Relaxed);
+        self.access_count.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    fn apply_to(&self, metadata: &mut StorageMetadata) {
+        metadata.accessed_at = self.accessed_at.load(Ordering::Relaxed);
+        metadata.access_count = self.access_count.load(Ordering::Relaxed);
+    }
+}
+
+/// A single index change, appended to the journal as one JSON line
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum IndexChange {
+    Put(StorageMetadata),
+    Remove(String),
+}
+
 /// Storage index to track files and metadata
+///
+/// In the backend the index is a snapshot (`index.json`) plus an append-only journal
+/// (`index.journal`) of changes made since the snapshot. Each store, update or delete
+/// appends one record instead of rewriting the whole index; once the journal reaches
+/// `INDEX_COMPACTION_THRESHOLD` records it is folded into a new snapshot. Access
+/// statistics are not journaled and are persisted with the next snapshot.
 #[derive(Debug)]
 struct StorageIndex {
     metadata: HashMap<String, StorageMetadata>,
-    key_to_path: HashMap<String, PathBuf>,
+    /// Backend object holding each entry's ciphertext
+    key_to_object: HashMap<String, String>,
+    /// Live access statistics; authoritative over the copies in `metadata`
+    access_stats: HashMap<String, AccessStats>,
+    /// Records in the journal since the last snapshot
+    journal_len: usize,
+    /// Sum of `stored_size` over all entries
+    total_bytes: u64,
 }
 
 impl StorageIndex {
     fn new() -> Self {
         Self {
             metadata: HashMap::new(),
-            key_to_path: HashMap::new(),
+            key_to_object: HashMap::new(),
+            access_stats: HashMap::new(),
+            journal_len: 0,
+            total_bytes: 0,
         }
     }
     
-    fn save_to_file(&self, path: &Path) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.metadata)?;
-        fs::write(path, json)?;
+    /// Add or replace an entry in memory, keeping the derived maps and totals in step
+    fn insert_entry(&mut self, metadata: StorageMetadata, object: String) {
+        let key = metadata.key.clone();
+        self.remove_entry(&key);
+        self.total_bytes += metadata.stored_size();
+        self.access_stats.insert(key.clone(), AccessStats::from_metadata(&metadata));
+        self.key_to_object.insert(key.clone(), object);
+        self.metadata.insert(key, metadata);
+    }
+    
+    /// Remove an entry from memory, returning its metadata and backend object
+    fn remove_entry(&mut self, key: &str) -> Option<(StorageMetadata, Option<String>)> {
+        let metadata = self.metadata.remove(key)?;
+        self.total_bytes -= metadata.stored_size();
+        self.access_stats.remove(key);
+        Some((metadata, self.key_to_object.remove(key)))
+    }
+    
+    /// Copy the live access statistics into `metadata`
+    fn sync_access_stats(&mut self) {
+        for (key, stats) in &self.access_stats {
+            if let Some(metadata) = self.metadata.get_mut(key) {
+                stats.apply_to(metadata);
+            }
+        }
+    }
+    
+    /// Metadata for `key` with current access statistics
+    fn current_metadata(&self, key: &str) -> Option<StorageMetadata> {
+        let mut metadata = self.metadata.get(key)?.clone();
+        if let Some(stats) = self.access_stats.get(key) {
+            stats.apply_to(&mut metadata);
+        }
+        Some(metadata)
+    }
+    
+    /// Write a full snapshot and empty the journal
+    ///
+    /// Backend writes are atomic, so a crash leaves either the old or the new snapshot but
+    /// never a truncated one. Journal records are idempotent, so a crash before the journal
+    /// is emptied only means they are replayed onto a snapshot that already contains them.
+    fn save(&mut self, backend: &dyn StorageBackend) -> Result<()> {
+        self.sync_access_stats();
+        
+        backend.write(INDEX_OBJECT, &serde_json::to_vec(&self.metadata)?)?;
+        backend.write(INDEX_JOURNAL_OBJECT, &[])?;
+        self.journal_len = 0;
         Ok(())
     }
     
-    fn load_from_file(&mut self, path: &Path) -> Result<()> {
-        if path.exists() {
-            let json = fs::read_to_string(path)?;
-            self.metadata = serde_json::from_str(&json)?;
-            
-            // Rebuild key_to_path mapping
-            for key in self.metadata.keys() {
-                let file_path = Self::key_to_file_path(path.parent().unwrap(), key);
-                self.key_to_path.insert(key.clone(), file_path);
+    /// Durably append a change to the journal
+    fn append_to_journal(&mut self, backend: &dyn StorageBackend, change: &IndexChange) -> Result<()> {
+        let mut record = serde_json::to_vec(change)?;
+        record.push(b'\n');
+        backend.append(INDEX_JOURNAL_OBJECT, &record)?;
+        
+        self.journal_len += 1;
+        Ok(())
+    }
+    
+    /// Durably append several changes to the journal with one backend write
+    fn append_batch_to_journal(&mut self, backend: &dyn StorageBackend, changes: &[IndexChange]) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        
+        let mut records = Vec::new();
+        for change in changes {
+            serde_json::to_writer(&mut records, change)?;
+            records.push(b'\n');
+        }
+        backend.append(INDEX_JOURNAL_OBJECT, &records)?;
+        
+        self.journal_len += changes.len();
+        Ok(())
+    }
+    
+    fn load(&mut self, backend: &dyn StorageBackend) -> Result<()> {
+        if let Some(snapshot) = backend.read(INDEX_OBJECT)? {
+            self.metadata = serde_json::from_slice(&snapshot)?;
+        }
+        
+        if let Some(journal) = backend.read(INDEX_JOURNAL_OBJECT)? {
+            for record in journal.split(|&byte| byte == b'\n') {
+                if record.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                match serde_json::from_slice(record) {
+                    Ok(IndexChange::Put(metadata)) => {
+                        self.metadata.insert(metadata.key.clone(), metadata);
+                    }
+                    Ok(IndexChange::Remove(key)) => {
+                        self.metadata.remove(&key);
+                    }
+                    Err(e) => {
+                        // Only the final record can be torn by a crash mid-append
+                        warn!("Ignoring unreadable storage index journal record: {}", e);
+                        break;
+                    }
+                }
+                self.journal_len += 1;
             }
         }
+        
+        // Rebuild key_to_object mapping, access statistics and totals
+        for metadata in std::mem::take(&mut self.metadata).into_values() {
+            let object = Self::key_to_object_name(&metadata.key);
+            self.insert_entry(metadata, object);
+        }
         Ok(())
     }
     
-    fn key_to_file_path(storage_dir: &Path, key: &str) -> PathBuf {
-        // Use SHA-256 hash of key as filename to avoid filesystem issues
+    fn key_to_object_name(key: &str) -> String {
+        // Use SHA-256 hash of key as the object name to avoid filesystem issues
         let hash = Sha256::digest(key.as_bytes());
-        let filename = hex::encode(hash);
-        storage_dir.join(format!("{}.dat", filename))
+        format!("{}.dat", hex::encode(hash))
+    }
+}
+
+/// Contents list at the head of a backup archive
+#[derive(Serialize, Deserialize)]
+struct SnapshotManifest {
+    format_version: u32,
+    created_at: u64,
+    /// Hex storage master key; entry ciphertexts are only readable with it
+    master_key: String,
+    entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    metadata: StorageMetadata,
+    object_size: u64,
+    /// SHA-256 of the stored ciphertext
+    object_hash: String,
+}
+
+/// Kinds of record in a backup archive, bound into each record's associated data
+#[derive(Debug, Clone, Copy)]
+enum SnapshotRecord {
+    Manifest = 0,
+    Object = 1,
+    End = 2,
+}
+
+/// AES-256-GCM over the records of one backup archive
+///
+/// The archive is a header (magic, format version, KDF salt and rounds) followed by
+/// length-prefixed records: the manifest, one record per object in manifest order and an
+/// empty end record. The key comes from the passphrase and the random salt, so it is new
+/// for every archive and record nonces are simply a counter. Together with the header and
+/// record kind in the associated data this makes reordered, dropped or truncated records
+/// fail authentication.
+struct SnapshotCipher {
+    key: aead::LessSafeKey,
+    header: Vec<u8>,
+    sequence: u64,
+}
+
+impl SnapshotCipher {
+    fn new(passphrase: &str, header: Vec<u8>, salt: &[u8], iterations: u32) -> Result<Self> {
+        let iterations = std::num::NonZeroU32::new(iterations)
+            .ok_or_else(|| anyhow!("Snapshot KDF iterations must be greater than 0"))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        ring::pbkdf2::derive(ring::pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, passphrase.as_bytes(), &mut *key);
+        
+        Ok(Self {
+            key: aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_256_GCM, &*key)?),
+            header,
+            sequence: 0,
+        })
+    }
+    
+    fn next_nonce(&mut self) -> aead::Nonce {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.sequence.to_be_bytes());
+        self.sequence += 1;
+        aead::Nonce::assume_unique_for_key(nonce)
+    }
+    
+    fn aad(&self, record: SnapshotRecord) -> Vec<u8> {
+        let mut aad = self.header.clone();
+        aad.push(record as u8);
+        aad
+    }
+    
+    /// Encrypt and write one record, returning the bytes written
+    fn write_record<W: Write>(&mut self, writer: &mut W, record: SnapshotRecord, data: &[u8]) -> Result<u64> {
+        let aad = self.aad(record);
+        let nonce = self.next_nonce();
+        let mut sealed = data.to_vec();
+        self.key.seal_in_place_append_tag(nonce, aead::Aad::from(&aad[..]), &mut sealed)?;
+        
+        writer.write_all(&(sealed.len() as u32).to_le_bytes())?;
+        writer.write_all(&sealed)?;
+        Ok(4 + sealed.len() as u64)
+    }
+    
+    /// Read and decrypt the next record, which must be of kind `record`
+    fn read_record<R: Read>(&mut self, reader: &mut R, record: SnapshotRecord) -> Result<Zeroizing<Vec<u8>>> {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)
+            .map_err(|e| anyhow!("Snapshot is truncated before its {:?} record: {}", record, e))?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_SNAPSHOT_RECORD_BYTES {
+            return Err(EnclaveError::InvalidInput(format!("Snapshot record of {} bytes exceeds the limit", len)).into());
+        }
+        
+        let mut sealed = Zeroizing::new(vec![0u8; len]);
+        reader.read_exact(&mut sealed)
+            .map_err(|e| anyhow!("Snapshot is truncated inside its {:?} record: {}", record, e))?;
+        
+        let aad = self.aad(record);
+        let nonce = self.next_nonce();
+        let plaintext_len = self.key.open_in_place(nonce, aead::Aad::from(&aad[..]), &mut sealed)
+            .map_err(|_| EnclaveError::Crypto("Snapshot authentication failed: wrong passphrase or corrupted archive".into()))?
+            .len();
+        sealed.truncate(plaintext_len);
+        Ok(sealed)
     }
 }
 
 /// Main storage service for the enclave
 pub struct StorageService {
-    storage_dir: PathBuf,
-    index_file: PathBuf,
+    backend: Box<dyn StorageBackend>,
     index: Arc<RwLock<StorageIndex>>,
-    crypto_key: Vec<u8>, // Master encryption key for storage
+    /// Master encryption key for storage, wiped on drop; replaced by `restore_snapshot`
+    crypto_key: RwLock<Zeroizing<Vec<u8>>>,
+    sealing_service: Arc<SealingService>,
     enable_compression: bool,
+    /// Entries below this size skip compression
+    compression_min_bytes: u64,
+    /// Cold entries of at least this size use dense compression; 0 disables it
+    dense_compression_min_bytes: u64,
     max_file_size: u64,
+    open_unowned_entries: bool,
+    /// Decrypt version-0 entries, see `STORAGE_FORMAT_VERSION`
+    accept_legacy_format: bool,
+    /// Securely delete every object regardless of the entry's `secure_delete` flag
+    secure_delete_all: bool,
+    /// Quota on the sum of stored entry sizes; 0 disables it
+    max_total_bytes: u64,
+    eviction_policy: EvictionPolicy,
+    metrics: StorageMetrics,
 }
 
 impl StorageService {
@@ -107,32 +659,35 @@ impl StorageService {
     pub async fn new(config: &EncaveConfig) -> Result<Self> {
         info!("Initializing StorageService");
         
-        let storage_dir = PathBuf::from(&config.storage_path);
+        let backend = storage_backend::backend_from_config(config)?;
+        info!("Using {} storage backend", backend.kind());
         
-        // Create storage directory if it doesn't exist
-        if !storage_dir.exists() {
-            fs::create_dir_all(&storage_dir)?;
-            info!("Created storage directory: {:?}", storage_dir);
-        }
-        
-        let index_file = storage_dir.join("index.json");
         let mut index = StorageIndex::new();
         
         // Load existing index
-        if let Err(e) = index.load_from_file(&index_file) {
+        if let Err(e) = index.load(&*backend) {
             warn!("Failed to load storage index, starting fresh: {}", e);
         }
         
-        // Generate a master encryption key (in production this should be derived from enclave identity)
-        let crypto_key = Self::derive_master_key(&storage_dir)?;
+        // The master key is only ever written to disk sealed to the enclave identity
+        let sealing_service = Arc::new(SealingService::new(config)?);
+        let crypto_key = Self::load_master_key(&*backend, &sealing_service)?;
         
         Ok(Self {
-            storage_dir,
-            index_file,
+            backend,
             index: Arc::new(RwLock::new(index)),
-            crypto_key,
+            crypto_key: RwLock::new(crypto_key),
+            sealing_service,
             enable_compression: true,
+            compression_min_bytes: config.storage_compression_min_bytes,
+            dense_compression_min_bytes: config.storage_dense_compression_min_bytes,
             max_file_size: 100 * 1024 * 1024, // 100MB
+            open_unowned_entries: config.storage_open_unowned_entries,
+            accept_legacy_format: config.storage_accept_legacy_format,
+            secure_delete_all: config.storage_secure_delete,
+            max_total_bytes: config.storage_max_total_bytes,
+            eviction_policy: EvictionPolicy::from_name(&config.storage_eviction_policy)?,
+            metrics: StorageMetrics::default(),
         })
     }
     
@@ -158,62 +713,56 @@ impl StorageService {
         Ok(())
     }
     
-    /// Store data with optional compression and encryption
+    /// Store a new entry owned by `owner`, encrypted under `encryption_key`
     pub fn store_data(
         &self,
         key: &str,
         data: &[u8],
         encryption_key: &str,
-        compress: bool,
+        owner: &str,
+        options: StoreOptions,
     ) -> Result<String> {
+        let StoreOptions { compress, acl, secure_delete, pinned } = options;
         if key.is_empty() {
             return Err(anyhow!("Storage key cannot be empty"));
         }
         
+        if owner.is_empty() {
+            return Err(anyhow!("Storage owner cannot be empty"));
+        }
+        
         if data.len() > self.max_file_size as usize {
-            return Err(anyhow!("Data size exceeds maximum file size limit"));
+            return Err(EnclaveError::ResourceLimit("Data size exceeds maximum file size limit".into()).into());
         }
         
-        let mut index = self.index.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        let mut index = self.index_write();
         
-        // Check if key already exists
+        // A key held by someone the owner cannot read is reported like a missing key is
+        // elsewhere, so the collision does not reveal that it exists
         if index.metadata.contains_key(key) {
-            return Err(anyhow!("Key '{}' already exists", key));
+            self.check_access(&index, key, owner, StorageAccess::Read)?;
+            return Err(EnclaveError::AlreadyExists(format!("Key '{}' already exists", key)).into());
         }
         
-        let file_path = StorageIndex::key_to_file_path(&self.storage_dir, key);
+        let object = StorageIndex::key_to_object_name(key);
         
-        // Process data (compression + encryption)
-        let (processed_data, compression_type) = if compress && self.enable_compression {
-            let compressed = self.compress_data(data, CompressionType::Lz4)?;
-            if compressed.len() < data.len() {
-                (compressed, Some(CompressionType::Lz4))
-            } else {
-                (data.to_vec(), None)
-            }
-        } else {
-            (data.to_vec(), None)
-        };
-        
-        // Encrypt data
-        let encrypted_data = self.encrypt_data(&processed_data, encryption_key)?;
+        // A new entry has not been read yet, so large ones are treated as cold
+        let (encrypted_data, compression_type, compressed_size, compression_policy) =
+            self.seal_data(key, data, encryption_key, compress, false)?;
+        self.make_room(&mut index, compressed_size.unwrap_or(data.len() as u64), owner, None)?;
         
-        // Write to file
-        fs::write(&file_path, &encrypted_data)?;
+        // Write to the backend
+        self.backend.write(&object, &encrypted_data)?;
         
-        // Calculate hash of original data
-        let hash = hex::encode(Sha256::digest(data));
+        // Calculate hashes of original data
+        let (hash, chunk_hashes) = compute_integrity_hashes(data);
         
         // Create metadata
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
         let metadata = StorageMetadata {
             key: key.to_string(),
             size: data.len() as u64,
-            compressed_size: if compression_type.is_some() {
-                Some(processed_data.len() as u64)
-            } else {
-                None
-            },
+            compressed_size,
             created_at: now,
             accessed_at: now,
             modified_at: now,
@@ -221,87 +770,139 @@ impl StorageService {
             encryption: true,
             hash,
             access_count: 0,
+            format_version: STORAGE_FORMAT_VERSION,
+            owner: owner.to_string(),
+            acl,
+            pinned,
+            chunk_hashes,
+            secure_delete,
+            compression_policy: Some(compression_policy),
         };
         
         // Update index
-        index.metadata.insert(key.to_string(), metadata.clone());
-        index.key_to_path.insert(key.to_string(), file_path);
-        
-        // Save index
+        self.record_index_change(&mut index, IndexChange::Put(metadata.clone()))?;
+        index.insert_entry(metadata.clone(), object);
+        self.compact_index_if_needed(&mut index);
         drop(index);
-        self.save_index()?;
         
-        info!("Stored data for key '{}': {} bytes", key, data.len());
+        self.metrics.writes.incr();
+        self.metrics.bytes_written.add(encrypted_data.len() as u64);
+        info!("Stored data for key '{}': {} bytes", redact(key), data.len());
         
         // Return metadata as JSON
         Ok(serde_json::to_string(&metadata)?)
     }
     
-    /// Retrieve data with decryption and decompression
-    pub fn retrieve_data(&self, key: &str, encryption_key: &str) -> Result<Vec<u8>> {
+    /// Replace the contents of an existing entry, keeping its owner and ACL.
+    /// Requires write access.
+    pub fn update_data(
+        &self,
+        key: &str,
+        data: &[u8],
+        encryption_key: &str,
+        compress: bool,
+        principal: &str,
+    ) -> Result<String> {
         if key.is_empty() {
             return Err(anyhow!("Storage key cannot be empty"));
         }
         
-        let mut index = self.index.write().map_err(|_| anyhow!("Lock poisoned"))?;
+        if data.len() > self.max_file_size as usize {
+            return Err(EnclaveError::ResourceLimit("Data size exceeds maximum file size limit".into()).into());
+        }
         
-        let file_path = index.key_to_path.get(key)
-            .ok_or_else(|| anyhow!("File path for key '{}' not found", key))?.clone();
+        let mut index = self.index_write();
+        self.check_access(&index, key, principal, StorageAccess::Write)?;
         
-        let metadata = index.metadata.get_mut(key)
-            .ok_or_else(|| anyhow!("Key '{}' not found", key))?;
+        let object = index.key_to_object.get(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Storage object for key '{}' not found", key)))?.clone();
         
-        // Read encrypted data from file
-        let encrypted_data = fs::read(file_path)?;
+        let mut metadata = index.current_metadata(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key)))?;
+        let hot = metadata.access_count >= HOT_ACCESS_COUNT;
         
-        // Decrypt data
-        let decrypted_data = self.decrypt_data(&encrypted_data, encryption_key)?;
+        let (encrypted_data, compression_type, compressed_size, compression_policy) =
+            self.seal_data(key, data, encryption_key, compress, hot)?;
+        self.make_room(&mut index, compressed_size.unwrap_or(data.len() as u64), &metadata.owner, Some(key))?;
+        self.backend.write(&object, &encrypted_data)?;
         
-        // Decompress if needed
-        let original_data = if let Some(compression_type) = &metadata.compression {
-            self.decompress_data(&decrypted_data, compression_type.clone())?
-        } else {
-            decrypted_data
-        };
+        metadata.size = data.len() as u64;
+        metadata.compressed_size = compressed_size;
+        metadata.compression = compression_type;
+        metadata.compression_policy = Some(compression_policy);
+        (metadata.hash, metadata.chunk_hashes) = compute_integrity_hashes(data);
+        metadata.modified_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        metadata.format_version = STORAGE_FORMAT_VERSION;
+        
+        self.record_index_change(&mut index, IndexChange::Put(metadata.clone()))?;
+        index.insert_entry(metadata.clone(), object);
+        self.compact_index_if_needed(&mut index);
+        drop(index);
+        
+        self.metrics.writes.incr();
+        self.metrics.bytes_written.add(encrypted_data.len() as u64);
+        info!("Updated data for key '{}': {} bytes", redact(key), data.len());
         
-        // Verify hash
-        let computed_hash = hex::encode(Sha256::digest(&original_data));
-        if computed_hash != metadata.hash {
-            return Err(anyhow!("Data integrity check failed for key '{}'", key));
+        Ok(serde_json::to_string(&metadata)?)
+    }
+    
+    /// Retrieve data with decryption and decompression. Requires read access.
+    ///
+    /// Only the shared index lock is taken, so reads run concurrently with each other and
+    /// block only while a store, update or delete holds the lock. Previously every read took
+    /// the exclusive lock for its whole duration, which is dominated by the PBKDF2 key
+    /// derivation (roughly 25ms per read), so at most one read could make progress at a time.
+    pub fn retrieve_data(&self, key: &str, encryption_key: &str, principal: &str) -> Result<Vec<u8>> {
+        if key.is_empty() {
+            return Err(anyhow!("Storage key cannot be empty"));
         }
         
-        // Update access metadata
-        metadata.accessed_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        metadata.access_count += 1;
+        let index = self.index_read();
+        self.check_access(&index, key, principal, StorageAccess::Read)?;
+        
+        let object = index.key_to_object.get(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Storage object for key '{}' not found", key)))?;
+        
+        let metadata = index.metadata.get(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key)))?;
+        
+        // Read encrypted data from the backend
+        let encrypted_data = self.backend.read(object)?
+            .ok_or_else(|| EnclaveError::NotFound(format!("Storage object for key '{}' is missing", key)))?;
+        
+        // Decrypt data
+        let decrypted_data = self.decrypt_data(&encrypted_data, encryption_key, key, metadata.format_version)?;
+        
+        // Decompress if needed, verifying hashes as the plaintext is produced
+        let original_data = self.decompress_and_verify(decrypted_data, metadata)?;
         
+        // Access statistics are persisted with the next index snapshot, not journaled
+        if let Some(stats) = index.access_stats.get(key) {
+            stats.record(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+        }
         drop(index);
-        self.save_index()?;
         
-        debug!("Retrieved data for key '{}': {} bytes", key, original_data.len());
+        self.metrics.reads.incr();
+        self.metrics.bytes_read.add(encrypted_data.len() as u64);
+        debug!("Retrieved data for key '{}': {} bytes", redact(key), original_data.len());
         Ok(original_data)
     }
     
-    /// Delete stored data
-    pub fn delete_data(&self, key: &str) -> Result<String> {
+    /// Delete stored data. Requires write access.
+    pub fn delete_data(&self, key: &str, principal: &str) -> Result<String> {
         if key.is_empty() {
             return Err(anyhow!("Storage key cannot be empty"));
         }
         
-        let mut index = self.index.write().map_err(|_| anyhow!("Lock poisoned"))?;
-        
-        let metadata = index.metadata.remove(key)
-            .ok_or_else(|| anyhow!("Key '{}' not found", key))?;
-        
-        if let Some(file_path) = index.key_to_path.remove(key) {
-            if file_path.exists() {
-                fs::remove_file(&file_path)?;
-            }
-        }
+        let mut index = self.index_write();
+        self.check_access(&index, key, principal, StorageAccess::Write)?;
         
+        self.remove_entry(&mut index, key)?;
+        self.compact_index_if_needed(&mut index);
         drop(index);
-        self.save_index()?;
         
-        info!("Deleted data for key '{}'", key);
+        self.metrics.deletes.incr();
+        info!("Deleted data for key '{}'", redact(key));
         
         let result = serde_json::json!({
             "deleted": true,
@@ -312,33 +913,101 @@ impl StorageService {
         Ok(result.to_string())
     }
     
-    /// Get metadata for stored data
-    pub fn get_metadata(&self, key: &str) -> Result<String> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
-        
-        let metadata = index.metadata.get(key)
-            .ok_or_else(|| anyhow!("Key '{}' not found", key))?;
+    /// Delete several entries, journaling all removals in a single index write
+    ///
+    /// Keys that are missing or that `principal` may not write are skipped and reported
+    /// under `failed` with the reason; the rest are deleted.
+    pub fn delete_many(&self, keys: &[String], principal: &str) -> Result<String> {
+        let mut index = self.index_write();
+        let result = self.remove_entries(&mut index, keys, principal)?;
+        drop(index);
         
-        Ok(serde_json::to_string_pretty(metadata)?)
+        info!("Deleted {} of {} storage keys", result["deleted"], keys.len());
+        Ok(result.to_string())
     }
     
-    /// List all storage keys
-    pub fn list_keys(&self) -> Result<String> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+    /// Delete every entry whose key starts with `prefix`, as with `delete_many`
+    pub fn delete_prefix(&self, prefix: &str, principal: &str) -> Result<String> {
+        if prefix.is_empty() {
+            return Err(EnclaveError::InvalidInput("Delete prefix cannot be empty".into()).into());
+        }
         
-        let keys: Vec<&String> = index.metadata.keys().collect();
-        let result = serde_json::json!({
-            "keys": keys,
-            "count": keys.len(),
-            "timestamp": SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
-        });
+        let mut index = self.index_write();
+        let mut keys: Vec<String> = index.metadata.keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        keys.sort();
+        let result = self.remove_entries(&mut index, &keys, principal)?;
+        drop(index);
         
+        info!("Deleted {} storage keys with prefix '{}'", result["deleted"], redact(prefix));
         Ok(result.to_string())
     }
     
+    /// Whether an entry is stored under `key`, regardless of who owns it
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index_read().metadata.contains_key(key)
+    }
+    
+    /// Exclude an entry from quota eviction
+    pub fn pin(&self, key: &str) -> Result<()> {
+        self.set_pinned(key, true)
+    }
+    
+    /// Make a pinned entry evictable again
+    pub fn unpin(&self, key: &str) -> Result<()> {
+        self.set_pinned(key, false)
+    }
+    
+    fn set_pinned(&self, key: &str, pinned: bool) -> Result<()> {
+        let mut index = self.index_write();
+        
+        let mut metadata = index.current_metadata(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key)))?;
+        if metadata.pinned == pinned {
+            return Ok(());
+        }
+        metadata.pinned = pinned;
+        
+        self.record_index_change(&mut index, IndexChange::Put(metadata.clone()))?;
+        if let Some(entry) = index.metadata.get_mut(key) {
+            entry.pinned = pinned;
+        }
+        self.compact_index_if_needed(&mut index);
+        
+        debug!("{} storage key '{}'", if pinned { "Pinned" } else { "Unpinned" }, redact(key));
+        Ok(())
+    }
+    
+    /// Get metadata for stored data, subject to the same read check as `retrieve_data`
+    pub fn get_metadata(&self, key: &str, principal: &str) -> Result<String> {
+        let index = self.index_read();
+        self.check_access(&index, key, principal, StorageAccess::Read)?;
+        
+        let metadata = index.current_metadata(key)
+            .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key)))?;
+        
+        Ok(serde_json::to_string_pretty(&metadata)?)
+    }
+    
+    /// Page of the storage keys `principal` may read, in sorted order
+    pub fn list_keys(&self, principal: &str, limit: Option<usize>, offset: Option<usize>) -> Result<String> {
+        let index = self.index_read();
+        
+        // Sorted so consecutive pages neither repeat nor skip keys
+        let mut keys: Vec<&String> = index.metadata.iter()
+            .filter(|(_, metadata)| metadata.permits(principal, StorageAccess::Read, self.open_unowned_entries))
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        let total = keys.len();
+        Ok(paginate(keys, limit, offset, total).to_string())
+    }
+    
     /// Get storage usage statistics
     pub fn get_usage_stats(&self) -> Result<String> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         
         let total_files = index.metadata.len();
         let total_size: u64 = index.metadata.values().map(|m| m.size).sum();
@@ -367,37 +1036,477 @@ impl StorageService {
         Ok(serde_json::to_string_pretty(&stats)?)
     }
     
+    /// Stream a backup of the whole store to `writer`, encrypted under `passphrase`
+    ///
+    /// The archive holds every entry's metadata and ciphertext together with the storage
+    /// master key, so it restores on another enclave; anyone with the archive and the
+    /// passphrase can read every entry whose encryption key they also know. The manifest
+    /// lists a SHA-256 per object, so objects are read twice. Writes wait until the snapshot
+    /// is complete, which keeps it consistent.
+    pub fn create_snapshot(&self, mut writer: impl Write, passphrase: &str) -> Result<String> {
+        if passphrase.is_empty() {
+            return Err(EnclaveError::InvalidInput("Snapshot passphrase cannot be empty".into()).into());
+        }
+        
+        let index = self.index_read();
+        
+        let mut keys: Vec<&String> = index.key_to_object.keys().collect();
+        keys.sort();
+        let mut entries = Vec::with_capacity(keys.len());
+        let mut objects = Vec::with_capacity(keys.len());
+        for key in keys {
+            let object = &index.key_to_object[key];
+            let metadata = index.current_metadata(key)
+                .ok_or_else(|| EnclaveError::NotFound(format!("Key '{}' not found", key)))?;
+            let data = self.backend.read(object)?
+                .ok_or_else(|| EnclaveError::NotFound(format!("Storage object for key '{}' is missing", key)))?;
+            entries.push(SnapshotEntry {
+                metadata,
+                object_size: data.len() as u64,
+                object_hash: hex::encode(Sha256::digest(&data)),
+            });
+            objects.push(object);
+        }
+        
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut manifest = SnapshotManifest {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            created_at,
+            master_key: hex::encode(&**self.crypto_key.read_or_recover()),
+            entries,
+        };
+        let manifest_json = Zeroizing::new(serde_json::to_vec(&manifest)?);
+        manifest.master_key.zeroize();
+        
+        let mut salt = [0u8; 16];
+        rand::SystemRandom::new().fill(&mut salt)?;
+        let mut header = SNAPSHOT_MAGIC.to_vec();
+        header.extend_from_slice(&SNAPSHOT_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&SNAPSHOT_KDF_ITERATIONS.to_le_bytes());
+        writer.write_all(&header)?;
+        
+        let mut cipher = SnapshotCipher::new(passphrase, header.clone(), &salt, SNAPSHOT_KDF_ITERATIONS)?;
+        let mut snapshot_bytes = header.len() as u64;
+        snapshot_bytes += cipher.write_record(&mut writer, SnapshotRecord::Manifest, &manifest_json)?;
+        
+        let mut object_bytes = 0;
+        for (object, entry) in objects.iter().zip(&manifest.entries) {
+            let data = self.backend.read(object)?
+                .ok_or_else(|| EnclaveError::NotFound(format!("Storage object for key '{}' is missing", entry.metadata.key)))?;
+            object_bytes += data.len() as u64;
+            snapshot_bytes += cipher.write_record(&mut writer, SnapshotRecord::Object, &data)?;
+        }
+        snapshot_bytes += cipher.write_record(&mut writer, SnapshotRecord::End, &[])?;
+        writer.flush()?;
+        drop(index);
+        
+        info!("Created storage snapshot of {} entries ({} bytes)", manifest.entries.len(), snapshot_bytes);
+        
+        let result = serde_json::json!({
+            "format_version": SNAPSHOT_FORMAT_VERSION,
+            "entries": manifest.entries.len(),
+            "object_bytes": object_bytes,
+            "snapshot_bytes": snapshot_bytes,
+            "created_at": created_at,
+        });
+        Ok(result.to_string())
+    }
+    
+    /// Open the store configured by `config` and rebuild it from an archive written by
+    /// `create_snapshot`, returning the service and the JSON restore report
+    ///
+    /// The store must be empty. Restoring replaces the index and the master key, which the
+    /// audit log and the account service read when they are constructed, so the restored
+    /// service is only handed out once the restore is complete; build the other services
+    /// on top of it afterwards.
+    pub async fn restore_from_snapshot(config: &EncaveConfig, reader: impl Read, passphrase: &str) -> Result<(Self, String)> {
+        let service = Self::new(config).await?;
+        let report = service.restore_snapshot(reader, passphrase)?;
+        Ok((service, report))
+    }
+    
+    /// Rebuild this store from a snapshot archive
+    ///
+    /// Every object is checked against the manifest hash before the index is written, and
+    /// objects already written are removed again if any check fails. The archive's master
+    /// key replaces this store's, sealed to this enclave.
+    fn restore_snapshot(&self, mut reader: impl Read, passphrase: &str) -> Result<String> {
+        let mut index = self.index_write();
+        if !index.metadata.is_empty() {
+            return Err(EnclaveError::AlreadyExists(format!(
+                "Storage must be empty to restore a snapshot; it holds {} entries", index.metadata.len()
+            )).into());
+        }
+        
+        let mut header = [0u8; 32];
+        reader.read_exact(&mut header)
+            .map_err(|e| anyhow!("Snapshot is truncated inside its header: {}", e))?;
+        if &header[..8] != SNAPSHOT_MAGIC {
+            return Err(EnclaveError::InvalidInput("Not a storage snapshot".into()).into());
+        }
+        let format_version = u32::from_le_bytes(header[8..12].try_into()?);
+        if format_version != SNAPSHOT_FORMAT_VERSION {
+            return Err(EnclaveError::InvalidInput(format!("Unsupported snapshot format version: {}", format_version)).into());
+        }
+        let iterations = u32::from_le_bytes(header[28..32].try_into()?);
+        if iterations > MAX_SNAPSHOT_KDF_ITERATIONS {
+            return Err(EnclaveError::InvalidInput(format!("Snapshot KDF iterations {} exceed the limit", iterations)).into());
+        }
+        
+        let mut cipher = SnapshotCipher::new(passphrase, header.to_vec(), &header[12..28], iterations)?;
+        let manifest_json = cipher.read_record(&mut reader, SnapshotRecord::Manifest)?;
+        let mut manifest: SnapshotManifest = serde_json::from_slice(&manifest_json)?;
+        let master_key = Zeroizing::new(hex::decode(&manifest.master_key)?);
+        manifest.master_key.zeroize();
+        if master_key.len() != 32 {
+            return Err(anyhow!("Snapshot master key has invalid length {}", master_key.len()));
+        }
+        
+        let mut seen = std::collections::HashSet::new();
+        if let Some(entry) = manifest.entries.iter().find(|entry| entry.metadata.key.is_empty() || !seen.insert(&entry.metadata.key)) {
+            return Err(anyhow!("Snapshot manifest has an empty or duplicate key '{}'", entry.metadata.key));
+        }
+        
+        let mut written = Vec::new();
+        let restored = self.restore_snapshot_objects(&mut reader, &mut cipher, &manifest.entries, &mut written);
+        let object_bytes = match restored {
+            Ok(object_bytes) => object_bytes,
+            Err(e) => {
+                for object in &written {
+                    if let Err(e) = self.backend.delete(object) {
+                        warn!("Failed to remove partially restored storage object: {}", e);
+                    }
+                }
+                return Err(e);
+            }
+        };
+        
+        self.backend.write(SEALED_MASTER_KEY_FILE, &self.sealing_service.seal_data(SealDomain::Internal, &master_key)?)?;
+        *self.crypto_key.write_or_recover() = master_key;
+        
+        let entry_count = manifest.entries.len();
+        for (entry, object) in manifest.entries.into_iter().zip(written) {
+            index.insert_entry(entry.metadata, object);
+        }
+        index.save(&*self.backend)?;
+        if self.max_total_bytes > 0 && index.total_bytes > self.max_total_bytes {
+            warn!("Restored storage holds {} bytes, over the {} byte quota", index.total_bytes, self.max_total_bytes);
+        }
+        drop(index);
+        
+        info!("Restored storage snapshot of {} entries ({} bytes)", entry_count, object_bytes);
+        
+        let result = serde_json::json!({
+            "format_version": format_version,
+            "entries": entry_count,
+            "object_bytes": object_bytes,
+            "created_at": manifest.created_at,
+            "timestamp": SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+        });
+        Ok(result.to_string())
+    }
+    
+    /// Read, verify and write the objects of a snapshot, recording each written object in
+    /// `written` so the caller can undo a failed restore. Returns the object bytes restored.
+    fn restore_snapshot_objects<R: Read>(
+        &self,
+        reader: &mut R,
+        cipher: &mut SnapshotCipher,
+        entries: &[SnapshotEntry],
+        written: &mut Vec<String>,
+    ) -> Result<u64> {
+        let mut object_bytes = 0;
+        for entry in entries {
+            let data = cipher.read_record(reader, SnapshotRecord::Object)?;
+            if data.len() as u64 != entry.object_size || hex::encode(Sha256::digest(&*data)) != entry.object_hash {
+                return Err(EnclaveError::Crypto(format!(
+                    "Snapshot object for key '{}' does not match its manifest hash", entry.metadata.key
+                )).into());
+            }
+            
+            let object = StorageIndex::key_to_object_name(&entry.metadata.key);
+            self.backend.write(&object, &data)?;
+            written.push(object);
+            object_bytes += data.len() as u64;
+        }
+        
+        cipher.read_record(reader, SnapshotRecord::End)?;
+        Ok(object_bytes)
+    }
+    
+    /// Fail with `StorageError::AccessDenied` unless `key` exists and `principal` may access it
+    fn check_access(&self, index: &StorageIndex, key: &str, principal: &str, access: StorageAccess) -> Result<()> {
+        let permitted = index.metadata.get(key)
+            .is_some_and(|metadata| metadata.permits(principal, access, self.open_unowned_entries));
+        if !permitted {
+            warn!("Denied {:?} access to storage key '{}' for principal '{}'", access, redact(key), redact(principal));
+            return Err(StorageError::AccessDenied { key: key.to_string() }.into());
+        }
+        Ok(())
+    }
+    
+    /// Journal and remove an entry, then delete its object. Journaling first means a crash
+    /// cannot leave an index entry pointing at a deleted object.
+    fn remove_entry(&self, index: &mut StorageIndex, key: &str) -> Result<()> {
+        self.record_index_change(index, IndexChange::Remove(key.to_string()))?;
+        if let Some((metadata, Some(object))) = index.remove_entry(key) {
+            self.delete_object(&object, &metadata)?;
+        }
+        Ok(())
+    }
+    
+    /// Remove an entry's backend object, overwriting it first when the entry or the
+    /// configuration asks for secure deletion
+    fn delete_object(&self, object: &str, metadata: &StorageMetadata) -> Result<bool> {
+        if self.secure_delete_all || metadata.secure_delete {
+            self.backend.erase(object)
+        } else {
+            self.backend.delete(object)
+        }
+    }
+    
+    /// Batch form of `remove_entry` for the keys `principal` may write, returning the JSON
+    /// report for `delete_many`. All removals go to the journal in one append.
+    fn remove_entries(&self, index: &mut StorageIndex, keys: &[String], principal: &str) -> Result<serde_json::Value> {
+        let mut removable = Vec::new();
+        let mut failed = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for key in keys {
+            if key.is_empty() {
+                failed.push(serde_json::json!({ "key": key, "error": "Storage key cannot be empty" }));
+            } else if !seen.insert(key.as_str()) {
+                continue;
+            } else if let Err(e) = self.check_access(index, key, principal, StorageAccess::Write) {
+                failed.push(serde_json::json!({ "key": key, "error": e.to_string() }));
+            } else {
+                removable.push(key.clone());
+            }
+        }
+        
+        let changes: Vec<IndexChange> = removable.iter().cloned().map(IndexChange::Remove).collect();
+        index.append_batch_to_journal(&*self.backend, &changes)?;
+        for key in &removable {
+            if let Some((metadata, Some(object))) = index.remove_entry(key) {
+                // The entry is already gone from the index; a stray object is only wasted space
+                if let Err(e) = self.delete_object(&object, &metadata) {
+                    warn!("Failed to delete storage object for key '{}': {}", redact(key), e);
+                }
+            }
+            self.metrics.deletes.incr();
+        }
+        self.compact_index_if_needed(index);
+        
+        Ok(serde_json::json!({
+            "deleted": removable.len(),
+            "deleted_keys": removable,
+            "failed": failed,
+            "timestamp": SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+        }))
+    }
+    
+    /// Ensure a write of `needed` bytes by `owner` fits under `max_total_bytes`
+    ///
+    /// Under the LRU policy the least recently accessed unpinned entries of the same owner
+    /// are evicted until it fits, so one principal's writes never push out another's data.
+    /// `replacing` names the entry being overwritten: its current size is freed by the
+    /// write itself and it is never evicted. A write that cannot fit even after evicting
+    /// every candidate fails without evicting anything.
+    fn make_room(&self, index: &mut StorageIndex, needed: u64, owner: &str, replacing: Option<&str>) -> Result<()> {
+        if self.max_total_bytes == 0 {
+            return Ok(());
+        }
+        
+        let replaced = replacing
+            .and_then(|key| index.metadata.get(key))
+            .map_or(0, StorageMetadata::stored_size);
+        let mut used = index.total_bytes - replaced;
+        if used + needed <= self.max_total_bytes {
+            return Ok(());
+        }
+        
+        let mut candidates: Vec<(u64, String, u64)> = match self.eviction_policy {
+            EvictionPolicy::Reject => Vec::new(),
+            EvictionPolicy::Lru => index.metadata.values()
+                .filter(|metadata| {
+                    metadata.owner == owner && !metadata.pinned && Some(metadata.key.as_str()) != replacing
+                })
+                .map(|metadata| {
+                    let accessed_at = index.access_stats.get(&metadata.key)
+                        .map_or(metadata.accessed_at, |stats| stats.accessed_at.load(Ordering::Relaxed));
+                    (accessed_at, metadata.key.clone(), metadata.stored_size())
+                })
+                .collect(),
+        };
+        
+        let evictable: u64 = candidates.iter().map(|(_, _, size)| size).sum();
+        if used - evictable + needed > self.max_total_bytes {
+            return Err(StorageError::QuotaExceeded {
+                needed,
+                available: self.max_total_bytes.saturating_sub(used - evictable),
+            }.into());
+        }
+        
+        candidates.sort();
+        for (_, key, size) in candidates {
+            if used + needed <= self.max_total_bytes {
+                break;
+            }
+            self.remove_entry(index, &key)?;
+            used -= size;
+            self.metrics.evictions.incr();
+            info!("Evicted storage key '{}' ({} bytes) to stay within quota", redact(&key), size);
+        }
+        Ok(())
+    }
+    
+    /// Compress (when worthwhile) and encrypt data for `key`, returning the ciphertext,
+    /// the compression applied and the compressed size
+    fn seal_data(
+        &self,
+        key: &str,
+        data: &[u8],
+        encryption_key: &str,
+        compress: bool,
+        hot: bool,
+    ) -> Result<(Vec<u8>, Option<CompressionType>, Option<u64>, CompressionPolicy)> {
+        let policy = self.compression_policy(data, compress, hot);
+        let compression = match policy {
+            CompressionPolicy::Fast => Some(CompressionType::Lz4),
+            CompressionPolicy::Dense => Some(CompressionType::Zstd),
+            _ => None,
+        };
+        
+        let (processed_data, compression_type, policy) = match compression {
+            Some(compression) => {
+                let compressed = self.compress_data(data, compression.clone())?;
+                if compressed.len() < data.len() {
+                    (compressed, Some(compression), policy)
+                } else {
+                    (data.to_vec(), None, CompressionPolicy::NotSmaller)
+                }
+            }
+            None => (data.to_vec(), None, policy),
+        };
+        
+        let compressed_size = compression_type.as_ref().map(|_| processed_data.len() as u64);
+        let encrypted_data = self.encrypt_data(&processed_data, encryption_key, key, STORAGE_FORMAT_VERSION)?;
+        Ok((encrypted_data, compression_type, compressed_size, policy))
+    }
+    
+    /// Pick how to compress `data`: small and already-compressed data is stored as is,
+    /// large data that is not `hot` is compressed densely and everything else with lz4
+    fn compression_policy(&self, data: &[u8], compress: bool, hot: bool) -> CompressionPolicy {
+        let size = data.len() as u64;
+        if !compress || !self.enable_compression {
+            CompressionPolicy::Disabled
+        } else if size < self.compression_min_bytes {
+            CompressionPolicy::BelowThreshold
+        } else if COMPRESSED_SIGNATURES.iter().any(|signature| data.starts_with(signature)) {
+            CompressionPolicy::AlreadyCompressed
+        } else if !hot && self.dense_compression_min_bytes > 0 && size >= self.dense_compression_min_bytes {
+            CompressionPolicy::Dense
+        } else {
+            CompressionPolicy::Fast
+        }
+    }
+    
     /// Compress data using specified algorithm
     fn compress_data(&self, data: &[u8], compression: CompressionType) -> Result<Vec<u8>> {
         match compression {
             CompressionType::Gzip => {
-                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-                encoder.write_all(data)?;
-                Ok(encoder.finish()?)
+                Err(anyhow!("Gzip is only decoded for existing entries"))
             }
             CompressionType::Lz4 => {
                 Ok(compress_prepend_size(data))
             }
+            CompressionType::Zstd => {
+                Ok(zstd::bulk::compress(data, DENSE_ZSTD_LEVEL)?)
+            }
         }
     }
     
-    /// Decompress data using specified algorithm
-    fn decompress_data(&self, compressed_data: &[u8], compression: CompressionType) -> Result<Vec<u8>> {
-        match compression {
-            CompressionType::Gzip => {
-                let mut decoder = GzDecoder::new(compressed_data);
-                let mut decompressed = Vec::new();
-                decoder.read_to_end(&mut decompressed)?;
-                Ok(decompressed)
+    /// Recover an entry's plaintext from its decrypted bytes, checking it against the
+    /// recorded hashes chunk by chunk. Zstd and gzip are decoded incrementally so a corrupt
+    /// chunk is reported before the remainder is decompressed.
+    ///
+    /// Output is capped at the recorded size (and the file size limit) while it is
+    /// produced, so a payload that inflates beyond it fails without being expanded.
+    fn decompress_and_verify(&self, decrypted_data: Vec<u8>, metadata: &StorageMetadata) -> Result<Vec<u8>> {
+        let limit = metadata.size.min(self.max_file_size);
+        let mut verifier = IntegrityVerifier::new(metadata);
+        
+        let original_data = match &metadata.compression {
+            Some(CompressionType::Gzip) => {
+                let decoder = GzDecoder::new(decrypted_data.as_slice());
+                Self::read_capped(decoder, limit, &mut verifier, &metadata.key)?
             }
-            CompressionType::Lz4 => {
-                Ok(decompress_size_prepended(compressed_data)?)
+            Some(CompressionType::Zstd) => {
+                let decoder = zstd::stream::read::Decoder::with_buffer(decrypted_data.as_slice())?;
+                Self::read_capped(decoder, limit, &mut verifier, &metadata.key)?
+            }
+            Some(CompressionType::Lz4) => {
+                // The block decoder writes into a buffer of the declared size and fails
+                // rather than growing it, so checking the declaration bounds the output
+                let (declared_size, block) = lz4_flex::block::uncompressed_size(&decrypted_data)?;
+                if declared_size as u64 > limit {
+                    return Err(Self::oversized(&metadata.key, limit));
+                }
+                let original_data = lz4_flex::decompress(block, declared_size)?;
+                drop(decrypted_data);
+                for chunk in original_data.chunks(INTEGRITY_CHUNK_SIZE) {
+                    verifier.update(chunk)?;
+                }
+                original_data
             }
+            None => {
+                for chunk in decrypted_data.chunks(INTEGRITY_CHUNK_SIZE) {
+                    verifier.update(chunk)?;
+                }
+                decrypted_data
+            }
+        };
+        
+        verifier.finish()?;
+        Ok(original_data)
+    }
+    
+    /// Drain a streaming decoder, verifying as it goes and failing once it produces more
+    /// than `limit` bytes
+    fn read_capped(decoder: impl Read, limit: u64, verifier: &mut IntegrityVerifier, key: &str) -> Result<Vec<u8>> {
+        // One byte past the limit is enough to tell an oversized payload apart
+        let mut decoder = decoder.take(limit + 1);
+        let mut original_data = Vec::with_capacity(limit as usize);
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = decoder.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            if (original_data.len() + read) as u64 > limit {
+                return Err(Self::oversized(key, limit));
+            }
+            verifier.update(&buffer[..read])?;
+            original_data.extend_from_slice(&buffer[..read]);
         }
+        Ok(original_data)
     }
     
-    /// Encrypt data using AES-256-GCM
-    fn encrypt_data(&self, data: &[u8], user_key: &str) -> Result<Vec<u8>> {
+    fn oversized(key: &str, limit: u64) -> anyhow::Error {
+        anyhow!("Entry '{}' decompresses beyond its recorded size of {} bytes", key, limit)
+    }
+    
+    /// Associated data binding a ciphertext to its storage slot
+    fn storage_aad(storage_key: &str, format_version: u32) -> Result<Vec<u8>> {
+        match format_version {
+            0 => Ok(Vec::new()),
+            1 => Ok(format!("neo-storage:v1:{}", storage_key).into_bytes()),
+            other => Err(anyhow!("Unsupported storage format version: {}", other)),
+        }
+    }
+    
+    /// Encrypt data using AES-256-GCM, authenticating the storage key name
+    fn encrypt_data(&self, data: &[u8], user_key: &str, storage_key: &str, format_version: u32) -> Result<Vec<u8>> {
         // Derive encryption key from master key and user key
         let key = self.derive_encryption_key(user_key)?;
         
@@ -407,12 +1516,13 @@ impl StorageService {
         let mut nonce = [0u8; 12];
         ring::rand::SystemRandom::new().fill(&mut nonce)?;
         
+        let aad = Self::storage_aad(storage_key, format_version)?;
         let mut in_out = data.to_vec();
         let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, &key)?;
         let less_safe_key = aead::LessSafeKey::new(unbound_key);
         let _encrypted_result = less_safe_key.seal_in_place_append_tag(
             aead::Nonce::assume_unique_for_key(nonce),
-            aead::Aad::empty(),
+            aead::Aad::from(&aad[..]),
             &mut in_out,
         )?;
         
@@ -424,8 +1534,17 @@ impl StorageService {
         Ok(result)
     }
     
-    /// Decrypt data using AES-256-GCM
-    fn decrypt_data(&self, encrypted_data: &[u8], user_key: &str) -> Result<Vec<u8>> {
+    /// Decrypt data using AES-256-GCM. Fails authentication if the ciphertext was
+    /// written for a different storage key. Version-0 ciphertexts are refused unless legacy
+    /// migration is enabled, since their empty associated data would let an attacker swap
+    /// objects between keys by downgrading the recorded version.
+    fn decrypt_data(&self, encrypted_data: &[u8], user_key: &str, storage_key: &str, format_version: u32) -> Result<Vec<u8>> {
+        if format_version == 0 && !self.accept_legacy_format {
+            return Err(EnclaveError::PermissionDenied(format!(
+                "Entry '{}' uses the legacy storage format; enable storage_accept_legacy_format to migrate it",
+                redact(storage_key)
+            )).into());
+        }
         if encrypted_data.len() < 28 { // 12 (nonce) + 16 (tag) minimum
             return Err(anyhow!("Encrypted data too short"));
         }
@@ -438,86 +1557,191 @@ impl StorageService {
         let nonce = &encrypted_data[0..12];
         let ciphertext_and_tag = &encrypted_data[12..];
         
+        let aad = Self::storage_aad(storage_key, format_version)?;
         let mut in_out = ciphertext_and_tag.to_vec();
         let unbound_key = aead::UnboundKey::new(&aead::AES_256_GCM, &key)?;
         let less_safe_key = aead::LessSafeKey::new(unbound_key);
         let plaintext = less_safe_key.open_in_place(
             aead::Nonce::try_assume_unique_for_key(nonce)?,
-            aead::Aad::empty(),
+            aead::Aad::from(&aad[..]),
             &mut in_out,
-        )?;
+        )
+        .map_err(|_| anyhow!("Authentication failed for storage key '{}'", redact(storage_key)))?;
         
         Ok(plaintext.to_vec())
     }
     
-    /// Derive master encryption key for storage
-    fn derive_master_key(storage_dir: &Path) -> Result<Vec<u8>> {
-        let key_file = storage_dir.join(".master_key");
-        
-        if key_file.exists() {
-            // Load existing key
-            let key = fs::read(&key_file)?;
-            if key.len() == 32 {
-                return Ok(key);
+    /// Sealing service that protects the master key, shared for sealing other secrets
+    pub fn sealing_service(&self) -> &Arc<SealingService> {
+        &self.sealing_service
+    }
+    
+    /// Load the sealed master encryption key, creating it on first start
+    ///
+    /// A plaintext `.master_key` left by earlier versions is sealed and then removed, so
+    /// existing entries stay readable.
+    fn load_master_key(backend: &dyn StorageBackend, sealing_service: &SealingService) -> Result<Zeroizing<Vec<u8>>> {
+        if let Some(sealed_key) = backend.read(SEALED_MASTER_KEY_FILE)? {
+            let key = Zeroizing::new(sealing_service.unseal_data(SealDomain::Internal, &sealed_key)
+                .map_err(|e| anyhow!("Failed to unseal storage master key: {}", e))?);
+            if key.len() != 32 {
+                return Err(anyhow!("Sealed storage master key has invalid length {}", key.len()));
             }
+            if sealing_service.needs_reseal(&sealed_key) {
+                backend.write(SEALED_MASTER_KEY_FILE, &sealing_service.seal_data(SealDomain::Internal, &key)?)?;
+                info!("Re-sealed storage master key to the enclave signer");
+            }
+            return Ok(key);
         }
         
-        // Generate new master key
-        let mut key = vec![0u8; 32];
-        ring::rand::SystemRandom::new().fill(&mut key)?;
+        let legacy_key = match backend.read(LEGACY_MASTER_KEY_FILE) {
+            Ok(Some(key)) if key.len() == 32 => Some(Zeroizing::new(key)),
+            _ => None,
+        };
+        let key = match legacy_key {
+            Some(key) => key,
+            None => {
+                let mut key = Zeroizing::new(vec![0u8; 32]);
+                ring::rand::SystemRandom::new().fill(&mut key)?;
+                key
+            }
+        };
         
-        // Save to file with restricted permissions
-        fs::write(&key_file, &key)?;
+        // Backend writes are atomic, so a crash never leaves a truncated sealed key
+        backend.write(SEALED_MASTER_KEY_FILE, &sealing_service.seal_data(SealDomain::Internal, &key)?)?;
         
-        // Set file permissions to owner-only (Unix-style)
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&key_file)?.permissions();
-            perms.set_mode(0o600);
-            fs::set_permissions(&key_file, perms)?;
+        if backend.erase(LEGACY_MASTER_KEY_FILE)? {
+            info!("Sealed existing master encryption key and removed the plaintext copy");
+        } else {
+            info!("Generated new sealed master encryption key");
         }
-        
-        info!("Generated new master encryption key");
         Ok(key)
     }
     
     /// Derive encryption key from master key and user key
-    fn derive_encryption_key(&self, user_key: &str) -> Result<Vec<u8>> {
+    fn derive_encryption_key(&self, user_key: &str) -> Result<Zeroizing<Vec<u8>>> {
         use ring::{digest, pbkdf2};
         use std::num::NonZeroU32;
         
         let iterations = NonZeroU32::new(100_000).unwrap();
         let salt = b"neo-service-layer-storage";
         
-        let mut derived_key = vec![0u8; 32];
+        let mut derived_key = Zeroizing::new(vec![0u8; 32]);
+        let master_key = self.crypto_key.read_or_recover();
+        let password = Zeroizing::new(format!("{}{}", hex::encode(&**master_key), user_key));
+        drop(master_key);
         pbkdf2::derive(
             pbkdf2::PBKDF2_HMAC_SHA256,
             iterations,
             salt,
-            format!("{}{}", hex::encode(&self.crypto_key), user_key).as_bytes(),
+            password.as_bytes(),
             &mut derived_key,
         );
         
         Ok(derived_key)
     }
     
-    /// Save index to disk
+    /// Probe the on-disk index and remaining disk space
+    pub fn health_check(&self) -> ServiceHealth {
+        let entry_count = match self.index.read() {
+            Ok(index) => index.metadata.len(),
+            Err(_) => return ServiceHealth::unhealthy("storage", "Storage index lock poisoned"),
+        };
+        
+        // Make sure the persisted index can still be read back
+        if let Err(e) = StorageIndex::new().load(&*self.backend) {
+            return ServiceHealth::unhealthy("storage", format!("Storage index not loadable: {}", e));
+        }
+        
+        let fs_stats = match self.get_occlum_filesystem_stats() {
+            Ok(stats) => stats,
+            Err(e) => return ServiceHealth::unhealthy("storage", format!("Failed to read filesystem stats: {}", e)),
+        };
+        
+        let details = serde_json::json!({
+            "entry_count": entry_count,
+            "available_space": fs_stats.available_space,
+            "total_space": fs_stats.total_space,
+            "min_free_space": MIN_HEALTHY_FREE_SPACE,
+        });
+        
+        if fs_stats.available_space < MIN_HEALTHY_FREE_SPACE {
+            ServiceHealth::degraded(
+                "storage",
+                format!("Low disk space: {} bytes available", fs_stats.available_space),
+                details,
+            )
+        } else {
+            ServiceHealth::healthy("storage", details)
+        }
+    }
+    
+    /// Operation counters for this service
+    pub fn metrics(&self) -> &StorageMetrics {
+        &self.metrics
+    }
+    
+    /// Shared access to the index, rebuilding it first if a panic poisoned its lock
+    fn index_read(&self) -> RwLockReadGuard<'_, StorageIndex> {
+        if self.index.is_poisoned() {
+            drop(self.index_write());
+        }
+        self.index.read_or_recover()
+    }
+    
+    /// Exclusive access to the index
+    ///
+    /// A panic while the index was locked may have left its maps and totals out of step,
+    /// so a poisoned index is reloaded from the backend, where every change is journaled
+    /// before it is applied in memory. Access statistics since the last snapshot are lost.
+    fn index_write(&self) -> RwLockWriteGuard<'_, StorageIndex> {
+        self.index.write().unwrap_or_else(|poisoned| {
+            self.index.clear_poison();
+            locks::record_recovery("storage index");
+            let mut index = poisoned.into_inner();
+            let mut reloaded = StorageIndex::new();
+            match reloaded.load(&*self.backend) {
+                Ok(()) => *index = reloaded,
+                Err(e) => error!("Failed to reload the storage index, keeping the in-memory copy: {}", e),
+            }
+            index
+        })
+    }
+    
+    /// Write a full index snapshot, folding in the journal and access statistics
     fn save_index(&self) -> Result<()> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
-        index.save_to_file(&self.index_file)
+        let mut index = self.index_write();
+        index.save(&*self.backend)
+    }
+    
+    /// Journal an index change. Call with the index write lock held and before applying
+    /// the change in memory, so a failed write leaves the index untouched.
+    fn record_index_change(&self, index: &mut StorageIndex, change: IndexChange) -> Result<()> {
+        index.append_to_journal(&*self.backend, &change)
+    }
+    
+    /// Fold the journal into a new snapshot once it reaches `INDEX_COMPACTION_THRESHOLD`
+    /// records. Failures are only logged: every change is already durable in the journal.
+    fn compact_index_if_needed(&self, index: &mut StorageIndex) {
+        if index.journal_len < INDEX_COMPACTION_THRESHOLD {
+            return;
+        }
+        match index.save(&*self.backend) {
+            Ok(()) => debug!("Compacted storage index journal"),
+            Err(e) => warn!("Failed to compact storage index journal: {}", e),
+        }
     }
     
     /// Validate storage integrity
     async fn validate_storage_integrity(&self) -> Result<()> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         
         let mut corrupted_keys = Vec::new();
         
-        for (key, metadata) in &index.metadata {
-            if let Some(file_path) = index.key_to_path.get(key) {
-                if !file_path.exists() {
-                    warn!("Storage file missing for key '{}': {:?}", key, file_path);
+        for key in index.metadata.keys() {
+            if let Some(object) = index.key_to_object.get(key) {
+                if !self.backend.exists(object)? {
+                    warn!("Storage object missing for key '{}': {}", redact(key), object);
                     corrupted_keys.push(key.clone());
                 }
             }
@@ -572,7 +1796,6 @@ impl StorageService {
         let mut stats = DetailedStorageStats {
             total_used_space: 0,
             file_count: 0,
-            directory_count: 0,
             largest_file_size: 0,
             smallest_file_size: u64::MAX,
             average_file_size: 0,
@@ -583,13 +1806,11 @@ impl StorageService {
             inode_usage: 0,
         };
         
-        if !self.storage_dir.exists() {
-            return Ok(stats);
+        // Per-object analysis of everything the backend holds
+        for object in self.backend.list()? {
+            Self::analyze_object(&object, &mut stats);
         }
         
-        // Recursive directory traversal with detailed analysis
-        self.analyze_directory_recursive(&self.storage_dir, &mut stats)?;
-        
         // Calculate derived statistics
         if stats.file_count > 0 {
             stats.average_file_size = stats.total_used_space / stats.file_count as u64;
@@ -599,7 +1820,7 @@ impl StorageService {
         }
         
         // Calculate compression savings from metadata
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         for metadata in index.metadata.values() {
             if let Some(compressed_size) = metadata.compressed_size {
                 stats.compression_savings += metadata.size.saturating_sub(compressed_size);
@@ -609,107 +1830,62 @@ impl StorageService {
         Ok(stats)
     }
     
-    /// Recursively analyze directory structure for detailed statistics
-    fn analyze_directory_recursive(&self, dir: &Path, stats: &mut DetailedStorageStats) -> Result<()> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let metadata = entry.metadata()?;
-            
-            if metadata.is_file() {
-                let file_size = metadata.len();
-                stats.total_used_space += file_size;
-                stats.file_count += 1;
-                
-                // Track size statistics
-                stats.largest_file_size = stats.largest_file_size.max(file_size);
-                stats.smallest_file_size = stats.smallest_file_size.min(file_size);
-                
-                // Age analysis
-                if let Ok(created) = metadata.created() {
-                    if let Ok(age) = created.elapsed() {
-                        let age_days = age.as_secs() / (24 * 3600);
-                        *stats.files_by_age.entry(age_days).or_insert(0) += 1;
-                    }
-                }
-                
-                // Size buckets for analysis
-                let size_bucket = match file_size {
-                    0..=1024 => "tiny",          // 0-1KB
-                    1025..=10240 => "small",     // 1-10KB
-                    10241..=102400 => "medium",  // 10-100KB
-                    102401..=1048576 => "large", // 100KB-1MB
-                    _ => "huge",                 // >1MB
-                };
-                *stats.files_by_size.entry(size_bucket.to_string()).or_insert(0) += 1;
-                
-                // Check for wasted space (sparse files, excessive metadata, etc.)
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::MetadataExt;
-                    let blocks = metadata.blocks();
-                    let block_size = metadata.blksize();
-                    let allocated_size = blocks * block_size;
-                    if allocated_size > file_size {
-                        stats.wasted_space += allocated_size - file_size;
-                    }
-                }
-                
-                stats.inode_usage += 1;
-                
-            } else if metadata.is_dir() {
-                stats.directory_count += 1;
-                stats.inode_usage += 1;
-                
-                // Recursively analyze subdirectories
-                self.analyze_directory_recursive(&path, stats)?;
+    /// Fold a single backend object into the usage statistics
+    fn analyze_object(object: &BackendObject, stats: &mut DetailedStorageStats) {
+        let file_size = object.size;
+        stats.total_used_space += file_size;
+        stats.file_count += 1;
+        
+        // Track size statistics
+        stats.largest_file_size = stats.largest_file_size.max(file_size);
+        stats.smallest_file_size = stats.smallest_file_size.min(file_size);
+        
+        // Age analysis
+        if let Some(created) = object.created_at {
+            if let Ok(age) = created.elapsed() {
+                let age_days = age.as_secs() / (24 * 3600);
+                *stats.files_by_age.entry(age_days).or_insert(0) += 1;
             }
         }
         
-        Ok(())
+        // Size buckets for analysis
+        let size_bucket = match file_size {
+            0..=1024 => "tiny",          // 0-1KB
+            1025..=10240 => "small",     // 1-10KB
+            10241..=102400 => "medium",  // 10-100KB
+            102401..=1048576 => "large", // 100KB-1MB
+            _ => "huge",                 // >1MB
+        };
+        *stats.files_by_size.entry(size_bucket.to_string()).or_insert(0) += 1;
+        
+        // Check for wasted space (sparse files, excessive metadata, etc.)
+        if let Some(allocated_size) = object.allocated_size {
+            if allocated_size > file_size {
+                stats.wasted_space += allocated_size - file_size;
+            }
+        }
+        
+        stats.inode_usage += 1;
     }
     
     /// Get Occlum LibOS specific filesystem statistics
     fn get_occlum_filesystem_stats(&self) -> Result<OcclumFilesystemStats> {
-        #[cfg(unix)]
-        {
-            use std::ffi::CString;
-            use std::mem;
-            
-            // Use libc statvfs for accurate filesystem statistics in Occlum
-            let path_cstr = CString::new(self.storage_dir.to_str().unwrap())?;
-            let mut statvfs_buf: libc::statvfs = unsafe { mem::zeroed() };
-            
-            let result = unsafe { libc::statvfs(path_cstr.as_ptr(), &mut statvfs_buf) };
-            
-            if result == 0 {
-                let block_size = statvfs_buf.f_frsize as u64;
-                let total_blocks = statvfs_buf.f_blocks as u64;
-                let free_blocks = statvfs_buf.f_bavail as u64;
-                let total_inodes = statvfs_buf.f_files as u64;
-                let free_inodes = statvfs_buf.f_favail as u64;
-                
-                Ok(OcclumFilesystemStats {
-                    total_space: total_blocks * block_size,
-                    available_space: free_blocks * block_size,
-                    used_space: (total_blocks - free_blocks) * block_size,
-                    total_inodes,
-                    available_inodes: free_inodes,
-                    block_size,
-                    filesystem_type: "occlum".to_string(),
-                })
-            } else {
-                // Fallback to basic estimation
-                self.get_fallback_filesystem_stats()
-            }
-        }
-        #[cfg(not(unix))]
-        {
-            self.get_fallback_filesystem_stats()
+        match self.backend.capacity()? {
+            Some(capacity) => Ok(OcclumFilesystemStats {
+                total_space: capacity.total_space,
+                available_space: capacity.available_space,
+                used_space: capacity.total_space.saturating_sub(capacity.available_space),
+                total_inodes: capacity.total_inodes,
+                available_inodes: capacity.available_inodes,
+                block_size: capacity.block_size,
+                filesystem_type: "occlum".to_string(),
+            }),
+            // Fallback to basic estimation
+            None => self.get_fallback_filesystem_stats(),
         }
     }
     
-    /// Fallback filesystem statistics for non-Unix or when statvfs fails
+    /// Fallback statistics for backends that cannot report their capacity
     fn get_fallback_filesystem_stats(&self) -> Result<OcclumFilesystemStats> {
         // Use directory metadata as fallback
         let used_space = self.calculate_used_space()?;
@@ -844,6 +2020,9 @@ impl StorageService {
     pub async fn optimize_storage(&self) -> Result<String> {
         info!("Starting storage optimization");
         
+        // The access-pattern passes below read access statistics from the metadata
+        self.index_write().sync_access_stats();
+        
         let before_stats = self.calculate_detailed_storage_usage()?;
         let mut optimization_results = StorageOptimizationResults {
             files_processed: 0,
@@ -887,17 +2066,12 @@ impl StorageService {
     
     /// Clean up orphaned files that don't have metadata entries
     async fn cleanup_orphaned_files(&self) -> Result<u64> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         let mut bytes_reclaimed = 0u64;
         
-        for entry in fs::read_dir(&self.storage_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            
-            if path.is_file() && path.extension().map(|s| s == "dat").unwrap_or(false) {
-                let filename = path.file_stem().unwrap().to_str().unwrap();
-                
-                // Check if this file has a corresponding metadata entry
+        for object in self.backend.list()? {
+            if let Some(filename) = object.name.strip_suffix(".dat") {
+                // Check if this object has a corresponding metadata entry
                 let has_metadata = index.metadata.values()
                     .any(|meta| {
                         let expected_hash = hex::encode(Sha256::digest(meta.key.as_bytes()));
@@ -905,10 +2079,9 @@ impl StorageService {
                     });
                 
                 if !has_metadata {
-                    let file_size = entry.metadata()?.len();
-                    fs::remove_file(&path)?;
-                    bytes_reclaimed += file_size;
-                    info!("Removed orphaned file: {:?} ({} bytes)", path, file_size);
+                    self.backend.delete(&object.name)?;
+                    bytes_reclaimed += object.size;
+                    info!("Removed orphaned object: {} ({} bytes)", object.name, object.size);
                 }
             }
         }
@@ -918,7 +2091,7 @@ impl StorageService {
     
     /// Optimize compression for files based on access patterns
     async fn optimize_compression(&self) -> Result<u32> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         let mut optimized_count = 0u32;
         
         for metadata in index.metadata.values() {
@@ -926,7 +2099,7 @@ impl StorageService {
             if metadata.access_count > 10 && metadata.compression.is_none() {
                 // This would trigger recompression in a real implementation
                 optimized_count += 1;
-                debug!("Would recompress frequently accessed file: {}", metadata.key);
+                debug!("Would recompress frequently accessed file: {}", redact(&metadata.key));
             }
         }
         
@@ -935,7 +2108,7 @@ impl StorageService {
     
     /// Consolidate small files to reduce fragmentation
     async fn consolidate_small_files(&self) -> Result<u32> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         let small_files: Vec<_> = index.metadata.values()
             .filter(|meta| meta.size < 1024 && meta.access_count < 5)
             .collect();
@@ -948,7 +2121,7 @@ impl StorageService {
     
     /// Archive old, infrequently accessed files
     async fn archive_old_files(&self) -> Result<u32> {
-        let index = self.index.read().map_err(|_| anyhow!("Lock poisoned"))?;
+        let index = self.index_read();
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
         let ninety_days = 90 * 24 * 3600;
         
@@ -970,7 +2143,6 @@ impl StorageService {
 struct DetailedStorageStats {
     total_used_space: u64,
     file_count: usize,
-    directory_count: usize,
     largest_file_size: u64,
     smallest_file_size: u64,
     average_file_size: u64,
@@ -1002,4 +2174,354 @@ struct StorageOptimizationResults {
     compression_improved: u32,
     files_archived: u32,
     optimization_time_ms: u64,
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditEvent, AuditLog};
+    use crate::test_support::{core_services, test_config};
+
+    #[tokio::test]
+    async fn restored_store_feeds_services_built_on_it() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_config = test_config(source_dir.path());
+        let (storage, audit, _crypto) = core_services(&source_config).await;
+        storage.store_data("user_entry", b"user data", "entry key", "alice", StoreOptions { compress: true, ..StoreOptions::default() }).unwrap();
+        audit.append(AuditEvent::new("test", "before_snapshot", "user_entry")).unwrap();
+        let head_hash = audit.head_hash().unwrap();
+        let entry_count = audit.entry_count().unwrap();
+        
+        let mut archive = Vec::new();
+        storage.create_snapshot(&mut archive, "passphrase").unwrap();
+        
+        let target_dir = tempfile::tempdir().unwrap();
+        let target_config = test_config(target_dir.path());
+        let (restored, report) = StorageService::restore_from_snapshot(&target_config, archive.as_slice(), "passphrase").await.unwrap();
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert!(report["entries"].as_u64().unwrap() > 0);
+        
+        let restored = Arc::new(restored);
+        let audit = Arc::new(AuditLog::new(restored.clone()).unwrap());
+        assert_eq!(audit.head_hash().unwrap(), head_hash);
+        assert_eq!(audit.entry_count().unwrap(), entry_count);
+        let verification: serde_json::Value = serde_json::from_str(&audit.verify_chain().unwrap()).unwrap();
+        assert_eq!(verification["valid"], true);
+        
+        assert_eq!(restored.retrieve_data("user_entry", "entry key", "alice").unwrap(), b"user data");
+        
+        // New entries continue the restored chain
+        audit.append(AuditEvent::new("test", "after_restore", "user_entry")).unwrap();
+        let verification: serde_json::Value = serde_json::from_str(&audit.verify_chain().unwrap()).unwrap();
+        assert_eq!(verification["valid"], true);
+    }
+    
+    #[tokio::test]
+    async fn restore_refuses_a_populated_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let (storage, audit, _crypto) = core_services(&config).await;
+        audit.append(AuditEvent::new("test", "before_snapshot", "store")).unwrap();
+        let mut archive = Vec::new();
+        storage.create_snapshot(&mut archive, "passphrase").unwrap();
+        
+        assert!(StorageService::restore_from_snapshot(&config, archive.as_slice(), "passphrase").await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn metadata_and_listings_follow_read_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, _audit, _crypto) = core_services(&test_config(dir.path())).await;
+        let shared = StorageAcl { readers: vec!["bob".to_string()], ..StorageAcl::default() };
+        storage.store_data("alice_private", b"a", "k", "alice", StoreOptions::default()).unwrap();
+        storage.store_data("alice_shared", b"b", "k", "alice", StoreOptions { acl: shared, ..StoreOptions::default() }).unwrap();
+        
+        assert!(storage.get_metadata("alice_private", "alice").is_ok());
+        assert!(storage.get_metadata("alice_shared", "bob").is_ok());
+        let denied = storage.get_metadata("alice_private", "bob").unwrap_err();
+        assert!(matches!(denied.downcast_ref::<StorageError>(), Some(StorageError::AccessDenied { .. })));
+        
+        let listed = |principal: &str| -> Vec<String> {
+            let page: serde_json::Value = serde_json::from_str(&storage.list_keys(principal, None, None).unwrap()).unwrap();
+            serde_json::from_value(page["items"].clone()).unwrap()
+        };
+        assert_eq!(listed("alice"), ["alice_private", "alice_shared"]);
+        assert_eq!(listed("bob"), ["alice_shared"]);
+        assert!(listed("mallory").is_empty());
+    }
+    
+    #[tokio::test]
+    async fn colliding_with_another_principals_key_is_access_denied() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, _audit, _crypto) = core_services(&test_config(dir.path())).await;
+        storage.store_data("taken", b"a", "k", "alice", StoreOptions::default()).unwrap();
+        
+        let collision = storage.store_data("taken", b"b", "k", "mallory", StoreOptions::default()).unwrap_err();
+        assert!(matches!(collision.downcast_ref::<StorageError>(), Some(StorageError::AccessDenied { .. })));
+        
+        let own = storage.store_data("taken", b"b", "k", "alice", StoreOptions::default()).unwrap_err();
+        assert!(matches!(own.downcast_ref::<EnclaveError>(), Some(EnclaveError::AlreadyExists(_))));
+    }
+    
+    /// Storage under `dir` with an LRU quota of `max_total_bytes`
+    async fn quota_storage(dir: &std::path::Path, max_total_bytes: u64) -> StorageService {
+        let mut config = test_config(dir);
+        config.storage_max_total_bytes = max_total_bytes;
+        config.storage_eviction_policy = "lru".to_string();
+        StorageService::new(&config).await.unwrap()
+    }
+    
+    #[tokio::test]
+    async fn eviction_only_removes_the_writers_own_unpinned_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = quota_storage(dir.path(), 300).await;
+        let block = [7u8; 100];
+        storage.store_data("alice_pinned", &block, "k", "alice", StoreOptions { pinned: true, ..StoreOptions::default() }).unwrap();
+        storage.store_data("alice_old", &block, "k", "alice", StoreOptions::default()).unwrap();
+        storage.store_data("bob_entry", &block, "k", "bob", StoreOptions::default()).unwrap();
+        
+        // Bob has nothing evictable but his own entry, and alice's are off limits
+        storage.store_data("bob_second", &block, "k", "bob", StoreOptions::default()).unwrap();
+        assert!(storage.contains_key("alice_pinned") && storage.contains_key("alice_old"));
+        assert!(!storage.contains_key("bob_entry"));
+        
+        // Alice's write evicts her unpinned entry, never the pinned one
+        storage.store_data("alice_new", &block, "k", "alice", StoreOptions::default()).unwrap();
+        assert!(storage.contains_key("alice_pinned") && !storage.contains_key("alice_old"));
+        assert!(storage.contains_key("bob_second"));
+    }
+    
+    #[tokio::test]
+    async fn writes_that_only_fit_by_evicting_others_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = quota_storage(dir.path(), 200).await;
+        let block = [7u8; 100];
+        storage.store_data("alice_a", &block, "k", "alice", StoreOptions::default()).unwrap();
+        storage.store_data("alice_b", &block, "k", "alice", StoreOptions::default()).unwrap();
+        
+        let refused = storage.store_data("mallory", &block, "k", "mallory", StoreOptions::default()).unwrap_err();
+        assert!(matches!(refused.downcast_ref::<StorageError>(), Some(StorageError::QuotaExceeded { .. })));
+        assert!(storage.contains_key("alice_a") && storage.contains_key("alice_b"));
+    }
+    
+    #[tokio::test]
+    async fn pinned_entries_are_pinned_when_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = quota_storage(dir.path(), 0).await;
+        storage.store_data("pinned", b"data", "k", "alice", StoreOptions { pinned: true, ..StoreOptions::default() }).unwrap();
+        
+        let metadata: serde_json::Value = serde_json::from_str(&storage.get_metadata("pinned", "alice").unwrap()).unwrap();
+        assert_eq!(metadata["pinned"], true);
+    }
+    
+    #[tokio::test]
+    async fn decompression_bombs_stop_at_the_recorded_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageService::new(&test_config(dir.path())).await.unwrap();
+        storage.store_data("small", &[7u8; 1024], "entry key", "alice", StoreOptions { compress: true, ..StoreOptions::default() }).unwrap();
+        let mut metadata: StorageMetadata = serde_json::from_str(&storage.get_metadata("small", "alice").unwrap()).unwrap();
+        
+        let bomb = vec![0u8; 4 * 1024 * 1024];
+        for compression in [CompressionType::Gzip, CompressionType::Lz4, CompressionType::Zstd] {
+            metadata.compression = Some(compression.clone());
+            let payload = match compression {
+                CompressionType::Gzip => gzip(&bomb),
+                compression => storage.compress_data(&bomb, compression).unwrap(),
+            };
+            let error = storage.decompress_and_verify(payload, &metadata).unwrap_err();
+            assert!(error.to_string().contains("beyond its recorded size of 1024 bytes"), "{}", error);
+        }
+    }
+    
+    /// Gzip as earlier versions compressed dense entries
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+    
+    #[tokio::test]
+    async fn gzip_entries_still_decode_but_are_no_longer_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageService::new(&test_config(dir.path())).await.unwrap();
+        let data = b"compressed by an earlier version ".repeat(64);
+        storage.store_data("legacy", &data, "entry key", "alice", StoreOptions { compress: true, ..StoreOptions::default() }).unwrap();
+        let mut metadata: StorageMetadata = serde_json::from_str(&storage.get_metadata("legacy", "alice").unwrap()).unwrap();
+        
+        metadata.compression = Some(CompressionType::Gzip);
+        assert_eq!(storage.decompress_and_verify(gzip(&data), &metadata).unwrap(), data);
+        assert!(storage.compress_data(&data, CompressionType::Gzip).is_err());
+    }
+    
+    #[tokio::test]
+    async fn compression_policy_is_recorded_for_each_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.storage_dense_compression_min_bytes = 64 * 1024;
+        let storage = StorageService::new(&config).await.unwrap();
+        
+        let mut random = vec![0u8; 4096];
+        rand::SystemRandom::new().fill(&mut random).unwrap();
+        random[0] = 0;
+        let gzipped = [&[0x1f, 0x8b][..], &[0u8; 4096]].concat();
+        let cases = vec![
+            ("disabled", vec![1u8; 4096], false, CompressionPolicy::Disabled, None),
+            ("small", vec![1u8; 100], true, CompressionPolicy::BelowThreshold, None),
+            ("gzipped", gzipped, true, CompressionPolicy::AlreadyCompressed, None),
+            ("fast", vec![1u8; 4096], true, CompressionPolicy::Fast, Some(CompressionType::Lz4)),
+            ("dense", b"rarely read ".repeat(16 * 1024), true, CompressionPolicy::Dense, Some(CompressionType::Zstd)),
+            ("random", random, true, CompressionPolicy::NotSmaller, None),
+        ];
+        for (key, data, compress, _, _) in &cases {
+            storage.store_data(key, data, "entry key", "alice", StoreOptions { compress: *compress, ..StoreOptions::default() }).unwrap();
+        }
+        
+        // The policy is part of the persisted metadata, so a reopened store reports it too
+        drop(storage);
+        let storage = StorageService::new(&config).await.unwrap();
+        for (key, data, _, policy, compression) in &cases {
+            let metadata: StorageMetadata = serde_json::from_str(&storage.get_metadata(key, "alice").unwrap()).unwrap();
+            assert_eq!(metadata.compression_policy, Some(*policy), "{}", key);
+            assert_eq!(
+                format!("{:?}", metadata.compression), format!("{:?}", compression), "{}", key,
+            );
+            assert_eq!(metadata.compressed_size.is_some(), compression.is_some(), "{}", key);
+            assert_eq!(&storage.retrieve_data(key, "entry key", "alice").unwrap(), data, "{}", key);
+        }
+        
+        // Once an entry is read often, rewriting it switches to fast compression
+        for _ in 0..HOT_ACCESS_COUNT {
+            storage.retrieve_data("dense", "entry key", "alice").unwrap();
+        }
+        let data = b"now read often ".repeat(16 * 1024);
+        storage.update_data("dense", &data, "entry key", true, "alice").unwrap();
+        let metadata: StorageMetadata = serde_json::from_str(&storage.get_metadata("dense", "alice").unwrap()).unwrap();
+        assert_eq!(metadata.compression_policy, Some(CompressionPolicy::Fast));
+        assert!(matches!(metadata.compression, Some(CompressionType::Lz4)));
+        assert_eq!(storage.retrieve_data("dense", "entry key", "alice").unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn legacy_format_entries_need_the_migration_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageService::new(&test_config(dir.path())).await.unwrap();
+        storage.store_data("legacy", b"old secret", "entry key", "alice", StoreOptions::default()).unwrap();
+        // Rewrite the entry as an earlier version would have stored it
+        {
+            let mut index = storage.index_write();
+            let object = index.key_to_object.get("legacy").unwrap().clone();
+            storage.backend.write(&object, &storage.encrypt_data(b"old secret", "entry key", "legacy", 0).unwrap()).unwrap();
+            let mut metadata = index.current_metadata("legacy").unwrap();
+            metadata.format_version = 0;
+            index.insert_entry(metadata, object);
+        }
+        
+        let error = storage.retrieve_data("legacy", "entry key", "alice").unwrap_err();
+        assert!(matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::PermissionDenied(_))), "{}", error);
+        
+        let mut migrating = StorageService::new(&test_config(dir.path())).await.unwrap();
+        migrating.index = storage.index.clone();
+        migrating.accept_legacy_format = true;
+        assert_eq!(migrating.retrieve_data("legacy", "entry key", "alice").unwrap(), b"old secret");
+        migrating.update_data("legacy", b"old secret", "entry key", false, "alice").unwrap();
+        assert_eq!(storage.retrieve_data("legacy", "entry key", "alice").unwrap(), b"old secret");
+    }
+
+    #[tokio::test]
+    async fn master_key_is_wiped_on_drop() {
+        fn wiped_on_drop<T: zeroize::ZeroizeOnDrop>(_: &T) {}
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageService::new(&test_config(dir.path())).await.unwrap();
+        let master_key = storage.crypto_key.read().unwrap();
+        wiped_on_drop(&*master_key);
+        assert_eq!(master_key.len(), 32);
+        assert!(master_key.iter().any(|&b| b != 0));
+    }
+
+    #[tokio::test]
+    async fn prefix_deletes_leave_unrelated_keys_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let storage = StorageService::new(&config).await.unwrap();
+        for key in ["tenant_a/1", "tenant_a/2", "tenant_ab", "tenant_b/1"] {
+            storage.store_data(key, key.as_bytes(), "k", "alice", StoreOptions::default()).unwrap();
+        }
+        storage.store_data("tenant_a/bob", b"b", "k", "bob", StoreOptions { secure_delete: true, ..StoreOptions::default() }).unwrap();
+        
+        let result: serde_json::Value = serde_json::from_str(&storage.delete_prefix("tenant_a/", "alice").unwrap()).unwrap();
+        assert_eq!(result["deleted"], 2);
+        assert_eq!(result["deleted_keys"], serde_json::json!(["tenant_a/1", "tenant_a/2"]));
+        assert_eq!(result["failed"][0]["key"], "tenant_a/bob");
+        assert!(storage.delete_prefix("", "alice").is_err());
+        
+        // The removals were journaled, so a reopened store agrees
+        drop(storage);
+        let storage = StorageService::new(&config).await.unwrap();
+        assert!(!storage.contains_key("tenant_a/1") && !storage.contains_key("tenant_a/2"));
+        assert_eq!(storage.retrieve_data("tenant_ab", "k", "alice").unwrap(), b"tenant_ab");
+        assert_eq!(storage.retrieve_data("tenant_b/1", "k", "alice").unwrap(), b"tenant_b/1");
+        assert!(storage.contains_key("tenant_a/bob"));
+    }
+    
+    #[tokio::test]
+    async fn bulk_deletes_skip_duplicates_and_report_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, _audit, _crypto) = core_services(&test_config(dir.path())).await;
+        storage.store_data("one", b"1", "k", "alice", StoreOptions::default()).unwrap();
+        storage.store_data("two", b"2", "k", "alice", StoreOptions { secure_delete: true, ..StoreOptions::default() }).unwrap();
+        storage.store_data("three", b"3", "k", "alice", StoreOptions::default()).unwrap();
+        
+        let keys: Vec<String> = ["one", "two", "one", ""].iter().map(|key| key.to_string()).collect();
+        let result: serde_json::Value = serde_json::from_str(&storage.delete_many(&keys, "alice").unwrap()).unwrap();
+        assert_eq!(result["deleted"], 2);
+        assert_eq!(result["deleted_keys"], serde_json::json!(["one", "two"]));
+        assert_eq!(result["failed"].as_array().unwrap().len(), 1);
+        assert_eq!(result["failed"][0]["key"], "");
+        assert!(storage.contains_key("three"));
+    }
+
+    #[tokio::test]
+    async fn key_listings_page_through_every_key_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (storage, _audit, _crypto) = core_services(&test_config(dir.path())).await;
+        for i in 0..7 {
+            storage.store_data(&format!("key_{}", i), b"v", "k", "alice", StoreOptions::default()).unwrap();
+        }
+        storage.store_data("bobs", b"v", "k", "bob", StoreOptions::default()).unwrap();
+        
+        let mut seen = Vec::new();
+        let mut offset = Some(0);
+        while let Some(next) = offset {
+            let page: serde_json::Value = serde_json::from_str(&storage.list_keys("alice", Some(3), Some(next)).unwrap()).unwrap();
+            assert_eq!(page["total"], 7);
+            assert_eq!(page["limit"], 3);
+            seen.extend(page["items"].as_array().unwrap().iter().map(|key| key.as_str().unwrap().to_string()));
+            offset = page["next_offset"].as_u64().map(|next| next as usize);
+        }
+        assert_eq!(seen, (0..7).map(|i| format!("key_{}", i)).collect::<Vec<_>>());
+        
+        let beyond: serde_json::Value = serde_json::from_str(&storage.list_keys("alice", Some(3), Some(9)).unwrap()).unwrap();
+        assert_eq!(beyond["items"], serde_json::json!([]));
+        assert_eq!(beyond["total"], 7);
+    }
+
+    #[tokio::test]
+    async fn objects_moved_between_keys_fail_authentication() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = StorageService::new(&test_config(dir.path())).await.unwrap();
+        storage.store_data("A", b"alice's secret", "shared key", "alice", StoreOptions::default()).unwrap();
+        storage.store_data("B", b"bobbys payload", "shared key", "alice", StoreOptions::default()).unwrap();
+        
+        // Same principal, same encryption key and same length: only the AAD binds each
+        // ciphertext to its own key
+        let (object_a, object_b) = {
+            let index = storage.index_read();
+            (index.key_to_object["A"].clone(), index.key_to_object["B"].clone())
+        };
+        storage.backend.write(&object_b, &storage.backend.read(&object_a).unwrap().unwrap()).unwrap();
+        
+        let error = storage.retrieve_data("B", "shared key", "alice").unwrap_err();
+        assert!(error.to_string().contains("Authentication failed for storage key"), "{}", error);
+        assert_eq!(storage.retrieve_data("A", "shared key", "alice").unwrap(), b"alice's secret");
+    }
+}
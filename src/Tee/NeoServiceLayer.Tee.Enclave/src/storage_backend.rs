@@ -0,0 +1,535 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use log::{info, warn};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::EncaveConfig;
+use crate::error::EnclaveError;
+use crate::locks::RwLockExt;
+
+/// An object held by a backend, as reported by [`StorageBackend::list`]
+#[derive(Debug, Clone)]
+pub struct BackendObject {
+    pub name: String,
+    pub size: u64,
+    pub created_at: Option<SystemTime>,
+    /// Space the object occupies on the underlying medium, when known
+    pub allocated_size: Option<u64>,
+}
+
+/// Space available to a backend
+#[derive(Debug, Clone)]
+pub struct BackendCapacity {
+    pub total_space: u64,
+    pub available_space: u64,
+    pub total_inodes: u64,
+    pub available_inodes: u64,
+    pub block_size: u64,
+}
+
+/// Flat namespace of named byte objects underneath `StorageService`
+///
+/// The service keeps entries, the index snapshot and journal, and the sealed master key as
+/// objects here; encryption, compression and metadata all happen above this layer.
+pub trait StorageBackend: Send + Sync {
+    /// Short identifier reported in diagnostics
+    fn kind(&self) -> &'static str;
+
+    /// Contents of `name`, or `None` if it does not exist
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Replace `name` with `data` atomically and durably: readers see either the old or the
+    /// new contents, never a mix
+    fn write(&self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// Durably append `data` to `name`, creating it if needed
+    fn append(&self, name: &str, data: &[u8]) -> Result<()>;
+
+    /// Remove `name`, returning whether it existed
+    fn delete(&self, name: &str) -> Result<bool>;
+
+    /// Remove an object that held secret material, overwriting it first where the medium
+    /// would otherwise keep the old bytes around
+    fn erase(&self, name: &str) -> Result<bool> {
+        self.delete(name)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool>;
+
+    fn list(&self) -> Result<Vec<BackendObject>>;
+
+    /// Total and free space, or `None` when the backend cannot tell
+    fn capacity(&self) -> Result<Option<BackendCapacity>>;
+}
+
+/// Name of the filesystem holding `path` if it is copy-on-write
+#[cfg(target_os = "linux")]
+fn copy_on_write_filesystem(path: &Path) -> Option<&'static str> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const COPY_ON_WRITE_MAGICS: &[(u32, &str)] = &[
+        (0x9123_683E, "btrfs"),
+        (0x2FC1_2FC1, "zfs"),
+        (0xCA45_1A4E, "bcachefs"),
+        (0x3434, "nilfs2"),
+    ];
+
+    let path_cstr = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statfs only writes into the zero-initialized buffer
+    let mut statfs_buf: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path_cstr.as_ptr(), &mut statfs_buf) } != 0 {
+        return None;
+    }
+    COPY_ON_WRITE_MAGICS.iter()
+        .find(|(magic, _)| statfs_buf.f_type as u32 == *magic)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_on_write_filesystem(_path: &Path) -> Option<&'static str> {
+    None
+}
+
+/// Backend names accepted by `EncaveConfig::storage_backend`
+pub const VALID_STORAGE_BACKENDS: &[&str] = &["filesystem", "memory"];
+
+/// Build the backend selected by `config.storage_backend`
+pub fn backend_from_config(config: &EncaveConfig) -> Result<Box<dyn StorageBackend>> {
+    match config.storage_backend.as_str() {
+        "filesystem" => Ok(Box::new(FilesystemBackend::new(PathBuf::from(&config.storage_path))?)),
+        "memory" => Ok(Box::new(InMemoryBackend::new())),
+        other => Err(anyhow!(
+            "Unknown storage backend '{}', expected one of {:?}", other, VALID_STORAGE_BACKENDS
+        )),
+    }
+}
+
+/// A directory that file operations cannot escape
+///
+/// The root is canonicalized once; paths are taken relative to it and rejected if they are
+/// absolute, contain `.` or `..`, or pass through a symlink leading outside the root.
+/// Anything that reads or writes files for storage should resolve its paths here.
+#[derive(Debug, Clone)]
+pub struct PathSandbox {
+    root: PathBuf,
+}
+
+impl PathSandbox {
+    /// Create `root` if it does not exist and pin its canonical form
+    pub fn new(root: &Path) -> Result<Self> {
+        if !root.exists() {
+            fs::create_dir_all(root)?;
+            info!("Created storage directory: {:?}", root);
+        }
+        Ok(Self { root: fs::canonicalize(root)? })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve `relative` inside the root, or fail with `PermissionDenied` if it would escape
+    pub fn resolve(&self, relative: impl AsRef<Path>) -> Result<PathBuf> {
+        let relative = relative.as_ref();
+        let escapes = || EnclaveError::PermissionDenied(format!("Path {:?} escapes the storage directory", relative));
+        if relative.as_os_str().is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(escapes().into());
+        }
+        
+        // Only symlinks can still lead out; the first missing component ends the walk
+        // since nothing below it exists yet
+        let mut current = self.root.clone();
+        for component in relative.components() {
+            current.push(component);
+            match fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    // Dangling links are rejected too: writing through one creates its target
+                    match fs::canonicalize(&current) {
+                        Ok(target) if target.starts_with(&self.root) => {}
+                        _ => return Err(escapes().into()),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+/// Chunk size used when overwriting a file before it is erased
+const ERASE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Objects stored as files in a single directory, readable only by the enclave user
+pub struct FilesystemBackend {
+    sandbox: PathSandbox,
+    /// Name of the copy-on-write filesystem holding `root`, where overwriting a file in
+    /// place does not destroy its old blocks
+    copy_on_write: Option<&'static str>,
+    /// Set once the ineffective-erase warning has been logged
+    erase_warned: AtomicBool,
+}
+
+impl FilesystemBackend {
+    pub fn new(root: PathBuf) -> Result<Self> {
+        let sandbox = PathSandbox::new(&root)?;
+        let copy_on_write = copy_on_write_filesystem(sandbox.root());
+        Ok(Self { sandbox, copy_on_write, erase_warned: AtomicBool::new(false) })
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        self.sandbox.resolve(name)
+    }
+
+    fn open_options() -> OpenOptions {
+        let mut options = OpenOptions::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+    }
+}
+
+impl StorageBackend for FilesystemBackend {
+    fn kind(&self) -> &'static str {
+        "filesystem"
+    }
+
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(name)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes a temporary file, syncs it and renames it over the old one
+    fn write(&self, name: &str, data: &[u8]) -> Result<()> {
+        let temp_path = self.path(&format!("{}.tmp", name))?;
+        {
+            let mut file = Self::open_options().write(true).create(true).truncate(true).open(&temp_path)?;
+            file.write_all(data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, self.path(name)?)?;
+        Ok(())
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> Result<()> {
+        let mut file = Self::open_options().create(true).append(true).open(self.path(name)?)?;
+        file.write_all(data)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        match fs::remove_file(self.path(name)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Overwrites the file in place with random bytes before unlinking it so its contents
+    /// do not linger in the file's blocks. Copy-on-write filesystems write the new bytes
+    /// elsewhere, so there this only logs a warning that the old contents survive.
+    fn erase(&self, name: &str) -> Result<bool> {
+        let path = self.path(name)?;
+        let len = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(filesystem) = self.copy_on_write {
+            if !self.erase_warned.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Storage directory {:?} is on {}, a copy-on-write filesystem; overwriting files before deletion does not remove their old contents",
+                    self.sandbox.root(), filesystem
+                );
+            }
+        }
+        {
+            let rng = SystemRandom::new();
+            let mut chunk = vec![0u8; ERASE_CHUNK_SIZE];
+            let mut file = OpenOptions::new().write(true).open(&path)?;
+            let mut remaining = len;
+            while remaining > 0 {
+                let n = remaining.min(ERASE_CHUNK_SIZE as u64) as usize;
+                rng.fill(&mut chunk[..n]).map_err(|_| anyhow!("Failed to generate random bytes"))?;
+                file.write_all(&chunk[..n])?;
+                remaining -= n as u64;
+            }
+            file.sync_all()?;
+        }
+        self.delete(name)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.path(name)?.try_exists()?)
+    }
+
+    fn list(&self) -> Result<Vec<BackendObject>> {
+        let mut objects = Vec::new();
+        for entry in fs::read_dir(self.sandbox.root())? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            #[cfg(unix)]
+            let allocated_size = {
+                use std::os::unix::fs::MetadataExt;
+                Some(metadata.blocks() * metadata.blksize())
+            };
+            #[cfg(not(unix))]
+            let allocated_size = None;
+
+            objects.push(BackendObject {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size: metadata.len(),
+                created_at: metadata.created().ok(),
+                allocated_size,
+            });
+        }
+        Ok(objects)
+    }
+
+    /// Uses `statvfs` on the storage directory; `None` if that fails
+    fn capacity(&self) -> Result<Option<BackendCapacity>> {
+        #[cfg(unix)]
+        {
+            use std::ffi::CString;
+            use std::os::unix::ffi::OsStrExt;
+
+            let path_cstr = CString::new(self.sandbox.root().as_os_str().as_bytes())?;
+            // SAFETY: statvfs only writes into the zero-initialized buffer
+            let mut statvfs_buf: libc::statvfs = unsafe { std::mem::zeroed() };
+            let result = unsafe { libc::statvfs(path_cstr.as_ptr(), &mut statvfs_buf) };
+            if result != 0 {
+                return Ok(None);
+            }
+
+            let block_size = statvfs_buf.f_frsize as u64;
+            Ok(Some(BackendCapacity {
+                total_space: statvfs_buf.f_blocks as u64 * block_size,
+                available_space: statvfs_buf.f_bavail as u64 * block_size,
+                total_inodes: statvfs_buf.f_files as u64,
+                available_inodes: statvfs_buf.f_favail as u64,
+                block_size,
+            }))
+        }
+        #[cfg(not(unix))]
+        {
+            Ok(None)
+        }
+    }
+}
+
+/// Objects kept in process memory and lost on restart; for tests and ephemeral deployments
+#[derive(Default)]
+pub struct InMemoryBackend {
+    objects: RwLock<HashMap<String, (Vec<u8>, SystemTime)>>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StorageBackend for InMemoryBackend {
+    fn kind(&self) -> &'static str {
+        "memory"
+    }
+
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let objects = self.objects.read_or_recover();
+        Ok(objects.get(name).map(|(data, _)| data.clone()))
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> Result<()> {
+        let mut objects = self.objects.write_or_recover();
+        objects.insert(name.to_string(), (data.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn append(&self, name: &str, data: &[u8]) -> Result<()> {
+        let mut objects = self.objects.write_or_recover();
+        objects.entry(name.to_string())
+            .or_insert_with(|| (Vec::new(), SystemTime::now()))
+            .0
+            .extend_from_slice(data);
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        let mut objects = self.objects.write_or_recover();
+        Ok(objects.remove(name).is_some())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        let objects = self.objects.read_or_recover();
+        Ok(objects.contains_key(name))
+    }
+
+    fn list(&self) -> Result<Vec<BackendObject>> {
+        let objects = self.objects.read_or_recover();
+        Ok(objects.iter()
+            .map(|(name, (data, created_at))| BackendObject {
+                name: name.clone(),
+                size: data.len() as u64,
+                created_at: Some(*created_at),
+                allocated_size: None,
+            })
+            .collect())
+    }
+
+    fn capacity(&self) -> Result<Option<BackendCapacity>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Behaviour every backend must share
+    fn exercise(backend: &dyn StorageBackend) {
+        assert_eq!(backend.read("entry").unwrap(), None);
+        assert!(!backend.exists("entry").unwrap());
+
+        backend.write("entry", b"first").unwrap();
+        backend.write("entry", b"second").unwrap();
+        assert_eq!(backend.read("entry").unwrap().as_deref(), Some(&b"second"[..]));
+        assert!(backend.exists("entry").unwrap());
+
+        backend.append("journal", b"one,").unwrap();
+        backend.append("journal", b"two").unwrap();
+        assert_eq!(backend.read("journal").unwrap().as_deref(), Some(&b"one,two"[..]));
+
+        let mut listed: Vec<(String, u64)> = backend.list().unwrap().into_iter().map(|o| (o.name, o.size)).collect();
+        listed.sort();
+        assert_eq!(listed, vec![("entry".to_string(), 6), ("journal".to_string(), 7)]);
+
+        assert!(backend.delete("entry").unwrap());
+        assert!(!backend.delete("entry").unwrap());
+        assert!(backend.erase("journal").unwrap());
+        assert!(!backend.erase("journal").unwrap());
+        assert!(backend.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn in_memory_backend_behaves_like_storage() {
+        exercise(&InMemoryBackend::new());
+    }
+
+    #[test]
+    fn filesystem_backend_behaves_like_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = FilesystemBackend::new(dir.path().to_path_buf()).unwrap();
+        exercise(&backend);
+        assert!(backend.capacity().unwrap().is_some_and(|capacity| capacity.total_space > 0));
+    }
+
+    #[test]
+    fn config_selects_the_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::test_support::test_config(dir.path());
+        assert_eq!(backend_from_config(&config).unwrap().kind(), "filesystem");
+        config.storage_backend = "memory".to_string();
+        assert_eq!(backend_from_config(&config).unwrap().kind(), "memory");
+        config.storage_backend = "s3".to_string();
+        assert!(backend_from_config(&config).is_err());
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn storage_service_on_the_memory_backend_leaves_no_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::test_support::test_config(dir.path());
+        config.storage_backend = "memory".to_string();
+        let storage = crate::storage::StorageService::new(&config).await.unwrap();
+
+        storage.store_data("entry", b"kept in memory", "key", "alice", Default::default()).unwrap();
+        assert_eq!(storage.retrieve_data("entry", "key", "alice").unwrap(), b"kept in memory");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn sandbox_rejects_paths_that_escape_the_root() {
+        let parent = tempfile::tempdir().unwrap();
+        let root = parent.path().join("store");
+        // A root spelled with `..` is pinned to its canonical form
+        let sandbox = PathSandbox::new(&parent.path().join("other/../store")).unwrap();
+        assert_eq!(sandbox.root(), fs::canonicalize(&root).unwrap());
+
+        let denied = |relative: &str| {
+            let error = sandbox.resolve(relative).unwrap_err();
+            assert!(matches!(error.downcast_ref::<EnclaveError>(), Some(EnclaveError::PermissionDenied(_))), "{}: {}", relative, error);
+        };
+        for crafted in ["", ".", "..", "../outside", "archive/../../outside", "./entry", "/etc/passwd"] {
+            denied(crafted);
+        }
+        assert_eq!(sandbox.resolve("archive/entry").unwrap(), sandbox.root().join("archive/entry"));
+        assert_eq!(sandbox.resolve("missing/deeper/entry").unwrap(), sandbox.root().join("missing/deeper/entry"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sandbox_only_follows_symlinks_that_stay_inside() {
+        let parent = tempfile::tempdir().unwrap();
+        let sandbox = PathSandbox::new(&parent.path().join("store")).unwrap();
+        fs::create_dir(parent.path().join("outside")).unwrap();
+        fs::create_dir(sandbox.root().join("archive")).unwrap();
+        std::os::unix::fs::symlink(parent.path().join("outside"), sandbox.root().join("escape")).unwrap();
+        std::os::unix::fs::symlink(parent.path().join("nowhere"), sandbox.root().join("dangling")).unwrap();
+        std::os::unix::fs::symlink(sandbox.root().join("archive"), sandbox.root().join("alias")).unwrap();
+
+        assert!(sandbox.resolve("escape").is_err());
+        assert!(sandbox.resolve("escape/entry").is_err());
+        assert!(sandbox.resolve("dangling").is_err());
+        assert!(sandbox.resolve("alias/entry").is_ok());
+
+        let backend = FilesystemBackend::new(sandbox.root().to_path_buf()).unwrap();
+        assert!(backend.write("escape/entry", b"data").is_err());
+        assert!(backend.write("../outside/entry", b"data").is_err());
+        assert!(backend.read("escape/entry").is_err());
+        assert_eq!(fs::read_dir(parent.path().join("outside")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn filesystem_erase_overwrites_the_object_in_place() {
+        use std::io::{Read, Seek};
+        let parent = tempfile::tempdir().unwrap();
+        let backend = FilesystemBackend::new(parent.path().join("store")).unwrap();
+        let original = vec![0x5a; ERASE_CHUNK_SIZE + 17];
+        backend.write("secret", &original).unwrap();
+
+        // Both a hard link and an open handle outlive the unlink and still see the inode
+        let link = parent.path().join("secret-link");
+        fs::hard_link(backend.path("secret").unwrap(), &link).unwrap();
+        let mut handle = fs::File::open(backend.path("secret").unwrap()).unwrap();
+
+        assert!(backend.erase("secret").unwrap());
+        assert!(!backend.exists("secret").unwrap());
+
+        let linked = fs::read(&link).unwrap();
+        assert_eq!(linked.len(), original.len());
+        assert_ne!(linked, original);
+        let mut held = Vec::new();
+        handle.rewind().unwrap();
+        handle.read_to_end(&mut held).unwrap();
+        assert_eq!(held, linked);
+    }
+}
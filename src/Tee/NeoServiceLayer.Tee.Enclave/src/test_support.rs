@@ -0,0 +1,278 @@
+//! Helpers shared by the unit tests, and software stand-ins for the SGX SDK functions
+//! the enclave links against so the tests link and run on a plain host.
+
+use sha2::{Digest, Sha256};
+use std::os::raw::c_uint;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::{EncaveConfig, EncaveRuntime};
+use crate::audit::AuditLog;
+use crate::crypto::CryptoService;
+use crate::ffi_crypto::SgxEccStateHandle;
+use crate::storage::StorageService;
+
+/// Simulation-mode configuration keeping storage under `dir`
+pub fn test_config(dir: &Path) -> EncaveConfig {
+    EncaveConfig {
+        sgx_simulation_mode: true,
+        storage_path: dir.to_string_lossy().into_owned(),
+        enable_oracle: false,
+        ..EncaveConfig::default()
+    }
+}
+
+/// Storage, audit log and crypto services wired together as the runtime does
+pub async fn core_services(config: &EncaveConfig) -> (Arc<StorageService>, Arc<AuditLog>, Arc<CryptoService>) {
+    let storage = Arc::new(StorageService::new(config).await.unwrap());
+    let audit = Arc::new(AuditLog::new(storage.clone()).unwrap());
+    let crypto = Arc::new(CryptoService::new(config, audit.clone()).await.unwrap());
+    (storage, audit, crypto)
+}
+
+/// Build a runtime from a synchronous test. It owns a tokio runtime, so it must also be
+/// dropped outside any async context.
+pub fn runtime(config: EncaveConfig) -> EncaveRuntime {
+    tokio::runtime::Runtime::new().unwrap()
+        .block_on(EncaveRuntime::new(config))
+        .unwrap()
+}
+
+/// Install the runtime the FFI exports use, once per test process. It is never torn
+/// down, so tests sharing it must use their own keys and ids.
+pub fn install_ffi_runtime() {
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    INSTALLED.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("nsl-ffi-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        *crate::RUNTIME.write().unwrap() = Some(Arc::new(Mutex::new(runtime(test_config(&dir)))));
+    });
+}
+
+/// Assert that `key_id` is attestation-bound, with a quote committing to the public key
+/// the crypto service exports for it
+pub fn assert_attested(crypto: &CryptoService, key_id: &str) {
+    let metadata = crypto.get_key_metadata(key_id).unwrap();
+    assert!(metadata.attestation_bound, "{}", key_id);
+    let public_key = crypto.get_public_key(key_id, true).unwrap();
+    let report_data = crate::attestation::key_report_data(key_id, &metadata.key_type, metadata.generation, &public_key).unwrap();
+    crate::attestation::inspect_quote(metadata.attestation_quote.as_deref().unwrap(), &report_data).unwrap();
+}
+
+/// Key the test auth policies are signed with
+const POLICY_SIGNING_KEY: [u8; 32] = [7; 32];
+
+/// Write `policy` and its signature to `dir/policy.json`, returning the policy path and
+/// the hex public key to configure as `auth_policy_public_key`
+pub fn write_signed_policy(dir: &Path, policy: &serde_json::Value) -> (String, String) {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    let key = SigningKey::from_bytes(&POLICY_SIGNING_KEY);
+    let path = dir.join("policy.json");
+    let contents = policy.to_string();
+    std::fs::write(&path, &contents).unwrap();
+    std::fs::write(crate::auth::signature_path(&path), hex::encode(key.sign(contents.as_bytes()).to_bytes())).unwrap();
+    (path.to_string_lossy().into_owned(), hex::encode(key.verifying_key().as_bytes()))
+}
+
+/// Hex SHA-256 of a bearer token, as auth policies store it
+pub fn token_sha256(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+const SGX_SUCCESS: c_uint = 0;
+const SGX_ERROR_INVALID_PARAMETER: c_uint = 2;
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_sha256_msg(src: *const u8, src_len: usize, hash: *mut [u8; 32]) -> c_uint {
+    let digest = Sha256::digest(std::slice::from_raw_parts(src, src_len));
+    (*hash).copy_from_slice(&digest);
+    SGX_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_ripemd160_msg(src: *const u8, src_len: usize, hash: *mut [u8; 20]) -> c_uint {
+    *hash = ripemd160(std::slice::from_raw_parts(src, src_len));
+    SGX_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_read_rand(rand: *mut u8, length: usize) -> c_uint {
+    use ring::rand::SecureRandom;
+    match ring::rand::SystemRandom::new().fill(std::slice::from_raw_parts_mut(rand, length)) {
+        Ok(()) => SGX_SUCCESS,
+        Err(_) => 1,
+    }
+}
+
+/// `sgx_generic_ecresult_t` values reported by `sgx_ecdsa_verify`
+const SGX_EC_VALID: u8 = 0;
+const SGX_EC_INVALID_SIGNATURE: u8 = 8;
+
+/// The SDK keeps scalars and coordinates little-endian
+fn reversed(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().rev().copied().collect()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_ecc256_open_context(_handle: *mut SgxEccStateHandle) -> c_uint {
+    SGX_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_ecc256_close_context(_handle: SgxEccStateHandle) -> c_uint {
+    SGX_SUCCESS
+}
+
+/// Deterministic (RFC 6979) stand-in; the SDK signs with a random nonce
+#[no_mangle]
+pub unsafe extern "C" fn sgx_ecdsa_sign(
+    data: *const u8,
+    data_size: usize,
+    private_key: *const [u8; 32],
+    signature: *mut [u8; 64],
+    _handle: SgxEccStateHandle,
+) -> c_uint {
+    use p256::ecdsa::signature::Signer;
+    let Ok(key) = p256::ecdsa::SigningKey::from_slice(&reversed(&*private_key)) else {
+        return SGX_ERROR_INVALID_PARAMETER;
+    };
+    let sig: p256::ecdsa::Signature = key.sign(std::slice::from_raw_parts(data, data_size));
+    let bytes = sig.to_bytes();
+    let signature = &mut *signature;
+    signature[..32].copy_from_slice(&reversed(&bytes[..32]));
+    signature[32..].copy_from_slice(&reversed(&bytes[32..]));
+    SGX_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn sgx_ecdsa_verify(
+    data: *const u8,
+    data_size: usize,
+    public_key: *const [u8; 64],
+    signature: *const [u8; 64],
+    result: *mut u8,
+    _handle: SgxEccStateHandle,
+) -> c_uint {
+    use p256::ecdsa::signature::Verifier;
+    let (public_key, signature) = (&*public_key, &*signature);
+    let point = [&[0x04][..], &reversed(&public_key[..32]), &reversed(&public_key[32..])].concat();
+    let Ok(key) = p256::ecdsa::VerifyingKey::from_sec1_bytes(&point) else {
+        return SGX_ERROR_INVALID_PARAMETER;
+    };
+    let sig = [reversed(&signature[..32]), reversed(&signature[32..])].concat();
+    let valid = p256::ecdsa::Signature::from_slice(&sig)
+        .is_ok_and(|sig| key.verify(std::slice::from_raw_parts(data, data_size), &sig).is_ok());
+    *result = if valid { SGX_EC_VALID } else { SGX_EC_INVALID_SIGNATURE };
+    SGX_SUCCESS
+}
+
+/// RIPEMD-160 as specified by Dobbertin, Bosselaers and Preneel
+fn ripemd160(data: &[u8]) -> [u8; 20] {
+    const R_LEFT: [usize; 80] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+        7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5, 2, 14, 11, 8,
+        3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12,
+        1, 9, 11, 10, 0, 8, 12, 4, 13, 3, 7, 15, 14, 5, 6, 2,
+        4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+    ];
+    const R_RIGHT: [usize; 80] = [
+        5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12,
+        6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12, 4, 9, 1, 2,
+        15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13,
+        8, 6, 4, 1, 3, 11, 15, 0, 5, 12, 2, 13, 9, 7, 10, 14,
+        12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+    ];
+    const S_LEFT: [u32; 80] = [
+        11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8,
+        7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15, 9, 11, 7, 13, 12,
+        11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5,
+        11, 12, 14, 15, 14, 15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12,
+        9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+    ];
+    const S_RIGHT: [u32; 80] = [
+        8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6,
+        9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12, 7, 6, 15, 13, 11,
+        9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5,
+        15, 5, 8, 11, 14, 14, 6, 14, 6, 9, 12, 9, 12, 5, 15, 8,
+        8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+    ];
+    const K_LEFT: [u32; 5] = [0x0000_0000, 0x5a82_7999, 0x6ed9_eba1, 0x8f1b_bcdc, 0xa953_fd4e];
+    const K_RIGHT: [u32; 5] = [0x50a2_8be6, 0x5c4d_d124, 0x6d70_3ef3, 0x7a6d_76e9, 0x0000_0000];
+
+    fn f(round: usize, x: u32, y: u32, z: u32) -> u32 {
+        match round {
+            0 => x ^ y ^ z,
+            1 => (x & y) | (!x & z),
+            2 => (x | !y) ^ z,
+            3 => (x & z) | (y & !z),
+            _ => x ^ (y | !z),
+        }
+    }
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut h: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block.chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let [mut al, mut bl, mut cl, mut dl, mut el] = h;
+        let [mut ar, mut br, mut cr, mut dr, mut er] = h;
+        for j in 0..80 {
+            let round = j / 16;
+            let t = al.wrapping_add(f(round, bl, cl, dl))
+                .wrapping_add(words[R_LEFT[j]])
+                .wrapping_add(K_LEFT[round])
+                .rotate_left(S_LEFT[j])
+                .wrapping_add(el);
+            al = el;
+            el = dl;
+            dl = cl.rotate_left(10);
+            cl = bl;
+            bl = t;
+
+            let t = ar.wrapping_add(f(4 - round, br, cr, dr))
+                .wrapping_add(words[R_RIGHT[j]])
+                .wrapping_add(K_RIGHT[round])
+                .rotate_left(S_RIGHT[j])
+                .wrapping_add(er);
+            ar = er;
+            er = dr;
+            dr = cr.rotate_left(10);
+            cr = br;
+            br = t;
+        }
+        let t = h[1].wrapping_add(cl).wrapping_add(dr);
+        h[1] = h[2].wrapping_add(dl).wrapping_add(er);
+        h[2] = h[3].wrapping_add(el).wrapping_add(ar);
+        h[3] = h[4].wrapping_add(al).wrapping_add(br);
+        h[4] = h[0].wrapping_add(bl).wrapping_add(cr);
+        h[0] = t;
+    }
+
+    let mut out = [0u8; 20];
+    for (chunk, word) in out.chunks_exact_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ripemd160_matches_reference_vectors() {
+        assert_eq!(hex::encode(ripemd160(b"")), "9c1185a5c5e9fc54612808977ee8f548b2258d31");
+        assert_eq!(hex::encode(ripemd160(b"abc")), "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc");
+        assert_eq!(
+            hex::encode(ripemd160(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890")),
+            "9b752e45573d4b39f4dbd3323cab82bf63326bfb"
+        );
+    }
+}